@@ -0,0 +1,300 @@
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    ops::Not,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    config::{fnv1a, Hook, HookTarget},
+    report::SectionKind,
+    slack,
+};
+
+/// Where we archive every report that we post, e.g. for compliance.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum ArchiveConfig {
+    /// A clone of a Git repository, with one commit per run.
+    Git {
+        path: PathBuf,
+
+        /// If `true`, push after each commit.
+        #[serde(default)]
+        push: bool,
+
+        /// If specified, reports are encrypted before they're committed.
+        #[serde(default)]
+        encrypt: Option<Encryption>,
+    },
+}
+
+/// How we encrypt archived reports, which may mention private projects, before storing
+/// them in a shared place.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Encryption {
+    /// With `age`, for a recipient, i.e. a public key such as `age1ql3z7hjy54pw3hyww5...`.
+    Age(String),
+
+    /// With `gpg`, for a public key of the keyring (its id, fingerprint or email).
+    Gpg(String),
+}
+impl Encryption {
+    fn extension(&self) -> &'static str {
+        match self {
+            Encryption::Age(_) => "age",
+            Encryption::Gpg(_) => "gpg",
+        }
+    }
+
+    /// Encrypt `data` to `output`, with the tool installed on this machine.
+    fn encrypt(&self, data: &[u8], output: &Path) -> Result<(), anyhow::Error> {
+        let mut command = match self {
+            Encryption::Age(recipient) => {
+                let mut command = Command::new("age");
+                command.arg("--recipient").arg(recipient);
+                command
+            }
+            Encryption::Gpg(key) => {
+                let mut command = Command::new("gpg");
+                command.args(["--batch", "--yes", "--trust-model", "always", "--encrypt"]);
+                command.arg("--recipient").arg(key);
+                command
+            }
+        };
+        let tool = command.get_program().to_string_lossy().to_string();
+        let mut child = command
+            .arg("--output")
+            .arg(output)
+            .stdin(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Could not run {tool}"))?;
+        child
+            .stdin
+            .take()
+            .context("Could not open stdin")?
+            .write_all(data)
+            .with_context(|| format!("Could not write to {tool}"))?;
+        let output = child
+            .wait_with_output()
+            .with_context(|| format!("Could not run {tool}"))?;
+        if output.status.success().not() {
+            return Err(anyhow!(
+                "{tool} failed: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl ArchiveConfig {
+    /// Resolve relative paths against a directory, e.g. that of a tenant.
+    pub fn relative_to(&self, dir: &Path) -> Self {
+        match self {
+            ArchiveConfig::Git {
+                path,
+                push,
+                encrypt,
+            } => ArchiveConfig::Git {
+                path: dir.join(path),
+                push: *push,
+                encrypt: encrypt.clone(),
+            },
+        }
+    }
+}
+
+/// A section, as posted to a hook.
+#[derive(Serialize)]
+struct Entry {
+    at: DateTime<Utc>,
+    project: Url,
+
+    /// The hook, without its secret url.
+    hook: String,
+    kind: SectionKind,
+    section: serde_json::Value,
+}
+
+/// The sections posted during a run, until they're committed to the archive.
+#[derive(Default)]
+pub struct Archive {
+    config: Option<ArchiveConfig>,
+    entries: Vec<Entry>,
+}
+impl Archive {
+    pub fn new(config: Option<ArchiveConfig>) -> Self {
+        Archive {
+            config,
+            entries: vec![],
+        }
+    }
+
+    /// Record that a section was posted to a hook, given its label (see `hook_label`).
+    pub fn record(
+        &mut self,
+        project: &Url,
+        label: &str,
+        kind: SectionKind,
+        section: &slack::Section,
+    ) -> Result<(), anyhow::Error> {
+        if self.config.is_none() {
+            return Ok(());
+        }
+        self.entries.push(Entry {
+            at: Utc::now(),
+            project: project.clone(),
+            hook: label.to_string(),
+            kind,
+            section: serde_json::to_value(section).context("Could not serialize section")?,
+        });
+        Ok(())
+    }
+
+    /// Append all the sections recorded during this run to the archive.
+    pub fn commit(&mut self) -> Result<(), anyhow::Error> {
+        let Some(ref config) = self.config else {
+            return Ok(());
+        };
+        if self.entries.is_empty() {
+            return Ok(());
+        }
+        let now = Utc::now();
+        match config {
+            ArchiveConfig::Git {
+                path,
+                push,
+                encrypt,
+            } => {
+                let file = file(now, encrypt.as_ref());
+                std::fs::create_dir_all(path.join("reports"))
+                    .context("Could not create archive directory")?;
+                let mut lines = vec![];
+                for entry in &self.entries {
+                    serde_json::to_writer(&mut lines, entry)
+                        .context("Could not serialize entry")?;
+                    lines.push(b'\n');
+                }
+                match encrypt {
+                    Some(encryption) => encryption
+                        .encrypt(&lines, &path.join(&file))
+                        .context("Could not encrypt archive")?,
+                    None => OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(path.join(&file))
+                        .context("Could not open archive")?
+                        .write_all(&lines)
+                        .context("Could not write archive")?,
+                }
+                git(path, &["add", &file.to_string_lossy()])?;
+                git(
+                    path,
+                    &[
+                        "commit",
+                        "--quiet",
+                        "-m",
+                        &format!("Reports of {}", now.to_rfc3339()),
+                    ],
+                )?;
+                if *push {
+                    git(path, &["push", "--quiet"])?;
+                }
+            }
+        }
+        self.entries.clear();
+        Ok(())
+    }
+}
+
+/// The file of the archive, relative to its root, to which we write the reports of a run.
+///
+/// One file per day, so that files remain small. Encrypted files cannot be appended to,
+/// so there's one per run.
+fn file(now: DateTime<Utc>, encryption: Option<&Encryption>) -> PathBuf {
+    let name = match encryption {
+        None => format!("{}.jsonl", now.format("%Y-%m-%d")),
+        Some(encryption) => format!(
+            "{}.jsonl.{}",
+            now.format("%Y-%m-%dT%H%M%SZ"),
+            encryption.extension()
+        ),
+    };
+    PathBuf::from("reports").join(name)
+}
+
+/// Run a git command in a repository.
+fn git(repository: &Path, args: &[&str]) -> Result<(), anyhow::Error> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repository)
+        .args(args)
+        .output()
+        .context("Could not run git")?;
+    if output.status.success().not() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+    Ok(())
+}
+
+/// A name for a hook that doesn't reveal its url: its name or alias, if any, the channel
+/// of a bot, or a hash.
+pub fn hook_label(hook: &Hook) -> String {
+    if let Some(ref name) = hook.name.as_ref().or(hook.alias.as_ref()) {
+        return name.to_string();
+    }
+    match hook.target {
+        HookTarget::Url(ref url) => format!("hook-{:016x}", fnv1a(url.as_str().as_bytes())),
+        HookTarget::Bot { ref channel, .. } => format!("slack:{channel}"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use chrono::{TimeZone, Utc};
+    use url::Url;
+
+    use super::{file, hook_label, Encryption};
+    use crate::config::{Hook, SlackHook};
+
+    /// Do encrypted reports get a file of their own for each run?
+    #[test]
+    fn test_file() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 6, 9, 30, 0).unwrap();
+        assert_eq!(file(now, None), Path::new("reports/2025-01-06.jsonl"));
+        let age = Encryption::Age("age1recipient".to_string());
+        assert_eq!(
+            file(now, Some(&age)),
+            Path::new("reports/2025-01-06T093000Z.jsonl.age")
+        );
+    }
+
+    /// Do we name hooks without leaking their url?
+    #[test]
+    fn test_hook_label() {
+        let url = Url::parse("https://hooks.slack.com/services/YOUR/SLACK/HOOK").unwrap();
+        let mut hook = Hook::from(SlackHook::from(url));
+        let label = hook_label(&hook);
+        assert!(label.starts_with("hook-"));
+        assert!(!label.contains("SLACK"));
+        assert_eq!(label, hook_label(&hook));
+
+        hook.alias = Some("sdk-team".to_string());
+        assert_eq!(hook_label(&hook), "sdk-team");
+    }
+}
@@ -0,0 +1,86 @@
+use std::ops::Not;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc, Weekday};
+
+use crate::config::BusinessHours;
+
+/// Whether a day is a working day, i.e. Monday to Friday, except `holidays`.
+pub fn is_working_day(day: NaiveDate, holidays: &[NaiveDate]) -> bool {
+    matches!(day.weekday(), Weekday::Sat | Weekday::Sun).not() && holidays.contains(&day).not()
+}
+
+/// The working time between two moments, i.e. the time within business hours of the
+/// working days in between.
+pub fn elapsed(hours: &BusinessHours, from: DateTime<Utc>, to: DateTime<Utc>) -> Duration {
+    let mut total = Duration::zero();
+    let mut day = from.date_naive();
+    while day <= to.date_naive() {
+        if is_working_day(day, &hours.holidays) {
+            let open = day.and_time(hours.start).and_utc().max(from);
+            let close = day.and_time(hours.end).and_utc().min(to);
+            if close > open {
+                total += close - open;
+            }
+        }
+        let Some(next) = day.succ_opt() else {
+            break;
+        };
+        day = next;
+    }
+    total
+}
+
+/// A threshold given as a duration (e.g. `stale_after: 3d`), in working time: whole days
+/// count as working days.
+pub fn threshold(hours: &BusinessHours, duration: Duration) -> Duration {
+    let days = duration.num_days();
+    hours.day() * days as i32 + (duration - Duration::days(days))
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, NaiveDate, TimeZone, Utc};
+
+    use super::{elapsed, is_working_day, threshold};
+    use crate::config::BusinessHours;
+
+    /// Are nights, weekends and holidays left out of waiting times?
+    #[test]
+    fn test_elapsed() {
+        let christmas = NaiveDate::from_ymd_opt(2024, 12, 25).unwrap();
+        let hours = BusinessHours {
+            holidays: vec![christmas],
+            ..BusinessHours::default()
+        };
+        assert!(is_working_day(christmas, &[]));
+        assert!(!is_working_day(christmas, &hours.holidays));
+
+        // A Friday afternoon.
+        let friday = Utc.with_ymd_and_hms(2024, 5, 3, 15, 0, 0).unwrap();
+        assert_eq!(
+            elapsed(&hours, friday, friday + Duration::hours(1)),
+            Duration::hours(1)
+        );
+        // 2 hours on Friday, none during the weekend, 1 hour on Monday morning.
+        let monday = Utc.with_ymd_and_hms(2024, 5, 6, 10, 0, 0).unwrap();
+        assert_eq!(elapsed(&hours, friday, monday), Duration::hours(3));
+        assert_eq!(elapsed(&hours, monday, friday), Duration::zero());
+
+        // From Christmas Eve at noon to Boxing Day at noon.
+        let eve = Utc.with_ymd_and_hms(2024, 12, 24, 12, 0, 0).unwrap();
+        assert_eq!(
+            elapsed(&hours, eve, eve + Duration::days(2)),
+            Duration::hours(8)
+        );
+    }
+
+    /// Do days of thresholds count as working days?
+    #[test]
+    fn test_threshold() {
+        let hours = BusinessHours::default();
+        assert_eq!(
+            threshold(&hours, Duration::days(3) + Duration::hours(2)),
+            Duration::hours(26)
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use url::Url;
+
+/// The longest line allowed by RFC 5545, in bytes, excluding the line break.
+const MAX_LINE: usize = 75;
+
+/// A deadline, shown as an all-day event.
+#[derive(Debug, Clone)]
+pub struct Event {
+    /// Stable across exports, so that calendars update events rather than duplicate them.
+    pub uid: String,
+    pub summary: String,
+    pub description: String,
+    pub url: Url,
+    pub date: NaiveDate,
+}
+
+/// Escape a text value, e.g. commas and line breaks.
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                result.push('\\');
+                result.push(c);
+            }
+            '\n' => result.push_str("\\n"),
+            '\r' => {}
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// Append a content line, folded every 75 bytes without splitting characters.
+fn push_line(ics: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE {
+            ics.push_str("\r\n ");
+            // The leading space counts towards the next line.
+            width = 1;
+        }
+        ics.push(c);
+        width += c.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
+
+/// Render events as an iCalendar feed.
+pub fn render(events: &[Event], now: DateTime<Utc>) -> String {
+    let stamp = now.format("%Y%m%dT%H%M%SZ");
+    let mut ics = String::new();
+    push_line(&mut ics, "BEGIN:VCALENDAR");
+    push_line(&mut ics, "VERSION:2.0");
+    push_line(&mut ics, "PRODID:-//pasqal-io//qastor//EN");
+    push_line(&mut ics, "CALSCALE:GREGORIAN");
+    push_line(&mut ics, "X-WR-CALNAME:qastor");
+    for event in events {
+        push_line(&mut ics, "BEGIN:VEVENT");
+        push_line(&mut ics, &format!("UID:{}", escape(&event.uid)));
+        push_line(&mut ics, &format!("DTSTAMP:{stamp}"));
+        push_line(
+            &mut ics,
+            &format!("DTSTART;VALUE=DATE:{}", event.date.format("%Y%m%d")),
+        );
+        if let Some(next) = event.date.succ_opt() {
+            push_line(
+                &mut ics,
+                &format!("DTEND;VALUE=DATE:{}", next.format("%Y%m%d")),
+            );
+        }
+        push_line(&mut ics, &format!("SUMMARY:{}", escape(&event.summary)));
+        push_line(
+            &mut ics,
+            &format!("DESCRIPTION:{}", escape(&event.description)),
+        );
+        push_line(&mut ics, &format!("URL:{}", event.url));
+        push_line(&mut ics, "TRANSP:TRANSPARENT");
+        push_line(&mut ics, "END:VEVENT");
+    }
+    push_line(&mut ics, "END:VCALENDAR");
+    ics
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use url::Url;
+
+    use super::{render, Event};
+
+    /// Do we produce valid iCalendar, with escaped and folded lines?
+    #[test]
+    fn test_render() {
+        let event = Event {
+            uid: "owner1-project1-milestone-3@qastor".to_string(),
+            summary: "project1: v1.0, finally; really".to_string(),
+            description: format!("2 open issues\n{}", "é".repeat(40)),
+            url: Url::parse("https://github.com/owner1/project1/milestone/3").unwrap(),
+            date: NaiveDate::from_ymd_opt(2024, 12, 31).unwrap(),
+        };
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let ics = render(&[event], now);
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+        assert!(ics.contains("\r\nDTSTAMP:20240501T120000Z\r\n"));
+        assert!(ics.contains("\r\nDTSTART;VALUE=DATE:20241231\r\nDTEND;VALUE=DATE:20250101\r\n"));
+        assert!(ics.contains("\r\nSUMMARY:project1: v1.0\\, finally\\; really\r\n"));
+        assert!(ics.contains("\r\nDESCRIPTION:2 open issues\\n"));
+        for line in ics.split("\r\n") {
+            assert!(line.len() <= 75, "{line}");
+        }
+        assert!(ics.contains("\r\n é"));
+    }
+}
@@ -0,0 +1,163 @@
+use std::{collections::HashMap, ops::Not};
+
+use anyhow::Context;
+use octocrab::Octocrab;
+use serde::Deserialize;
+
+use crate::config::Project;
+
+/// Who owns a project, as described by a catalog file in its repository.
+///
+/// Either a Backstage-style descriptor:
+///
+/// ```yaml
+/// metadata:
+///   annotations:
+///     slack.com/channel: sdk
+///     qastor/escalation: alice, bob
+/// spec:
+///   owner: team-sdk
+/// ```
+///
+/// or a flat file:
+///
+/// ```yaml
+/// owner: team-sdk
+/// slack_channel: sdk
+/// escalation: [alice, bob]
+/// ```
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct Ownership {
+    /// The owning team.
+    pub team: Option<String>,
+
+    /// The channel to which reports should be posted, resolved through the
+    /// `channels` of the secrets.
+    pub channel: Option<String>,
+
+    /// Who to ping when something needs attention.
+    pub escalation: Vec<String>,
+}
+impl Ownership {
+    pub fn parse(source: &str) -> Result<Self, anyhow::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Payload {
+            Backstage {
+                #[serde(default)]
+                metadata: Metadata,
+                spec: Spec,
+            },
+            Flat {
+                #[serde(default)]
+                owner: Option<String>,
+                #[serde(default)]
+                slack_channel: Option<String>,
+                #[serde(default)]
+                escalation: Vec<String>,
+            },
+        }
+        #[derive(Deserialize, Default)]
+        struct Metadata {
+            #[serde(default)]
+            annotations: HashMap<String, String>,
+        }
+        #[derive(Deserialize)]
+        struct Spec {
+            #[serde(default)]
+            owner: Option<String>,
+        }
+        let payload: Payload = serde_yaml::from_str(source).context("Invalid catalog file")?;
+        Ok(match payload {
+            Payload::Backstage { metadata, spec } => Ownership {
+                team: spec.owner,
+                channel: metadata.annotations.get("slack.com/channel").cloned(),
+                escalation: metadata
+                    .annotations
+                    .get("qastor/escalation")
+                    .map(|contacts| {
+                        contacts
+                            .split(',')
+                            .map(|contact| contact.trim().to_string())
+                            .filter(|contact| contact.is_empty().not())
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            },
+            Payload::Flat {
+                owner,
+                slack_channel,
+                escalation,
+            } => Ownership {
+                team: owner,
+                channel: slack_channel,
+                escalation,
+            },
+        })
+    }
+}
+
+/// Fetch and parse the catalog file of a project, if it has one.
+pub async fn fetch_ownership(
+    octocrab: &Octocrab,
+    project: &Project,
+    path: &str,
+) -> Result<Option<Ownership>, anyhow::Error> {
+    let content = match octocrab
+        .repos(&project.owner, &project.repo)
+        .get_content()
+        .path(path)
+        .send()
+        .await
+    {
+        Ok(content) => content,
+        Err(octocrab::Error::GitHub { source, .. })
+            if source.status_code == reqwest::StatusCode::NOT_FOUND =>
+        {
+            return Ok(None)
+        }
+        Err(err) => return Err(err).with_context(|| format!("Couldn't fetch {path}")),
+    };
+    let Some(source) = content
+        .items
+        .first()
+        .and_then(|item| item.decoded_content())
+    else {
+        return Ok(None);
+    };
+    Ownership::parse(&source).map(Some)
+}
+
+#[cfg(test)]
+mod test {
+    use super::Ownership;
+
+    /// Can both Backstage-style and flat catalog files be parsed?
+    #[test]
+    fn test_ownership_parse() {
+        let expected = Ownership {
+            team: Some("team-sdk".to_string()),
+            channel: Some("sdk".to_string()),
+            escalation: vec!["alice".to_string(), "bob".to_string()],
+        };
+        let backstage = r#"
+            apiVersion: backstage.io/v1alpha1
+            kind: Component
+            metadata:
+                name: sdk
+                annotations:
+                    slack.com/channel: sdk
+                    qastor/escalation: "alice, bob"
+            spec:
+                type: library
+                owner: team-sdk
+        "#;
+        assert_eq!(Ownership::parse(backstage).unwrap(), expected);
+        let flat = r#"
+            owner: team-sdk
+            slack_channel: sdk
+            escalation: [alice, bob]
+        "#;
+        assert_eq!(Ownership::parse(flat).unwrap(), expected);
+    }
+}
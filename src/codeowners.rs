@@ -0,0 +1,174 @@
+use std::ops::Not;
+
+use anyhow::Context;
+use octocrab::Octocrab;
+use regex::Regex;
+
+use crate::config::Project;
+
+/// Where GitHub looks for the CODEOWNERS file of a repository, in this order.
+const LOCATIONS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+/// A rule of a CODEOWNERS file: the files matching a pattern and their owners.
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: Regex,
+
+    /// Users (`@alice`), teams (`@org/team`) or emails. Empty if the files have no owner.
+    owners: Vec<String>,
+}
+
+/// The owners of the files of a repository, as described by its CODEOWNERS file.
+///
+/// ```text
+/// # The last matching pattern wins.
+/// *           @org/core
+/// /frontend/  @org/frontend
+/// *.rs        @org/backend
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CodeOwners {
+    rules: Vec<Rule>,
+}
+impl CodeOwners {
+    /// Parse a CODEOWNERS file.
+    pub fn parse(source: &str) -> Self {
+        let rules = source
+            .lines()
+            .map(str::trim)
+            .filter(|line| line.is_empty().not() && line.starts_with('#').not())
+            .filter_map(|line| {
+                let mut words = line
+                    .split_whitespace()
+                    .take_while(|word| word.starts_with('#').not());
+                let pattern = pattern(words.next()?)?;
+                Some(Rule {
+                    pattern,
+                    owners: words.map(str::to_string).collect(),
+                })
+            })
+            .collect();
+        CodeOwners { rules }
+    }
+
+    /// The owners of a file, given its path from the root of the repository.
+    pub fn owners(&self, path: &str) -> &[String] {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.pattern.is_match(path))
+            .map_or(&[], |rule| rule.owners.as_slice())
+    }
+}
+
+/// Translate a pattern of CODEOWNERS (i.e. of `.gitignore`, mostly) into a regex matching
+/// paths from the root of the repository.
+fn pattern(pattern: &str) -> Option<Regex> {
+    // Patterns with a slash other than at the end are relative to the root, others match
+    // at any depth.
+    let anchored = pattern.trim_end_matches('/').contains('/');
+    let directory = pattern.ends_with('/');
+    // Without wildcards, the last part may name a directory, e.g. `/docs`, but `docs/*` only
+    // matches the files right inside `docs`.
+    let wildcard = pattern
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .is_some_and(|last| last.contains(['*', '?']));
+    let pattern = pattern.trim_start_matches('/').trim_end_matches('/');
+    let mut regex = String::from(match anchored {
+        true => "^",
+        false => "^(?:.*/)?",
+    });
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    // A pattern matching a directory matches everything inside.
+    regex.push_str(match (directory, wildcard) {
+        (true, _) => "/.*$",
+        (false, true) => "$",
+        (false, false) => "(?:/.*)?$",
+    });
+    Regex::new(&regex).ok()
+}
+
+/// Fetch and parse the CODEOWNERS file of a project, if it has one.
+pub async fn fetch(
+    octocrab: &Octocrab,
+    project: &Project,
+) -> Result<Option<CodeOwners>, anyhow::Error> {
+    for path in LOCATIONS {
+        let content = match octocrab
+            .repos(&project.owner, &project.repo)
+            .get_content()
+            .path(path)
+            .send()
+            .await
+        {
+            Ok(content) => content,
+            Err(octocrab::Error::GitHub { source, .. })
+                if source.status_code == reqwest::StatusCode::NOT_FOUND =>
+            {
+                continue
+            }
+            Err(err) => return Err(err).with_context(|| format!("Couldn't fetch {path}")),
+        };
+        if let Some(source) = content
+            .items
+            .first()
+            .and_then(|item| item.decoded_content())
+        {
+            return Ok(Some(CodeOwners::parse(&source)));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use super::CodeOwners;
+
+    /// Does the last matching pattern win, with the semantics of `.gitignore`?
+    #[test]
+    fn test_owners() {
+        let owners = CodeOwners::parse(
+            "\
+# Default owners.
+*                 @org/core
+*.rs              @org/backend # Rust
+/frontend/        @org/frontend @alice
+docs/**/*.md      @org/docs
+build/logs/
+/assets/*         @bob
+/vendor           @carol
+",
+        );
+        assert_eq!(owners.owners("README.md"), ["@org/core"]);
+        assert_eq!(owners.owners("src/main.rs"), ["@org/backend"]);
+        assert_eq!(
+            owners.owners("frontend/src/app.rs"),
+            ["@org/frontend", "@alice"]
+        );
+        assert_eq!(owners.owners("src/frontend/app.ts"), ["@org/core"]);
+        assert_eq!(owners.owners("docs/guide/setup.md"), ["@org/docs"]);
+        assert_eq!(owners.owners("docs/index.md"), ["@org/docs"]);
+        assert!(owners.owners("build/logs/today.txt").is_empty());
+        assert_eq!(owners.owners("assets/logo.svg"), ["@bob"]);
+        assert_eq!(owners.owners("assets/icons/logo.svg"), ["@org/core"]);
+        assert_eq!(owners.owners("vendor/lib/mod.rs"), ["@carol"]);
+    }
+}
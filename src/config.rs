@@ -1,14 +1,34 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Not,
+};
 
 use anyhow::{anyhow, Context};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime, Utc};
 use derive_more::{AsRef, Deref, Display, From};
+use itertools::Itertools;
 use lazy_regex::{lazy_regex, Lazy};
 use regex::Regex;
-use serde::{de::Unexpected, Deserialize};
+use serde::{de::Unexpected, Deserialize, Serialize};
 use url::Url;
 
+use crate::{
+    archive::ArchiveConfig,
+    dependabot::AlertSeverity,
+    discord,
+    enrich::EnrichmentConfig,
+    error::Error,
+    humanize::Locale,
+    i18n,
+    report::{SectionKind, Severity},
+    slack::{self, MessageOptions},
+    state::StateConfig,
+    teams,
+    tz::Zone,
+};
+
 /// The name of a repository.
-#[derive(Hash, PartialEq, Eq, Debug, Deserialize, Display, AsRef)]
+#[derive(Hash, PartialEq, Eq, Debug, Clone, Deserialize, Display, AsRef)]
 pub struct RepoName(String);
 impl From<&RepoName> for String {
     fn from(repo_name: &RepoName) -> String {
@@ -26,9 +46,199 @@ impl From<&str> for RepoName {
 /// Typically looks like https://hooks.slack.com/services/XXX/YYY/ZZZ
 ///
 /// Confidentiality: secret.
-#[derive(Deserialize, AsRef, From, PartialEq, Debug, Deref)]
+#[derive(Deserialize, AsRef, From, Clone, PartialEq, Debug, Deref)]
 pub struct SlackHook(Url);
 
+/// The service behind a hook, which decides how we format messages.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HookKind {
+    Slack,
+    Discord,
+    Teams,
+
+    /// A generic webhook, e.g. of a dashboard, which receives the findings of each run as
+    /// JSON rather than reports, see `webhook::Payload`. Never guessed.
+    Json,
+}
+impl HookKind {
+    /// Guess the service from the url of a hook, Slack if we don't recognize it.
+    pub fn detect(url: &Url) -> Self {
+        if discord::is_discord(url) {
+            HookKind::Discord
+        } else if teams::is_teams(url) {
+            HookKind::Teams
+        } else {
+            HookKind::Slack
+        }
+    }
+}
+
+/// Where a hook posts.
+///
+/// Confidentiality: secret.
+#[derive(PartialEq, Debug)]
+pub enum HookTarget {
+    /// An incoming webhook, e.g. of Slack or Discord.
+    Url(SlackHook),
+
+    /// A Slack channel, to which a bot posts with the Slack Web API. Unlike incoming
+    /// webhooks, any channel will do, as long as the bot has joined it.
+    Bot {
+        /// The token of `slack_bot` in the secrets, filled by `Secrets::attach_bot`.
+        token: String,
+
+        /// The id of the channel, e.g. `C0123456789`.
+        channel: String,
+    },
+}
+
+/// A hook to which we post reports, along with what it wants to receive.
+///
+/// In the secrets, either a bare url or an object
+/// `{"url": ..., "name": ..., "kind": "teams", "fallbacks": [...], "severities": [...], "alias": ..., "locale": "fr", "unfurl_links": false, "link_style": "compact", "format": "plain"}`,
+/// where `"channel": ...` may replace `url` to post as the `slack_bot` of the secrets.
+#[derive(PartialEq, Debug)]
+pub struct Hook {
+    pub target: HookTarget,
+
+    /// The service behind `url`, if we can't guess it from `url`. Bots always post to
+    /// Slack. The service of each of the `fallbacks` is guessed from its own url.
+    pub kind: Option<HookKind>,
+
+    /// Hooks to try, in order, if posting to `url` keeps failing (e.g. it has been revoked).
+    pub fallbacks: Vec<SlackHook>,
+
+    /// The severities to which this hook subscribes. If empty, all of them.
+    pub severities: Vec<Severity>,
+
+    /// A name for this hook, used e.g. as the target of routes.
+    pub alias: Option<String>,
+
+    /// How this hook appears in logs, the archive and the outcome of runs, e.g. `#sdk on
+    /// Slack`, rather than a hash of its url.
+    pub name: Option<String>,
+
+    /// How messages are posted to this hook.
+    pub options: MessageOptions,
+
+    /// If specified, the language of the reports posted to this hook, instead of the one of
+    /// the project.
+    pub locale: Option<Locale>,
+}
+impl Hook {
+    /// The service behind this hook, explicit or guessed from its url.
+    pub fn kind(&self) -> HookKind {
+        match self.target {
+            HookTarget::Url(ref url) => self.kind.unwrap_or_else(|| HookKind::detect(url)),
+            HookTarget::Bot { .. } => HookKind::Slack,
+        }
+    }
+
+    /// The fallbacks of this hook, as hooks of their own with the same options.
+    pub fn fallback_hooks(&self) -> Vec<Hook> {
+        self.fallbacks
+            .iter()
+            .map(|url| Hook {
+                target: HookTarget::Url(url.clone()),
+                kind: None,
+                fallbacks: vec![],
+                severities: self.severities.clone(),
+                alias: self.alias.clone(),
+                name: self.name.clone(),
+                options: self.options.clone(),
+                locale: self.locale,
+            })
+            .collect()
+    }
+
+    /// Should this hook receive sections with this severity?
+    pub fn accepts(&self, severity: Severity) -> bool {
+        self.severities.is_empty() || self.severities.contains(&severity)
+    }
+}
+impl From<SlackHook> for Hook {
+    fn from(url: SlackHook) -> Self {
+        Hook {
+            target: HookTarget::Url(url),
+            kind: None,
+            fallbacks: vec![],
+            severities: vec![],
+            alias: None,
+            name: None,
+            options: MessageOptions::default(),
+            locale: None,
+        }
+    }
+}
+impl<'de> Deserialize<'de> for Hook {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Payload {
+            Url(SlackHook),
+            Detailed {
+                #[serde(default)]
+                url: Option<SlackHook>,
+                #[serde(default)]
+                channel: Option<String>,
+                #[serde(default)]
+                kind: Option<HookKind>,
+                #[serde(default)]
+                fallbacks: Vec<SlackHook>,
+                #[serde(default)]
+                severities: Vec<Severity>,
+                #[serde(default)]
+                alias: Option<String>,
+                #[serde(default)]
+                name: Option<String>,
+                #[serde(default)]
+                locale: Option<Locale>,
+                #[serde(flatten)]
+                options: MessageOptions,
+            },
+        }
+        Ok(match Payload::deserialize(deserializer)? {
+            Payload::Url(url) => Hook::from(url),
+            Payload::Detailed {
+                url,
+                channel,
+                kind,
+                fallbacks,
+                severities,
+                alias,
+                name,
+                locale,
+                options,
+            } => Hook {
+                target: match (url, channel) {
+                    (Some(url), None) => HookTarget::Url(url),
+                    (None, Some(channel)) => HookTarget::Bot {
+                        token: String::new(),
+                        channel,
+                    },
+                    _ => {
+                        return Err(D::Error::custom(
+                            "a hook needs either a `url` or a `channel`",
+                        ))
+                    }
+                },
+                kind,
+                fallbacks,
+                severities,
+                alias,
+                name,
+                options,
+                locale,
+            },
+        })
+    }
+}
+
 pub struct ProjectToHook {
     pub project: Url,
     pub hook: SlackHook,
@@ -52,148 +262,2312 @@ impl ProjectToHook {
     }
 }
 
+/// The credentials of a GitHub App, e.g. for org-wide deployments that shouldn't
+/// depend on the token of a single person.
+#[derive(Deserialize)]
+pub struct GitHubApp {
+    pub app_id: u64,
+
+    /// The private key of the app, PEM-encoded.
+    pub private_key: String,
+}
+
+/// A message summarizing all the projects, updated in place after each run, posted
+/// with the Slack Web API.
+#[derive(Deserialize)]
+pub struct StatusBoard {
+    /// A bot token, with scopes `chat:write` and, to pin the message, `pins:write`.
+    pub token: String,
+
+    /// The id of the channel, e.g. `C0123456789`.
+    pub channel: String,
+}
+
+/// A Slack bot, posting with the Slack Web API rather than incoming webhooks.
+#[derive(Deserialize)]
+pub struct SlackBot {
+    /// A bot token, with scope `chat:write` and, depending on `project_channels` and
+    /// `usergroups` in the config, `channels:read`, `channels:join`, `channels:manage`,
+    /// `usergroups:read`, `usergroups:write`.
+    pub token: String,
+}
+
+/// A Matrix room, to which we post the reports of all projects.
+///
+/// Confidentiality: secret.
+#[derive(Deserialize)]
+pub struct MatrixRoom {
+    /// The homeserver, e.g. `https://matrix.example.org`.
+    pub homeserver: Url,
+
+    /// The access token of an account that has joined the room.
+    pub access_token: String,
+
+    /// The id of the room, e.g. `!abcdef:example.org`.
+    pub room_id: String,
+}
+
+/// A Telegram chat, to which a bot posts the reports of all projects.
+///
+/// Confidentiality: secret.
+#[derive(Deserialize)]
+pub struct TelegramChat {
+    /// The token of the bot, as given by @BotFather.
+    pub bot_token: String,
+
+    /// The chat, which the bot must have joined.
+    pub chat_id: TelegramChatId,
+}
+
+/// The id of a Telegram chat, e.g. `-1001234567890`, or the username of a channel, e.g.
+/// `@qastor_reports`.
+#[derive(Deserialize, Serialize, Display, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum TelegramChatId {
+    Id(i64),
+    Username(String),
+}
+
+/// How we talk to an SMTP server.
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    /// Upgrade the connection with STARTTLS, by default on port 587.
+    #[default]
+    Starttls,
+
+    /// TLS from the start, by default on port 465.
+    Tls,
+
+    /// No encryption, by default on port 25, e.g. for a relay on localhost.
+    None,
+}
+
+/// An SMTP server, to email reports to the recipients of each project.
+///
+/// Confidentiality: secret.
+#[derive(Deserialize)]
+pub struct Smtp {
+    /// The host of the server, e.g. `smtp.example.org`.
+    pub host: String,
+
+    /// If unspecified, the default port of `security`.
+    #[serde(default)]
+    pub port: Option<u16>,
+
+    #[serde(default)]
+    pub security: SmtpSecurity,
+
+    #[serde(default)]
+    pub username: Option<String>,
+
+    #[serde(default)]
+    pub password: Option<String>,
+
+    /// The sender, e.g. `Qastor <qastor@example.org>`.
+    pub from: String,
+}
+
+/// A Slack user group whose members follow those of a GitHub team, see `qastor
+/// sync-usergroups`.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct UsergroupSync {
+    /// The GitHub team, as `org/slug`, e.g. `pasqal-io/sdk-reviewers`.
+    pub team: String,
+
+    /// The id of the Slack user group, e.g. `S0123456789`.
+    pub usergroup: String,
+}
+impl UsergroupSync {
+    /// The organization and the slug of the team, if `team` is well-formed.
+    pub fn team(&self) -> Option<(&str, &str)> {
+        self.team
+            .split_once('/')
+            .filter(|(org, slug)| org.is_empty().not() && slug.is_empty().not())
+    }
+}
+
+/// A Slack channel per project, to which the Slack bot posts the reports of the project.
+#[derive(Deserialize, Clone, Debug)]
+pub struct ProjectChannels {
+    /// The name of the channel of each project, where `{owner}` and `{repo}` are replaced
+    /// with those of the project, e.g. `reviews-{repo}`.
+    pub name: String,
+
+    /// Whether we create the channel if it doesn't exist.
+    #[serde(default)]
+    pub create: bool,
+
+    /// The id of a user group (e.g. `S0123456789`) to invite to the channels we create.
+    #[serde(default)]
+    pub invite_usergroup: Option<String>,
+
+    /// Whether we keep a pinned message with the backlog of the project in its channel,
+    /// updated on every run, in addition to the reports.
+    #[serde(default)]
+    pub pinned_summary: bool,
+}
+impl ProjectChannels {
+    /// The name of the channel of a project.
+    pub fn name_for(&self, project: &Project) -> String {
+        crate::slack::channel_name(
+            &self
+                .name
+                .replace("{owner}", &project.owner)
+                .replace("{repo}", project.repo.as_ref()),
+        )
+    }
+}
+
 /// All the secrets we rely upon.
 ///
 /// Typically an environment variable QASTOR_SECRETS, containing a JSON string.
 #[derive(Deserialize)]
 pub struct Secrets {
+    /// A hook used to notify the administrators of qastor, e.g. when a project
+    /// cannot be monitored anymore.
+    #[serde(default)]
+    pub admin_hook: Option<SlackHook>,
+
+    /// Hooks by channel name, for projects that declare their channel (or owning team)
+    /// in a catalog file.
+    #[serde(default)]
+    pub channels: HashMap<String, Vec<Hook>>,
+
+    /// A GitHub token, to monitor private repositories and get higher rate limits.
+    ///
+    /// Also read from env `QASTOR_GITHUB_TOKEN` or `GITHUB_TOKEN`.
+    #[serde(default)]
+    pub github_token: Option<String>,
+
+    /// A GitHub App installed on the owners of the projects. Takes precedence over
+    /// `github_token` for the owners on which it's installed.
+    #[serde(default)]
+    pub github_app: Option<GitHubApp>,
+
+    /// If specified, where to maintain the status board.
+    #[serde(default)]
+    pub status_board: Option<StatusBoard>,
+
+    /// The secret with which GitHub signs the webhooks received by `qastor serve --listen`.
+    /// Webhooks are rejected without it.
+    #[serde(default)]
+    pub webhook_secret: Option<String>,
+
+    /// If specified, the Slack bot posting to the channels of projects (see
+    /// `project_channels` in the config) and to those of hooks with a `channel`.
+    #[serde(default)]
+    pub slack_bot: Option<SlackBot>,
+
+    /// If specified, a Matrix room that receives the reports of all projects, except
+    /// for items routed to an alias.
+    #[serde(default)]
+    pub matrix: Option<MatrixRoom>,
+
+    /// If specified, a Telegram chat that receives the reports of all projects, except
+    /// for items routed to an alias.
+    #[serde(default)]
+    pub telegram: Option<TelegramChat>,
+
+    /// If specified, the SMTP server through which we email reports to the recipients
+    /// of each project, see `email` in the config.
+    #[serde(default)]
+    pub smtp: Option<Smtp>,
+
+    /// A hook that receives all the reports after the config has changed, until the new
+    /// config is promoted.
+    #[serde(default)]
+    pub preview_hook: Option<Hook>,
+
     #[serde(flatten)]
-    pub repo_to_hook: HashMap<Url, Vec<SlackHook>>,
+    pub repo_to_hook: HashMap<Url, Vec<Hook>>,
+}
+impl Secrets {
+    /// Load secrets from the environment.
+    ///
+    /// Source 1: big variable `QASTOR_SECRETS`.
+    /// Source 2: any variable `QASTOR_HOOK.*` can contain a mapping (and `QASTOR_ADMIN_HOOK`
+    /// the hook for administrators).
+    /// Source 3: `QASTOR_GITHUB_TOKEN` or, failing that, `GITHUB_TOKEN`.
+    pub fn from_env() -> Result<Self, anyhow::Error> {
+        let env_secrets = std::env::var("QASTOR_SECRETS").unwrap_or_else(|_| "{}".to_string());
+        let mut secrets: Secrets = serde_json::from_str(&env_secrets)
+            .map_err(|err| Error::Config(err.to_string()))
+            .context("Invalid env QASTOR_SECRETS")?;
+        for (key, value) in std::env::vars() {
+            if key == "QASTOR_ADMIN_HOOK" {
+                if value.is_empty() {
+                    // GitHub CI sets missing secrets to "".
+                    continue;
+                }
+                let hook = Url::parse(&value)
+                    .map_err(|err| Error::Config(err.to_string()))
+                    .with_context(|| format!("Invalid env variable {key}:{value}"))?;
+                secrets.admin_hook = Some(SlackHook::from(hook));
+            } else if key.starts_with("QASTOR_HOOK") {
+                let project_to_hook = ProjectToHook::from_env_var(&value)
+                    .map_err(|err| Error::Config(format!("{err:#}")))
+                    .with_context(|| format!("Invalid env variable {key}:{value}"))?;
+                secrets
+                    .repo_to_hook
+                    .entry(project_to_hook.project)
+                    .or_default()
+                    .push(Hook::from(project_to_hook.hook));
+            }
+        }
+        secrets.attach_bot()?;
+        if secrets.github_token.is_none() {
+            // GitHub CI sets missing secrets to "".
+            secrets.github_token = ["QASTOR_GITHUB_TOKEN", "GITHUB_TOKEN"]
+                .into_iter()
+                .filter_map(|key| std::env::var(key).ok())
+                .find(|token| token.is_empty().not());
+        }
+        Ok(secrets)
+    }
+
+    /// Let the hooks that target a channel post as `slack_bot`, which they require.
+    pub fn attach_bot(&mut self) -> Result<(), anyhow::Error> {
+        let hooks = self
+            .repo_to_hook
+            .values_mut()
+            .chain(self.channels.values_mut())
+            .flatten()
+            .chain(self.preview_hook.as_mut());
+        for hook in hooks {
+            let HookTarget::Bot { ref mut token, .. } = hook.target else {
+                continue;
+            };
+            let Some(ref bot) = self.slack_bot else {
+                return Err(Error::Config(
+                    "hooks with a `channel` require a `slack_bot` in the secrets".to_string(),
+                )
+                .into());
+            };
+            token.clone_from(&bot.token);
+        }
+        Ok(())
+    }
+
+    /// Check that the secrets fit the config, e.g. that each project has somewhere to
+    /// post and that routes target existing aliases.
+    ///
+    /// Returns a description of each problem, if any.
+    pub fn problems(&self, config: &Config) -> Vec<String> {
+        let mut problems = vec![];
+        if self.smtp.is_none()
+            && config
+                .projects
+                .iter()
+                .any(|project| project.email.is_empty().not())
+        {
+            problems.push("`email` requires `smtp` in the secrets".to_string());
+        }
+        if config.project_channels.is_some() && self.slack_bot.is_none() {
+            problems.push("`project_channels` requires a `slack_bot` in the secrets".to_string());
+        }
+        if let Some(ref hours) = config.business_hours {
+            if hours.start >= hours.end {
+                problems.push("`business_hours` must start before they end".to_string());
+            }
+        }
+        if let Some(ref update) = config.self_update {
+            if update.repo().is_none() {
+                problems.push(format!(
+                    "Repository {} of `self_update` is not `owner/repo`",
+                    update.repo
+                ));
+            }
+            if self.admin_hook.is_none() {
+                problems.push("`self_update` requires an `admin_hook` in the secrets".to_string());
+            }
+        }
+        if config.force_push_dms && self.slack_bot.is_none() {
+            problems.push("`force_push_dms` requires a `slack_bot` in the secrets".to_string());
+        }
+        if config.usergroups.is_empty().not() && self.slack_bot.is_none() {
+            problems.push("`usergroups` requires a `slack_bot` in the secrets".to_string());
+        }
+        for sync in &config.usergroups {
+            if sync.team().is_none() {
+                problems.push(format!(
+                    "Team {} of `usergroups` is not `org/slug`",
+                    sync.team
+                ));
+            }
+        }
+        for (locale, messages) in &config.messages {
+            for (key, message) in messages.iter().sorted() {
+                if i18n::is_known(key).not() {
+                    problems.push(format!(
+                        "Message {key} of `messages` ({locale:?}) is unknown"
+                    ));
+                } else if i18n::is_valid(key, message).not() {
+                    problems.push(format!(
+                        "Message {key} of `messages` ({locale:?}) is not a valid format of dates"
+                    ));
+                }
+            }
+        }
+        // Channels may receive the reports of any project, through the catalog.
+        let shared = self.matrix.is_some()
+            || self.telegram.is_some()
+            || (config.project_channels.is_some() && self.slack_bot.is_some())
+            || (config.catalog.is_some() && self.channels.is_empty().not());
+        for project in &config.projects {
+            let hooks = self.repo_to_hook.get(&project.url);
+            if hooks.is_none_or(Vec::is_empty) && shared.not() {
+                problems.push(format!("{}: no hook, reports go nowhere", project.url));
+            }
+            for route in &project.routes {
+                let exists = hooks
+                    .into_iter()
+                    .flatten()
+                    .any(|hook| hook.alias.as_deref() == Some(route.hook.as_str()));
+                if exists.not() {
+                    problems.push(format!(
+                        "{}: no hook with alias {}, target of a route",
+                        project.url, route.hook
+                    ));
+                }
+                if route.title.is_none() && route.fields.is_empty() {
+                    problems.push(format!(
+                        "{}: a route to {} has neither a title nor fields, so every item would take it",
+                        project.url, route.hook
+                    ));
+                }
+            }
+            for escalation in &project.escalations {
+                let exists = hooks
+                    .into_iter()
+                    .flatten()
+                    .any(|hook| hook.alias.as_deref() == Some(escalation.hook.as_str()));
+                if exists.not() {
+                    problems.push(format!(
+                        "{}: no hook with alias {}, target of an escalation",
+                        project.url, escalation.hook
+                    ));
+                }
+            }
+            for (owner, alias) in &project.code_owners {
+                let exists = hooks
+                    .into_iter()
+                    .flatten()
+                    .any(|hook| hook.alias.as_ref() == Some(alias));
+                if exists.not() {
+                    problems.push(format!(
+                        "{}: no hook with alias {alias}, target of code owner {owner}",
+                        project.url
+                    ));
+                }
+            }
+            for (label, alias) in &project.label_routes {
+                let exists = hooks
+                    .into_iter()
+                    .flatten()
+                    .any(|hook| hook.alias.as_ref() == Some(alias));
+                if exists.not() {
+                    problems.push(format!(
+                        "{}: no hook with alias {alias}, target of label {label}",
+                        project.url
+                    ));
+                }
+            }
+            let targets = [
+                (
+                    project
+                        .security
+                        .as_ref()
+                        .and_then(|security| security.hook.as_ref()),
+                    "security alerts",
+                ),
+                (
+                    project.stars.as_ref().and_then(|stars| stars.hook.as_ref()),
+                    "star milestones",
+                ),
+            ];
+            for (alias, news) in targets {
+                let Some(alias) = alias else {
+                    continue;
+                };
+                let exists = hooks
+                    .into_iter()
+                    .flatten()
+                    .any(|hook| hook.alias.as_ref() == Some(alias));
+                if exists.not() {
+                    problems.push(format!(
+                        "{}: no hook with alias {alias}, target of {news}",
+                        project.url
+                    ));
+                }
+            }
+            for alias in config
+                .sla
+                .iter()
+                .filter_map(|policy| policy.escalate_to.as_ref())
+                .unique()
+            {
+                let exists = hooks
+                    .into_iter()
+                    .flatten()
+                    .any(|hook| hook.alias.as_ref() == Some(alias));
+                if exists.not() {
+                    problems.push(format!(
+                        "{}: no hook with alias {alias}, to which SLA breaches are escalated",
+                        project.url
+                    ));
+                }
+            }
+        }
+        for url in self.repo_to_hook.keys() {
+            if config.projects.iter().all(|project| &project.url != url) {
+                problems.push(format!(
+                    "{url}: hooks for a project missing from the config"
+                ));
+            }
+        }
+        problems.sort();
+        problems
+    }
 }
 
-/// Configuration of a single project.
-pub struct Project {
-    /// Full url for the project. Used for display only.
-    pub url: Url,
+/// How we report failing workflows, see `Config::ci`.
+#[derive(Deserialize, Default, Clone, Debug, PartialEq, Eq)]
+pub struct CiAlerts {
+    /// If `true`, only say when a workflow that succeeded last time fails, rather than
+    /// whenever it fails.
+    #[serde(default)]
+    pub only_transitions: bool,
+}
 
-    /// Owner (user or org) of the repository. Used for fetching issues.
-    pub owner: String,
+/// How we suggest reviewers for the PRs that nobody was asked to review, see
+/// `Config::suggest_reviewers`.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ReviewerSuggestions {
+    /// How many reviewers we suggest for each PR.
+    #[serde(default = "ReviewerSuggestions::default_max")]
+    pub max: usize,
 
-    /// Name (user or org) of the repository. Used for fetching issues.
-    pub repo: RepoName,
+    /// How many of the files touched by each PR we look at, the most changed first.
+    #[serde(default = "ReviewerSuggestions::default_files")]
+    pub files: usize,
+
+    /// How many of the latest commits of each file we look at.
+    #[serde(default = "ReviewerSuggestions::default_commits")]
+    pub commits: u8,
+}
+impl ReviewerSuggestions {
+    fn default_max() -> usize {
+        2
+    }
+    fn default_files() -> usize {
+        5
+    }
+    fn default_commits() -> u8 {
+        20
+    }
 }
 
-impl<'de> Deserialize<'de> for Project {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        use serde::de::Error;
-        #[derive(Deserialize)]
-        struct Payload {
-            url: Url,
+/// Where we look for new releases of qastor.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SelfUpdate {
+    /// The repository of qastor, as `owner/repo`.
+    #[serde(default = "SelfUpdate::default_repo")]
+    pub repo: String,
+}
+impl SelfUpdate {
+    fn default_repo() -> String {
+        "pasqal-io/github-bot".to_string()
+    }
+
+    /// The owner and the name of the repository, if `repo` is well-formed.
+    pub fn repo(&self) -> Option<(&str, &str)> {
+        self.repo
+            .split_once('/')
+            .filter(|(owner, repo)| owner.is_empty().not() && repo.is_empty().not())
+    }
+}
+
+/// The working hours of the team, in UTC, so that waiting times and service levels count
+/// working time only.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct BusinessHours {
+    /// When the working day starts, e.g. `09:00`.
+    pub start: NaiveTime,
+
+    /// When the working day ends, e.g. `17:00`.
+    pub end: NaiveTime,
+
+    /// Days off, besides weekends, e.g. `2024-12-25`.
+    pub holidays: Vec<NaiveDate>,
+}
+impl Default for BusinessHours {
+    fn default() -> Self {
+        BusinessHours {
+            start: NaiveTime::from_hms_opt(9, 0, 0).expect("a valid time"),
+            end: NaiveTime::from_hms_opt(17, 0, 0).expect("a valid time"),
+            holidays: vec![],
         }
-        let payload: Payload = Payload::deserialize(deserializer)?;
-        let Some(mut segments) = payload.url.path_segments() else {
-            return Err(D::Error::invalid_value(
-                Unexpected::Str(payload.url.as_str()),
-                &"a url https://github.com/<owner>/<project> (missing path)",
-            ));
-        };
-        let Some(owner) = segments.next() else {
-            return Err(D::Error::invalid_value(
-                Unexpected::Str(payload.url.as_str()),
-                &"a url https://github.com/<owner>/<project> (missing owner)",
-            ));
-        };
-        let Some(project) = segments.next() else {
-            return Err(D::Error::invalid_value(
-                Unexpected::Str(payload.url.as_str()),
-                &"a url https://github.com/<owner>/<project> (missing project)",
-            ));
-        };
-        let owner = owner.to_string();
-        let repo = RepoName(project.to_string());
-        Ok(Project {
-            url: payload.url,
-            owner,
-            repo,
-        })
+    }
+}
+impl BusinessHours {
+    /// The length of a working day.
+    pub fn day(&self) -> chrono::Duration {
+        self.end - self.start
     }
 }
 
-/// The configuration for qastor.
-#[derive(Deserialize)]
-pub struct Config {
-    /// The projects to monitor.
-    #[serde(default)]
-    pub projects: Vec<Project>,
+/// When Slack bots deliver what they post, whenever qastor runs, e.g. every morning at 09:00
+/// in Paris, with Slack's scheduled messages.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ScheduledDelivery {
+    /// The local time of delivery, e.g. `09:00`.
+    pub time: NaiveTime,
 
-    /// How often we're expecting to monitor the projects, as a number followed by a unit d/h/m/s.
-    ///
-    /// This variable only affects how far back we're looking in time for changes in issues.
+    /// The offset of the local time from UTC, e.g. `+02:00`. UTC by default.
     #[serde(
-        deserialize_with = "Config::deserialize_update_frequency",
-        default = "Config::default_update_frequency"
+        default = "ScheduledDelivery::default_utc_offset",
+        deserialize_with = "ScheduledDelivery::deserialize_utc_offset"
     )]
-    pub update_frequency: chrono::Duration,
+    pub utc_offset: FixedOffset,
 }
-impl Config {
-    /// Custom deserialization for update frequency.
-    ///
-    /// We don't want to specify the duration in seconds, as that's annoying, so implementing
-    /// a shorthand notation.
-    fn deserialize_update_frequency<'de, D>(deserializer: D) -> Result<chrono::Duration, D::Error>
+impl ScheduledDelivery {
+    fn default_utc_offset() -> FixedOffset {
+        FixedOffset::east_opt(0).expect("a valid offset")
+    }
+
+    fn deserialize_utc_offset<'de, D>(deserializer: D) -> Result<FixedOffset, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
         use serde::de::Error;
         let source = String::deserialize(deserializer)?;
-        let regex = lazy_regex!("([[:digit:]]+) *([hmsd])");
-        let found = regex.captures(&source).ok_or_else(|| {
-            D::Error::invalid_value(
-                Unexpected::Str(&source),
-                &"numbers followed by a unit d/h/m/s",
-            )
-        })?;
-        let digits = found.get(1).expect("we should have digits");
-        let unit = found.get(2).expect("we should have a unit");
-        let digits: i64 = digits
-            .as_str()
+        source
             .parse()
-            .map_err(|_| D::Error::invalid_value(Unexpected::Str(digits.as_str()), &"numbers"))?;
-        let unit: char = unit.as_str().parse().map_err(|_| {
-            D::Error::invalid_value(Unexpected::Str(unit.as_str()), &"a unit d/h/m/s")
-        })?;
-        let result = match unit {
-            'd' => chrono::Duration::days(digits),
-            'h' => chrono::Duration::hours(digits),
-            'm' => chrono::Duration::minutes(digits),
-            's' => chrono::Duration::seconds(digits),
-            _ => unreachable!(),
-        };
-        Ok(result)
+            .map_err(|_| D::Error::invalid_value(Unexpected::Str(&source), &"e.g. +02:00"))
     }
 
-    fn default_update_frequency() -> chrono::Duration {
-        chrono::Duration::hours(2)
+    /// The next delivery after `now`: today at `time` if that's still ahead, tomorrow
+    /// otherwise.
+    pub fn next(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        // Slack rejects times in the past, leave some time to post.
+        const MARGIN: chrono::Duration = chrono::Duration::minutes(1);
+        let local = now.with_timezone(&self.utc_offset).date_naive();
+        [local, local + chrono::Days::new(1)]
+            .into_iter()
+            .filter_map(|day| {
+                day.and_time(self.time)
+                    .and_local_timezone(self.utc_offset)
+                    .single()
+            })
+            .map(|at| at.with_timezone(&Utc))
+            .find(|at| *at > now + MARGIN)
+            .expect("tomorrow is ahead")
     }
 }
 
-#[cfg(test)]
-mod test {
-    use crate::{config::Config, config::RepoName};
+/// A service level for the issues with a label, e.g. customer-reported bugs: a maintainer
+/// must comment within some business days of the issue being opened.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SlaPolicy {
+    /// The label of the issues, e.g. `customer`.
+    pub label: String,
 
-    use super::ProjectToHook;
+    /// How many business days (Monday to Friday, in UTC, except the holidays of
+    /// `business_hours`) maintainers have to comment.
+    pub first_response_business_days: u32,
 
-    /// Can a typical config be parsed?
-    #[test]
-    fn test_config_parse() {
-        let source = r#"
-            projects:
-                - url: "https://github.com/owner1/project1"
-                - url: "https://github.com/owner2/project2"
-            update_frequency: 15m
-        "#;
-        let config: Config = serde_yaml::from_str(source).unwrap();
-        assert_eq!(config.update_frequency, chrono::Duration::minutes(15));
-        assert_eq!(config.projects.len(), 2);
-        assert_eq!(config.projects[0].owner, "owner1");
-        assert_eq!(config.projects[0].repo, RepoName::from("project1"));
-        assert_eq!(config.projects[1].owner, "owner2");
+    /// The alias of the hooks to which breaches are escalated, e.g. `support-leads`. If
+    /// unspecified, or if a project has no such hook, breaches go to its usual hooks.
+    #[serde(default)]
+    pub escalate_to: Option<String>,
+}
+
+/// When issues are stuck in triage, see `Config::triage`.
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Triage {
+    /// How many times we report an issue before it's stuck, e.g. `3`.
+    pub after: u32,
+
+    /// Labels that don't count as triage, e.g. `needs-triage`, applied by issue forms.
+    #[serde(default)]
+    pub ignore_labels: Vec<String>,
+}
+impl Triage {
+    /// Whether an issue with these labels has been triaged, ignoring case.
+    pub fn labelled<'a>(&self, mut labels: impl Iterator<Item = &'a str>) -> bool {
+        labels.any(|label| {
+            self.ignore_labels
+                .iter()
+                .any(|ignored| ignored.eq_ignore_ascii_case(label))
+                .not()
+        })
+    }
+}
+
+/// Send the items whose title matches a regex, e.g. `^\[sdk\]` or `^docs:`, and/or whose
+/// issue form fields match regexes, e.g. `Component: ^SDK$`, to the hooks with a given
+/// alias.
+#[derive(Deserialize, Clone, Debug)]
+pub struct Route {
+    #[serde(default, deserialize_with = "deserialize_optional_regex")]
+    pub title: Option<Regex>,
+
+    /// Regexes that the answers of issue forms must match, by label of the field
+    /// (ignoring case). Items without such fields, e.g. PRs, never match.
+    #[serde(default, deserialize_with = "deserialize_regex_map")]
+    pub fields: HashMap<String, Regex>,
+
+    /// The alias of the target hooks.
+    pub hook: String,
+}
+impl Route {
+    /// Whether an item with this title and these issue form fields takes this route.
+    pub fn matches(&self, title: &str, fields: &[(String, String)]) -> bool {
+        self.title
+            .as_ref()
+            .is_none_or(|regex| regex.is_match(title))
+            && self.fields.iter().all(|(label, regex)| {
+                fields.iter().any(|(name, answer)| {
+                    name.eq_ignore_ascii_case(label) && regex.is_match(answer)
+                })
+            })
+    }
+}
+
+fn deserialize_regex<'de, D>(deserializer: D) -> Result<Regex, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+    let source = String::deserialize(deserializer)?;
+    Regex::new(&source).map_err(|err| D::Error::custom(format!("invalid regex {source}: {err}")))
+}
+
+fn deserialize_optional_regex<'de, D>(deserializer: D) -> Result<Option<Regex>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    deserialize_regex(deserializer).map(Some)
+}
+
+fn deserialize_regex_map<'de, D>(deserializer: D) -> Result<HashMap<String, Regex>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+    HashMap::<String, String>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(key, source)| {
+            let regex = Regex::new(&source)
+                .map_err(|err| D::Error::custom(format!("invalid regex {source}: {err}")))?;
+            Ok((key, regex))
+        })
+        .collect()
+}
+
+/// A section that PR descriptions must contain, e.g. `## Testing`.
+#[derive(Deserialize, Clone, Debug)]
+pub struct RequiredSection {
+    /// The name displayed when the section is missing.
+    pub name: String,
+
+    /// A regex matching the section, e.g. `(?m)^#+ *Testing`.
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub pattern: Regex,
+}
+
+/// Checks of the descriptions of PRs, flagged in reports so that authors can fix
+/// them before a reviewer has to ask for context.
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct DescriptionCheck {
+    #[serde(default)]
+    pub required_sections: Vec<RequiredSection>,
+}
+impl DescriptionCheck {
+    /// The names of the required sections missing from a description, in order.
+    pub fn missing_sections<'a>(&'a self, body: &str) -> Vec<&'a str> {
+        self.required_sections
+            .iter()
+            .filter(|section| section.pattern.is_match(body).not())
+            .map(|section| section.name.as_str())
+            .collect()
+    }
+}
+
+/// Configuration of a single project.
+#[derive(Clone)]
+pub struct Project {
+    /// Full url for the project. Used for display only.
+    pub url: Url,
+
+    /// Owner (user or org) of the repository. Used for fetching issues.
+    pub owner: String,
+
+    /// Name (user or org) of the repository. Used for fetching issues.
+    pub repo: RepoName,
+
+    /// If specified, the base url of the API of a GitHub Enterprise Server, e.g.
+    /// `https://ghe-api.example.com/api/v3`, rather than that of github.com.
+    pub api_url: Option<Url>,
+
+    /// If specified, the host of the links to the project, e.g. `https://ghe.example.com`,
+    /// when browsers don't reach it at the host of `url`.
+    pub web_url: Option<Url>,
+
+    /// Routes for items, by title. The first matching route wins. Items that match
+    /// no route go to the hooks that are not the target of any route.
+    pub routes: Vec<Route>,
+
+    /// If specified, overrides `per_page` of the config for this project.
+    pub per_page: Option<u8>,
+
+    /// Overrides `item_caps` of the config for this project.
+    pub item_caps: HashMap<SectionKind, usize>,
+
+    /// If specified, overrides `max_items` of the config for this project.
+    pub max_items: Option<usize>,
+
+    /// If specified, overrides `update_frequency` of the config for this project.
+    pub update_frequency: Option<chrono::Duration>,
+
+    /// Who receives the pending PRs and updated issues by email, e.g. stakeholders who
+    /// don't use chat. Requires `smtp` in the secrets.
+    pub email: Vec<String>,
+
+    /// If non-empty, only issues and PRs with at least one of these labels are reported.
+    pub include_labels: Vec<String>,
+
+    /// Issues and PRs with any of these labels are never reported, e.g. `wontfix`.
+    pub exclude_labels: Vec<String>,
+
+    /// What to do with draft PRs that have requested reviewers.
+    pub drafts: Drafts,
+
+    /// Tiers of escalation of the PRs waiting for reviews. The tier with the longest
+    /// `after` that a PR has waited wins.
+    pub escalations: Vec<Escalation>,
+
+    /// Don't check the project, e.g. while it's too noisy, without losing its settings.
+    /// Overridden by `qastor disable` and `qastor enable`.
+    pub disabled: bool,
+
+    /// The fields of issue forms to display along with issues, by label, e.g. `Severity`.
+    pub issue_fields: Vec<String>,
+
+    /// Announce the releases and tags published since the last run.
+    pub releases: bool,
+
+    /// If specified, list the open Dependabot alerts of the project, and optionally those
+    /// of code scanning and secret scanning.
+    pub security: Option<SecurityAlerts>,
+
+    /// If specified, celebrate star and fork milestones, e.g. of open-source projects.
+    pub stars: Option<StarAlerts>,
+
+    /// If specified, the day of the week (e.g. `friday`) on which we post the progress of
+    /// each open milestone.
+    pub milestone_report: Option<chrono::Weekday>,
+
+    /// The aliases of the hooks of code owners, by owner in the CODEOWNERS file of the
+    /// project, e.g. `@org/frontend: frontend`. PRs waiting for reviews that match no route
+    /// go to the hooks of the owners of the files they touch, if any.
+    pub code_owners: HashMap<String, String>,
+
+    /// The aliases of the hooks of some labels, in lowercase, e.g. `p0: oncall` or
+    /// `area:runtime: runtime`. Issues and PRs with these labels (ignoring case) go to these
+    /// hooks rather than to the hooks that are not the target of any route, besides the one
+    /// of the route they match, if any, each hook receiving them once.
+    pub label_routes: BTreeMap<String, String>,
+
+    /// If specified, the directory of the templates of this project, laid out like
+    /// `Config::templates`, whose templates take precedence over those of the config.
+    pub templates: Option<std::path::PathBuf>,
+
+    /// If specified, the language of the reports about this project, instead of `locale`.
+    pub locale: Option<Locale>,
+
+    /// Overrides of the titles and emojis of sections, by kind, e.g. to call updated issues
+    /// "🧾 Fresh tickets".
+    pub sections: HashMap<SectionKind, SectionStyle>,
+}
+
+/// How a kind of section looks, see `Project::sections`, without going as far as templates.
+#[derive(Deserialize, Default, Clone, Debug, PartialEq, Eq)]
+pub struct SectionStyle {
+    /// If specified, replaces the title, with the placeholders of templates except `{rows}`,
+    /// e.g. `{title}` (the built-in title) or `{repo}`.
+    #[serde(default)]
+    pub title: Option<String>,
+
+    /// If specified, the emoji in front of the title, e.g. `🧾`.
+    #[serde(default)]
+    pub emoji: Option<String>,
+}
+
+/// Which security alerts of a project we list, and where we send them.
+#[derive(Deserialize, Default, Clone, Debug)]
+pub struct SecurityAlerts {
+    /// If specified, the alias of the hooks that receive the alerts (and only them), e.g.
+    /// those of the security team.
+    #[serde(default)]
+    pub hook: Option<String>,
+
+    /// Only list alerts at least this severe, e.g. `high`.
+    #[serde(default)]
+    pub min_severity: AlertSeverity,
+
+    /// Also list the open alerts of code scanning, e.g. CodeQL.
+    #[serde(default)]
+    pub code_scanning: bool,
+
+    /// Also list the open alerts of secret scanning, which are always critical.
+    #[serde(default)]
+    pub secret_scanning: bool,
+}
+
+/// When we celebrate the popularity of a project, and where.
+#[derive(Deserialize, Clone, Debug)]
+pub struct StarAlerts {
+    /// The counts of stars (and forks) worth celebrating once crossed.
+    #[serde(default = "StarAlerts::default_milestones")]
+    pub milestones: Vec<u64>,
+
+    /// If specified, also say when the project gains at least this many stars within the window
+    /// of the section (see `windows`), by default between two runs.
+    #[serde(default)]
+    pub spike: Option<u64>,
+
+    /// If specified, the alias of the hooks that receive the news (and only them), e.g. the
+    /// community hook.
+    #[serde(default)]
+    pub hook: Option<String>,
+}
+impl StarAlerts {
+    fn default_milestones() -> Vec<u64> {
+        vec![100, 500, 1_000, 5_000, 10_000, 50_000, 100_000]
+    }
+}
+
+/// Send the PRs waiting for reviews for too long to the hooks with a given alias, e.g.
+/// those of the team leads.
+#[derive(Deserialize, Clone, Debug)]
+pub struct Escalation {
+    /// How long PRs wait before being escalated, e.g. `5d`, in working time with
+    /// `business_hours`.
+    #[serde(deserialize_with = "Config::deserialize_duration")]
+    pub after: chrono::Duration,
+
+    /// The alias of the target hooks.
+    pub hook: String,
+
+    /// The GitHub logins to mention, e.g. the team lead, see `user_map`.
+    #[serde(default)]
+    pub mention: Vec<String>,
+
+    /// The severity of the sections of escalated PRs, by default `warning`, so that they also
+    /// reach the hooks that only subscribe to warnings (or critical sections).
+    #[serde(default = "Escalation::default_severity")]
+    pub severity: Severity,
+}
+impl Escalation {
+    fn default_severity() -> Severity {
+        Severity::Warning
+    }
+}
+
+/// What to do with draft PRs that have requested reviewers.
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Drafts {
+    /// Report them along with other PRs.
+    #[default]
+    Include,
+
+    /// Never report them.
+    Ignore,
+
+    /// Report them in a section of their own, without mentioning reviewers.
+    Separate,
+}
+
+impl<'de> Deserialize<'de> for Project {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+        #[derive(Deserialize)]
+        struct Payload {
+            url: Url,
+            #[serde(default)]
+            api_url: Option<Url>,
+            #[serde(default)]
+            web_url: Option<Url>,
+            #[serde(default)]
+            routes: Vec<Route>,
+            #[serde(default)]
+            per_page: Option<u8>,
+            #[serde(default)]
+            item_caps: HashMap<SectionKind, usize>,
+            #[serde(default)]
+            max_items: Option<usize>,
+            #[serde(default, deserialize_with = "Config::deserialize_optional_duration")]
+            update_frequency: Option<chrono::Duration>,
+            #[serde(default)]
+            email: Vec<String>,
+            #[serde(default)]
+            include_labels: Vec<String>,
+            #[serde(default)]
+            exclude_labels: Vec<String>,
+            #[serde(default)]
+            drafts: Drafts,
+            #[serde(default)]
+            escalations: Vec<Escalation>,
+            #[serde(default)]
+            disabled: bool,
+            #[serde(default)]
+            issue_fields: Vec<String>,
+            #[serde(default)]
+            releases: bool,
+            #[serde(default)]
+            security: Option<SecurityAlerts>,
+            #[serde(default)]
+            stars: Option<StarAlerts>,
+            #[serde(default)]
+            milestone_report: Option<chrono::Weekday>,
+            #[serde(default)]
+            code_owners: HashMap<String, String>,
+            #[serde(default)]
+            label_routes: BTreeMap<String, String>,
+            #[serde(default)]
+            templates: Option<std::path::PathBuf>,
+            #[serde(default)]
+            locale: Option<Locale>,
+            #[serde(default)]
+            sections: HashMap<SectionKind, SectionStyle>,
+        }
+        let payload: Payload = Payload::deserialize(deserializer)?;
+        let (owner, repo) = Project::parse_url(&payload.url)
+            .map_err(|err| D::Error::invalid_value(Unexpected::Str(payload.url.as_str()), &err))?;
+        Ok(Project {
+            url: payload.url,
+            owner,
+            repo,
+            api_url: payload.api_url,
+            web_url: payload.web_url,
+            routes: payload.routes,
+            per_page: payload.per_page,
+            item_caps: payload.item_caps,
+            max_items: payload.max_items,
+            update_frequency: payload.update_frequency,
+            email: payload.email,
+            include_labels: payload.include_labels,
+            exclude_labels: payload.exclude_labels,
+            drafts: payload.drafts,
+            escalations: payload.escalations,
+            disabled: payload.disabled,
+            issue_fields: payload.issue_fields,
+            releases: payload.releases,
+            security: payload.security,
+            stars: payload.stars,
+            milestone_report: payload.milestone_report,
+            code_owners: payload.code_owners,
+            label_routes: payload
+                .label_routes
+                .into_iter()
+                .map(|(label, hook)| (label.to_lowercase(), hook))
+                .collect(),
+            templates: payload.templates,
+            locale: payload.locale,
+            sections: payload.sections,
+        })
+    }
+}
+
+impl Project {
+    /// Whether an issue or PR with these labels passes `include_labels` and
+    /// `exclude_labels`, ignoring case.
+    pub fn shows<'a>(&self, labels: impl IntoIterator<Item = &'a str>) -> bool {
+        let labels = labels.into_iter().collect_vec();
+        let has = |wanted: &String| {
+            labels
+                .iter()
+                .any(|label| label.eq_ignore_ascii_case(wanted))
+        };
+        (self.include_labels.is_empty() || self.include_labels.iter().any(has))
+            && self.exclude_labels.iter().any(has).not()
+    }
+
+    /// Extract the owner and repository name from a url https://github.com/<owner>/<project>.
+    fn parse_url(url: &Url) -> Result<(String, RepoName), &'static str> {
+        let Some(mut segments) = url.path_segments() else {
+            return Err("a url https://github.com/<owner>/<project> (missing path)");
+        };
+        let Some(owner) = segments.next() else {
+            return Err("a url https://github.com/<owner>/<project> (missing owner)");
+        };
+        let Some(project) = segments.next() else {
+            return Err("a url https://github.com/<owner>/<project> (missing project)");
+        };
+        Ok((owner.to_string(), RepoName(project.to_string())))
+    }
+
+    /// The page of the project in browsers, at `web_url` if specified.
+    pub fn html_url(&self) -> Url {
+        let Some(ref web_url) = self.web_url else {
+            return self.url.clone();
+        };
+        let mut url = web_url.clone();
+        let path = format!(
+            "{}/{}/{}",
+            web_url.path().trim_end_matches('/'),
+            self.owner,
+            self.repo
+        );
+        url.set_path(&path);
+        url
+    }
+
+    /// The same project, once it has moved to a new url (e.g. renamed or transferred).
+    pub fn moved_to(&self, url: Url) -> Result<Project, anyhow::Error> {
+        let (owner, repo) = Project::parse_url(&url).map_err(|err| anyhow!("expected {err}"))?;
+        Ok(Project {
+            url,
+            owner,
+            repo,
+            ..self.clone()
+        })
+    }
+
+    /// The alias of the hooks that should receive an item with this title, if it
+    /// matches a route.
+    pub fn route(&self, title: &str) -> Option<&str> {
+        self.route_fields(title, &[])
+    }
+
+    /// The alias of the hooks that should receive an issue with this title and these
+    /// issue form fields, if it matches a route.
+    pub fn route_fields(&self, title: &str, fields: &[(String, String)]) -> Option<&str> {
+        self.routes
+            .iter()
+            .find(|route| route.matches(title, fields))
+            .map(|route| route.hook.as_str())
+    }
+
+    /// The aliases of the hooks that should receive an item with this title, these issue
+    /// form fields and these labels: that of the first route it matches, then those of its
+    /// labels (see `label_routes`), without duplicates.
+    pub fn routes_for<'a>(
+        &self,
+        title: &str,
+        fields: &[(String, String)],
+        labels: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<String> {
+        let labelled = labels
+            .into_iter()
+            .filter_map(|label| self.label_routes.get(&label.to_lowercase()))
+            .map(String::as_str);
+        self.route_fields(title, fields)
+            .into_iter()
+            .chain(labelled)
+            .unique()
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// The aliases of the hooks of these code owners (e.g. `@org/frontend`, ignoring case),
+    /// see `code_owners`, without duplicates.
+    pub fn code_owner_hooks<'a>(&self, owners: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+        owners
+            .into_iter()
+            .filter_map(|owner| {
+                self.code_owners
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(owner))
+                    .map(|(_, hook)| hook.clone())
+            })
+            .unique()
+            .collect()
+    }
+
+    /// Whether the hooks with this alias only receive the items routed or escalated to them,
+    /// including the breaches of `sla`.
+    pub fn is_target(&self, alias: &str, sla: &[SlaPolicy]) -> bool {
+        self.routes.iter().any(|route| route.hook == alias)
+            || self
+                .escalations
+                .iter()
+                .any(|escalation| escalation.hook == alias)
+            || self
+                .security
+                .as_ref()
+                .and_then(|security| security.hook.as_deref())
+                == Some(alias)
+            || self.stars.as_ref().and_then(|stars| stars.hook.as_deref()) == Some(alias)
+            || self.code_owners.values().any(|hook| hook == alias)
+            || self.label_routes.values().any(|hook| hook == alias)
+            || sla
+                .iter()
+                .any(|policy| policy.escalate_to.as_deref() == Some(alias))
+    }
+}
+
+/// How we find issues and pull requests.
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FetchMode {
+    /// List issues and pull requests of each repository, two calls per repository.
+    #[default]
+    PerRepository,
+
+    /// Search across all the repositories of each owner at once, a handful of calls
+    /// per owner. Better when monitoring dozens of repositories. Requires authentication.
+    Search,
+
+    /// As `Search` for issues, but fetch the pull requests of 10 repositories per GraphQL
+    /// query rather than searching them. Better for large organizations of which only
+    /// some repositories are monitored. Requires authentication.
+    Batch,
+}
+
+/// How far back a section of a report looks.
+///
+/// In the config, `all`, `since_last_run` or a duration, e.g. `24h`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Window {
+    /// Everything, regardless of age.
+    All,
+
+    /// Everything since the last successful run for the project, or within
+    /// `update_frequency` for the first run.
+    SinceLastRun,
+
+    /// Everything within a duration.
+    Last(chrono::Duration),
+}
+impl Window {
+    /// The start of the window, if any.
+    pub fn since(
+        self,
+        now: DateTime<Utc>,
+        last_run: Option<DateTime<Utc>>,
+        update_frequency: chrono::Duration,
+    ) -> Option<DateTime<Utc>> {
+        match self {
+            Window::All => None,
+            Window::SinceLastRun => Some(last_run.unwrap_or(now - update_frequency)),
+            Window::Last(duration) => Some(now - duration),
+        }
+    }
+}
+impl<'de> Deserialize<'de> for Window {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+        let source = String::deserialize(deserializer)?;
+        match source.as_str() {
+            "all" => Ok(Window::All),
+            "since_last_run" => Ok(Window::SinceLastRun),
+            _ => parse_duration(&source).map(Window::Last).ok_or_else(|| {
+                D::Error::invalid_value(
+                    Unexpected::Str(&source),
+                    &"`all`, `since_last_run` or a number followed by a unit d/h/m/s",
+                )
+            }),
+        }
+    }
+}
+
+/// Parse a duration written as a number followed by a unit d/h/m/s, e.g. `12h`.
+fn parse_duration(source: &str) -> Option<chrono::Duration> {
+    let regex = lazy_regex!("^([[:digit:]]+) *([hmsd])$");
+    let found = regex.captures(source.trim())?;
+    let digits: i64 = found[1].parse().ok()?;
+    match &found[2] {
+        "d" => Some(chrono::Duration::days(digits)),
+        "h" => Some(chrono::Duration::hours(digits)),
+        "m" => Some(chrono::Duration::minutes(digits)),
+        "s" => Some(chrono::Duration::seconds(digits)),
+        _ => None,
+    }
+}
+
+/// FNV-1a, which, unlike `DefaultHasher`, is stable across versions of Rust.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// The configuration for qastor.
+#[derive(Deserialize)]
+pub struct Config {
+    /// The projects to monitor.
+    #[serde(default)]
+    pub projects: Vec<Project>,
+
+    /// How often we're expecting to monitor the projects, as a number followed by a unit d/h/m/s.
+    ///
+    /// Projects checked less than `update_frequency` ago (give or take 10%) are skipped, and,
+    /// by default, this is how far back we're looking in time for changes in issues.
+    #[serde(
+        deserialize_with = "Config::deserialize_update_frequency",
+        default = "Config::default_update_frequency"
+    )]
+    pub update_frequency: chrono::Duration,
+
+    /// Where we store information between two runs.
+    #[serde(default)]
+    pub state: StateConfig,
+
+    /// Emojis displayed in front of issues and PRs carrying a given label, e.g. `bug: 🐞`.
+    ///
+    /// Label names are case-insensitive, as on GitHub.
+    #[serde(default)]
+    pub label_emojis: HashMap<String, String>,
+
+    /// The Slack member ids of GitHub users, by login, e.g. `alice: U0123456789`, so that
+    /// reports mention requested reviewers rather than merely listing their logins.
+    ///
+    /// Logins are case-insensitive, as on GitHub.
+    #[serde(default)]
+    pub user_map: HashMap<String, String>,
+
+    /// If specified, the local time at which `qastor remind-reviewers` delivers reminders
+    /// to each reviewer, e.g. `09:00` in their own time zone (see `user_time_zones`), with
+    /// Slack's scheduled messages. Reminders are sent right away otherwise.
+    #[serde(default)]
+    pub remind_at: Option<NaiveTime>,
+
+    /// The time zones of GitHub users, by login, as named by the IANA database, e.g.
+    /// `alice: Europe/Paris`, see `remind_at`. UTC by default.
+    ///
+    /// Logins are case-insensitive, as on GitHub.
+    #[serde(default, deserialize_with = "Config::deserialize_time_zones")]
+    pub user_time_zones: HashMap<String, Zone>,
+
+    /// The language of reports, e.g. "3 days ago" (`en`) or "il y a 3 jours" (`fr`), unless
+    /// overridden by the `locale` of a project.
+    #[serde(default)]
+    pub locale: Locale,
+
+    /// Overrides of the messages of reports, by locale then key, e.g.
+    /// `fr: { drafts.title: "Brouillons de {link}" }`, see `i18n`.
+    #[serde(default)]
+    pub messages: HashMap<Locale, HashMap<String, String>>,
+
+    /// If `true`, before listing issues and PRs, check whether anything was pushed to or
+    /// updated in the repository since the last run, and skip the project otherwise.
+    ///
+    /// This saves many API calls when monitoring many quiet repositories, at the cost of
+    /// not repeating reminders for these repositories.
+    #[serde(default)]
+    pub skip_unchanged: bool,
+
+    /// If `true`, Slack bots (see `project_channels` and hooks with a `channel`) post
+    /// the first section of the day about each project as usual, then reply in its thread
+    /// for the rest of the day (UTC), rather than filling the channel with near-identical
+    /// reports.
+    #[serde(default)]
+    pub thread_follow_ups: bool,
+
+    /// If `true`, Slack bots edit their latest message about each kind of section of a
+    /// project rather than posting a new one, so that channels show one live report per
+    /// project. Takes precedence over `thread_follow_ups` and `reping_after`.
+    #[serde(default)]
+    pub update_in_place: bool,
+
+    /// If specified, Slack bots (see `project_channels` and hooks with a `channel`)
+    /// schedule the sections of reports for this local time rather than posting them right
+    /// away. Takes precedence over `update_in_place` and `thread_follow_ups`.
+    #[serde(default)]
+    pub deliver_at: Option<ScheduledDelivery>,
+
+    /// How we find issues and pull requests.
+    #[serde(default)]
+    pub fetch: FetchMode,
+
+    /// If specified, warn about open milestones due within this number of days (or overdue)
+    /// that still have open issues.
+    #[serde(default)]
+    pub milestone_alarm_days: Option<u32>,
+
+    /// Labels of interest, e.g. `regression`: whenever they are added to (or removed from)
+    /// an issue, we say so in a dedicated section, regardless of other updates.
+    #[serde(default)]
+    pub label_alerts: Vec<String>,
+
+    /// List the discussions without an answer or a reply from a maintainer, e.g. support
+    /// questions, opened within the window of the section.
+    #[serde(default)]
+    pub discussions: bool,
+
+    /// If specified, say when workflow runs fail on the default branch of projects.
+    #[serde(default)]
+    pub ci: Option<CiAlerts>,
+
+    /// If specified, list the open PRs that nobody was asked to review, suggesting reviewers
+    /// among the recent authors of the files they touch.
+    #[serde(default)]
+    pub suggest_reviewers: Option<ReviewerSuggestions>,
+
+    /// If specified, open PRs without activity for this duration (e.g. `14d`) are listed in a
+    /// section of their own, whether or not they wait for reviews. In working time with
+    /// `business_hours`, where days are working days.
+    #[serde(default, deserialize_with = "Config::deserialize_optional_duration")]
+    pub stale_after: Option<chrono::Duration>,
+
+    /// If specified, the day of the week (e.g. `monday`) on which we post a digest of each
+    /// project, with the ages of its open PRs.
+    #[serde(default)]
+    pub weekly_digest: Option<chrono::Weekday>,
+
+    /// Service levels for the issues with some labels. Open issues that didn't receive a
+    /// first response from a maintainer in time are escalated once.
+    #[serde(default)]
+    pub sla: Vec<SlaPolicy>,
+
+    /// If specified, we follow the issues from one run to the next, and single out those
+    /// that we keep reporting while nobody labels or assigns them.
+    #[serde(default)]
+    pub triage: Option<Triage>,
+
+    /// If specified, how long items have been waiting is measured in working time, and
+    /// service levels skip holidays.
+    #[serde(default)]
+    pub business_hours: Option<BusinessHours>,
+
+    /// How far back each section of the report looks, e.g. `updated_issues: since_last_run`.
+    ///
+    /// By default, updated issues are those updated within `update_frequency` and all
+    /// pending reviews are listed.
+    #[serde(default)]
+    pub windows: HashMap<SectionKind, Window>,
+
+    /// If specified, tell the admin hook whenever a newer release of qastor is out.
+    #[serde(default)]
+    pub self_update: Option<SelfUpdate>,
+
+    /// Issues and PRs opened by these users are never reported, e.g. `renovate[bot]`.
+    #[serde(default)]
+    pub ignore_authors: Vec<String>,
+
+    /// If `true`, issues and PRs opened by any bot (whose login ends with `[bot]`, e.g.
+    /// `dependabot[bot]`) are never reported.
+    #[serde(default)]
+    pub ignore_bots: bool,
+
+    /// If specified, the directory of the templates of the sections of reports, see
+    /// `template::Templates`.
+    #[serde(default)]
+    pub templates: Option<std::path::PathBuf>,
+
+    /// If specified, where we archive every report that we post.
+    #[serde(default)]
+    pub archive: Option<ArchiveConfig>,
+
+    /// If specified, a directory where we keep the messages rendered for each sink, one
+    /// file per run, see `qastor resend`.
+    #[serde(default)]
+    pub replay: Option<std::path::PathBuf>,
+
+    /// If specified, the path of a catalog file in each repository (e.g. `catalog-info.yaml`)
+    /// declaring its owning team, Slack channel and escalation contacts.
+    #[serde(default)]
+    pub catalog: Option<String>,
+
+    /// Limits on the details fetched for each item, e.g. the reviews of each PR.
+    #[serde(default)]
+    pub enrichment: EnrichmentConfig,
+
+    /// If specified, how long a new config is previewed (see `preview_hook` in the secrets)
+    /// before it's promoted automatically.
+    #[serde(default, deserialize_with = "Config::deserialize_optional_duration")]
+    pub preview_timeout: Option<chrono::Duration>,
+
+    /// If specified, PRs waiting for reviews and updated issues are only announced again
+    /// once this duration has elapsed since we last announced them, e.g. `1d`.
+    #[serde(default, deserialize_with = "Config::deserialize_optional_duration")]
+    pub reping_after: Option<chrono::Duration>,
+
+    /// If specified, how many items we request per page from GitHub, at most 100. Larger pages
+    /// cost fewer API calls but are slower to fetch. By default, GitHub's default (30)
+    /// for listing issues and PRs of a project, 100 elsewhere.
+    #[serde(default)]
+    pub per_page: Option<u8>,
+
+    /// The maximal number of items displayed in each kind of section, e.g. `updated_issues: 20`.
+    #[serde(default)]
+    pub item_caps: HashMap<SectionKind, usize>,
+
+    /// The maximal number of issues (resp. PRs) that we list per project, page after page.
+    /// By default, 500.
+    #[serde(default)]
+    pub max_items: Option<usize>,
+
+    /// If `true`, summarize the open Dependabot PRs, e.g. for the person on dependency duty.
+    #[serde(default)]
+    pub dependency_updates: bool,
+
+    /// If `true`, PRs whose head was force-pushed since the last run are flagged (⚠️), as
+    /// their previous reviews may no longer apply. Costs one API call per PR, within the
+    /// `enrichment` budget.
+    #[serde(default)]
+    pub force_pushes: bool,
+
+    /// With `force_pushes`, also send a direct message on Slack to the users who had
+    /// reviewed a force-pushed PR. Requires `slack_bot` and `user_map`.
+    #[serde(default)]
+    pub force_push_dms: bool,
+
+    /// If `true`, a 👀 reaction of a requested reviewer on a PR acknowledges it: later
+    /// reports still list the PR, but no longer call out every reviewer.
+    #[serde(default)]
+    pub reaction_acks: bool,
+
+    /// If specified, flag PRs with an empty description or missing required sections.
+    #[serde(default)]
+    pub description_check: Option<DescriptionCheck>,
+
+    /// If specified (along with `slack_bot` in the secrets), the Slack bot also posts the
+    /// reports of each project to a channel of its own.
+    #[serde(default)]
+    pub project_channels: Option<ProjectChannels>,
+
+    /// Slack user groups whose members follow those of GitHub teams, mapped with
+    /// `user_map`, see `qastor sync-usergroups`. Requires a `slack_bot` in the secrets.
+    #[serde(default)]
+    pub usergroups: Vec<UsergroupSync>,
+
+    /// A hash of the source of the config, to detect changes.
+    #[serde(skip)]
+    pub fingerprint: String,
+}
+impl Config {
+    /// Parse the config from the source of `config.yml`.
+    pub fn parse(source: &str) -> Result<Self, anyhow::Error> {
+        let mut config: Config = serde_yaml::from_str(source)?;
+        config.fingerprint = format!("{:016x}", fnv1a(source.as_bytes()));
+        Ok(config)
+    }
+
+    fn deserialize_optional_duration<'de, D>(
+        deserializer: D,
+    ) -> Result<Option<chrono::Duration>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Config::deserialize_duration(deserializer).map(Some)
+    }
+
+    fn deserialize_duration<'de, D>(deserializer: D) -> Result<chrono::Duration, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+        let source = String::deserialize(deserializer)?;
+        parse_duration(&source).ok_or_else(|| {
+            D::Error::invalid_value(
+                Unexpected::Str(&source),
+                &"a number followed by a unit d/h/m/s",
+            )
+        })
+    }
+
+    fn deserialize_time_zones<'de, D>(deserializer: D) -> Result<HashMap<String, Zone>, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+        HashMap::<String, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(login, source)| match Zone::load(&source) {
+                Ok(zone) => Ok((login, zone)),
+                Err(_) => Err(D::Error::invalid_value(
+                    Unexpected::Str(&source),
+                    &"a time zone, e.g. Europe/Paris",
+                )),
+            })
+            .collect()
+    }
+
+    /// Custom deserialization for update frequency.
+    ///
+    /// We don't want to specify the duration in seconds, as that's annoying, so implementing
+    /// a shorthand notation.
+    fn deserialize_update_frequency<'de, D>(deserializer: D) -> Result<chrono::Duration, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+        let source = String::deserialize(deserializer)?;
+        let regex = lazy_regex!("([[:digit:]]+) *([hmsd])");
+        let found = regex.captures(&source).ok_or_else(|| {
+            D::Error::invalid_value(
+                Unexpected::Str(&source),
+                &"numbers followed by a unit d/h/m/s",
+            )
+        })?;
+        let digits = found.get(1).expect("we should have digits");
+        let unit = found.get(2).expect("we should have a unit");
+        let digits: i64 = digits
+            .as_str()
+            .parse()
+            .map_err(|_| D::Error::invalid_value(Unexpected::Str(digits.as_str()), &"numbers"))?;
+        let unit: char = unit.as_str().parse().map_err(|_| {
+            D::Error::invalid_value(Unexpected::Str(unit.as_str()), &"a unit d/h/m/s")
+        })?;
+        let result = match unit {
+            'd' => chrono::Duration::days(digits),
+            'h' => chrono::Duration::hours(digits),
+            'm' => chrono::Duration::minutes(digits),
+            's' => chrono::Duration::seconds(digits),
+            _ => unreachable!(),
+        };
+        Ok(result)
+    }
+
+    /// How often we monitor a project.
+    pub fn update_frequency_for(&self, project: &Project) -> chrono::Duration {
+        project.update_frequency.unwrap_or(self.update_frequency)
+    }
+
+    /// How often we need to run to monitor every project on time.
+    pub fn shortest_update_frequency(&self) -> chrono::Duration {
+        self.projects
+            .iter()
+            .filter_map(|project| project.update_frequency)
+            .fold(self.update_frequency, std::cmp::min)
+    }
+
+    /// How far back a section of the report looks for a project.
+    pub fn window(&self, project: &Project, kind: SectionKind) -> Window {
+        match self.windows.get(&kind) {
+            Some(window) => *window,
+            None if kind == SectionKind::UpdatedIssues => {
+                Window::Last(self.update_frequency_for(project))
+            }
+            None if matches!(kind, SectionKind::LabelChanges | SectionKind::CiFailures) => {
+                Window::SinceLastRun
+            }
+            None => Window::All,
+        }
+    }
+
+    /// How many items we request per page from GitHub for a project, if specified.
+    pub fn per_page(&self, project: &Project) -> Option<u8> {
+        project
+            .per_page
+            .or(self.per_page)
+            .map(|per_page| per_page.clamp(1, 100))
+    }
+
+    /// The maximal number of items displayed in a section for a project, if any.
+    pub fn item_cap(&self, project: &Project, kind: SectionKind) -> Option<usize> {
+        project
+            .item_caps
+            .get(&kind)
+            .or_else(|| self.item_caps.get(&kind))
+            .copied()
+    }
+
+    /// How many issues (resp. PRs) we list for a project at most.
+    pub fn max_items(&self, project: &Project) -> usize {
+        const DEFAULT_MAX_ITEMS: usize = 500;
+        project
+            .max_items
+            .or(self.max_items)
+            .unwrap_or(DEFAULT_MAX_ITEMS)
+    }
+
+    fn default_update_frequency() -> chrono::Duration {
+        chrono::Duration::hours(2)
+    }
+
+    /// Whether the issues and PRs opened by a user are left out of reports, see
+    /// `ignore_authors` and `ignore_bots`.
+    pub fn ignores_author(&self, login: &str) -> bool {
+        (self.ignore_bots && login.ends_with("[bot]"))
+            || self
+                .ignore_authors
+                .iter()
+                .any(|ignored| ignored.eq_ignore_ascii_case(login))
+    }
+
+    /// The days off of the team, besides weekends, see `business_hours`.
+    pub fn holidays(&self) -> &[NaiveDate] {
+        self.business_hours
+            .as_ref()
+            .map_or(&[], |hours| hours.holidays.as_slice())
+    }
+
+    /// A GitHub user, as a Slack mention if we know their member id, see `user_map`.
+    pub fn mention(&self, login: &str) -> String {
+        match self.slack_member(login) {
+            Some(member) => slack::mention(member, login),
+            None => login.to_string(),
+        }
+    }
+
+    /// The language of the reports about a project, see `locale`.
+    pub fn locale_for(&self, project: &Project) -> Locale {
+        project.locale.unwrap_or(self.locale)
+    }
+
+    /// The message with this key in `locale`, see `i18n` and `messages`.
+    pub fn message(&self, locale: Locale, key: &str, vars: &[(&str, String)]) -> String {
+        i18n::message(&self.messages, locale, key, vars)
+    }
+
+    /// The message with this key in the language of a project.
+    pub fn text(&self, project: &Project, key: &str, vars: &[(&str, String)]) -> String {
+        self.message(self.locale_for(project), key, vars)
+    }
+
+    /// The headers of two columns of a section in `locale`, as `column.<key>` messages.
+    pub fn columns(&self, locale: Locale, keys: [&str; 2]) -> [String; 2] {
+        keys.map(|key| format!("*{}*", self.message(locale, &format!("column.{key}"), &[])))
+    }
+
+    /// A GitHub team, as a mention of its Slack user group if it has one, see `usergroups`.
+    pub fn mention_team(&self, team: &str) -> String {
+        match self
+            .usergroups
+            .iter()
+            .find(|sync| sync.team.eq_ignore_ascii_case(team))
+        {
+            Some(sync) => slack::mention_usergroup(&sync.usergroup, team),
+            None => team.to_string(),
+        }
+    }
+
+    /// The Slack member id of a GitHub user, if known, see `user_map`.
+    pub fn slack_member(&self, login: &str) -> Option<&str> {
+        self.user_map
+            .iter()
+            .find_map(|(name, member)| name.eq_ignore_ascii_case(login).then_some(member.as_str()))
+    }
+
+    /// When a reminder should land in the direct messages of a GitHub user, see `remind_at`,
+    /// unless it should be sent right away.
+    pub fn reminder_time(&self, login: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let time = self.remind_at?;
+        match self
+            .user_time_zones
+            .iter()
+            .find_map(|(name, zone)| name.eq_ignore_ascii_case(login).then_some(zone))
+        {
+            Some(zone) => Some(zone.next(time, now)),
+            None => Some(
+                ScheduledDelivery {
+                    time,
+                    utc_offset: ScheduledDelivery::default_utc_offset(),
+                }
+                .next(now),
+            ),
+        }
+    }
+
+    /// The emojis to display for an item carrying these labels, e.g. "🐞 ✨ ".
+    ///
+    /// Empty if none of the labels has an emoji.
+    pub fn label_emojis<'a>(&self, labels: impl IntoIterator<Item = &'a str>) -> String {
+        let mut result = String::new();
+        for label in labels {
+            let emoji = self
+                .label_emojis
+                .iter()
+                .find_map(|(name, emoji)| name.eq_ignore_ascii_case(label).then_some(emoji));
+            if let Some(emoji) = emoji {
+                result.push_str(emoji);
+                result.push(' ');
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ops::Not;
+
+    use chrono::{TimeZone, Utc};
+
+    use crate::{
+        config::Config,
+        config::{RepoName, Window},
+        report::{SectionKind, Severity},
+        slack::{LinkStyle, MessageFormat, MessageOptions},
+    };
+
+    use super::{Drafts, Hook, HookKind, HookTarget, ProjectToHook, ScheduledDelivery, Secrets};
+
+    /// Can a typical config be parsed?
+    #[test]
+    fn test_config_parse() {
+        let source = r#"
+            projects:
+                - url: "https://github.com/owner1/project1"
+                - url: "https://github.com/owner2/project2"
+            update_frequency: 15m
+        "#;
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        assert_eq!(config.update_frequency, chrono::Duration::minutes(15));
+        assert_eq!(config.projects.len(), 2);
+        assert_eq!(config.projects[0].owner, "owner1");
+        assert_eq!(config.projects[0].repo, RepoName::from("project1"));
+        assert_eq!(config.projects[1].owner, "owner2");
         assert_eq!(config.projects[1].repo, RepoName::from("project2"));
     }
 
+    /// Is the re-ping interval parsed as a duration, and off by default?
+    #[test]
+    fn test_reping_after() {
+        let source = r#"
+            projects:
+                - url: "https://github.com/owner1/project1"
+            reping_after: 1d
+        "#;
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        assert_eq!(config.reping_after, Some(chrono::Duration::days(1)));
+
+        let source = r#"
+            projects:
+                - url: "https://github.com/owner1/project1"
+        "#;
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        assert_eq!(config.reping_after, None);
+    }
+
+    /// Are items routed by title or issue form fields, first matching route first?
+    #[test]
+    fn test_routes() {
+        let source = r#"
+            projects:
+                - url: "https://github.com/owner1/project1"
+                  routes:
+                    - title: "^\\[sdk\\]"
+                      hook: sdk
+                    - title: "^(docs|feat)(\\(.*\\))?:"
+                      hook: docs
+                    - fields:
+                        component: "^SDK$"
+                      hook: sdk
+                  escalations:
+                    - after: 5d
+                      hook: leads
+                      mention: [alice]
+                  security:
+                    hook: security
+                  code_owners:
+                    "@owner1/frontend": web
+                    "@alice": web
+                    "@owner1/backend": backend
+                  label_routes:
+                    P0: oncall
+                    "area:runtime": runtime
+                    "area:sdk": sdk
+            sla:
+                - label: customer
+                  first_response_business_days: 2
+                  escalate_to: support
+        "#;
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        let project = &config.projects[0];
+        assert_eq!(project.route("[sdk] Fix the client"), Some("sdk"));
+        assert_eq!(project.route("docs: Explain routes"), Some("docs"));
+        assert_eq!(project.route("feat(cli): Add routes"), Some("docs"));
+        assert_eq!(project.route("Fix [sdk]"), None);
+        let fields = [("Component".to_string(), "SDK".to_string())];
+        assert_eq!(project.route_fields("Crash", &fields), Some("sdk"));
+        assert_eq!(project.route_fields("docs: Crash", &fields), Some("docs"));
+        assert_eq!(project.escalations[0].after, chrono::Duration::days(5));
+        assert_eq!(project.escalations[0].severity, Severity::Warning);
+        assert!(project.is_target("sdk", &config.sla));
+        assert!(project.is_target("leads", &config.sla));
+        assert!(project.is_target("security", &config.sla));
+        assert!(project.is_target("web", &config.sla));
+        assert!(project.is_target("oncall", &config.sla));
+        assert!(project.is_target("support", &config.sla));
+        assert!(project.is_target("support", &[]).not());
+        assert_eq!(
+            project.routes_for("[sdk] Crash", &[], ["p0", "area:runtime", "area:sdk"]),
+            ["sdk", "oncall", "runtime"]
+        );
+        assert_eq!(
+            project.routes_for("Crash", &[], ["bug", "Area:Runtime"]),
+            ["runtime"]
+        );
+        assert_eq!(
+            project.routes_for("Crash", &[], ["bug"]),
+            Vec::<String>::new()
+        );
+        assert!(project.is_target("ops", &config.sla).not());
+        assert_eq!(
+            project.code_owner_hooks(["@Owner1/Frontend", "@alice", "@bob"]),
+            ["web"]
+        );
+    }
+
+    /// Can each section have its own window?
+    #[test]
+    fn test_windows() {
+        let source = r#"
+            projects:
+                - url: "https://github.com/owner1/project1"
+            update_frequency: 12h
+            windows:
+                updated_issues: since_last_run
+                pending_reviews: 7d
+        "#;
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        let project = &config.projects[0];
+        assert_eq!(
+            config.window(project, SectionKind::UpdatedIssues),
+            Window::SinceLastRun
+        );
+        assert_eq!(
+            config.window(project, SectionKind::PendingReviews),
+            Window::Last(chrono::Duration::days(7))
+        );
+        assert_eq!(
+            config.window(project, SectionKind::DueMilestones),
+            Window::All
+        );
+        assert!(serde_yaml::from_str::<Config>("windows: { updated_issues: soon }").is_err());
+    }
+
+    /// Can projects override the update frequency, and their default window with it?
+    #[test]
+    fn test_update_frequency() {
+        let source = r#"
+            projects:
+                - url: "https://github.com/owner1/hot"
+                  update_frequency: 15m
+                - url: "https://github.com/owner1/sleepy"
+            update_frequency: 1d
+        "#;
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        let (hot, sleepy) = (&config.projects[0], &config.projects[1]);
+        assert_eq!(
+            config.update_frequency_for(hot),
+            chrono::Duration::minutes(15)
+        );
+        assert_eq!(
+            config.update_frequency_for(sleepy),
+            chrono::Duration::days(1)
+        );
+        assert_eq!(
+            config.shortest_update_frequency(),
+            chrono::Duration::minutes(15)
+        );
+        assert_eq!(
+            config.window(hot, SectionKind::UpdatedIssues),
+            Window::Last(chrono::Duration::minutes(15))
+        );
+        assert_eq!(
+            config.window(sleepy, SectionKind::UpdatedIssues),
+            Window::Last(chrono::Duration::days(1))
+        );
+    }
+
+    /// Does each project get its own channel?
+    #[test]
+    fn test_project_channels() {
+        let source = r##"
+            projects:
+                - url: "https://github.com/owner1/My.Project"
+            project_channels:
+                name: "#reviews-{repo}"
+                create: true
+        "##;
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        let channels = config.project_channels.as_ref().unwrap();
+        assert_eq!(channels.name_for(&config.projects[0]), "reviews-my-project");
+        assert!(channels.create);
+        assert!(channels.invite_usergroup.is_none());
+    }
+
+    /// Do links to projects on GitHub Enterprise go to their web host?
+    #[test]
+    fn test_web_url() {
+        let source = r#"
+            projects:
+                - url: "https://ghe-api.example.com/owner1/project1"
+                  api_url: "https://ghe-api.example.com/api/v3"
+                  web_url: "https://ghe.example.com/"
+                - url: "https://github.com/owner1/project2"
+        "#;
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        let (hosted, public) = (&config.projects[0], &config.projects[1]);
+        assert_eq!(
+            hosted.html_url().as_str(),
+            "https://ghe.example.com/owner1/project1"
+        );
+        let moved = hosted
+            .moved_to(url::Url::parse("https://ghe.example.com/owner2/project1").unwrap())
+            .unwrap();
+        assert_eq!(moved.api_url, hosted.api_url);
+        assert_eq!(public.html_url(), public.url);
+    }
+
+    /// Can projects override the page size and item caps?
+    #[test]
+    fn test_quotas() {
+        let source = r#"
+            per_page: 50
+            item_caps:
+                updated_issues: 20
+            projects:
+                - url: "https://github.com/owner1/monorepo"
+                  per_page: 250
+                  max_items: 2000
+                  item_caps:
+                    updated_issues: 5
+                    pending_reviews: 10
+                - url: "https://github.com/owner1/tiny"
+        "#;
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        let (monorepo, tiny) = (&config.projects[0], &config.projects[1]);
+        assert_eq!(config.per_page(monorepo), Some(100));
+        assert_eq!(config.per_page(tiny), Some(50));
+        assert_eq!(
+            config.item_cap(monorepo, SectionKind::UpdatedIssues),
+            Some(5)
+        );
+        assert_eq!(
+            config.item_cap(monorepo, SectionKind::PendingReviews),
+            Some(10)
+        );
+        assert_eq!(config.item_cap(tiny, SectionKind::UpdatedIssues), Some(20));
+        assert_eq!(config.item_cap(tiny, SectionKind::PendingReviews), None);
+        assert_eq!(config.max_items(monorepo), 2000);
+        assert_eq!(config.max_items(tiny), 500);
+    }
+
+    /// Do we find the sections missing from a PR description?
+    #[test]
+    fn test_description_check() {
+        let source = r#"
+            description_check:
+                required_sections:
+                    - name: Summary
+                      pattern: "(?m)^#+ *Summary"
+                    - name: Testing
+                      pattern: "(?mi)^#+ *test"
+        "#;
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        let check = config.description_check.unwrap();
+        assert_eq!(
+            check.missing_sections("## Summary\nFixes things"),
+            ["Testing"]
+        );
+        assert!(check
+            .missing_sections("## Summary\n\n### Tests\nCI")
+            .is_empty());
+        assert_eq!(check.missing_sections(""), ["Summary", "Testing"]);
+    }
+
+    /// Do we detect config changes?
+    #[test]
+    fn test_fingerprint() {
+        let config = Config::parse("update_frequency: 12h\npreview_timeout: 1d").unwrap();
+        assert_eq!(config.preview_timeout, Some(chrono::Duration::days(1)));
+        assert_eq!(config.fingerprint.len(), 16);
+        let same = Config::parse("update_frequency: 12h\npreview_timeout: 1d").unwrap();
+        assert_eq!(config.fingerprint, same.fingerprint);
+        let changed = Config::parse("update_frequency: 6h\npreview_timeout: 1d").unwrap();
+        assert_ne!(config.fingerprint, changed.fingerprint);
+        assert_eq!(changed.update_frequency, chrono::Duration::hours(6));
+    }
+
+    /// Are label emojis picked in label order, ignoring case?
+    #[test]
+    fn test_label_emojis() {
+        let source = r#"
+            label_emojis:
+                bug: "🐞"
+                enhancement: "✨"
+        "#;
+        let config: Config = serde_yaml::from_str(source).unwrap();
+        assert_eq!(
+            config.label_emojis(["Enhancement", "wontfix", "bug"]),
+            "✨ 🐞 "
+        );
+        assert_eq!(config.label_emojis(["wontfix"]), "");
+    }
+
+    /// Are mapped reviewers mentioned, ignoring case, and others merely named?
+    #[test]
+    fn test_mention() {
+        let config: Config = serde_yaml::from_str("user_map:\n    Alice: U0123456789").unwrap();
+        assert_eq!(config.mention("alice"), "<@U0123456789|alice>");
+        assert_eq!(config.mention("bob"), "bob");
+    }
+
+    /// Are reports delivered at the next local delivery time, today or tomorrow?
+    #[test]
+    fn test_deliver_at() {
+        let delivery: ScheduledDelivery =
+            serde_yaml::from_str("time: \"09:00\"\nutc_offset: \"+02:00\"").unwrap();
+        let at = |h, m| Utc.with_ymd_and_hms(2024, 5, 6, h, m, 0).unwrap();
+        assert_eq!(delivery.next(at(5, 0)), at(7, 0));
+        assert_eq!(
+            delivery.next(at(7, 30)),
+            Utc.with_ymd_and_hms(2024, 5, 7, 7, 0, 0).unwrap()
+        );
+        // Late in the evening in UTC, it's already tomorrow in Paris.
+        assert_eq!(
+            delivery.next(at(23, 0)),
+            Utc.with_ymd_and_hms(2024, 5, 7, 7, 0, 0).unwrap()
+        );
+        let delivery: ScheduledDelivery = serde_yaml::from_str("time: \"09:00\"").unwrap();
+        assert_eq!(delivery.next(at(5, 0)), at(9, 0));
+    }
+
+    /// Do reminders land in the morning of each reviewer, in their own time zone?
+    #[test]
+    fn test_reminder_time() {
+        if crate::tz::Zone::load("Europe/Paris").is_err() {
+            // No time zone database on this system.
+            return;
+        }
+        let config: Config = serde_yaml::from_str(
+            "remind_at: \"09:00\"\nuser_time_zones:\n    Alice: Europe/Paris\n    bob: America/New_York",
+        )
+        .unwrap();
+        // Summer time.
+        let now = Utc.with_ymd_and_hms(2024, 5, 6, 5, 0, 0).unwrap();
+        let at = |m, d, h| Some(Utc.with_ymd_and_hms(2024, m, d, h, 0, 0).unwrap());
+        assert_eq!(config.reminder_time("alice", now), at(5, 6, 7));
+        assert_eq!(config.reminder_time("bob", now), at(5, 6, 13));
+        assert_eq!(config.reminder_time("carol", now), at(5, 6, 9));
+        let now = Utc.with_ymd_and_hms(2024, 5, 6, 10, 0, 0).unwrap();
+        assert_eq!(config.reminder_time("alice", now), at(5, 7, 7));
+        // Winter time.
+        let now = Utc.with_ymd_and_hms(2024, 12, 2, 5, 0, 0).unwrap();
+        assert_eq!(config.reminder_time("alice", now), at(12, 2, 8));
+        assert_eq!(config.reminder_time("bob", now), at(12, 2, 14));
+        let config: Config = serde_yaml::from_str("user_map: {}").unwrap();
+        assert_eq!(config.reminder_time("alice", now), None);
+        assert!(serde_yaml::from_str::<Config>("user_time_zones:\n    alice: Paris").is_err());
+    }
+
+    /// Are teams with a synced user group mentioned as that group, and others merely named?
+    #[test]
+    fn test_mention_team() {
+        let config: Config = serde_yaml::from_str(
+            "usergroups:\n    - team: Org/Reviewers\n      usergroup: S0123456789",
+        )
+        .unwrap();
+        assert_eq!(
+            config.mention_team("org/reviewers"),
+            "<!subteam^S0123456789|org/reviewers>"
+        );
+        assert_eq!(config.mention_team("org/docs"), "org/docs");
+    }
+
+    /// Are issues and PRs filtered by their labels?
+    #[test]
+    fn test_label_filters() {
+        let config = Config::parse(
+            r#"
+projects:
+  - url: "https://github.com/owner1/project1"
+    include_labels: [sdk, docs]
+    exclude_labels: [WIP]
+    drafts: separate
+"#,
+        )
+        .unwrap();
+        let project = &config.projects[0];
+        assert!(project.shows(["SDK"]));
+        assert!(project.shows(["bug", "docs"]));
+        assert!(!project.shows(["bug"]));
+        assert!(!project.shows([]));
+        assert!(!project.shows(["sdk", "wip"]));
+        assert_eq!(project.drafts, Drafts::Separate);
+    }
+
+    /// Are bots and ignored authors left out?
+    #[test]
+    fn test_ignores_author() {
+        let config: Config = serde_yaml::from_str("ignore_authors: [Alice]").unwrap();
+        assert!(config.ignores_author("alice"));
+        assert!(!config.ignores_author("dependabot[bot]"));
+        let config: Config = serde_yaml::from_str("ignore_bots: true").unwrap();
+        assert!(config.ignores_author("dependabot[bot]"));
+        assert!(!config.ignores_author("alice"));
+    }
+
+    /// Are business hours given as times of day, with holidays?
+    #[test]
+    fn test_business_hours() {
+        let config: Config = serde_yaml::from_str(
+            "business_hours:\n    start: \"08:30\"\n    holidays: [2024-12-25]",
+        )
+        .unwrap();
+        let hours = config.business_hours.as_ref().unwrap();
+        assert_eq!(hours.day(), chrono::Duration::minutes(8 * 60 + 30));
+        assert_eq!(
+            config.holidays(),
+            [chrono::NaiveDate::from_ymd_opt(2024, 12, 25).unwrap()]
+        );
+    }
+
+    /// Can hooks be given both as bare urls and with their subscriptions?
+    #[test]
+    fn test_secrets_parse() {
+        let source = r#"{
+            "https://github.com/owner1/project1": [
+                "https://hooks.slack.com/services/YOUR/SLACK/HOOK",
+                {
+                    "url": "https://hooks.slack.com/services/ANOTHER/SLACK/HOOK",
+                    "fallbacks": [
+                        "https://hooks.slack.com/services/BACKUP/SLACK/HOOK",
+                        "https://discord.com/api/webhooks/BACKUP/HOOK"
+                    ],
+                    "severities": ["warning", "critical"],
+                    "unfurl_links": false,
+                    "link_style": "compact"
+                },
+                "https://contoso.webhook.office.com/webhookb2/TEAMS/HOOK",
+                {
+                    "url": "https://prod.example.com/workflows/TEAMS/HOOK",
+                    "kind": "teams",
+                    "name": "Teams bridge",
+                    "locale": "fr",
+                    "format": "plain"
+                },
+                {
+                    "channel": "C0123456789",
+                    "severities": ["critical"]
+                }
+            ],
+            "slack_bot": { "token": "xoxb-123" }
+        }"#;
+        let mut secrets: Secrets = serde_json::from_str(source).unwrap();
+        secrets.attach_bot().unwrap();
+        assert!(secrets.channels.is_empty());
+        let project = url::Url::parse("https://github.com/owner1/project1").unwrap();
+        let hooks = &secrets.repo_to_hook[&project];
+        assert_eq!(hooks.len(), 5);
+        assert!(hooks[0].accepts(Severity::Info));
+        assert!(!hooks[1].accepts(Severity::Info));
+        assert!(hooks[1].accepts(Severity::Critical));
+        assert_eq!(hooks[0].options, MessageOptions::default());
+        assert!(hooks[0].fallbacks.is_empty());
+        // Fallbacks are rendered for their own service, with the options of their hook.
+        let fallbacks = hooks[1].fallback_hooks();
+        assert_eq!(
+            fallbacks.iter().map(Hook::kind).collect::<Vec<_>>(),
+            [HookKind::Slack, HookKind::Discord]
+        );
+        assert!(fallbacks
+            .iter()
+            .all(|hook| hook.accepts(Severity::Info).not()));
+        assert_eq!(fallbacks[1].options.link_style, LinkStyle::Compact);
+        assert_eq!(hooks[1].options.unfurl_links, Some(false));
+        assert_eq!(hooks[1].options.link_style, LinkStyle::Compact);
+        assert_eq!(hooks[1].kind(), HookKind::Slack);
+        assert_eq!(hooks[2].kind(), HookKind::Teams);
+        assert_eq!(hooks[3].kind(), HookKind::Teams);
+        assert_eq!(hooks[3].name.as_deref(), Some("Teams bridge"));
+        assert_eq!(hooks[3].options.format, MessageFormat::Plain);
+        assert_eq!(hooks[3].locale, Some(crate::humanize::Locale::French));
+        assert_eq!(hooks[1].locale, None);
+        assert_eq!(hooks[1].options.format, MessageFormat::Rich);
+        assert_eq!(
+            hooks[4].target,
+            HookTarget::Bot {
+                token: "xoxb-123".to_string(),
+                channel: "C0123456789".to_string()
+            }
+        );
+        assert_eq!(hooks[4].kind(), HookKind::Slack);
+        assert_eq!(crate::archive::hook_label(&hooks[4]), "slack:C0123456789");
+
+        // A channel without a bot.
+        secrets.slack_bot = None;
+        assert!(secrets.attach_bot().is_err());
+        assert!(serde_json::from_str::<Hook>(
+            r#"{ "url": "https://hooks.slack.com/x", "channel": "C0123456789" }"#
+        )
+        .is_err());
+    }
+
+    /// Do we spot projects without hooks and routes without targets?
+    #[test]
+    fn test_secrets_problems() {
+        let config = Config::parse(
+            r#"
+projects:
+  - url: "https://github.com/owner1/project1"
+    routes:
+      - title: "^sdk"
+        hook: sdk
+      - title: "^docs"
+        hook: docs
+      - hook: sdk
+    escalations:
+      - after: 5d
+        hook: leads
+    security:
+      hook: security
+    stars:
+      hook: community
+    code_owners:
+      "@owner1/frontend": frontend
+    label_routes:
+      P0: oncall
+  - url: "https://github.com/owner1/project2"
+messages:
+  fr:
+    date: "%d/%m/%Q"
+    date_time: "%d/%m/%Y %H:%M"
+usergroups:
+  - team: owner1/reviewers
+    usergroup: S0123456789
+  - team: reviewers
+    usergroup: S0123456789
+"#,
+        )
+        .unwrap();
+        let secrets: Secrets = serde_json::from_str(
+            r#"{
+            "https://github.com/owner1/project1": [
+                { "url": "https://hooks.slack.com/services/SDK/SLACK/HOOK", "alias": "sdk" }
+            ],
+            "https://github.com/owner1/project3": ["https://hooks.slack.com/services/A/B/C"]
+        }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            secrets.problems(&config),
+            vec![
+                "Message date of `messages` (French) is not a valid format of dates",
+                "Team reviewers of `usergroups` is not `org/slug`",
+                "`usergroups` requires a `slack_bot` in the secrets",
+                "https://github.com/owner1/project1: a route to sdk has neither a title nor fields, so every item would take it",
+                "https://github.com/owner1/project1: no hook with alias community, target of star milestones",
+                "https://github.com/owner1/project1: no hook with alias docs, target of a route",
+                "https://github.com/owner1/project1: no hook with alias frontend, target of code owner @owner1/frontend",
+                "https://github.com/owner1/project1: no hook with alias leads, target of an escalation",
+                "https://github.com/owner1/project1: no hook with alias oncall, target of label p0",
+                "https://github.com/owner1/project1: no hook with alias security, target of security alerts",
+                "https://github.com/owner1/project2: no hook, reports go nowhere",
+                "https://github.com/owner1/project3: hooks for a project missing from the config",
+            ]
+        );
+    }
+
     /// Can a typical ProjectToHook be parsed?
     #[test]
     fn test_project_to_hook_parse() {
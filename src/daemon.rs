@@ -0,0 +1,183 @@
+use std::{
+    hash::{BuildHasher, Hasher},
+    path::Path,
+};
+
+use chrono::{DateTime, Utc};
+use log::info;
+
+/// How much the delay between two runs may vary, as a fraction of the period.
+///
+/// Keeps several instances (or tenants) started at the same time from hitting GitHub
+/// all at once.
+const JITTER: f64 = 0.1;
+
+/// The delay until the next run, i.e. `period` give or take 10%.
+pub fn next_delay(period: chrono::Duration) -> std::time::Duration {
+    // Each `RandomState` is randomly seeded, which is all the randomness we need.
+    let random = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    jittered(period, random)
+}
+
+/// `period`, shifted by up to `JITTER` in either direction depending on `random`.
+fn jittered(period: chrono::Duration, random: u64) -> std::time::Duration {
+    let period = period.to_std().unwrap_or_default().as_secs_f64();
+    let factor = 1.0 + JITTER * (2.0 * (random as f64 / u64::MAX as f64) - 1.0);
+    std::time::Duration::from_secs_f64(period * factor)
+}
+
+/// Whether a tenant (or a project) is due for a new run, given its `update_frequency`.
+///
+/// Runs up to 10% early are tolerated, so that jitter doesn't delay them by a whole period.
+pub fn is_due(
+    now: DateTime<Utc>,
+    last_run: Option<DateTime<Utc>>,
+    update_frequency: chrono::Duration,
+) -> bool {
+    match last_run {
+        None => true,
+        Some(last) => now - last >= update_frequency - update_frequency / 10,
+    }
+}
+
+/// Stop requests that don't come as signals, e.g. from the Windows Service Control Manager.
+static STOP: tokio::sync::Notify = tokio::sync::Notify::const_new();
+
+/// Ask `shutdown_signal` to return, e.g. when the Windows service is stopped.
+pub fn request_stop() {
+    STOP.notify_one();
+}
+
+/// Wait until we are asked to stop, with SIGTERM (e.g. by systemd, launchd or Docker),
+/// Ctrl+C or `request_stop`.
+pub async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = terminate.recv() => info!("Received SIGTERM"),
+                    _ = tokio::signal::ctrl_c() => info!("Received Ctrl+C"),
+                    _ = STOP.notified() => info!("Asked to stop"),
+                }
+                return;
+            }
+            Err(err) => log::warn!("Could not listen to SIGTERM: {err}"),
+        }
+    }
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => info!("Received Ctrl+C"),
+        _ = STOP.notified() => info!("Asked to stop"),
+    }
+}
+
+/// A launchd agent (e.g. `~/Library/LaunchAgents/<label>.plist`) running `qastor serve` from
+/// `dir`, which holds `config.yml`, restarted if it fails and logging to `log`.
+///
+/// launchd stops agents with SIGTERM, then waits for the run in progress, if any.
+pub fn launchd_plist(label: &str, exe: &Path, dir: &Path, log: &Path) -> String {
+    /// How long launchd waits after SIGTERM before killing us, in seconds.
+    const EXIT_TIMEOUT: u32 = 300;
+    let escape = |text: &str| html_escape::encode_text(text).into_owned();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+        <string>{exe}</string>
+        <string>serve</string>
+    </array>
+    <key>WorkingDirectory</key>
+    <string>{dir}</string>
+    <key>EnvironmentVariables</key>
+    <dict>
+        <key>RUST_LOG</key>
+        <string>info</string>
+    </dict>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+    <key>ExitTimeOut</key>
+    <integer>{EXIT_TIMEOUT}</integer>
+    <key>StandardOutPath</key>
+    <string>{log}</string>
+    <key>StandardErrorPath</key>
+    <string>{log}</string>
+</dict>
+</plist>
+"#,
+        label = escape(label),
+        exe = escape(&exe.to_string_lossy()),
+        dir = escape(&dir.to_string_lossy()),
+        log = escape(&log.to_string_lossy()),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, Utc};
+
+    use std::path::Path;
+
+    use super::{is_due, jittered, launchd_plist};
+
+    /// Do runs stay within 10% of the period?
+    #[test]
+    fn test_jittered() {
+        let period = chrono::Duration::minutes(10);
+        assert_eq!(jittered(period, 0).as_secs(), 540);
+        assert!((jittered(period, u64::MAX / 2).as_secs_f64() - 600.0).abs() < 1.0);
+        assert_eq!(jittered(period, u64::MAX).as_secs(), 660);
+        assert_eq!(jittered(chrono::Duration::seconds(-1), 0).as_secs(), 0);
+    }
+
+    /// Does the launchd agent serve from the right directory, with paths escaped?
+    #[test]
+    fn test_launchd_plist() {
+        let plist = launchd_plist(
+            "io.pasqal.qastor",
+            Path::new("/usr/local/bin/qastor"),
+            Path::new("/Users/alice/R&D"),
+            Path::new("/Users/alice/R&D/qastor.log"),
+        );
+        assert!(plist.contains("<string>io.pasqal.qastor</string>"));
+        assert!(plist
+            .contains("<string>/usr/local/bin/qastor</string>\n        <string>serve</string>"));
+        assert!(plist
+            .contains("<key>WorkingDirectory</key>\n    <string>/Users/alice/R&amp;D</string>"));
+        assert!(plist.contains("<string>/Users/alice/R&amp;D/qastor.log</string>"));
+    }
+
+    /// Do we wait for `update_frequency` between two runs, give or take jitter?
+    #[test]
+    fn test_is_due() {
+        let now = Utc::now();
+        assert!(is_due(now, None, Duration::hours(12)));
+        assert!(!is_due(
+            now,
+            Some(now - Duration::hours(1)),
+            Duration::hours(12)
+        ));
+        assert!(is_due(
+            now,
+            Some(now - Duration::minutes(11 * 60)),
+            Duration::hours(12)
+        ));
+        assert!(is_due(
+            now,
+            Some(now - Duration::hours(12)),
+            Duration::hours(12)
+        ));
+    }
+}
@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use derive_more::Display;
+use lazy_regex::{lazy_regex, Lazy};
+use octocrab::{Octocrab, Page};
+use regex::Regex;
+use serde::Deserialize;
+use url::Url;
+
+use crate::{config::Project, error::Error, github};
+
+/// An update of a single dependency, as described by a Dependabot PR.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyUpdate {
+    pub name: String,
+    pub from: String,
+    pub to: String,
+
+    /// The changes between both versions, or at least the home of the dependency.
+    pub changes: Option<Url>,
+
+    /// The badge displaying Dependabot's compatibility score.
+    pub badge: Option<Url>,
+}
+impl DependencyUpdate {
+    pub fn compatibility(&self) -> Compatibility {
+        compatibility(&self.from, &self.to)
+    }
+}
+
+/// An open Dependabot PR.
+pub struct DependabotPull {
+    pub number: u64,
+    pub url: Url,
+    pub title: String,
+
+    /// The updates, more than one for grouped updates.
+    pub updates: Vec<DependencyUpdate>,
+}
+
+/// How severe a vulnerability is, most severe first.
+#[derive(Deserialize, Display, Default, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertSeverity {
+    #[display("critical")]
+    Critical,
+    #[display("high")]
+    High,
+    #[display("medium")]
+    Medium,
+    #[display("low")]
+    #[default]
+    Low,
+}
+impl AlertSeverity {
+    /// Whether this severity is `min` or worse.
+    pub fn is_at_least(self, min: AlertSeverity) -> bool {
+        self <= min
+    }
+}
+
+/// An open Dependabot alert, i.e. a vulnerable dependency, as returned by the REST API.
+#[derive(Deserialize, Debug, Clone)]
+pub struct DependabotAlert {
+    pub number: u64,
+    pub html_url: Url,
+    pub dependency: AlertDependency,
+    pub security_advisory: SecurityAdvisory,
+}
+#[derive(Deserialize, Debug, Clone)]
+pub struct AlertDependency {
+    pub package: AlertPackage,
+}
+#[derive(Deserialize, Debug, Clone)]
+pub struct AlertPackage {
+    /// E.g. `cargo` or `npm`.
+    pub ecosystem: String,
+    pub name: String,
+}
+#[derive(Deserialize, Debug, Clone)]
+pub struct SecurityAdvisory {
+    pub summary: String,
+    pub severity: AlertSeverity,
+}
+
+/// Group alerts by severity, most severe first, then by package.
+pub fn by_severity(
+    alerts: impl IntoIterator<Item = DependabotAlert>,
+) -> Vec<(AlertSeverity, Vec<DependabotAlert>)> {
+    let mut groups = std::collections::BTreeMap::<AlertSeverity, Vec<DependabotAlert>>::new();
+    for alert in alerts {
+        groups
+            .entry(alert.security_advisory.severity)
+            .or_default()
+            .push(alert);
+    }
+    groups
+        .into_iter()
+        .map(|(severity, mut alerts)| {
+            alerts.sort_by(|a, b| {
+                (&a.dependency.package.name, a.number).cmp(&(&b.dependency.package.name, b.number))
+            });
+            (severity, alerts)
+        })
+        .collect()
+}
+
+/// Whether an update may break its dependents, following semver as Cargo does.
+#[derive(Display, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compatibility {
+    #[display("semver-compatible")]
+    Compatible,
+    #[display("breaking")]
+    Breaking,
+    #[display("unknown")]
+    Unknown,
+}
+
+/// Compare two versions, e.g. `1.0.190` and `v1.0.200`.
+pub fn compatibility(from: &str, to: &str) -> Compatibility {
+    fn parse(version: &str) -> Option<[u64; 3]> {
+        let mut numbers = version
+            .trim_start_matches('v')
+            .split(['.', '-', '+'])
+            .map(|part| part.parse::<u64>().ok());
+        let major = numbers.next()??;
+        let minor = numbers.next().flatten().unwrap_or(0);
+        let patch = numbers.next().flatten().unwrap_or(0);
+        Some([major, minor, patch])
+    }
+    let (Some(from), Some(to)) = (parse(from), parse(to)) else {
+        return Compatibility::Unknown;
+    };
+    // The first non-zero component is the one that breaks compatibility.
+    let significant = from.iter().position(|n| *n != 0).unwrap_or(2);
+    if from[..=significant] == to[..=significant] {
+        Compatibility::Compatible
+    } else {
+        Compatibility::Breaking
+    }
+}
+
+/// Extract the updates from the description of a Dependabot PR, either for a single
+/// dependency ("Bumps [serde](...) from 1.0.190 to 1.0.200.") or for a group ("Updates
+/// `serde` from 1.0.190 to 1.0.200", once per dependency).
+pub fn parse_updates(body: &str) -> Vec<DependencyUpdate> {
+    static UPDATE: Lazy<Regex> = lazy_regex!(
+        r"(?m)^(?:Updates `([^`]+)`|Bumps \[([^\]]+)\]\([^)]*\)) from (\S+?) to (\S+?)\.?\s*$"
+    );
+    static LINK: Lazy<Regex> = lazy_regex!(r"\[([^\]!]+)\]\((https?://[^)\s]+)\)");
+    static COMPARE: Lazy<Regex> = lazy_regex!(r#"<a href="([^"]+)">Compare view</a>"#);
+    static BADGE: Lazy<Regex> = lazy_regex!(
+        r"https://dependabot-badges\.githubapp\.com/badges/compatibility_score\?[^)\s]+"
+    );
+
+    let homes: HashMap<&str, &str> = LINK
+        .captures_iter(body)
+        .map(|found| {
+            let (_, [name, url]) = found.extract();
+            (name, url)
+        })
+        .collect();
+    let badges: HashMap<String, Url> = BADGE
+        .find_iter(body)
+        .filter_map(|found| Url::parse(found.as_str()).ok())
+        .filter_map(|url| {
+            let name = url
+                .query_pairs()
+                .find(|(key, _)| key == "dependency-name")?
+                .1
+                .into_owned();
+            Some((name, url))
+        })
+        .collect();
+
+    let headers = UPDATE.captures_iter(body).collect::<Vec<_>>();
+    let mut updates = vec![];
+    for (i, found) in headers.iter().enumerate() {
+        // Each update is described until the next one.
+        let start = found.get(0).map_or(0, |m| m.end());
+        let end = headers
+            .get(i + 1)
+            .and_then(|next| next.get(0))
+            .map_or(body.len(), |m| m.start());
+        let name = found
+            .get(1)
+            .or_else(|| found.get(2))
+            .map_or("", |m| m.as_str());
+        let changes = COMPARE
+            .captures(&body[start..end])
+            .map(|compare| compare[1].to_string())
+            .or_else(|| homes.get(name).map(|url| url.to_string()))
+            .and_then(|url| Url::parse(&html_escape::decode_html_entities(&url)).ok());
+        updates.push(DependencyUpdate {
+            name: name.to_string(),
+            from: found[3].to_string(),
+            to: found[4].to_string(),
+            changes,
+            badge: badges.get(name).cloned(),
+        });
+    }
+    updates
+}
+
+/// List the open Dependabot PRs of a project.
+pub async fn fetch_pulls(
+    octocrab: &Octocrab,
+    project: &Project,
+    per_page: u8,
+) -> Result<Vec<DependabotPull>, anyhow::Error> {
+    let query = format!(
+        "repo:{}/{} is:pr is:open author:app/dependabot",
+        project.owner, project.repo
+    );
+    let items = octocrab
+        .search()
+        .issues_and_pull_requests(&query)
+        .per_page(per_page)
+        .send()
+        .await
+        .map_err(Error::from_github)
+        .context("Couldn't search Dependabot PRs")?
+        .take_items();
+    Ok(items
+        .into_iter()
+        .map(|issue| DependabotPull {
+            number: issue.number,
+            url: issue.html_url,
+            updates: parse_updates(issue.body.as_deref().unwrap_or_default()),
+            title: issue.title,
+        })
+        .collect())
+}
+
+/// List the open Dependabot alerts of a project, most severe first.
+///
+/// Requires authentication, with "Dependabot alerts: read" for GitHub apps.
+pub async fn fetch_alerts(
+    octocrab: &Octocrab,
+    project: &Project,
+    per_page: u8,
+) -> Result<Vec<DependabotAlert>, anyhow::Error> {
+    /// Enough to find the most severe ones among the alerts of any sane project.
+    const MAX_ALERTS: usize = 1000;
+    let first: Page<DependabotAlert> = octocrab
+        .get(
+            format!(
+                "/repos/{owner}/{repo}/dependabot/alerts",
+                owner = project.owner,
+                repo = project.repo
+            ),
+            Some(&serde_json::json!({
+                "state": "open",
+                "sort": "created",
+                "per_page": per_page,
+            })),
+        )
+        .await
+        .map_err(Error::from_github)
+        .context("Couldn't download Dependabot alerts")?;
+    let (alerts, _) = github::collect_pages(octocrab, first, MAX_ALERTS)
+        .await
+        .context("Couldn't download Dependabot alerts")?;
+    Ok(by_severity(alerts)
+        .into_iter()
+        .flat_map(|(_, alerts)| alerts)
+        .collect())
+}
+
+/// Fetch Dependabot's compatibility score, as a percentage, from its badge.
+pub async fn fetch_score(client: reqwest::Client, badge: Url) -> Result<u8, anyhow::Error> {
+    static SCORE: Lazy<Regex> = lazy_regex!(r"(\d{1,3})%");
+    let svg = client
+        .get(badge)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .context("Couldn't fetch compatibility badge")?
+        .text()
+        .await
+        .context("Couldn't read compatibility badge")?;
+    let score = SCORE
+        .captures(&svg)
+        .context("No compatibility score in badge")?;
+    score[1].parse().context("Invalid compatibility score")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        by_severity, compatibility, parse_updates, AlertSeverity, Compatibility, DependabotAlert,
+    };
+
+    /// Do we follow Cargo's flavor of semver?
+    #[test]
+    fn test_compatibility() {
+        assert_eq!(
+            compatibility("1.0.190", "1.0.200"),
+            Compatibility::Compatible
+        );
+        assert_eq!(compatibility("v1.9.0", "v2.0.0"), Compatibility::Breaking);
+        assert_eq!(compatibility("0.11.3", "0.11.4"), Compatibility::Compatible);
+        assert_eq!(compatibility("0.11.3", "0.12.0"), Compatibility::Breaking);
+        assert_eq!(compatibility("0.0.3", "0.0.4"), Compatibility::Breaking);
+        assert_eq!(compatibility("abc", "1.0.0"), Compatibility::Unknown);
+    }
+
+    /// Are alerts grouped by severity, most severe first, then by package?
+    #[test]
+    fn test_by_severity() {
+        let alert = |number: u64, package: &str, severity: &str| {
+            serde_json::json!({
+                "number": number,
+                "html_url": format!("https://github.com/owner/repo/security/dependabot/{number}"),
+                "dependency": { "package": { "ecosystem": "cargo", "name": package } },
+                "security_advisory": { "summary": "Vulnerable", "severity": severity },
+            })
+        };
+        let alerts: Vec<DependabotAlert> = serde_json::from_value(serde_json::json!([
+            alert(1, "openssl", "low"),
+            alert(2, "tokio", "critical"),
+            alert(3, "h2", "critical"),
+            alert(4, "openssl", "high"),
+        ]))
+        .unwrap();
+        let groups: Vec<_> = by_severity(alerts)
+            .into_iter()
+            .map(|(severity, alerts)| {
+                let numbers: Vec<_> = alerts.iter().map(|alert| alert.number).collect();
+                (severity, numbers)
+            })
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                (AlertSeverity::Critical, vec![3, 2]),
+                (AlertSeverity::High, vec![4]),
+                (AlertSeverity::Low, vec![1]),
+            ]
+        );
+    }
+
+    /// Can we read the descriptions of single and grouped Dependabot PRs?
+    #[test]
+    fn test_parse_updates() {
+        let single = r#"Bumps [serde](https://github.com/serde-rs/serde) from 1.0.190 to 1.0.200.
+<details>
+<summary>Commits</summary>
+<ul>
+<li><a href="https://github.com/serde-rs/serde/compare/v1.0.190...v1.0.200">Compare view</a></li>
+</ul>
+</details>
+<br />
+
+[![Dependabot compatibility score](https://dependabot-badges.githubapp.com/badges/compatibility_score?dependency-name=serde&package-manager=cargo&previous-version=1.0.190&new-version=1.0.200)](https://docs.github.com/en/github/managing-security-vulnerabilities/about-dependabot-security-updates#about-compatibility-scores)
+"#;
+        let updates = parse_updates(single);
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].name, "serde");
+        assert_eq!(updates[0].from, "1.0.190");
+        assert_eq!(updates[0].to, "1.0.200");
+        assert_eq!(
+            updates[0].changes.as_ref().unwrap().as_str(),
+            "https://github.com/serde-rs/serde/compare/v1.0.190...v1.0.200"
+        );
+        assert!(updates[0].badge.is_some());
+
+        let grouped = r#"Bumps the cargo group with 2 updates: [tokio](https://github.com/tokio-rs/tokio) and [regex](https://github.com/rust-lang/regex).
+
+Updates `tokio` from 1.33.0 to 1.37.0
+<details>
+<li><a href="https://github.com/tokio-rs/tokio/compare/tokio-1.33.0...tokio-1.37.0">Compare view</a></li>
+</details>
+<br />
+
+Updates `regex` from 1.10.2 to 1.10.4
+"#;
+        let updates = parse_updates(grouped);
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].name, "tokio");
+        assert_eq!(
+            updates[0].changes.as_ref().unwrap().as_str(),
+            "https://github.com/tokio-rs/tokio/compare/tokio-1.33.0...tokio-1.37.0"
+        );
+        assert_eq!(updates[1].name, "regex");
+        assert_eq!(updates[1].to, "1.10.4");
+        assert_eq!(
+            updates[1].changes.as_ref().unwrap().as_str(),
+            "https://github.com/rust-lang/regex"
+        );
+        assert!(updates[1].badge.is_none());
+    }
+}
@@ -0,0 +1,181 @@
+use chrono::{DateTime, Datelike, NaiveDate, Utc, Weekday};
+use itertools::Itertools;
+
+/// The buckets of `AgeHistogram`, by label and upper bound in days (exclusive).
+const BUCKETS: [(&str, Option<i64>); 4] = [
+    ("<1d", Some(1)),
+    ("1–3d", Some(3)),
+    ("3–7d", Some(7)),
+    (">7d", None),
+];
+
+/// The width of the longest bar, in characters.
+const MAX_BAR: usize = 20;
+
+/// The width of progress bars, in characters.
+const PROGRESS_BAR: usize = 10;
+
+/// Whether the weekly digest is due today, given the day of the week on which it's sent
+/// and the day it was last sent, if ever.
+pub fn is_due(now: DateTime<Utc>, day: Weekday, last_sent: Option<NaiveDate>) -> bool {
+    let today = now.date_naive();
+    today.weekday() == day && last_sent.is_none_or(|last| last < today)
+}
+
+/// How many open PRs fall into each age bucket.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct AgeHistogram {
+    counts: [usize; BUCKETS.len()],
+}
+impl AgeHistogram {
+    /// Sort PRs by age, given when they were opened.
+    pub fn new(opened: impl IntoIterator<Item = DateTime<Utc>>, now: DateTime<Utc>) -> Self {
+        let mut histogram = AgeHistogram::default();
+        for opened in opened {
+            let days = (now - opened).num_days();
+            let bucket = BUCKETS
+                .iter()
+                .position(|(_, bound)| bound.is_none_or(|bound| days < bound))
+                .expect("the last bucket has no bound");
+            histogram.counts[bucket] += 1;
+        }
+        histogram
+    }
+
+    /// The total number of PRs.
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// One line per bucket, e.g. `1–3d  ████ 4`, to display in monospace.
+    pub fn render(&self) -> String {
+        let max = self.counts.iter().copied().max().unwrap_or_default().max(1);
+        BUCKETS
+            .iter()
+            .zip(self.counts)
+            .map(|((label, _), count)| {
+                // Non-empty buckets get at least a sliver, so that they stand out.
+                let width = (count * MAX_BAR).div_ceil(max);
+                format!("{label:<5} {} {count}", "█".repeat(width))
+            })
+            .join("\n")
+    }
+}
+
+/// What happened in a project during the past week.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct WeeklyActivity {
+    /// The number of PRs merged.
+    pub merged: usize,
+
+    /// The number of issues opened, and closed.
+    pub opened: usize,
+    pub closed: usize,
+
+    /// The most active reviewers, with their number of reviews, if we could find out (it
+    /// requires GraphQL, hence authentication).
+    pub reviewers: Option<Vec<(String, usize)>>,
+}
+
+/// The `max` most active reviewers, with their number of reviews, given the author of each
+/// review. Ties are broken by login.
+pub fn top_reviewers(
+    authors: impl IntoIterator<Item = String>,
+    max: usize,
+) -> Vec<(String, usize)> {
+    authors
+        .into_iter()
+        .counts()
+        .into_iter()
+        .sorted_by(|(a, count_a), (b, count_b)| count_b.cmp(count_a).then(a.cmp(b)))
+        .take(max)
+        .collect()
+}
+
+/// A progress bar, e.g. `▓▓▓▓▓▓░░░░ 60%`, to display in monospace.
+pub fn progress_bar(closed: u64, open: u64) -> String {
+    let total = closed + open;
+    let percent = match total {
+        0 => 0,
+        _ => closed * 100 / total,
+    };
+    let done = match total {
+        0 => 0,
+        _ => (closed as usize * PROGRESS_BAR) / total as usize,
+    };
+    format!(
+        "{}{} {percent}%",
+        "▓".repeat(done),
+        "░".repeat(PROGRESS_BAR - done)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, TimeZone, Utc, Weekday};
+
+    use super::{is_due, progress_bar, top_reviewers, AgeHistogram};
+
+    /// Are PRs sorted into the right buckets, and drawn to scale?
+    #[test]
+    fn test_age_histogram() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 6, 12, 0, 0).unwrap();
+        let ages = [
+            Duration::hours(2),
+            Duration::hours(23),
+            Duration::hours(30),
+            Duration::days(3),
+            Duration::days(6),
+            Duration::days(7),
+            Duration::days(100),
+            Duration::days(200),
+        ];
+        let histogram = AgeHistogram::new(ages.iter().map(|age| now - *age), now);
+        assert_eq!(histogram.counts, [2, 1, 2, 3]);
+        assert_eq!(histogram.total(), 8);
+        assert_eq!(
+            histogram.render(),
+            "<1d   ██████████████ 2\n\
+             1–3d  ███████ 1\n\
+             3–7d  ██████████████ 2\n\
+             >7d   ████████████████████ 3"
+        );
+        assert_eq!(
+            AgeHistogram::new([], now).render().lines().next(),
+            Some("<1d    0")
+        );
+    }
+
+    /// Are progress bars rounded down, so that they're only full once everything is done?
+    #[test]
+    fn test_progress_bar() {
+        assert_eq!(progress_bar(6, 4), "▓▓▓▓▓▓░░░░ 60%");
+        assert_eq!(progress_bar(99, 1), "▓▓▓▓▓▓▓▓▓░ 99%");
+        assert_eq!(progress_bar(0, 0), "░░░░░░░░░░ 0%");
+    }
+
+    /// Are the reviewers with the most reviews first?
+    #[test]
+    fn test_top_reviewers() {
+        let authors = ["bob", "alice", "carol", "bob", "alice", "dave", "bob"];
+        assert_eq!(
+            top_reviewers(authors.map(str::to_string), 2),
+            [("bob".to_string(), 3), ("alice".to_string(), 2)]
+        );
+    }
+
+    /// Is the digest sent once, on the right day of the week?
+    #[test]
+    fn test_is_due() {
+        // A Monday.
+        let now = Utc.with_ymd_and_hms(2024, 5, 6, 12, 0, 0).unwrap();
+        assert!(is_due(now, Weekday::Mon, None));
+        assert!(!is_due(now, Weekday::Tue, None));
+        assert!(!is_due(now, Weekday::Mon, Some(now.date_naive())));
+        assert!(is_due(
+            now,
+            Weekday::Mon,
+            Some((now - Duration::days(7)).date_naive())
+        ));
+    }
+}
@@ -0,0 +1,109 @@
+use std::ops::Not;
+
+use itertools::Itertools;
+use serde::Serialize;
+use serde_json::json;
+use url::Url;
+
+use crate::slack::{self, MessageOptions};
+
+/// Discord's limit on the description of an embed.
+const MAX_DESCRIPTION: usize = 4096;
+
+/// Discord's limit on the content of a message.
+const MAX_CONTENT: usize = 2000;
+
+/// Whether a hook is a Discord webhook, e.g. `https://discord.com/api/webhooks/XXX/YYY`.
+pub fn is_discord(hook: &Url) -> bool {
+    matches!(
+        hook.host_str(),
+        Some("discord.com" | "discordapp.com" | "ptb.discord.com" | "canary.discord.com")
+    )
+}
+
+/// A section, as a Discord embed.
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct Embed {
+    description: String,
+}
+
+/// Render a section as an embed: the title, then one line per row of fields.
+pub fn embed(section: &slack::Section, options: &MessageOptions) -> Embed {
+    let mut description = slack::to_markdown(section.title(), options);
+    for row in &section.fields().chunks(2) {
+        description.push('\n');
+        description.push_str(
+            &row.map(|field| slack::to_markdown(field, options))
+                .filter(|field| field.is_empty().not())
+                .join(" · "),
+        );
+    }
+    Embed {
+        description: truncate(description, MAX_DESCRIPTION),
+    }
+}
+
+/// Cut text to `max` characters, ending with an ellipsis if it's too long.
+fn truncate(mut text: String, max: usize) -> String {
+    if text.chars().count() > max {
+        text = text.chars().take(max - 1).collect();
+        text.push('…');
+    }
+    text
+}
+
+/// Render a section as the plain text content of a message, without an embed.
+pub fn plain_payload(section: &slack::Section) -> serde_json::Value {
+    json!({ "content": truncate(section.plain_text(), MAX_CONTENT) })
+}
+
+/// Render a section as the body of a message to a Discord webhook.
+pub fn payload(section: &slack::Section, options: &MessageOptions) -> serde_json::Value {
+    json!({ "embeds": [embed(section, options)] })
+}
+
+#[cfg(test)]
+mod test {
+    use url::Url;
+
+    use super::{embed, is_discord, Embed};
+    use crate::slack::{self, LinkStyle, MessageOptions};
+
+    /// Do reports read the same on Discord?
+    #[test]
+    fn test_embed() {
+        let url = Url::parse("https://github.com/owner1/project1/pull/1").unwrap();
+        let mut section = slack::Section::new(format!(
+            "PRs of repo {} waiting for reviews",
+            slack::link(&url, Some("project1"))
+        ));
+        section.append_fields(&["*Request*".to_string(), "*Reviewer*".to_string()]);
+        section.append_fields(&[
+            format!("🐞 {}", slack::link(&url, Some("Fix <b>"))),
+            "alice".to_string(),
+        ]);
+        assert_eq!(
+            embed(&section, &MessageOptions::default()),
+            Embed {
+                description: "PRs of repo [project1](https://github.com/owner1/project1/pull/1) waiting for reviews\n\
+                    **Request** · **Reviewer**\n\
+                    🐞 [Fix <b>](https://github.com/owner1/project1/pull/1) · alice"
+                    .to_string()
+            }
+        );
+        let compact = MessageOptions {
+            link_style: LinkStyle::Compact,
+            ..Default::default()
+        };
+        assert!(embed(&section, &compact)
+            .description
+            .starts_with("PRs of repo project1 waiting"));
+
+        assert!(is_discord(
+            &Url::parse("https://discord.com/api/webhooks/1/abc").unwrap()
+        ));
+        assert!(!is_discord(
+            &Url::parse("https://hooks.slack.com/services/A/B/C").unwrap()
+        ));
+    }
+}
@@ -0,0 +1,111 @@
+use anyhow::Context;
+use itertools::Itertools;
+use lettre::{
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+
+use crate::{
+    config::{Smtp, SmtpSecurity},
+    error::Error,
+    slack,
+};
+
+/// Build an email with the given sections, as HTML with a plain text alternative.
+pub fn message(
+    from: &str,
+    to: &[String],
+    subject: &str,
+    sections: &[slack::Section],
+) -> Result<Message, anyhow::Error> {
+    let mailbox = |address: &str| {
+        address
+            .parse::<Mailbox>()
+            .map_err(|err| Error::Config(format!("invalid email address {address}: {err}")))
+    };
+    let mut builder = Message::builder().from(mailbox(from)?).subject(subject);
+    for address in to {
+        builder = builder.to(mailbox(address)?);
+    }
+    let plain = sections.iter().map(slack::Section::plain_text).join("\n\n");
+    let html = format!(
+        "<html><body>{}</body></html>",
+        sections.iter().map(slack::Section::to_html).join("")
+    );
+    builder
+        .multipart(MultiPart::alternative_plain_html(plain, html))
+        .context("Could not build email")
+}
+
+/// Email sections to some recipients.
+///
+/// Returns the number of bytes sent.
+pub async fn send(
+    smtp: &Smtp,
+    to: &[String],
+    subject: &str,
+    sections: &[slack::Section],
+) -> Result<usize, anyhow::Error> {
+    let message = message(&smtp.from, to, subject, sections)?;
+    let mut transport = match smtp.security {
+        SmtpSecurity::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp.host)
+            .with_context(|| format!("Could not set up SMTP with {}", smtp.host))?,
+        SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&smtp.host)
+            .with_context(|| format!("Could not set up SMTP with {}", smtp.host))?,
+        SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.host),
+    };
+    if let Some(port) = smtp.port {
+        transport = transport.port(port);
+    }
+    if let (Some(username), Some(password)) = (&smtp.username, &smtp.password) {
+        transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+    let sent_bytes = message.formatted().len();
+    transport
+        .build()
+        .send(message)
+        .await
+        .with_context(|| format!("Could not send email through {}", smtp.host))?;
+    Ok(sent_bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use url::Url;
+
+    use super::message;
+    use crate::slack;
+
+    /// Do emails carry both the HTML report and a plain text alternative?
+    #[test]
+    fn test_message() {
+        let url = Url::parse("https://github.com/owner1/project1/pull/1").unwrap();
+        let mut section =
+            slack::Section::new("PRs of repo project1 waiting for reviews".to_string());
+        section.append_fields(&[slack::link(&url, Some("Fix")), "alice".to_string()]);
+        let email = message(
+            "Qastor <qastor@example.org>",
+            &[
+                "alice@example.org".to_string(),
+                "bob@example.org".to_string(),
+            ],
+            "Report of owner1/project1",
+            &[section],
+        )
+        .unwrap();
+        let formatted = String::from_utf8(email.formatted()).unwrap();
+        assert!(formatted.contains("To: alice@example.org, bob@example.org"));
+        assert!(formatted.contains("Content-Type: multipart/alternative"));
+        assert!(formatted.contains("Content-Type: text/html"));
+        assert!(formatted.contains("PRs of repo project1 waiting for reviews\r\nFix\talice"));
+
+        assert!(message(
+            "qastor@example.org",
+            &["not an address".to_string()],
+            "Report",
+            &[]
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,131 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use log::{debug, warn};
+use serde::Deserialize;
+use tokio::{sync::Semaphore, task::JoinSet};
+
+/// Limits on enrichment, i.e. the details fetched with one API call per item
+/// (e.g. the reviews of each PR).
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EnrichmentConfig {
+    /// How many calls we make in parallel.
+    #[serde(default = "EnrichmentConfig::default_concurrency")]
+    pub concurrency: usize,
+
+    /// How many calls we make during a run, across all projects. Once exhausted,
+    /// reports omit the details.
+    #[serde(default = "EnrichmentConfig::default_budget")]
+    pub budget: usize,
+}
+impl EnrichmentConfig {
+    fn default_concurrency() -> usize {
+        4
+    }
+    fn default_budget() -> usize {
+        200
+    }
+}
+impl Default for EnrichmentConfig {
+    fn default() -> Self {
+        EnrichmentConfig {
+            concurrency: Self::default_concurrency(),
+            budget: Self::default_budget(),
+        }
+    }
+}
+
+/// Fetches details of many items in parallel, within a budget of API calls shared
+/// by the whole run.
+pub struct Enricher {
+    semaphore: Arc<Semaphore>,
+    remaining: AtomicUsize,
+}
+impl Enricher {
+    pub fn new(config: &EnrichmentConfig) -> Self {
+        Enricher {
+            semaphore: Arc::new(Semaphore::new(config.concurrency.max(1))),
+            remaining: AtomicUsize::new(config.budget),
+        }
+    }
+
+    /// Spend one API call, if the budget allows.
+    fn spend(&self) -> bool {
+        self.remaining
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |remaining| {
+                remaining.checked_sub(1)
+            })
+            .is_ok()
+    }
+
+    /// Fetch the details of each key, in parallel, as long as the budget lasts.
+    ///
+    /// Keys beyond the budget, or whose details could not be fetched, are missing
+    /// from the result.
+    pub async fn enrich<K, V, F, Fut>(
+        &self,
+        keys: impl IntoIterator<Item = K>,
+        fetch: F,
+    ) -> HashMap<K, V>
+    where
+        K: Eq + Hash + Clone + Send + 'static,
+        V: Send + 'static,
+        F: Fn(K) -> Fut,
+        Fut: Future<Output = Result<V, anyhow::Error>> + Send + 'static,
+    {
+        let mut tasks = JoinSet::new();
+        for key in keys {
+            if !self.spend() {
+                debug!("Enrichment budget exhausted, omitting details");
+                break;
+            }
+            let semaphore = self.semaphore.clone();
+            let details = fetch(key.clone());
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                (key, details.await)
+            });
+        }
+        let mut result = HashMap::new();
+        while let Some(joined) = tasks.join_next().await {
+            match joined {
+                Ok((key, Ok(value))) => {
+                    result.insert(key, value);
+                }
+                Ok((_, Err(err))) => warn!("Could not fetch details: {err:?}"),
+                Err(err) => warn!("Could not fetch details: {err:?}"),
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Enricher, EnrichmentConfig};
+
+    /// Do we stop fetching details once the budget is exhausted, across calls?
+    #[tokio::test]
+    async fn test_enrich_budget() {
+        let enricher = Enricher::new(&EnrichmentConfig {
+            concurrency: 2,
+            budget: 3,
+        });
+        let first = enricher.enrich([1, 2], |n| async move { Ok(n * 10) }).await;
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[&2], 20);
+        let second = enricher.enrich([3, 4], |n| async move { Ok(n * 10) }).await;
+        assert_eq!(second.len(), 1);
+        assert!(enricher
+            .enrich([5], |n| async move { Ok(n) })
+            .await
+            .is_empty());
+    }
+}
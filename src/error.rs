@@ -0,0 +1,155 @@
+use std::ops::Not;
+
+use derive_more::{Display, Error};
+use serde::Serialize;
+
+/// The classes of errors that we react to differently, e.g. by not retrying, by warning
+/// the administrators or by picking an exit code.
+///
+/// Errors are still propagated as `anyhow::Error`, with context, and this type at the
+/// root of the chain. Use `Error::find` to recover it.
+#[derive(Display, Error, Debug)]
+pub enum Error {
+    /// GitHub refused our credentials.
+    #[display("authentication failed: {_0}")]
+    Auth(#[error(not(source))] String),
+
+    /// GitHub's rate limit is exhausted.
+    #[display("rate limited: {_0}")]
+    RateLimited(#[error(not(source))] String),
+
+    /// The resource doesn't exist, or we're not allowed to see it.
+    #[display("not found: {_0}")]
+    NotFound(#[error(not(source))] String),
+
+    /// Slack, Discord, Teams, Matrix or Telegram refused a message, e.g. because the hook was
+    /// revoked.
+    #[display("{service} responded with an error {status}: {body}")]
+    SinkRejected {
+        /// The service behind the sink, e.g. "Slack".
+        service: String,
+        status: reqwest::StatusCode,
+        body: String,
+    },
+
+    /// The config or the secrets are invalid.
+    #[display("invalid configuration: {_0}")]
+    Config(#[error(not(source))] String),
+}
+/// The class of an error, e.g. for metrics and reports.
+#[derive(Serialize, Display, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    #[display("auth")]
+    Auth,
+    #[display("rate_limited")]
+    RateLimited,
+    #[display("not_found")]
+    NotFound,
+    #[display("sink_rejected")]
+    SinkRejected,
+    #[display("config")]
+    Config,
+    #[display("other")]
+    Other,
+}
+impl ErrorClass {
+    /// The exit code of the process when an error of this class is fatal.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            ErrorClass::Config => 2,
+            ErrorClass::Auth => 3,
+            ErrorClass::RateLimited => 4,
+            ErrorClass::SinkRejected => 5,
+            ErrorClass::NotFound | ErrorClass::Other => 1,
+        }
+    }
+}
+
+impl Error {
+    /// Classify an error returned by GitHub.
+    pub fn from_github(err: octocrab::Error) -> anyhow::Error {
+        let octocrab::Error::GitHub { ref source, .. } = err else {
+            return err.into();
+        };
+        let message = source.message.clone();
+        match source.status_code.as_u16() {
+            401 => Error::Auth(message).into(),
+            403 if message.to_lowercase().contains("rate limit") => {
+                Error::RateLimited(message).into()
+            }
+            429 => Error::RateLimited(message).into(),
+            404 => Error::NotFound(message).into(),
+            _ => err.into(),
+        }
+    }
+
+    /// The class of an error, if it has one.
+    pub fn find(err: &anyhow::Error) -> Option<&Error> {
+        err.chain().find_map(|cause| cause.downcast_ref::<Error>())
+    }
+
+    /// The class of an error, `Other` if it has none.
+    pub fn class(err: &anyhow::Error) -> ErrorClass {
+        match Error::find(err) {
+            Some(Error::Auth(_)) => ErrorClass::Auth,
+            Some(Error::RateLimited(_)) => ErrorClass::RateLimited,
+            Some(Error::NotFound(_)) => ErrorClass::NotFound,
+            Some(Error::SinkRejected { .. }) => ErrorClass::SinkRejected,
+            Some(Error::Config(_)) => ErrorClass::Config,
+            None => ErrorClass::Other,
+        }
+    }
+
+    /// Whether trying again with the same input may succeed.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::RateLimited(_) => true,
+            Error::SinkRejected { status, .. } => {
+                status.is_server_error() || *status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            Error::Auth(_) | Error::NotFound(_) | Error::Config(_) => false,
+        }
+    }
+
+    /// Whether the administrators need to step in, e.g. to renew a token.
+    pub fn needs_admin(&self) -> bool {
+        match self {
+            Error::Auth(_) | Error::Config(_) => true,
+            Error::SinkRejected { .. } => self.is_transient().not(),
+            Error::RateLimited(_) | Error::NotFound(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Context;
+
+    use super::{Error, ErrorClass};
+
+    /// Can we recover the class of an error below some context?
+    #[test]
+    fn test_find() {
+        let err = Err::<(), _>(Error::Auth("Bad credentials".to_string()))
+            .context("Couldn't fetch repository")
+            .context("Error handling project")
+            .unwrap_err();
+        assert!(matches!(Error::find(&err), Some(Error::Auth(_))));
+        assert_eq!(Error::class(&err), ErrorClass::Auth);
+        assert_eq!(Error::class(&err).exit_code(), 3);
+
+        let err = anyhow::anyhow!("Something else");
+        assert!(Error::find(&err).is_none());
+        assert_eq!(Error::class(&err), ErrorClass::Other);
+        assert_eq!(Error::class(&err).exit_code(), 1);
+
+        let rejected = Error::SinkRejected {
+            service: "Slack".to_string(),
+            status: reqwest::StatusCode::NOT_FOUND,
+            body: "no_service".to_string(),
+        };
+        assert!(!rejected.is_transient());
+        assert!(rejected.needs_admin());
+    }
+}
@@ -0,0 +1,92 @@
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+
+use crate::github::ReviewActivity;
+
+/// The review requests received and the reviews completed by a reviewer.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Tally {
+    /// On how many PRs the reviewer was asked for a review.
+    pub requested: usize,
+
+    /// How many PRs the reviewer reviewed.
+    pub reviewed: usize,
+}
+
+/// The review load of each reviewer, by lowercase login, across PRs.
+///
+/// Re-requests and successive reviews of the same PR count once.
+pub fn tally(activity: impl IntoIterator<Item = ReviewActivity>) -> BTreeMap<String, Tally> {
+    let mut tallies = BTreeMap::<String, Tally>::new();
+    for pull in activity {
+        for login in pull
+            .requested
+            .iter()
+            .map(|login| login.to_lowercase())
+            .unique()
+        {
+            tallies.entry(login).or_default().requested += 1;
+        }
+        for login in pull
+            .reviewed
+            .iter()
+            .map(|login| login.to_lowercase())
+            .unique()
+        {
+            tallies.entry(login).or_default().reviewed += 1;
+        }
+    }
+    tallies
+}
+
+/// The reviewers who received at least `max_share` (e.g. `0.5`) of all review requests,
+/// with their share.
+pub fn imbalances(tallies: &BTreeMap<String, Tally>, max_share: f64) -> Vec<(&str, f64)> {
+    let total: usize = tallies.values().map(|tally| tally.requested).sum();
+    if total == 0 {
+        return vec![];
+    }
+    tallies
+        .iter()
+        .map(|(login, tally)| (login.as_str(), tally.requested as f64 / total as f64))
+        .filter(|(_, share)| *share >= max_share)
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{imbalances, tally, Tally};
+    use crate::github::ReviewActivity;
+
+    /// Are requests and reviews counted once per PR, and overloaded reviewers spotted?
+    #[test]
+    fn test_tally() {
+        let activity = |requested: &[&str], reviewed: &[&str]| ReviewActivity {
+            requested: requested.iter().map(|login| login.to_string()).collect(),
+            reviewed: reviewed.iter().map(|login| login.to_string()).collect(),
+        };
+        let tallies = tally([
+            activity(&["alice", "Alice"], &["alice", "alice"]),
+            activity(&["alice", "bob"], &["bob"]),
+            activity(&["alice"], &[]),
+            activity(&[], &["carol"]),
+        ]);
+        assert_eq!(
+            tallies["alice"],
+            Tally {
+                requested: 3,
+                reviewed: 1
+            }
+        );
+        assert_eq!(
+            tallies["carol"],
+            Tally {
+                requested: 0,
+                reviewed: 1
+            }
+        );
+        assert_eq!(imbalances(&tallies, 0.6), vec![("alice", 0.75)]);
+        assert!(imbalances(&Default::default(), 0.6).is_empty());
+    }
+}
@@ -0,0 +1,1124 @@
+//! Fetching what each section of a report lists.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    ops::Not,
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use log::{error, warn};
+use octocrab::{
+    models::{issues::Issue, pulls::PullRequest, Milestone, Repository},
+    params, Octocrab,
+};
+use url::Url;
+
+use crate::{
+    codeowners,
+    config::{Config, Drafts, Project, Window},
+    dependabot::{self, DependabotAlert, DependabotPull},
+    digest::{self, AgeHistogram, WeeklyActivity},
+    enrich::Enricher,
+    error::Error,
+    github::{
+        self, Discussion, ForcePush, IssueDetails, LabelChange, PendingReview, SearchResults,
+        WorkflowRun,
+    },
+    lifecycle::Lifecycle,
+    popularity::{self, Change, Counts, Record},
+    release::{self, Published},
+    render::has_waited,
+    report::{self, SectionKind},
+    scanning::{self, Scanner, ScanningAlert},
+    sla::{self, Breach},
+    state::{Ack, State},
+};
+
+/// Drop the items that we have announced since a date, e.g. within `reping_after`, unless
+/// they have been updated since we announced them.
+fn drop_seen_since<T>(
+    state: &State,
+    project: &Url,
+    since: DateTime<Utc>,
+    items: &mut Vec<T>,
+    key: impl Fn(&T) -> (u64, Option<DateTime<Utc>>),
+) -> Result<(), anyhow::Error> {
+    let mut kept = Vec::with_capacity(items.len());
+    for item in items.drain(..) {
+        let (number, updated_at) = key(&item);
+        match state.seen(project, number)? {
+            Some(seen) if seen >= since && updated_at.is_none_or(|at| at <= seen) => {}
+            _ => kept.push(item),
+        }
+    }
+    *items = kept;
+    Ok(())
+}
+
+/// The items announced in the sections that went out, which we may then mark as seen.
+pub fn delivered_items(
+    notified: Vec<(SectionKind, u64)>,
+    delivered: &BTreeSet<SectionKind>,
+) -> Vec<u64> {
+    notified
+        .into_iter()
+        .filter(|(kind, _)| delivered.contains(kind))
+        .map(|(_, number)| number)
+        .unique()
+        .collect_vec()
+}
+
+/// List the PRs waiting for a review, up to `max_items` open PRs.
+///
+/// Returns the PRs, and whether we have seen all the open PRs.
+pub async fn fetch_pending_requests(
+    octocrab: &Octocrab,
+    config: &Config,
+    project: &Project,
+) -> Result<(Vec<PendingReview>, bool), anyhow::Error> {
+    let (requests, complete) = fetch_open_pulls(octocrab, config, project).await?;
+
+    // We're only interested in pending requests (i.e. requests with
+    // a pending review).
+    let mut result = vec![];
+    for pull in requests {
+        match PendingReview::from_pull(pull) {
+            Ok(Some(review)) => result.push(review),
+            Ok(None) => {}
+            Err(err) => error!("In project {}, {err}, skipping", project.url),
+        }
+    }
+    Ok((result, complete))
+}
+
+/// Whether the reports of a project list a PR waiting for a review, given its labels and its
+/// author. Drafts are up to `Project::drafts`.
+pub fn shows_request(config: &Config, project: &Project, pull: &PendingReview) -> bool {
+    project.shows(pull.labels.iter().map(String::as_str))
+        && pull
+            .author
+            .as_deref()
+            .is_none_or(|author| config.ignores_author(author).not())
+}
+
+/// List open pull requests, oldest first, up to `max_items`.
+///
+/// Returns whether the list is complete. If not, the most recent PRs are left out, rather
+/// than those waiting (or stale) for the longest time.
+pub async fn fetch_open_pulls(
+    octocrab: &Octocrab,
+    config: &Config,
+    project: &Project,
+) -> Result<(Vec<PullRequest>, bool), anyhow::Error> {
+    let handler = octocrab.pulls(&project.owner, &project.repo);
+    let mut list = handler
+        .list()
+        .state(params::State::Open)
+        .sort(params::pulls::Sort::Created)
+        .direction(params::Direction::Ascending);
+    if let Some(per_page) = config.per_page(project) {
+        list = list.per_page(per_page);
+    }
+    let first = list
+        .send()
+        .await
+        .map_err(Error::from_github)
+        .context("Couldn't download open pull requests")?;
+    github::collect_pages(octocrab, first, config.max_items(project))
+        .await
+        .context("Couldn't download open pull requests")
+}
+
+/// Fetch issue types and sub-issues.
+///
+/// They're only available through GraphQL. They're nice to have, so don't fail if
+/// we cannot get them (e.g. GraphQL requires authentication).
+pub async fn fetch_issue_details(
+    octocrab: &Octocrab,
+    project: &Project,
+    issues: &[Issue],
+) -> HashMap<u64, IssueDetails> {
+    let numbers = issues.iter().map(|issue| issue.number).collect_vec();
+    github::fetch_issue_details(octocrab, project, &numbers)
+        .await
+        .unwrap_or_else(|err| {
+            warn!("Could not fetch issue types and sub-issues: {err:?}");
+            HashMap::new()
+        })
+}
+
+/// The acknowledged PRs among `pulls`.
+///
+/// With `reaction_acks`, a 👀 reaction of a requested reviewer acknowledges a PR, and is
+/// recorded in the state so that we don't need to fetch it again.
+pub async fn fetch_acks(
+    config: &Config,
+    state: &mut State,
+    enricher: &Enricher,
+    octocrab: &Octocrab,
+    project: &Project,
+    configured: &Url,
+    pulls: &[PendingReview],
+) -> Result<HashMap<u64, Ack>, anyhow::Error> {
+    let mut acks = HashMap::new();
+    let mut unacked = vec![];
+    for pull in pulls {
+        match state.ack(configured, pull.number)? {
+            Some(ack) => {
+                acks.insert(pull.number, ack);
+            }
+            None => unacked.push(pull),
+        }
+    }
+    if config.reaction_acks.not() || unacked.is_empty() {
+        return Ok(acks);
+    }
+    let reactions = enricher
+        .enrich(unacked.iter().map(|pull| pull.number), |number| {
+            github::fetch_eyes(octocrab.clone(), project.clone(), number)
+        })
+        .await;
+    for pull in unacked {
+        let Some(reaction) = reactions
+            .get(&pull.number)
+            .and_then(|reactions| github::reviewer_reaction(&pull.reviewers, reactions))
+        else {
+            continue;
+        };
+        let ack = Ack {
+            by: reaction.login().unwrap_or_default().to_string(),
+            at: reaction.created_at,
+        };
+        state.set_ack(configured, pull.number, &ack)?;
+        acks.insert(pull.number, ack);
+    }
+    Ok(acks)
+}
+
+/// With `force_pushes`, the PRs whose head was force-pushed since the last run.
+pub async fn fetch_force_pushes(
+    config: &Config,
+    enricher: &Enricher,
+    octocrab: &Octocrab,
+    project: &Project,
+    last_run: Option<DateTime<Utc>>,
+    pulls: &[PendingReview],
+) -> HashMap<u64, ForcePush> {
+    // On the first run, we can't tell what reviewers have seen.
+    let Some(last_run) = last_run.filter(|_| config.force_pushes) else {
+        return HashMap::new();
+    };
+    enricher
+        .enrich(pulls.iter().map(|pull| pull.number), |number| {
+            github::fetch_force_push(octocrab.clone(), project.clone(), number)
+        })
+        .await
+        .into_iter()
+        .filter_map(|(number, push)| Some((number, push?)))
+        .filter(|(_, push)| push.at > last_run)
+        .collect()
+}
+
+/// The aliases of the hooks of the code owners of each PR, with `code_owners`, within the
+/// enrichment budget.
+///
+/// They're nice to have, so don't fail if we cannot get them: PRs then follow the routes of
+/// the project as usual.
+pub async fn fetch_code_owner_hooks(
+    enricher: &Enricher,
+    octocrab: &Octocrab,
+    project: &Project,
+    pulls: &[PendingReview],
+) -> HashMap<u64, Vec<String>> {
+    if project.code_owners.is_empty() || pulls.is_empty() {
+        return HashMap::new();
+    }
+    let owners = match codeowners::fetch(octocrab, project).await {
+        Ok(Some(owners)) => owners,
+        Ok(None) => {
+            warn!("Project {} has no CODEOWNERS file", project.url);
+            return HashMap::new();
+        }
+        Err(err) => {
+            warn!(
+                "Could not read the CODEOWNERS file of {}: {err:?}",
+                project.url
+            );
+            return HashMap::new();
+        }
+    };
+    let hooks: HashMap<_, _> = enricher
+        .enrich(pulls.iter().map(|pull| pull.number), |number| {
+            github::fetch_pull_files(octocrab.clone(), project.clone(), number)
+        })
+        .await
+        .into_iter()
+        .map(|(number, files)| {
+            let owners = files
+                .iter()
+                .flat_map(|file| owners.owners(&file.filename))
+                .map(String::as_str);
+            (number, project.code_owner_hooks(owners))
+        })
+        .collect();
+    if hooks.len() < pulls.len() {
+        warn!(
+            "Could not find the code owners of {} PRs of {} (see `enrichment`), they follow the routes instead",
+            pulls.len() - hooks.len(),
+            project.url
+        );
+    }
+    hooks
+}
+
+/// The open issues of a project that breach an SLA policy, and haven't been escalated yet.
+///
+/// Issues opened before we started checking a policy are left alone, as nobody was
+/// committed to it back then.
+async fn fetch_sla_breaches(
+    config: &Config,
+    state: &mut State,
+    octocrab: &Octocrab,
+    project: &Project,
+    configured: &Url,
+    now: DateTime<Utc>,
+) -> Result<Vec<Breach>, anyhow::Error> {
+    let per_page = config.per_page(project).unwrap_or(100);
+    let mut breaches: Vec<Breach> = vec![];
+    for policy in &config.sla {
+        let enabled = match state.sla_enabled(configured, &policy.label)? {
+            Some(enabled) => enabled,
+            None => {
+                state.set_sla_enabled(configured, &policy.label, now)?;
+                now
+            }
+        };
+        let issues = github::fetch_labelled_issues(octocrab, project, &policy.label, per_page)
+            .await
+            .unwrap_or_else(|err| {
+                warn!(
+                    "Could not fetch issues with label {}: {err:?}",
+                    policy.label
+                );
+                vec![]
+            });
+        for issue in issues {
+            if issue.created_at < enabled
+                || breaches
+                    .iter()
+                    .any(|breach| breach.issue.number == issue.number)
+                || state.escalated(configured, issue.number)?.is_some()
+                || sla::breach(policy, issue.created_at, None, now, config.holidays()).is_none()
+            {
+                continue;
+            }
+            // Past the deadline, a response may still have come in time.
+            let responded_at = if issue.comments == 0 {
+                None
+            } else {
+                match github::fetch_comments(octocrab, project, issue.number, per_page).await {
+                    Ok(comments) => sla::first_response(&comments),
+                    Err(err) => {
+                        warn!("Could not fetch comments of #{}: {err:?}", issue.number);
+                        continue;
+                    }
+                }
+            };
+            if let Some(deadline) = sla::breach(
+                policy,
+                issue.created_at,
+                responded_at,
+                now,
+                config.holidays(),
+            ) {
+                breaches.push(Breach {
+                    issue,
+                    policy: policy.clone(),
+                    deadline,
+                    responded_at,
+                });
+            }
+        }
+    }
+    report::sort(&mut breaches);
+    Ok(breaches)
+}
+
+/// Among issues that have vanished from the report, find those that have been
+/// transferred to another repository, along with their old number.
+///
+/// Like issue details, this is nice to have, so don't fail if we cannot find out.
+async fn fetch_transferred_issues(
+    octocrab: &Octocrab,
+    project: &Project,
+    vanished: impl Iterator<Item = u64>,
+) -> Vec<(u64, Issue)> {
+    /// Don't spend too many API calls if many issues have vanished at once.
+    const MAX_CHECKS: usize = 20;
+    let mut result = vec![];
+    for number in vanished.take(MAX_CHECKS) {
+        match github::transferred_to(octocrab, project, number).await {
+            Ok(Some(issue)) => result.push((number, issue)),
+            Ok(None) => {}
+            Err(err) => warn!("Could not check whether issue {number} was transferred: {err:?}"),
+        }
+    }
+    result
+}
+
+/// The releases and tags of a project published since the last run, along with its current
+/// tags.
+///
+/// The first time, we only list the tags.
+async fn fetch_published(
+    octocrab: &Octocrab,
+    state: &State,
+    project: &Project,
+    configured: &Url,
+    last_run: Option<DateTime<Utc>>,
+    per_page: u8,
+) -> Result<(Vec<Published>, Vec<String>), anyhow::Error> {
+    let releases = github::fetch_releases(octocrab, project, per_page).await?;
+    let tags = github::fetch_tags(octocrab, project, per_page).await?;
+    let (Some(known), Some(since)) = (state.tags(configured)?, last_run) else {
+        return Ok((vec![], tags));
+    };
+    let releases = releases.into_iter().map(Published::from).collect();
+    let published = release::published_since(&project.html_url(), releases, &tags, &known, since);
+    Ok((published, tags))
+}
+
+/// The open alerts of code scanning and secret scanning of a project, most severe first.
+async fn fetch_scanning_alerts(
+    octocrab: &Octocrab,
+    project: &Project,
+    per_page: u8,
+    code: bool,
+    secret: bool,
+) -> Vec<ScanningAlert> {
+    let mut alerts = vec![];
+    for (scanner, enabled) in [(Scanner::Code, code), (Scanner::Secret, secret)] {
+        if enabled.not() {
+            continue;
+        }
+        match scanning::fetch_alerts(octocrab, project, scanner, per_page).await {
+            Ok(found) => alerts.extend(found),
+            Err(err) => warn!("Could not fetch {scanner} alerts: {err:?}"),
+        }
+    }
+    alerts.sort_by_key(|alert| (alert.severity, alert.scanner, alert.number));
+    alerts
+}
+
+/// What the weekly digest of a project says.
+pub struct WeeklyDigest {
+    /// The ages of the open PRs, and whether we have counted all of them.
+    pub histogram: AgeHistogram,
+    pub complete: bool,
+
+    /// The oldest open PR, if any.
+    pub oldest: Option<PullRequest>,
+
+    /// What happened during the past week.
+    pub activity: WeeklyActivity,
+}
+
+/// Find out what the weekly digest of a project says, given its open PRs.
+pub async fn fetch_weekly_digest(
+    octocrab: &Octocrab,
+    config: &Config,
+    project: &Project,
+    pulls: &[PullRequest],
+    complete: bool,
+    now: DateTime<Utc>,
+) -> Result<WeeklyDigest, anyhow::Error> {
+    /// How many reviewers we thank.
+    const TOP_REVIEWERS: usize = 3;
+    let since = now - chrono::Duration::weeks(1);
+    let per_page = config.per_page(project).unwrap_or(100);
+    let merged = github::fetch_merged_since(octocrab, project, since, per_page).await?;
+    let issues = github::fetch_issues_updated_between(octocrab, project, since, now, per_page)
+        .await?
+        .into_iter()
+        .filter(|issue| issue.pull_request.is_none())
+        .collect_vec();
+    // Nice to have, GraphQL requires authentication.
+    let reviewers = match github::fetch_review_activity(octocrab, project, since).await {
+        Ok(activity) => Some(digest::top_reviewers(
+            activity.into_iter().flat_map(|pull| pull.reviewed),
+            TOP_REVIEWERS,
+        )),
+        Err(err) => {
+            warn!("Could not fetch the reviews of {}: {err:?}", project.url);
+            None
+        }
+    };
+    Ok(WeeklyDigest {
+        histogram: AgeHistogram::new(pulls.iter().filter_map(|pull| pull.created_at), now),
+        complete,
+        oldest: pulls
+            .iter()
+            .filter(|pull| pull.created_at.is_some())
+            .min_by_key(|pull| pull.created_at)
+            .cloned(),
+        activity: WeeklyActivity {
+            merged: merged.len(),
+            opened: issues
+                .iter()
+                .filter(|issue| issue.created_at >= since)
+                .count(),
+            closed: issues
+                .iter()
+                .filter(|issue| issue.closed_at.is_some_and(|at| at >= since))
+                .count(),
+            reviewers,
+        },
+    })
+}
+
+/// What we need to fetch the sections of a project.
+pub struct Scope<'a> {
+    pub config: &'a Config,
+    pub octocrab: &'a Octocrab,
+
+    /// The project at its current url.
+    pub project: &'a Project,
+
+    /// The url of the project in the config, under which the state remembers it.
+    pub configured: &'a Url,
+    pub repository: &'a Repository,
+    pub last_run: Option<DateTime<Utc>>,
+    pub now: DateTime<Utc>,
+}
+
+/// What each section of the report of a project lists, before caps and re-pings.
+pub struct Sections {
+    pub pending_requests: Vec<PendingReview>,
+
+    /// Whether we have seen all the open PRs, see `fetch_pending_requests`.
+    pub requests_complete: bool,
+    pub drafts: Vec<PendingReview>,
+    pub pending_issues: Vec<Issue>,
+
+    /// Whether we have seen all the issues updated within the window.
+    pub issues_complete: bool,
+
+    /// The open issues that we report, to find out next time which ones were transferred.
+    pub reported: Vec<u64>,
+    pub transferred: Vec<(u64, Issue)>,
+
+    /// With `triage`, the lifecycles of the issues, including those stuck in triage.
+    pub lifecycles: Option<BTreeMap<u64, Lifecycle>>,
+    pub stuck: Vec<Issue>,
+    pub due_milestones: Vec<(Milestone, Vec<Issue>)>,
+
+    /// `None` if the report isn't due, or if we couldn't fetch milestones.
+    pub milestone_progress: Option<Vec<Milestone>>,
+    pub dependency_updates: Vec<DependabotPull>,
+    pub label_changes: Vec<LabelChange>,
+    pub releases: Vec<Published>,
+
+    /// The current tags, if we could fetch them.
+    pub tags: Option<Vec<String>>,
+    pub discussions: Vec<Discussion>,
+    pub popularity: Vec<Change>,
+    pub popularity_record: Option<Record>,
+    pub security_alerts: Vec<DependabotAlert>,
+    pub scanning_alerts: Vec<ScanningAlert>,
+    pub ci_failures: Vec<WorkflowRun>,
+    pub sla_breaches: Vec<Breach>,
+    pub weekly_digest: Option<WeeklyDigest>,
+    pub unreviewed: Vec<PullRequest>,
+    pub stale_pulls: Vec<PullRequest>,
+
+    /// Whether we have seen all the open PRs, for `unreviewed` and `stale_pulls`.
+    pub open_complete: bool,
+}
+
+impl Sections {
+    /// The number of items of each section.
+    pub fn counts(&self) -> [(SectionKind, usize); 19] {
+        [
+            (SectionKind::PendingReviews, self.pending_requests.len()),
+            (SectionKind::DraftReviews, self.drafts.len()),
+            (SectionKind::UnreviewedPulls, self.unreviewed.len()),
+            (SectionKind::UpdatedIssues, self.pending_issues.len()),
+            (SectionKind::StalePulls, self.stale_pulls.len()),
+            (SectionKind::TransferredIssues, self.transferred.len()),
+            (SectionKind::DueMilestones, self.due_milestones.len()),
+            (
+                SectionKind::MilestoneProgress,
+                self.milestone_progress.as_ref().map_or(0, Vec::len),
+            ),
+            (
+                SectionKind::DependencyUpdates,
+                self.dependency_updates.len(),
+            ),
+            (SectionKind::SecurityAlerts, self.security_alerts.len()),
+            (SectionKind::ScanningAlerts, self.scanning_alerts.len()),
+            (SectionKind::LabelChanges, self.label_changes.len()),
+            (SectionKind::UnansweredDiscussions, self.discussions.len()),
+            (SectionKind::Releases, self.releases.len()),
+            (SectionKind::Popularity, self.popularity.len()),
+            (SectionKind::CiFailures, self.ci_failures.len()),
+            (SectionKind::SlaBreaches, self.sla_breaches.len()),
+            (SectionKind::StuckInTriage, self.stuck.len()),
+            (
+                SectionKind::WeeklyDigest,
+                self.weekly_digest
+                    .as_ref()
+                    .map_or(0, |digest| digest.histogram.total()),
+            ),
+        ]
+    }
+
+    /// Whether there is nothing to report. The weekly digest is worth sending even without
+    /// open PRs.
+    pub fn is_empty(&self) -> bool {
+        self.weekly_digest.is_none() && self.counts().iter().all(|(_, count)| *count == 0)
+    }
+
+    /// Drop the items that we have announced since a date, see `drop_seen_since`, except
+    /// the force-pushed PRs, which are worth another look.
+    pub fn drop_seen_since(
+        &mut self,
+        state: &State,
+        project: &Url,
+        since: DateTime<Utc>,
+        force_pushes: &HashMap<u64, ForcePush>,
+    ) -> Result<(), anyhow::Error> {
+        let (pushed, mut others): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending_requests)
+            .into_iter()
+            .partition(|pull| force_pushes.contains_key(&pull.number));
+        drop_seen_since(state, project, since, &mut others, |pull| {
+            (pull.number, pull.updated_at)
+        })?;
+        self.pending_requests = pushed;
+        self.pending_requests.extend(others);
+        report::sort(&mut self.pending_requests);
+        drop_seen_since(state, project, since, &mut self.pending_issues, |issue| {
+            (issue.number, Some(issue.updated_at))
+        })?;
+        drop_seen_since(state, project, since, &mut self.stuck, |issue| {
+            (issue.number, Some(issue.updated_at))
+        })?;
+        drop_seen_since(state, project, since, &mut self.drafts, |pull| {
+            (pull.number, pull.updated_at)
+        })?;
+        drop_seen_since(state, project, since, &mut self.unreviewed, |pull| {
+            (pull.number, pull.updated_at)
+        })?;
+        drop_seen_since(state, project, since, &mut self.stale_pulls, |pull| {
+            (pull.number, pull.updated_at)
+        })?;
+        drop_seen_since(state, project, since, &mut self.discussions, |discussion| {
+            (discussion.number, discussion.updated_at)
+        })
+    }
+}
+
+/// Fetch what each section of the report of a project lists.
+///
+/// Fails if we cannot list issues, PRs or SLA breaches. Other sections are nice to have, and
+/// left empty if we cannot fetch them.
+pub async fn fetch_sections(
+    scope: &Scope<'_>,
+    state: &mut State,
+    search: Option<&mut SearchResults>,
+    digest_due: bool,
+    milestone_report_due: bool,
+) -> Result<Sections, anyhow::Error> {
+    let &Scope {
+        config,
+        octocrab,
+        project,
+        configured,
+        repository,
+        last_run,
+        now,
+    } = scope;
+    let update_frequency = config.update_frequency_for(project);
+    let per_page = config.per_page(project).unwrap_or(100);
+
+    // Each section looks back as far as configured.
+    let issues_window = config.window(project, SectionKind::UpdatedIssues);
+    let issues_since = issues_window.since(now, last_run, update_frequency);
+    let requests_since =
+        config
+            .window(project, SectionKind::PendingReviews)
+            .since(now, last_run, update_frequency);
+
+    // List issues and pull requests, unless we have already found them, up to
+    // `max_items` of each.
+    let (issues, pending_requests, issues_complete) = match search {
+        Some(search) => {
+            let (issues, pending_requests) = search.take(project);
+            (issues, pending_requests, true)
+        }
+        None => {
+            let handler = octocrab.issues(&project.owner, &project.repo);
+            let mut list = handler.list();
+            if let Some(since) = issues_since {
+                list = list.since(since);
+            }
+            if let Some(per_page) = config.per_page(project) {
+                list = list.per_page(per_page);
+            }
+            let first = list
+                .send()
+                .await
+                .map_err(Error::from_github)
+                .context("Couldn't download recent issues")?;
+            let (issues, complete) =
+                github::collect_pages(octocrab, first, config.max_items(project))
+                    .await
+                    .context("Couldn't download recent issues")?;
+            (issues, None, complete)
+        }
+    };
+    let issues = issues
+        .into_iter()
+        .filter(|issue| issues_since.is_none_or(|since| issue.updated_at >= since))
+        .collect_vec();
+    let (pending_requests, requests_complete) = match pending_requests {
+        Some(pending_requests) => (pending_requests, true),
+        None => fetch_pending_requests(octocrab, config, project).await?,
+    };
+    let mut pending_requests = pending_requests
+        .into_iter()
+        .filter(|pull| match (requests_since, pull.created_at) {
+            (Some(since), Some(created_at)) => created_at >= since,
+            _ => true,
+        })
+        .filter(|pull| shows_request(config, project, pull))
+        .collect_vec();
+    report::sort(&mut pending_requests);
+
+    // ...and since requests are also issues, let's make sure that we
+    // don't display them twice.
+    let pending_numbers: HashSet<u64> = pending_requests.iter().map(|pull| pull.number).collect();
+    let mut pending_issues = issues
+        .into_iter()
+        .filter(|issue| pending_numbers.contains(&issue.number).not())
+        .filter(|issue| project.shows(issue.labels.iter().map(|label| label.name.as_str())))
+        .filter(|issue| config.ignores_author(&issue.user.login).not())
+        .collect_vec();
+    report::sort(&mut pending_issues);
+
+    // Drafts aren't ready for reviews yet, whatever their reviewers.
+    let (drafts, pending_requests): (Vec<_>, Vec<_>) = match project.drafts {
+        Drafts::Include => (vec![], pending_requests),
+        Drafts::Separate => pending_requests.into_iter().partition(|pull| pull.draft),
+        Drafts::Ignore => (
+            vec![],
+            pending_requests
+                .into_iter()
+                .filter(|pull| pull.draft.not())
+                .collect(),
+        ),
+    };
+
+    // Issues that we have reported last time and that have vanished may have been
+    // transferred to another repository. If so, let's say so rather than dropping them.
+    let reported = pending_issues
+        .iter()
+        .filter(|issue| issue.pull_request.is_none() && issue.closed_at.is_none())
+        .map(|issue| issue.number)
+        .collect_vec();
+    let transferred = fetch_transferred_issues(
+        octocrab,
+        project,
+        state.reported(configured)?.into_iter().filter(|number| {
+            pending_numbers.contains(number).not() && reported.contains(number).not()
+        }),
+    )
+    .await;
+
+    // With `triage`, issues that we keep reporting while nobody labels or assigns them get
+    // a section of their own.
+    let mut lifecycles = match config.triage {
+        Some(_) => Some(state.lifecycles(configured)?),
+        None => None,
+    };
+    let mut stuck = vec![];
+    if let (Some(triage), Some(lifecycles)) = (&config.triage, lifecycles.as_mut()) {
+        // The open issues don't tell us which issues were closed since the last run, so
+        // let's also look at all the issues updated since, open or closed.
+        let since = last_run.unwrap_or(now - update_frequency);
+        let updated = github::fetch_issues_updated_between(octocrab, project, since, now, per_page)
+            .await
+            .unwrap_or_else(|err| {
+                warn!("Could not fetch the issues updated since the last run: {err:?}");
+                vec![]
+            });
+        for issue in updated.iter().filter(|issue| issue.pull_request.is_none()) {
+            if let Some(lifecycle) = lifecycles.get_mut(&issue.number) {
+                lifecycle.observe(
+                    triage.labelled(issue.labels.iter().map(|label| label.name.as_str())),
+                    issue.assignees.is_empty().not(),
+                    issue.closed_at,
+                    now,
+                );
+            }
+        }
+        for issue in pending_issues
+            .iter()
+            .filter(|issue| issue.pull_request.is_none())
+        {
+            lifecycles
+                .entry(issue.number)
+                .or_insert_with(|| Lifecycle::new(now))
+                .observe(
+                    triage.labelled(issue.labels.iter().map(|label| label.name.as_str())),
+                    issue.assignees.is_empty().not(),
+                    issue.closed_at,
+                    now,
+                );
+        }
+        (stuck, pending_issues) = pending_issues.into_iter().partition(|issue| {
+            lifecycles
+                .get(&issue.number)
+                .is_some_and(|lifecycle| lifecycle.is_stuck(triage.after))
+        });
+    }
+
+    let due_milestones = match config.milestone_alarm_days {
+        None => vec![],
+        Some(days) => {
+            let deadline = now + chrono::Duration::days(days.into());
+            github::fetch_due_milestones(octocrab, project, deadline, per_page)
+                .await
+                .unwrap_or_else(|err| {
+                    warn!("Could not fetch milestones: {err:?}");
+                    vec![]
+                })
+        }
+    };
+
+    // `None` if the report isn't due, or if we couldn't fetch milestones, so that we try
+    // again on the next run.
+    let milestone_progress = if milestone_report_due {
+        github::fetch_open_milestones(octocrab, project, per_page)
+            .await
+            .inspect_err(|err| warn!("Could not fetch milestones: {err:?}"))
+            .ok()
+    } else {
+        None
+    };
+
+    let dependency_updates = if config.dependency_updates {
+        dependabot::fetch_pulls(octocrab, project, per_page)
+            .await
+            .unwrap_or_else(|err| {
+                warn!("Could not fetch Dependabot PRs: {err:?}");
+                vec![]
+            })
+    } else {
+        vec![]
+    };
+
+    let label_changes = if config.label_alerts.is_empty() {
+        vec![]
+    } else {
+        let since = config.window(project, SectionKind::LabelChanges).since(
+            now,
+            last_run,
+            update_frequency,
+        );
+        github::fetch_label_changes(
+            octocrab,
+            project,
+            &config.label_alerts,
+            since,
+            per_page,
+            config.max_items(project),
+        )
+        .await
+        .unwrap_or_else(|err| {
+            warn!("Could not fetch label changes: {err:?}");
+            vec![]
+        })
+    };
+
+    let (releases, tags) = if project.releases {
+        fetch_published(octocrab, state, project, configured, last_run, per_page)
+            .await
+            .map(|(releases, tags)| (releases, Some(tags)))
+            .unwrap_or_else(|err| {
+                warn!("Could not fetch releases: {err:?}");
+                (vec![], None)
+            })
+    } else {
+        (vec![], None)
+    };
+
+    let discussions = if config.discussions {
+        let since = config
+            .window(project, SectionKind::UnansweredDiscussions)
+            .since(now, last_run, update_frequency);
+        github::fetch_unanswered_discussions(octocrab, project, since, config.max_items(project))
+            .await
+            .unwrap_or_else(|err| {
+                warn!("Could not fetch discussions: {err:?}");
+                vec![]
+            })
+    } else {
+        vec![]
+    };
+
+    let (popularity, popularity_record) = match project.stars {
+        Some(ref stars) => {
+            let counts = Counts {
+                stars: repository.stargazers_count.unwrap_or_default().into(),
+                forks: repository.forks_count.unwrap_or_default().into(),
+            };
+            let window = match config.window(project, SectionKind::Popularity) {
+                Window::Last(duration) => Some(duration),
+                Window::All | Window::SinceLastRun => None,
+            };
+            let previous = state.popularity(configured)?;
+            let (changes, record) = popularity::changes(
+                previous.as_ref(),
+                counts,
+                now,
+                &stars.milestones,
+                stars.spike,
+                window,
+            );
+            (changes, Some(record))
+        }
+        None => (vec![], None),
+    };
+
+    let (security_alerts, scanning_alerts) = match project.security {
+        Some(ref security) => {
+            let dependabot = dependabot::fetch_alerts(octocrab, project, per_page)
+                .await
+                .unwrap_or_else(|err| {
+                    warn!("Could not fetch Dependabot alerts: {err:?}");
+                    vec![]
+                })
+                .into_iter()
+                .filter(|alert| {
+                    alert
+                        .security_advisory
+                        .severity
+                        .is_at_least(security.min_severity)
+                })
+                .collect_vec();
+            let scanning = fetch_scanning_alerts(
+                octocrab,
+                project,
+                per_page,
+                security.code_scanning,
+                security.secret_scanning,
+            )
+            .await
+            .into_iter()
+            .filter(|alert| alert.severity.is_at_least(security.min_severity))
+            .collect_vec();
+            (dependabot, scanning)
+        }
+        None => (vec![], vec![]),
+    };
+
+    let ci_failures = match (&config.ci, repository.default_branch.as_deref()) {
+        (Some(ci), Some(branch)) => {
+            let since = config.window(project, SectionKind::CiFailures).since(
+                now,
+                last_run,
+                update_frequency,
+            );
+            github::fetch_workflow_runs(octocrab, project, branch, per_page)
+                .await
+                .map(|runs| github::failing_workflows(runs, since, ci.only_transitions))
+                .unwrap_or_else(|err| {
+                    warn!("Could not fetch workflow runs: {err:?}");
+                    vec![]
+                })
+        }
+        _ => vec![],
+    };
+
+    let sla_breaches = if config.sla.is_empty() {
+        vec![]
+    } else {
+        fetch_sla_breaches(config, state, octocrab, project, configured, now).await?
+    };
+
+    // All open PRs, not only those waiting for a review.
+    let open_pulls =
+        if digest_due || config.stale_after.is_some() || config.suggest_reviewers.is_some() {
+            Some(fetch_open_pulls(octocrab, config, project).await?)
+        } else {
+            None
+        };
+    let weekly_digest = match open_pulls {
+        // Not worth failing the whole report, we'll try again on the next run.
+        Some((ref pulls, complete)) if digest_due => {
+            match fetch_weekly_digest(octocrab, config, project, pulls, complete, now).await {
+                Ok(digest) => Some(digest),
+                Err(err) => {
+                    warn!("Could not compute the weekly digest: {err:?}");
+                    None
+                }
+            }
+        }
+        _ => None,
+    };
+    let open_complete = open_pulls.as_ref().is_none_or(|(_, complete)| *complete);
+    let shown = |pull: &PullRequest| {
+        project.shows(
+            pull.labels
+                .iter()
+                .flatten()
+                .map(|label| label.name.as_str()),
+        ) && pull
+            .user
+            .as_ref()
+            .is_none_or(|user| config.ignores_author(&user.login).not())
+    };
+    // Nobody was asked to review these, not even a team. Drafts aren't ready for it.
+    let mut unreviewed = match (&open_pulls, &config.suggest_reviewers) {
+        (Some((pulls, _)), Some(_)) => pulls
+            .iter()
+            .filter(|pull| pull.requested_reviewers.as_ref().is_none_or(Vec::is_empty))
+            .filter(|pull| pull.requested_teams.as_ref().is_none_or(Vec::is_empty))
+            .filter(|pull| pull.draft != Some(true))
+            .filter(|pull| shown(pull))
+            .cloned()
+            .collect_vec(),
+        _ => vec![],
+    };
+    report::sort(&mut unreviewed);
+    let mut stale_pulls = match (open_pulls, config.stale_after) {
+        (Some((pulls, _)), Some(stale_after)) => pulls
+            .into_iter()
+            .filter(|pull| {
+                pull.updated_at
+                    .is_some_and(|at| has_waited(config, stale_after, at, now))
+            })
+            .filter(|pull| shown(pull))
+            .filter(|pull| project.drafts != Drafts::Ignore || pull.draft != Some(true))
+            .collect_vec(),
+        _ => vec![],
+    };
+    report::sort(&mut stale_pulls);
+
+    Ok(Sections {
+        pending_requests,
+        requests_complete,
+        drafts,
+        pending_issues,
+        issues_complete,
+        reported,
+        transferred,
+        lifecycles,
+        stuck,
+        due_milestones,
+        milestone_progress,
+        dependency_updates,
+        label_changes,
+        releases,
+        tags,
+        discussions,
+        popularity,
+        popularity_record,
+        security_alerts,
+        scanning_alerts,
+        ci_failures,
+        sla_breaches,
+        weekly_digest,
+        unreviewed,
+        stale_pulls,
+        open_complete,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeSet;
+
+    use chrono::{Duration, TimeZone, Utc};
+    use url::Url;
+
+    use crate::{
+        report::SectionKind,
+        state::{file::FileStore, State},
+    };
+
+    use super::{delivered_items, drop_seen_since};
+
+    /// Are items announced within `reping_after` left out, unless they were updated since?
+    #[test]
+    fn test_drop_seen_since() {
+        let path = std::env::temp_dir().join("qastor-test-drop-seen-since.json");
+        let _ = std::fs::remove_file(&path);
+        let project = Url::parse("https://github.com/owner1/project1").unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 5, 6, 12, 0, 0).unwrap();
+        let since = now - Duration::days(1);
+
+        let mut state = State::new(Box::new(FileStore::open(&path).unwrap()));
+        // Announced an hour ago, not updated since.
+        state
+            .set_seen(&project, 1, now - Duration::hours(1))
+            .unwrap();
+        // Announced two days ago.
+        state
+            .set_seen(&project, 2, now - Duration::days(2))
+            .unwrap();
+        // Announced an hour ago, updated since.
+        state
+            .set_seen(&project, 3, now - Duration::hours(1))
+            .unwrap();
+        // Announced an hour ago, without an update date.
+        state
+            .set_seen(&project, 5, now - Duration::hours(1))
+            .unwrap();
+        // Never announced: 4.
+        let mut items = vec![
+            (1, Some(now - Duration::hours(2))),
+            (2, None),
+            (3, Some(now - Duration::minutes(5))),
+            (4, None),
+            (5, None),
+        ];
+        drop_seen_since(&state, &project, since, &mut items, |item| *item).unwrap();
+        assert_eq!(
+            items.iter().map(|(number, _)| *number).collect::<Vec<_>>(),
+            vec![2, 3, 4]
+        );
+        let _ = std::fs::remove_file(&path);
+    }
+
+    /// Are items only marked as seen once a section announcing them went out?
+    #[test]
+    fn test_delivered_items() {
+        let notified = vec![
+            (SectionKind::PendingReviews, 1),
+            (SectionKind::PendingReviews, 2),
+            (SectionKind::UpdatedIssues, 2),
+            (SectionKind::UpdatedIssues, 3),
+        ];
+        assert_eq!(
+            delivered_items(
+                notified.clone(),
+                &BTreeSet::from([SectionKind::PendingReviews])
+            ),
+            vec![1, 2]
+        );
+        assert_eq!(
+            delivered_items(
+                notified.clone(),
+                &BTreeSet::from([SectionKind::UpdatedIssues])
+            ),
+            vec![2, 3]
+        );
+        assert_eq!(
+            delivered_items(notified, &BTreeSet::new()),
+            Vec::<u64>::new()
+        );
+    }
+}
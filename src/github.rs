@@ -0,0 +1,2582 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ops::Not,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use lazy_regex::{lazy_regex, Lazy};
+use log::{info, warn};
+use octocrab::{
+    models::{
+        issues::{Comment, Issue},
+        pulls::{PullRequest, ReviewState},
+        repos::{DiffEntry, Release},
+        AppId, Installation, Milestone, Repository,
+    },
+    params, Octocrab, Page,
+};
+use regex::Regex;
+use serde::{de::DeserializeOwned, Deserialize};
+use url::Url;
+
+use crate::{
+    config::{Project, Secrets},
+    error::Error,
+    state::SkipReason,
+};
+
+/// Whether a repository can still be monitored.
+pub enum Availability {
+    Available(Box<Repository>),
+    Unavailable(SkipReason),
+}
+
+/// Clients authenticated as the installations of our GitHub App, by lowercase owner.
+static INSTALLATIONS: Mutex<BTreeMap<String, Arc<Octocrab>>> = Mutex::new(BTreeMap::new());
+
+/// Clients of the projects with an `api_url`, e.g. on GitHub Enterprise Server, by API url.
+static HOSTS: Mutex<BTreeMap<Url, Host>> = Mutex::new(BTreeMap::new());
+
+/// The clients of a GitHub host other than github.com.
+#[derive(Clone)]
+struct Host {
+    /// For the REST API, at the `api_url`.
+    rest: Arc<Octocrab>,
+
+    /// For the GraphQL API, which isn't below the REST API, see `graphql_base`.
+    graphql: Arc<Octocrab>,
+}
+impl Host {
+    fn connect(api_url: &Url, token: Option<&str>) -> Result<Self, anyhow::Error> {
+        let client = |base: &Url| {
+            let builder = Octocrab::builder()
+                .base_uri(base.as_str())
+                .map_err(|err| Error::Config(err.to_string()))
+                .with_context(|| format!("Invalid GitHub API url {api_url}"))?;
+            let builder = match token {
+                Some(token) => builder.personal_token(token.to_string()),
+                None => builder,
+            };
+            builder
+                .build()
+                .map_err(|err| Error::Auth(err.to_string()))
+                .context("Invalid GitHub token")
+        };
+        Ok(Host {
+            rest: Arc::new(client(api_url)?),
+            graphql: Arc::new(client(&graphql_base(api_url))?),
+        })
+    }
+}
+
+/// Set up the GitHub clients: as the installation of our GitHub App on the owner of each
+/// project, if any, and otherwise with a token if we have one.
+///
+/// Projects with an `api_url` always use the token, as our GitHub App lives on github.com.
+/// Installation tokens expire after an hour, octocrab renews them as needed.
+pub async fn authenticate<'a>(
+    secrets: &Secrets,
+    projects: impl IntoIterator<Item = &'a Project>,
+) -> Result<(), anyhow::Error> {
+    let (hosted, projects): (Vec<_>, Vec<_>) = projects
+        .into_iter()
+        .partition(|project| project.api_url.is_some());
+    let mut hosts = BTreeMap::new();
+    for api_url in hosted
+        .into_iter()
+        .filter_map(|project| project.api_url.clone())
+    {
+        if hosts.contains_key(&api_url) {
+            continue;
+        }
+        let host = Host::connect(&api_url, secrets.github_token.as_deref())?;
+        hosts.insert(api_url, host);
+    }
+    *HOSTS.lock().unwrap() = hosts;
+    let owners = projects.into_iter().map(|project| project.owner.as_str());
+    let octocrab = match secrets.github_token {
+        Some(ref token) => Octocrab::builder()
+            .personal_token(token.clone())
+            .build()
+            .map_err(|err| Error::Auth(err.to_string()))
+            .context("Invalid GitHub token")?,
+        None => Octocrab::default(),
+    };
+    octocrab::initialise(octocrab);
+
+    let mut installations = BTreeMap::new();
+    if let Some(ref app) = secrets.github_app {
+        let key = jsonwebtoken::EncodingKey::from_rsa_pem(app.private_key.as_bytes())
+            .map_err(|err| Error::Config(err.to_string()))
+            .context("Invalid private key for the GitHub App")?;
+        let octocrab = Octocrab::builder()
+            .app(AppId(app.app_id), key)
+            .build()
+            .map_err(|err| Error::Auth(err.to_string()))
+            .context("Invalid GitHub App")?;
+        for owner in owners.into_iter().map(str::to_lowercase).unique() {
+            // Owners may be organizations or users.
+            let installation = match octocrab.apps().get_org_installation(&owner).await {
+                Ok(installation) => Ok(installation),
+                Err(_) => {
+                    octocrab
+                        .get::<Installation, _, _>(
+                            format!("/users/{owner}/installation"),
+                            None::<&()>,
+                        )
+                        .await
+                }
+            };
+            match installation {
+                Ok(installation) => {
+                    let client = octocrab
+                        .installation(installation.id)
+                        .context("Could not authenticate as installation")?;
+                    installations.insert(owner, Arc::new(client));
+                }
+                Err(err) => warn!(
+                    "The GitHub App is not installed on {owner}, using the default credentials: {err}"
+                ),
+            }
+        }
+    }
+    *INSTALLATIONS.lock().unwrap() = installations;
+    Ok(())
+}
+
+/// The GitHub client for the projects of an owner.
+pub fn instance(owner: &str) -> Arc<Octocrab> {
+    match INSTALLATIONS.lock().unwrap().get(&owner.to_lowercase()) {
+        Some(installation) => installation.clone(),
+        None => octocrab::instance(),
+    }
+}
+
+/// The GitHub client for a project, at its `api_url` if specified.
+pub fn instance_for(project: &Project) -> Arc<Octocrab> {
+    let host = project
+        .api_url
+        .as_ref()
+        .and_then(|api_url| Some(HOSTS.lock().unwrap().get(api_url)?.rest.clone()));
+    host.unwrap_or_else(|| instance(&project.owner))
+}
+
+/// Fetch the latest release of a repository, e.g. of qastor itself.
+pub async fn fetch_latest_release(
+    octocrab: &Octocrab,
+    owner: &str,
+    repo: &str,
+) -> Result<Release, anyhow::Error> {
+    octocrab
+        .repos(owner, repo)
+        .releases()
+        .get_latest()
+        .await
+        .map_err(Error::from_github)
+        .with_context(|| format!("Couldn't fetch the latest release of {owner}/{repo}"))
+}
+
+/// Fetch the latest releases of a project, newest first, drafts excluded.
+pub async fn fetch_releases(
+    octocrab: &Octocrab,
+    project: &Project,
+    per_page: u8,
+) -> Result<Vec<Release>, anyhow::Error> {
+    let page = octocrab
+        .repos(&project.owner, &project.repo)
+        .releases()
+        .list()
+        .per_page(per_page)
+        .send()
+        .await
+        .map_err(Error::from_github)
+        .context("Couldn't download releases")?;
+    Ok(page
+        .items
+        .into_iter()
+        .filter(|release| release.draft.not())
+        .collect())
+}
+
+/// Fetch the names of the latest tags of a project, newest first.
+pub async fn fetch_tags(
+    octocrab: &Octocrab,
+    project: &Project,
+    per_page: u8,
+) -> Result<Vec<String>, anyhow::Error> {
+    /// Beyond this, older tags look new once newer ones are deleted, which is rare.
+    const MAX_TAGS: usize = 1000;
+    let first = octocrab
+        .repos(&project.owner, &project.repo)
+        .list_tags()
+        .per_page(per_page)
+        .send()
+        .await
+        .map_err(Error::from_github)
+        .context("Couldn't download tags")?;
+    let (tags, _) = collect_pages(octocrab, first, MAX_TAGS)
+        .await
+        .context("Couldn't download tags")?;
+    Ok(tags.into_iter().map(|tag| tag.name).collect())
+}
+
+/// Fetch the repository metadata, to find out whether it's still alive.
+pub async fn check_repository(
+    octocrab: &Octocrab,
+    project: &Project,
+) -> Result<Availability, anyhow::Error> {
+    let repository = match octocrab.repos(&project.owner, &project.repo).get().await {
+        Ok(repository) => repository,
+        Err(octocrab::Error::GitHub { source, .. })
+            if source.status_code == reqwest::StatusCode::NOT_FOUND =>
+        {
+            return Ok(Availability::Unavailable(SkipReason::NotFound))
+        }
+        Err(err) => return Err(Error::from_github(err)).context("Couldn't fetch repository"),
+    };
+    if repository.archived == Some(true) {
+        return Ok(Availability::Unavailable(SkipReason::Archived));
+    }
+    if repository.disabled == Some(true) {
+        return Ok(Availability::Unavailable(SkipReason::Disabled));
+    }
+    Ok(Availability::Available(Box::new(repository)))
+}
+
+/// An API call that some section of the report depends on, to check our access ahead
+/// of time.
+pub struct Probe {
+    /// What we're trying to do, e.g. `list issues`.
+    pub what: &'static str,
+
+    /// The route, relative to the repository, e.g. `issues`.
+    pub path: &'static str,
+
+    /// The permission of a GitHub App (or fine-grained token) that this requires.
+    pub permission: &'static str,
+}
+
+/// Try each probe on a project, with a single item per call.
+///
+/// Returns a description of each probe that failed, along with the permission it needs.
+pub async fn check_access(octocrab: &Octocrab, project: &Project, probes: &[Probe]) -> Vec<String> {
+    let mut problems = vec![];
+    for probe in probes {
+        let result: Result<serde_json::Value, _> = octocrab
+            .get(
+                format!(
+                    "/repos/{owner}/{repo}/{path}",
+                    owner = project.owner,
+                    repo = project.repo,
+                    path = probe.path
+                ),
+                Some(&serde_json::json!({ "per_page": 1 })),
+            )
+            .await;
+        let Err(err) = result else {
+            continue;
+        };
+        let err = Error::from_github(err);
+        let reason = match Error::find(&err) {
+            Some(err) => err.to_string(),
+            None => err.root_cause().to_string(),
+        };
+        problems.push(format!(
+            "cannot {what} (needs \"{permission}\"): {reason}",
+            what = probe.what,
+            permission = probe.permission
+        ));
+        if matches!(Error::find(&err), Some(Error::Auth(_))) {
+            // No need to try the others.
+            break;
+        }
+    }
+    problems
+}
+
+/// The latest activity on a repository, i.e. its latest push or update.
+pub fn last_activity(repository: &Repository) -> Option<DateTime<Utc>> {
+    repository.pushed_at.max(repository.updated_at)
+}
+
+/// If the repository lives at a different url than the configured one (typically
+/// because it has been renamed or transferred), its new url.
+///
+/// GitHub redirects requests made to the old url, so we only find out by looking
+/// at the repository we actually received.
+pub fn moved_to(project: &Project, repository: &Repository) -> Option<Url> {
+    let actual = repository.html_url.as_ref()?;
+    if same_repository(&project.html_url(), actual) {
+        None
+    } else {
+        Some(actual.clone())
+    }
+}
+
+/// Compare two repository urls, ignoring case and trailing slashes, as GitHub does.
+fn same_repository(a: &Url, b: &Url) -> bool {
+    a.host_str() == b.host_str()
+        && a.path()
+            .trim_end_matches('/')
+            .eq_ignore_ascii_case(b.path().trim_end_matches('/'))
+}
+
+/// The base of the GraphQL API of the GitHub host whose REST API is at `api_url`, e.g.
+/// `https://github.example.com/api` for `https://github.example.com/api/v3` on GitHub
+/// Enterprise Server, where queries go to `/api/graphql`.
+fn graphql_base(api_url: &Url) -> Url {
+    let mut url = api_url.clone();
+    let path = api_url.path().trim_end_matches('/');
+    let path = path.strip_suffix("/v3").unwrap_or(path).to_string();
+    url.set_path(&path);
+    url
+}
+
+/// Send a GraphQL query with `octocrab`, or to the GraphQL API of the host at `api_url` if
+/// specified (see `Project::api_url`), returning its `data`.
+///
+/// Note that GitHub only accepts GraphQL queries from authenticated clients.
+pub async fn graphql<T: DeserializeOwned>(
+    octocrab: &Octocrab,
+    api_url: Option<&Url>,
+    query: &str,
+    variables: serde_json::Value,
+) -> Result<T, anyhow::Error> {
+    let (data, errors) = graphql_partial(octocrab, api_url, query, variables).await?;
+    if errors.is_empty().not() {
+        return Err(anyhow!(
+            "GraphQL query returned errors: {}",
+            errors.iter().format(", ")
+        ));
+    }
+    Ok(data)
+}
+
+/// Run a GraphQL query like `graphql`, but accept partial data along with errors, e.g.
+/// when some of the aliased repositories of a query are missing.
+///
+/// Returns the data and the errors, if any.
+pub async fn graphql_partial<T: DeserializeOwned>(
+    octocrab: &Octocrab,
+    api_url: Option<&Url>,
+    query: &str,
+    variables: serde_json::Value,
+) -> Result<(T, Vec<serde_json::Value>), anyhow::Error> {
+    #[derive(Deserialize)]
+    struct Response<T> {
+        data: Option<T>,
+        #[serde(default)]
+        errors: Vec<serde_json::Value>,
+    }
+    let host =
+        api_url.and_then(|api_url| Some(HOSTS.lock().unwrap().get(api_url)?.graphql.clone()));
+    let response: Response<T> = host
+        .as_deref()
+        .unwrap_or(octocrab)
+        .graphql(&serde_json::json!({
+            "query": query,
+            "variables": variables,
+        }))
+        .await
+        .context("GraphQL query failed")?;
+    match response.data {
+        Some(data) => Ok((data, response.errors)),
+        None if response.errors.is_empty() => Err(anyhow!("GraphQL query returned no data")),
+        None => Err(anyhow!(
+            "GraphQL query returned errors: {}",
+            response.errors.iter().format(", ")
+        )),
+    }
+}
+
+/// What we know about an issue beyond the REST API.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct IssueDetails {
+    /// The issue type (e.g. "Bug", "Feature"), if any.
+    pub issue_type: Option<String>,
+
+    /// If this issue is a sub-issue, the number of its parent.
+    pub parent: Option<u64>,
+}
+
+/// Fetch issue types and sub-issue relationships of a few issues.
+pub async fn fetch_issue_details(
+    octocrab: &Octocrab,
+    project: &Project,
+    numbers: &[u64],
+) -> Result<HashMap<u64, IssueDetails>, anyhow::Error> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Node {
+        number: u64,
+        issue_type: Option<Named>,
+        parent: Option<Numbered>,
+    }
+    #[derive(Deserialize)]
+    struct Named {
+        name: String,
+    }
+    #[derive(Deserialize)]
+    struct Numbered {
+        number: u64,
+    }
+    #[derive(Deserialize)]
+    struct Data {
+        repository: HashMap<String, Option<Node>>,
+    }
+    if numbers.is_empty() {
+        return Ok(HashMap::new());
+    }
+    // One aliased field per issue, so that we need a single round-trip.
+    let query = format!(
+        "query($owner: String!, $name: String!) {{ repository(owner: $owner, name: $name) {{ {fields} }} }}",
+        fields = numbers
+            .iter()
+            .map(|number| format!(
+                "issue{number}: issue(number: {number}) {{ number issueType {{ name }} parent {{ number }} }}"
+            ))
+            .format(" ")
+    );
+    let data: Data = graphql(
+        octocrab,
+        project.api_url.as_ref(),
+        &query,
+        serde_json::json!({
+            "owner": project.owner,
+            "name": project.repo.as_ref(),
+        }),
+    )
+    .await?;
+    Ok(data
+        .repository
+        .into_values()
+        .flatten()
+        .map(|node| {
+            (
+                node.number,
+                IssueDetails {
+                    issue_type: node.issue_type.map(|t| t.name),
+                    parent: node.parent.map(|p| p.number),
+                },
+            )
+        })
+        .collect())
+}
+
+/// Reorder issues so that sub-issues immediately follow their parent, whenever
+/// the parent is also part of the list.
+///
+/// Returns each issue with its depth (0 for top-level issues).
+pub fn roll_up_sub_issues<T>(
+    issues: Vec<T>,
+    number: impl Fn(&T) -> u64,
+    details: &HashMap<u64, IssueDetails>,
+) -> Vec<(T, usize)> {
+    let numbers: HashMap<u64, usize> = issues
+        .iter()
+        .enumerate()
+        .map(|(index, issue)| (number(issue), index))
+        .collect();
+    let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut roots = vec![];
+    for (index, issue) in issues.iter().enumerate() {
+        let parent = details
+            .get(&number(issue))
+            .and_then(|details| details.parent)
+            .and_then(|parent| numbers.get(&parent));
+        match parent {
+            Some(&parent) if parent != index => children.entry(parent).or_default().push(index),
+            _ => roots.push(index),
+        }
+    }
+    let mut order = Vec::with_capacity(issues.len());
+    let mut stack = roots
+        .into_iter()
+        .rev()
+        .map(|index| (index, 0))
+        .collect_vec();
+    while let Some((index, depth)) = stack.pop() {
+        order.push((index, depth));
+        if let Some(children) = children.get(&index) {
+            stack.extend(children.iter().rev().map(|&child| (child, depth + 1)));
+        }
+    }
+    let mut issues = issues.into_iter().map(Some).collect_vec();
+    order
+        .into_iter()
+        .filter_map(|(index, depth)| issues[index].take().map(|issue| (issue, depth)))
+        .collect()
+}
+
+/// If we're running low on API calls, wait until the rate limit resets.
+pub async fn wait_for_rate_limit(
+    octocrab: &Octocrab,
+    min_remaining: usize,
+) -> Result<(), anyhow::Error> {
+    let limit = octocrab
+        .ratelimit()
+        .get()
+        .await
+        .map_err(Error::from_github)
+        .context("Couldn't fetch rate limit")?;
+    let core = limit.resources.core;
+    if core.remaining >= min_remaining {
+        return Ok(());
+    }
+    let reset = DateTime::<Utc>::from_timestamp(core.reset as i64, 0)
+        .ok_or_else(|| anyhow!("Invalid rate limit reset {}", core.reset))?;
+    let delay = (reset - Utc::now()).to_std().unwrap_or_default();
+    info!(
+        "Only {} API calls remaining, waiting {}s for the rate limit to reset",
+        core.remaining,
+        delay.as_secs()
+    );
+    tokio::time::sleep(delay).await;
+    Ok(())
+}
+
+/// Read a list page by page, starting from its first page, until we have `max_items`.
+///
+/// Returns the items, and whether these are all the items of the list.
+pub async fn collect_pages<T: DeserializeOwned>(
+    octocrab: &Octocrab,
+    mut page: Page<T>,
+    max_items: usize,
+) -> Result<(Vec<T>, bool), anyhow::Error> {
+    let mut items = vec![];
+    loop {
+        let next = page.next.take();
+        items.extend(page.take_items());
+        if items.len() >= max_items {
+            let complete = next.is_none() && items.len() == max_items;
+            items.truncate(max_items);
+            return Ok((items, complete));
+        }
+        match octocrab
+            .get_page::<T>(&next)
+            .await
+            .map_err(Error::from_github)
+            .context("Couldn't download next page")?
+        {
+            Some(next) => page = next,
+            None => return Ok((items, true)),
+        }
+    }
+}
+
+/// Fetch all issues (open or closed) whose latest update happened in `[start, end)`.
+///
+/// Note that issues updated again after `end` are not returned, as we only know
+/// about their latest update.
+pub async fn fetch_issues_updated_between(
+    octocrab: &Octocrab,
+    project: &Project,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    per_page: u8,
+) -> Result<Vec<Issue>, anyhow::Error> {
+    let mut result = vec![];
+    for page in 1u32.. {
+        // Oldest updates first, so that we can stop as soon as we reach `end`.
+        let items = octocrab
+            .issues(&project.owner, &project.repo)
+            .list()
+            .state(params::State::All)
+            .since(start)
+            .sort(params::issues::Sort::Updated)
+            .direction(params::Direction::Ascending)
+            .per_page(per_page)
+            .page(page)
+            .send()
+            .await
+            .map_err(Error::from_github)
+            .context("Couldn't download issues")?
+            .take_items();
+        let complete = items.len() < per_page as usize;
+        let mut reached_end = false;
+        for issue in items {
+            if issue.updated_at >= end {
+                reached_end = true;
+                break;
+            }
+            result.push(issue);
+        }
+        if complete || reached_end {
+            break;
+        }
+    }
+    Ok(result)
+}
+
+/// Fetch the PRs of a project merged since `since`, most recently updated first.
+pub async fn fetch_merged_since(
+    octocrab: &Octocrab,
+    project: &Project,
+    since: DateTime<Utc>,
+    per_page: u8,
+) -> Result<Vec<PullRequest>, anyhow::Error> {
+    let mut result = vec![];
+    for page in 1u32.. {
+        // Most recent updates first, so that we can stop as soon as we reach `since`, as
+        // merging a PR updates it.
+        let items = octocrab
+            .pulls(&project.owner, &project.repo)
+            .list()
+            .state(params::State::Closed)
+            .sort(params::pulls::Sort::Updated)
+            .direction(params::Direction::Descending)
+            .per_page(per_page)
+            .page(page)
+            .send()
+            .await
+            .map_err(Error::from_github)
+            .context("Couldn't download closed pull requests")?
+            .take_items();
+        let complete = items.len() < per_page as usize;
+        let mut reached_since = false;
+        for pull in items {
+            if pull.updated_at.is_some_and(|at| at < since) {
+                reached_since = true;
+                break;
+            }
+            if pull.merged_at.is_some_and(|at| at >= since) {
+                result.push(pull);
+            }
+        }
+        if complete || reached_since {
+            break;
+        }
+    }
+    Ok(result)
+}
+
+/// Find the open milestones of a project which have a due date, soonest first.
+pub async fn fetch_milestones(
+    octocrab: &Octocrab,
+    project: &Project,
+    per_page: u8,
+) -> Result<Vec<Milestone>, anyhow::Error> {
+    let mut milestones = fetch_open_milestones(octocrab, project, per_page).await?;
+    milestones.retain(|milestone| milestone.due_on.is_some());
+    Ok(milestones)
+}
+
+/// Find the open milestones of a project, soonest due first, then those without a due date.
+pub async fn fetch_open_milestones(
+    octocrab: &Octocrab,
+    project: &Project,
+    per_page: u8,
+) -> Result<Vec<Milestone>, anyhow::Error> {
+    let mut milestones: Vec<Milestone> = octocrab
+        .get(
+            format!(
+                "/repos/{owner}/{repo}/milestones",
+                owner = project.owner,
+                repo = project.repo
+            ),
+            Some(&serde_json::json!({
+                "state": "open",
+                "sort": "due_on",
+                "direction": "asc",
+                "per_page": per_page,
+            })),
+        )
+        .await
+        .map_err(Error::from_github)
+        .context("Couldn't download milestones")?;
+    milestones.sort_by_key(|milestone| (milestone.due_on.is_none(), milestone.due_on));
+    Ok(milestones)
+}
+
+/// Find the open milestones due before `deadline` (including overdue ones) which still
+/// have open issues, along with these issues, soonest first.
+pub async fn fetch_due_milestones(
+    octocrab: &Octocrab,
+    project: &Project,
+    deadline: DateTime<Utc>,
+    per_page: u8,
+) -> Result<Vec<(Milestone, Vec<Issue>)>, anyhow::Error> {
+    let mut result = vec![];
+    for milestone in fetch_milestones(octocrab, project, per_page).await? {
+        if milestone.due_on > Some(deadline) || milestone.open_issues.unwrap_or_default() == 0 {
+            continue;
+        }
+        let issues = octocrab
+            .issues(&project.owner, &project.repo)
+            .list()
+            .milestone(milestone.number as u64)
+            .state(params::State::Open)
+            .per_page(per_page)
+            .send()
+            .await
+            .with_context(|| format!("Couldn't download issues of milestone {}", milestone.title))?
+            .take_items();
+        result.push((milestone, issues));
+    }
+    Ok(result)
+}
+
+/// List the open issues (not PRs) with a label, oldest first.
+pub async fn fetch_labelled_issues(
+    octocrab: &Octocrab,
+    project: &Project,
+    label: &str,
+    per_page: u8,
+) -> Result<Vec<Issue>, anyhow::Error> {
+    /// Past this, the oldest issues are what matters.
+    const MAX_ISSUES: usize = 1000;
+    let first = octocrab
+        .issues(&project.owner, &project.repo)
+        .list()
+        .labels(&[label.to_string()])
+        .state(params::State::Open)
+        .sort(params::issues::Sort::Created)
+        .direction(params::Direction::Ascending)
+        .per_page(per_page)
+        .send()
+        .await
+        .map_err(Error::from_github)
+        .with_context(|| format!("Couldn't download issues with label {label}"))?;
+    let (mut issues, complete) = collect_pages(octocrab, first, MAX_ISSUES)
+        .await
+        .with_context(|| format!("Couldn't download issues with label {label}"))?;
+    if complete.not() {
+        warn!(
+            "{} has more than {MAX_ISSUES} open issues with label {label}, only the oldest ones are checked",
+            project.url
+        );
+    }
+    issues.retain(|issue| issue.pull_request.is_none());
+    Ok(issues)
+}
+
+/// List the logins of the members of a team of an organization.
+pub async fn fetch_team_members(
+    octocrab: &Octocrab,
+    org: &str,
+    slug: &str,
+) -> Result<Vec<String>, anyhow::Error> {
+    /// Teams are rarely that large.
+    const MAX_MEMBERS: usize = 1000;
+    let first = octocrab
+        .teams(org)
+        .members(slug)
+        .per_page(100)
+        .send()
+        .await
+        .map_err(Error::from_github)
+        .with_context(|| format!("Couldn't list the members of team {org}/{slug}"))?;
+    let (members, _) = collect_pages(octocrab, first, MAX_MEMBERS)
+        .await
+        .with_context(|| format!("Couldn't list the members of team {org}/{slug}"))?;
+    Ok(members.into_iter().map(|member| member.login).collect())
+}
+
+/// List the first comments of an issue, oldest first.
+pub async fn fetch_comments(
+    octocrab: &Octocrab,
+    project: &Project,
+    number: u64,
+    per_page: u8,
+) -> Result<Vec<Comment>, anyhow::Error> {
+    Ok(octocrab
+        .issues(&project.owner, &project.repo)
+        .list_comments(number)
+        .per_page(per_page)
+        .send()
+        .await
+        .map_err(Error::from_github)
+        .with_context(|| format!("Couldn't download comments of issue #{number}"))?
+        .take_items())
+}
+
+/// A pull request waiting for reviews.
+///
+/// Built either from the REST API or from a search.
+#[derive(Debug, Clone)]
+pub struct PendingReview {
+    pub number: u64,
+    pub url: Url,
+    pub title: String,
+
+    /// The logins of the requested reviewers.
+    pub reviewers: Vec<String>,
+
+    /// The requested teams, as `org/slug` (or only `slug`, if GitHub didn't say which
+    /// organization).
+    pub teams: Vec<String>,
+    pub labels: Vec<String>,
+    pub body: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+
+    /// The login of the author, e.g. `dependabot[bot]`, unless their account is gone.
+    pub author: Option<String>,
+
+    /// Whether the PR is a draft.
+    pub draft: bool,
+
+    /// The number of review comments, if GitHub told us along with the PR.
+    pub review_comments: Option<u64>,
+}
+impl PendingReview {
+    /// The logins of the requested reviewers, then the requested teams.
+    pub fn requested(&self) -> impl Iterator<Item = &str> {
+        self.reviewers.iter().chain(&self.teams).map(String::as_str)
+    }
+
+    /// Convert a pull request from the REST API.
+    ///
+    /// Returns `None` if nobody (not even a team) has been requested for a review.
+    pub fn from_pull(pull: PullRequest) -> Result<Option<Self>, anyhow::Error> {
+        let reviewers = pull.requested_reviewers.unwrap_or_default();
+        let teams = pull.requested_teams.unwrap_or_default();
+        if reviewers.is_empty() && teams.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(PendingReview {
+            number: pull.number,
+            url: pull
+                .html_url
+                .ok_or_else(|| anyhow!("PR {} is missing a URL", pull.number))?,
+            title: pull
+                .title
+                .ok_or_else(|| anyhow!("PR {} is missing a title", pull.number))?,
+            reviewers: reviewers
+                .into_iter()
+                .map(|reviewer| reviewer.login)
+                .collect(),
+            teams: teams
+                .into_iter()
+                .map(|team| {
+                    // E.g. https://github.com/orgs/pasqal-io/teams/sdk.
+                    let org = team.html_url.as_deref().and_then(|url| {
+                        let (_, path) = url.split_once("/orgs/")?;
+                        Some(path.split_once('/')?.0.to_string())
+                    });
+                    match org {
+                        Some(org) => format!("{org}/{}", team.slug),
+                        None => team.slug,
+                    }
+                })
+                .collect(),
+            labels: pull
+                .labels
+                .into_iter()
+                .flatten()
+                .map(|label| label.name)
+                .collect(),
+            body: pull.body,
+            created_at: pull.created_at,
+            updated_at: pull.updated_at,
+            author: pull.user.map(|user| user.login),
+            draft: pull.draft.unwrap_or_default(),
+            review_comments: pull.review_comments,
+        }))
+    }
+}
+
+/// GitHub never returns more than 1000 search results, i.e. 10 pages of 100.
+const MAX_SEARCH_PAGES: u32 = 10;
+
+/// The reviews of a PR, keeping only the latest review of each reviewer.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct ReviewSummary {
+    pub approvals: usize,
+    pub changes_requested: usize,
+}
+
+/// Fetch the reviews of a PR.
+///
+/// Takes ownership of its arguments, so that it can run as a separate task.
+pub async fn fetch_review_summary(
+    octocrab: Octocrab,
+    project: Project,
+    number: u64,
+) -> Result<ReviewSummary, anyhow::Error> {
+    let reviews = octocrab
+        .pulls(&project.owner, &project.repo)
+        .list_reviews(number)
+        .per_page(100)
+        .send()
+        .await
+        .with_context(|| format!("Couldn't download reviews of PR {number}"))?
+        .take_items();
+    // Reviews are listed oldest first, only the latest one of each reviewer counts.
+    let mut latest = HashMap::new();
+    for review in reviews {
+        let (Some(user), Some(state)) = (review.user, review.state) else {
+            continue;
+        };
+        if matches!(state, ReviewState::Approved | ReviewState::ChangesRequested) {
+            latest.insert(user.login, state);
+        }
+    }
+    let mut summary = ReviewSummary::default();
+    for state in latest.into_values() {
+        match state {
+            ReviewState::Approved => summary.approvals += 1,
+            _ => summary.changes_requested += 1,
+        }
+    }
+    Ok(summary)
+}
+
+/// Fetch the files touched by a PR, most changed first.
+///
+/// Costs one API call per 100 files. Takes ownership of its arguments, so that it can run as
+/// a separate task.
+pub async fn fetch_pull_files(
+    octocrab: Octocrab,
+    project: Project,
+    number: u64,
+) -> Result<Vec<DiffEntry>, anyhow::Error> {
+    /// GitHub lists no more than this.
+    const MAX_FILES: usize = 3000;
+    let first: Page<DiffEntry> = octocrab
+        .get(
+            format!(
+                "/repos/{owner}/{repo}/pulls/{number}/files",
+                owner = project.owner,
+                repo = project.repo
+            ),
+            Some(&serde_json::json!({ "per_page": 100 })),
+        )
+        .await
+        .map_err(Error::from_github)
+        .with_context(|| format!("Couldn't download files of PR {number}"))?;
+    let (mut files, _) = collect_pages(&octocrab, first, MAX_FILES)
+        .await
+        .with_context(|| format!("Couldn't download files of PR {number}"))?;
+    files.sort_by_key(|file| std::cmp::Reverse(file.changes));
+    Ok(files)
+}
+
+/// The authors of the recent commits of the files touched by a PR, by file, most changed
+/// file first.
+///
+/// Looks at the `files` most changed files, and at the `commits` latest commits of each.
+/// Returns `None` if the PR has been reviewed already, e.g. by someone nobody asked, or by
+/// the reviewers that were requested, whose requests are then gone.
+/// Takes ownership of its arguments, so that it can run as a separate task.
+pub async fn fetch_recent_authors(
+    octocrab: Octocrab,
+    project: Project,
+    number: u64,
+    files: usize,
+    commits: u8,
+) -> Result<Option<Vec<Vec<String>>>, anyhow::Error> {
+    let reviews = octocrab
+        .pulls(&project.owner, &project.repo)
+        .list_reviews(number)
+        .per_page(100)
+        .send()
+        .await
+        .map_err(Error::from_github)
+        .with_context(|| format!("Couldn't download reviews of PR {number}"))?
+        .take_items();
+    // Pending reviews haven't been submitted yet.
+    if reviews.iter().any(|review| {
+        review
+            .state
+            .is_some_and(|state| state != ReviewState::Pending)
+    }) {
+        return Ok(None);
+    }
+    let touched = fetch_pull_files(octocrab.clone(), project.clone(), number).await?;
+    let mut authors = vec![];
+    for file in touched.into_iter().take(files) {
+        let history = octocrab
+            .repos(&project.owner, &project.repo)
+            .list_commits()
+            .path(&file.filename)
+            .per_page(commits)
+            .send()
+            .await
+            .map_err(Error::from_github)
+            .with_context(|| format!("Couldn't download commits of {}", file.filename))?
+            .take_items();
+        authors.push(
+            history
+                .into_iter()
+                .filter_map(|commit| Some(commit.author?.login))
+                .collect(),
+        );
+    }
+    Ok(Some(authors))
+}
+
+/// Up to `max` reviewers for a PR, among the authors of the recent commits of its files
+/// (see `fetch_recent_authors`), except those `excluded` (e.g. the author of the PR).
+///
+/// Those who touched the most files come first, then those with the most commits.
+pub fn suggest_reviewers(
+    authors: &[Vec<String>],
+    excluded: impl Fn(&str) -> bool,
+    max: usize,
+) -> Vec<String> {
+    // Login -> (files, commits).
+    let mut scores = HashMap::<&str, (usize, usize)>::new();
+    for file in authors {
+        for (login, commits) in file.iter().counts_by(String::as_str) {
+            if excluded(login) || login.ends_with("[bot]") {
+                continue;
+            }
+            let score = scores.entry(login).or_default();
+            score.0 += 1;
+            score.1 += commits;
+        }
+    }
+    scores
+        .into_iter()
+        .sorted_by(|(a, score_a), (b, score_b)| score_b.cmp(score_a).then(a.cmp(b)))
+        .take(max)
+        .map(|(login, _)| login.to_string())
+        .collect()
+}
+
+/// The latest force-push to the head of a PR.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForcePush {
+    pub at: DateTime<Utc>,
+
+    /// The users who had reviewed the PR before, whose reviews may no longer apply.
+    pub reviewers: Vec<String>,
+}
+
+/// Fetch the latest force-push to the head of a PR, if any.
+///
+/// Takes ownership of its arguments, so that it can run as a separate task.
+pub async fn fetch_force_push(
+    octocrab: Octocrab,
+    project: Project,
+    number: u64,
+) -> Result<Option<ForcePush>, anyhow::Error> {
+    #[derive(Deserialize)]
+    struct Data {
+        repository: Option<RepositoryNode>,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct RepositoryNode {
+        pull_request: Option<PullRequestNode>,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct PullRequestNode {
+        timeline_items: Connection<Pushed>,
+        latest_reviews: Connection<Review>,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Pushed {
+        created_at: DateTime<Utc>,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Review {
+        author: Option<Reviewer>,
+        submitted_at: Option<DateTime<Utc>>,
+    }
+    const QUERY: &str = "query($owner: String!, $name: String!, $number: Int!) {
+        repository(owner: $owner, name: $name) {
+            pullRequest(number: $number) {
+                timelineItems(itemTypes: [HEAD_REF_FORCE_PUSHED_EVENT], last: 1) {
+                    nodes { ... on HeadRefForcePushedEvent { createdAt } }
+                }
+                latestReviews(first: 20) { nodes { author { login } submittedAt } }
+            }
+        }
+    }";
+    let data: Data = graphql(
+        &octocrab,
+        project.api_url.as_ref(),
+        QUERY,
+        serde_json::json!({
+            "owner": project.owner,
+            "name": project.repo.to_string(),
+            "number": number,
+        }),
+    )
+    .await
+    .with_context(|| format!("Couldn't download the force-pushes of PR {number}"))?;
+    let Some(pull) = data
+        .repository
+        .and_then(|repository| repository.pull_request)
+    else {
+        return Ok(None);
+    };
+    let Some(at) = pull
+        .timeline_items
+        .nodes
+        .last()
+        .map(|pushed| pushed.created_at)
+    else {
+        return Ok(None);
+    };
+    let reviewers = pull
+        .latest_reviews
+        .nodes
+        .into_iter()
+        .filter(|review| review.submitted_at.is_some_and(|submitted| submitted < at))
+        .filter_map(|review| review.author?.login)
+        .collect();
+    Ok(Some(ForcePush { at, reviewers }))
+}
+
+/// A discussion without an answer or a reply from a maintainer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Discussion {
+    pub number: u64,
+    pub title: String,
+    pub url: Url,
+    pub category: String,
+    pub author: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiscussionNode {
+    number: u64,
+    title: String,
+    url: Url,
+    created_at: DateTime<Utc>,
+    #[serde(default)]
+    updated_at: Option<DateTime<Utc>>,
+    author: Option<Reviewer>,
+    category: Named,
+    answer: Option<serde::de::IgnoredAny>,
+    comments: Connection<DiscussionComment>,
+}
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DiscussionComment {
+    author_association: String,
+}
+
+/// List the discussions opened since `since` that have neither an answer nor a reply from
+/// a maintainer (owner, member or collaborator), oldest first.
+pub async fn fetch_unanswered_discussions(
+    octocrab: &Octocrab,
+    project: &Project,
+    since: Option<DateTime<Utc>>,
+    max_discussions: usize,
+) -> Result<Vec<Discussion>, anyhow::Error> {
+    #[derive(Deserialize)]
+    struct Data {
+        repository: Option<RepositoryNode>,
+    }
+    #[derive(Deserialize)]
+    struct RepositoryNode {
+        discussions: Discussions,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Discussions {
+        page_info: PageInfo,
+        nodes: Vec<DiscussionNode>,
+    }
+    const QUERY: &str = "query($owner: String!, $name: String!, $cursor: String) {
+        repository(owner: $owner, name: $name) {
+            discussions(first: 50, after: $cursor, orderBy: { field: CREATED_AT, direction: DESC }) {
+                pageInfo { hasNextPage endCursor }
+                nodes {
+                    number title url createdAt updatedAt
+                    author { login }
+                    category { name }
+                    answer { id }
+                    comments(first: 20) { nodes { authorAssociation } }
+                }
+            }
+        }
+    }";
+    let mut nodes = vec![];
+    let mut cursor: Option<String> = None;
+    loop {
+        let data: Data = graphql(
+            octocrab,
+            project.api_url.as_ref(),
+            QUERY,
+            serde_json::json!({
+                "owner": project.owner,
+                "name": project.repo.to_string(),
+                "cursor": cursor,
+            }),
+        )
+        .await
+        .context("Couldn't download discussions")?;
+        let Some(repository) = data.repository else {
+            break;
+        };
+        let page = repository.discussions;
+        // Newest first.
+        let reached_since = page
+            .nodes
+            .last()
+            .is_some_and(|node| since.is_some_and(|since| node.created_at < since));
+        nodes.extend(page.nodes);
+        if page.page_info.has_next_page.not() || reached_since || nodes.len() >= max_discussions {
+            break;
+        }
+        cursor = page.page_info.end_cursor;
+    }
+    Ok(unanswered_discussions(nodes, since))
+}
+
+/// Keep the discussions opened since `since` without an answer or a reply from a
+/// maintainer, oldest first.
+fn unanswered_discussions(
+    nodes: Vec<DiscussionNode>,
+    since: Option<DateTime<Utc>>,
+) -> Vec<Discussion> {
+    let mut discussions = nodes
+        .into_iter()
+        .filter(|node| since.is_none_or(|since| node.created_at >= since))
+        .filter(|node| node.answer.is_none())
+        .filter(|node| {
+            node.comments.nodes.iter().all(|comment| {
+                matches!(
+                    comment.author_association.as_str(),
+                    "OWNER" | "MEMBER" | "COLLABORATOR"
+                )
+                .not()
+            })
+        })
+        .map(|node| Discussion {
+            number: node.number,
+            title: node.title,
+            url: node.url,
+            category: node.category.name,
+            author: node.author.and_then(|author| author.login),
+            created_at: node.created_at,
+            updated_at: node.updated_at,
+        })
+        .collect_vec();
+    discussions.sort_by_key(|discussion| discussion.created_at);
+    discussions
+}
+
+/// Who was asked to review a PR, and who reviewed it, during a period.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReviewActivity {
+    /// The logins of the users whose review was requested, once per request.
+    pub requested: Vec<String>,
+
+    /// The logins of the authors of reviews, once per review.
+    pub reviewed: Vec<String>,
+}
+
+/// Fetch the review requests and reviews of the PRs of a project updated since `since`.
+pub async fn fetch_review_activity(
+    octocrab: &Octocrab,
+    project: &Project,
+    since: DateTime<Utc>,
+) -> Result<Vec<ReviewActivity>, anyhow::Error> {
+    #[derive(Deserialize)]
+    struct Data {
+        search: Search,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Search {
+        page_info: PageInfo,
+        nodes: Vec<PullNode>,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct PullNode {
+        timeline_items: Connection<Requested>,
+        reviews: Connection<Review>,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Requested {
+        // Teams don't have a login.
+        requested_reviewer: Option<Reviewer>,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Review {
+        author: Option<Reviewer>,
+        submitted_at: Option<DateTime<Utc>>,
+    }
+    const QUERY: &str = "query($query: String!, $since: DateTime!, $cursor: String) {
+        search(query: $query, type: ISSUE, first: 50, after: $cursor) {
+            pageInfo { hasNextPage endCursor }
+            nodes {
+                ... on PullRequest {
+                    timelineItems(itemTypes: [REVIEW_REQUESTED_EVENT], since: $since, first: 100) {
+                        nodes { ... on ReviewRequestedEvent { requestedReviewer { ... on User { login } } } }
+                    }
+                    reviews(first: 100) { nodes { author { login } submittedAt } }
+                }
+            }
+        }
+    }";
+    let search = format!(
+        "repo:{}/{} is:pr updated:>={}",
+        project.owner,
+        project.repo,
+        since.format("%Y-%m-%d")
+    );
+    let mut activity = vec![];
+    let mut cursor: Option<String> = None;
+    for _ in 0..MAX_SEARCH_PAGES {
+        let data: Data = graphql(
+            octocrab,
+            project.api_url.as_ref(),
+            QUERY,
+            serde_json::json!({
+                "query": search,
+                "since": since,
+                "cursor": cursor,
+            }),
+        )
+        .await
+        .context("Couldn't search review activity")?;
+        for node in data.search.nodes {
+            activity.push(ReviewActivity {
+                requested: node
+                    .timeline_items
+                    .nodes
+                    .into_iter()
+                    .filter_map(|requested| requested.requested_reviewer?.login)
+                    .collect(),
+                reviewed: node
+                    .reviews
+                    .nodes
+                    .into_iter()
+                    .filter(|review| review.submitted_at.is_some_and(|at| at >= since))
+                    .filter_map(|review| review.author?.login)
+                    .collect(),
+            });
+        }
+        if data.search.page_info.has_next_page.not() {
+            break;
+        }
+        cursor = data.search.page_info.end_cursor;
+    }
+    Ok(activity)
+}
+
+/// A label of interest added to or removed from an issue.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelChange {
+    pub number: u64,
+    pub title: String,
+    pub url: Url,
+    pub label: String,
+
+    /// Whether the label was added (or removed).
+    pub added: bool,
+    pub actor: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+/// An event of the issues of a repository, as returned by the REST API.
+#[derive(Deserialize)]
+struct IssueEvent {
+    event: String,
+    created_at: DateTime<Utc>,
+    actor: Option<EventActor>,
+    label: Option<EventLabel>,
+    issue: Option<EventIssue>,
+}
+#[derive(Deserialize, Debug, Clone)]
+struct EventActor {
+    login: String,
+}
+#[derive(Deserialize)]
+struct EventLabel {
+    name: String,
+}
+#[derive(Deserialize)]
+struct EventIssue {
+    number: u64,
+    title: String,
+    html_url: Url,
+    pull_request: Option<serde_json::Value>,
+}
+
+/// Find the issues to which some labels of interest have been added (or from which they
+/// have been removed) since a date, looking at up to `max_events` events.
+pub async fn fetch_label_changes(
+    octocrab: &Octocrab,
+    project: &Project,
+    labels: &[String],
+    since: Option<DateTime<Utc>>,
+    per_page: u8,
+    max_events: usize,
+) -> Result<Vec<LabelChange>, anyhow::Error> {
+    let mut events = vec![];
+    for page in 1u32.. {
+        // Newest first.
+        let items: Vec<IssueEvent> = octocrab
+            .get(
+                format!(
+                    "/repos/{owner}/{repo}/issues/events",
+                    owner = project.owner,
+                    repo = project.repo
+                ),
+                Some(&serde_json::json!({
+                    "per_page": per_page,
+                    "page": page,
+                })),
+            )
+            .await
+            .map_err(Error::from_github)
+            .context("Couldn't download issue events")?;
+        let complete = items.len() < per_page as usize;
+        let mut reached_since = false;
+        for event in items {
+            if since.is_some_and(|since| event.created_at < since) {
+                reached_since = true;
+                break;
+            }
+            events.push(event);
+        }
+        if complete || reached_since || events.len() >= max_events {
+            break;
+        }
+    }
+    Ok(label_changes(events, labels))
+}
+
+/// Keep the latest change of each label of interest on each issue, oldest first.
+///
+/// Events are expected newest first, as returned by GitHub.
+fn label_changes(events: Vec<IssueEvent>, labels: &[String]) -> Vec<LabelChange> {
+    let mut seen = std::collections::HashSet::new();
+    let mut changes = vec![];
+    for event in events {
+        let added = match event.event.as_str() {
+            "labeled" => true,
+            "unlabeled" => false,
+            _ => continue,
+        };
+        let (Some(label), Some(issue)) = (event.label, event.issue) else {
+            continue;
+        };
+        if issue.pull_request.is_some()
+            || labels
+                .iter()
+                .any(|wanted| wanted.eq_ignore_ascii_case(&label.name))
+                .not()
+        {
+            continue;
+        }
+        if seen.insert((issue.number, label.name.to_lowercase())).not() {
+            continue;
+        }
+        changes.push(LabelChange {
+            number: issue.number,
+            title: issue.title,
+            url: issue.html_url,
+            label: label.name,
+            added,
+            actor: event.actor.map(|actor| actor.login),
+            at: event.created_at,
+        });
+    }
+    changes.reverse();
+    changes
+}
+
+/// A completed run of a GitHub Actions workflow.
+#[derive(Deserialize, Debug, Clone)]
+pub struct WorkflowRun {
+    pub workflow_id: u64,
+
+    /// The name of the workflow, e.g. `CI`.
+    pub name: Option<String>,
+    pub head_sha: String,
+
+    /// E.g. `success`, `failure` or `cancelled`.
+    pub conclusion: Option<String>,
+    pub html_url: Url,
+    pub updated_at: DateTime<Utc>,
+    pub head_commit: Option<HeadCommit>,
+
+    /// Where the commit comes from, e.g. a fork for PRs whose branch has the same name.
+    head_repository: Option<HeadRepository>,
+}
+impl WorkflowRun {
+    /// Whether the run failed, as opposed to e.g. succeeded, was skipped or cancelled.
+    pub fn failed(&self) -> bool {
+        matches!(
+            self.conclusion.as_deref(),
+            Some("failure" | "timed_out" | "startup_failure")
+        )
+    }
+}
+#[derive(Deserialize, Debug, Clone)]
+pub struct HeadCommit {
+    pub message: String,
+}
+#[derive(Deserialize, Debug, Clone)]
+struct HeadRepository {
+    full_name: String,
+}
+
+/// Fetch the latest completed workflow runs on a branch of a project, newest first.
+///
+/// Runs of forks, e.g. of PRs from a branch with the same name, are left out.
+pub async fn fetch_workflow_runs(
+    octocrab: &Octocrab,
+    project: &Project,
+    branch: &str,
+    per_page: u8,
+) -> Result<Vec<WorkflowRun>, anyhow::Error> {
+    /// Enough for the latest runs of each workflow of busy projects.
+    const MAX_RUNS: usize = 300;
+    let first: Page<WorkflowRun> = octocrab
+        .get(
+            format!(
+                "/repos/{owner}/{repo}/actions/runs",
+                owner = project.owner,
+                repo = project.repo
+            ),
+            Some(&serde_json::json!({
+                "branch": branch,
+                "status": "completed",
+                "per_page": per_page,
+            })),
+        )
+        .await
+        .map_err(Error::from_github)
+        .context("Couldn't download workflow runs")?;
+    let (runs, _) = collect_pages(octocrab, first, MAX_RUNS)
+        .await
+        .context("Couldn't download workflow runs")?;
+    let full_name = format!("{}/{}", project.owner, project.repo);
+    Ok(runs
+        .into_iter()
+        .filter(|run| {
+            run.head_repository
+                .as_ref()
+                .is_none_or(|head| head.full_name.eq_ignore_ascii_case(&full_name))
+        })
+        .collect())
+}
+
+/// The latest run of each workflow, if it failed since a date, oldest first.
+///
+/// With `only_transitions`, workflows whose previous run had already failed are skipped, so
+/// that we only say when a workflow turns red. Runs are expected newest first.
+pub fn failing_workflows(
+    runs: Vec<WorkflowRun>,
+    since: Option<DateTime<Utc>>,
+    only_transitions: bool,
+) -> Vec<WorkflowRun> {
+    let mut by_workflow = BTreeMap::<u64, Vec<WorkflowRun>>::new();
+    for run in runs {
+        by_workflow.entry(run.workflow_id).or_default().push(run);
+    }
+    let mut failing = by_workflow
+        .into_values()
+        .filter_map(|runs| {
+            let mut runs = runs.into_iter();
+            let latest = runs.next()?;
+            if latest.failed().not()
+                || since.is_some_and(|since| latest.updated_at < since)
+                || (only_transitions && runs.next().is_some_and(|previous| previous.failed()))
+            {
+                return None;
+            }
+            Some(latest)
+        })
+        .collect_vec();
+    failing.sort_by_key(|run| run.updated_at);
+    failing
+}
+
+/// A reaction to an issue or a PR.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Reaction {
+    user: Option<EventActor>,
+    pub created_at: DateTime<Utc>,
+}
+impl Reaction {
+    /// Who reacted, if their account still exists.
+    pub fn login(&self) -> Option<&str> {
+        self.user.as_ref().map(|user| user.login.as_str())
+    }
+}
+
+/// Fetch the 👀 reactions to a PR (or an issue).
+///
+/// Takes ownership of its arguments, so that it can run as a separate task.
+pub async fn fetch_eyes(
+    octocrab: Octocrab,
+    project: Project,
+    number: u64,
+) -> Result<Vec<Reaction>, anyhow::Error> {
+    octocrab
+        .get(
+            format!(
+                "/repos/{owner}/{repo}/issues/{number}/reactions",
+                owner = project.owner,
+                repo = project.repo
+            ),
+            Some(&serde_json::json!({
+                "content": "eyes",
+                "per_page": 100,
+            })),
+        )
+        .await
+        .map_err(Error::from_github)
+        .with_context(|| format!("Couldn't download reactions to PR {number}"))
+}
+
+/// The earliest reaction of a requested reviewer, if any.
+pub fn reviewer_reaction<'a>(
+    reviewers: &[String],
+    reactions: &'a [Reaction],
+) -> Option<&'a Reaction> {
+    reactions
+        .iter()
+        .filter(|reaction| {
+            reaction.login().is_some_and(|login| {
+                reviewers
+                    .iter()
+                    .any(|reviewer| reviewer.eq_ignore_ascii_case(login))
+            })
+        })
+        .min_by_key(|reaction| reaction.created_at)
+}
+
+/// A key identifying a repository in search results, e.g. `owner/repo`, ignoring case.
+fn repository_key(owner: &str, repo: &str) -> String {
+    format!("{owner}/{repo}").to_lowercase()
+}
+
+/// Extract the key of a repository from its API url, e.g.
+/// `https://api.github.com/repos/owner/repo`.
+fn repository_key_from_api_url(url: &Url) -> Option<String> {
+    let mut segments = url.path_segments()?.rev();
+    let repo = segments.next()?;
+    let owner = segments.next()?;
+    Some(repository_key(owner, repo))
+}
+
+/// Whether an issue belongs to a project.
+pub fn is_in_project(issue: &Issue, project: &Project) -> bool {
+    repository_key_from_api_url(&issue.repository_url).as_deref()
+        == Some(repository_key(&project.owner, project.repo.as_ref()).as_str())
+}
+
+/// If an issue has been transferred to another repository, the issue at its new location.
+///
+/// GitHub redirects requests for transferred issues, so we find out by looking at
+/// the issue we actually received.
+pub async fn transferred_to(
+    octocrab: &Octocrab,
+    project: &Project,
+    number: u64,
+) -> Result<Option<Issue>, anyhow::Error> {
+    let issue = match octocrab
+        .issues(&project.owner, &project.repo)
+        .get(number)
+        .await
+    {
+        Ok(issue) => issue,
+        // Deleted issues are gone, not transferred.
+        Err(octocrab::Error::GitHub { source, .. })
+            if source.status_code == reqwest::StatusCode::NOT_FOUND
+                || source.status_code == reqwest::StatusCode::GONE =>
+        {
+            return Ok(None)
+        }
+        Err(err) => return Err(err).with_context(|| format!("Couldn't fetch issue {number}")),
+    };
+    Ok(is_in_project(&issue, project).not().then_some(issue))
+}
+
+/// Run an arbitrary search of issues and PRs, e.g. `is:pr review-requested:@me`, returning
+/// at most `limit` results, best matches first.
+pub async fn search_issues(
+    octocrab: &Octocrab,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<Issue>, anyhow::Error> {
+    const PER_PAGE: usize = 100;
+    let mut found = vec![];
+    for page in 1..=MAX_SEARCH_PAGES {
+        let items = octocrab
+            .search()
+            .issues_and_pull_requests(query)
+            .per_page(PER_PAGE.min(limit) as u8)
+            .page(page)
+            .send()
+            .await
+            .map_err(Error::from_github)
+            .with_context(|| format!("Couldn't search {query}"))?
+            .take_items();
+        let complete = items.len() < PER_PAGE.min(limit);
+        found.extend(items);
+        if complete || found.len() >= limit {
+            break;
+        }
+    }
+    found.truncate(limit);
+    Ok(found)
+}
+
+/// The owner that a search is about, if it says, e.g. `pasqal-io` for `repo:pasqal-io/qadence`
+/// or `org:pasqal-io`, so that we search with the credentials of that owner.
+pub fn search_owner(query: &str) -> Option<&str> {
+    query.split_whitespace().find_map(|term| {
+        let (qualifier, value) = term.split_once(':')?;
+        match qualifier {
+            "repo" => value.split_once('/').map(|(owner, _)| owner),
+            "org" | "user" => Some(value),
+            _ => None,
+        }
+        .filter(|owner| owner.is_empty().not())
+    })
+}
+
+/// Everything we need about the repositories of an owner, found through the
+/// Search API rather than by listing each repository.
+#[derive(Default)]
+pub struct SearchResults {
+    issues: HashMap<String, Vec<Issue>>,
+
+    /// `None` if we couldn't search for pending reviews.
+    reviews: Option<HashMap<String, Vec<PendingReview>>>,
+
+    /// The repositories whose pending reviews we couldn't fetch in a batch, which need to
+    /// be listed separately.
+    unlisted: HashSet<String>,
+}
+impl SearchResults {
+    /// Take the issues (updated since the search date) and pending reviews of a project.
+    ///
+    /// Pending reviews are `None` if they need to be listed separately.
+    pub fn take(&mut self, project: &Project) -> (Vec<Issue>, Option<Vec<PendingReview>>) {
+        let key = repository_key(&project.owner, project.repo.as_ref());
+        let reviews = match self.unlisted.contains(&key) {
+            true => None,
+            false => self
+                .reviews
+                .as_mut()
+                .map(|reviews| reviews.remove(&key).unwrap_or_default()),
+        };
+        (self.issues.remove(&key).unwrap_or_default(), reviews)
+    }
+}
+
+/// Find all the issues updated since a date (or all open issues) and all the pull
+/// requests waiting for reviews across all the repositories of an owner (user or org).
+///
+/// This takes a handful of queries, regardless of the number of repositories. If `batch`
+/// is specified, the pending reviews of these repositories are fetched in batches rather
+/// than searched, see `batch_pending_reviews`. Only for owners on github.com, with the
+/// client from `instance`.
+pub async fn search(
+    octocrab: &Octocrab,
+    owner: &str,
+    since: Option<DateTime<Utc>>,
+    per_page: u8,
+    batch: Option<&[&Project]>,
+) -> Result<SearchResults, anyhow::Error> {
+    let mut results = SearchResults::default();
+
+    // Issues (and PRs, as in per-repository mode) through the REST API.
+    let query = match since {
+        Some(since) => format!(
+            "user:{owner} updated:>={since}",
+            since = since.format("%Y-%m-%dT%H:%M:%SZ")
+        ),
+        None => format!("user:{owner} is:open"),
+    };
+    for page in 1..=MAX_SEARCH_PAGES {
+        let items = octocrab
+            .search()
+            .issues_and_pull_requests(&query)
+            .per_page(per_page)
+            .page(page)
+            .send()
+            .await
+            .map_err(Error::from_github)
+            .context("Couldn't search recent issues")?
+            .take_items();
+        let complete = items.len() < per_page as usize;
+        for issue in items {
+            let Some(key) = repository_key_from_api_url(&issue.repository_url) else {
+                continue;
+            };
+            results.issues.entry(key).or_default().push(issue);
+        }
+        if complete {
+            break;
+        }
+    }
+
+    // Review requests are not part of the REST search results, and GraphQL
+    // requires authentication.
+    let reviews = match batch {
+        Some(projects) => batch_pending_reviews(octocrab, projects).await,
+        None => search_pending_reviews(octocrab, owner).await,
+    };
+    match reviews {
+        Ok(reviews) => {
+            results.unlisted = batch
+                .into_iter()
+                .flatten()
+                .map(|project| repository_key(&project.owner, project.repo.as_ref()))
+                .filter(|key| reviews.contains_key(key).not())
+                .collect();
+            results.reviews = Some(reviews);
+        }
+        Err(err) => warn!(
+            "Could not search pending reviews of {owner}, listing them per repository: {err:?}"
+        ),
+    }
+    Ok(results)
+}
+
+/// A pull request, as returned by GraphQL queries that use `PULL_FIELDS`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PullNode {
+    number: u64,
+    title: String,
+    url: Url,
+    body: Option<String>,
+    created_at: DateTime<Utc>,
+    #[serde(default)]
+    updated_at: Option<DateTime<Utc>>,
+    is_draft: bool,
+    author: Option<Author>,
+    repository: NodeRepository,
+    labels: Connection<Named>,
+    review_requests: Connection<ReviewRequest>,
+    review_threads: Connection<ReviewThread>,
+}
+#[derive(Deserialize)]
+struct Author {
+    #[serde(rename = "__typename")]
+    typename: String,
+    login: String,
+}
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeRepository {
+    name_with_owner: String,
+}
+#[derive(Deserialize)]
+struct Connection<T> {
+    nodes: Vec<T>,
+}
+#[derive(Deserialize)]
+struct Named {
+    name: String,
+}
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReviewRequest {
+    requested_reviewer: Option<Reviewer>,
+}
+#[derive(Deserialize)]
+struct ReviewThread {
+    comments: Count,
+}
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Count {
+    total_count: u64,
+}
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Reviewer {
+    // Teams don't have a login, but a slug, e.g. `pasqal-io/sdk`.
+    login: Option<String>,
+    #[serde(default)]
+    combined_slug: Option<String>,
+}
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PageInfo {
+    has_next_page: bool,
+    end_cursor: Option<String>,
+}
+impl PullNode {
+    /// The key of the repository of the PR, then the PR if it's waiting for reviews.
+    fn into_pending_review(self) -> (String, Option<PendingReview>) {
+        let key = self.repository.name_with_owner.to_lowercase();
+        let requested = self
+            .review_requests
+            .nodes
+            .into_iter()
+            .filter_map(|request| request.requested_reviewer)
+            .collect_vec();
+        let reviewers = requested
+            .iter()
+            .filter_map(|reviewer| reviewer.login.clone())
+            .collect_vec();
+        let teams = requested
+            .into_iter()
+            .filter_map(|reviewer| reviewer.combined_slug)
+            .collect_vec();
+        if reviewers.is_empty() && teams.is_empty() {
+            return (key, None);
+        }
+        let review = PendingReview {
+            number: self.number,
+            url: self.url,
+            title: self.title,
+            reviewers,
+            teams,
+            labels: self.labels.nodes.into_iter().map(|l| l.name).collect(),
+            body: self.body,
+            created_at: Some(self.created_at),
+            updated_at: self.updated_at,
+            // Unlike the REST API, GraphQL omits the suffix of bots.
+            author: self.author.map(|author| match author.typename.as_str() {
+                "Bot" => format!("{}[bot]", author.login),
+                _ => author.login,
+            }),
+            draft: self.is_draft,
+            review_comments: Some(
+                self.review_threads
+                    .nodes
+                    .iter()
+                    .map(|thread| thread.comments.total_count)
+                    .sum(),
+            ),
+        };
+        (key, Some(review))
+    }
+}
+
+/// The fields of `PullNode`, as a GraphQL fragment named `pull`.
+const PULL_FIELDS: &str = "fragment pull on PullRequest {
+    number title url body createdAt updatedAt isDraft
+    author { __typename login }
+    repository { nameWithOwner }
+    labels(first: 20) { nodes { name } }
+    reviewRequests(first: 20) {
+        nodes { requestedReviewer { ... on User { login } ... on Bot { login } ... on Team { combinedSlug } } }
+    }
+    reviewThreads(first: 100) { nodes { comments { totalCount } } }
+}";
+
+/// Find all the pull requests waiting for reviews across all the repositories of an
+/// owner, indexed by repository key.
+async fn search_pending_reviews(
+    octocrab: &Octocrab,
+    owner: &str,
+) -> Result<HashMap<String, Vec<PendingReview>>, anyhow::Error> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Data {
+        search: Search,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Search {
+        page_info: PageInfo,
+        nodes: Vec<PullNode>,
+    }
+    let query = format!(
+        "query($query: String!, $cursor: String) {{
+            search(query: $query, type: ISSUE, first: 100, after: $cursor) {{
+                pageInfo {{ hasNextPage endCursor }}
+                nodes {{ ...pull }}
+            }}
+        }}
+        {PULL_FIELDS}"
+    );
+    let mut reviews: HashMap<String, Vec<PendingReview>> = HashMap::new();
+    let search = format!("user:{owner} is:pr is:open");
+    let mut cursor: Option<String> = None;
+    for _ in 0..MAX_SEARCH_PAGES {
+        // Only projects on github.com are searched, see `search`.
+        let data: Data = graphql(
+            octocrab,
+            None,
+            &query,
+            serde_json::json!({
+                "query": search,
+                "cursor": cursor,
+            }),
+        )
+        .await
+        .context("Couldn't search pending reviews")?;
+        for node in data.search.nodes {
+            if let (key, Some(review)) = node.into_pending_review() {
+                reviews.entry(key).or_default().push(review);
+            }
+        }
+        if !data.search.page_info.has_next_page {
+            break;
+        }
+        cursor = data.search.page_info.end_cursor;
+    }
+    Ok(reviews)
+}
+
+/// How many repositories we query at once in `batch_pending_reviews`.
+const BATCH_SIZE: usize = 10;
+
+/// A GraphQL query for the open PRs of some repositories, with one aliased field
+/// (`repo0`, `repo1`, ...) per repository, and its variables.
+fn batch_query(projects: &[&Project]) -> (String, serde_json::Value) {
+    let mut variables = serde_json::Map::new();
+    for (i, project) in projects.iter().enumerate() {
+        variables.insert(format!("owner{i}"), project.owner.clone().into());
+        variables.insert(format!("name{i}"), project.repo.to_string().into());
+    }
+    let query = format!(
+        "query({params}) {{ {fields} }}\n{PULL_FIELDS}",
+        params = (0..projects.len())
+            .map(|i| format!("$owner{i}: String!, $name{i}: String!"))
+            .format(", "),
+        fields = (0..projects.len())
+            .map(|i| format!(
+                "repo{i}: repository(owner: $owner{i}, name: $name{i}) {{ \
+                pullRequests(states: OPEN, first: 100, orderBy: {{field: CREATED_AT, direction: DESC}}) {{ \
+                pageInfo {{ hasNextPage endCursor }} nodes {{ ...pull }} }} }}"
+            ))
+            .format(" "),
+    );
+    (query, serde_json::Value::Object(variables))
+}
+
+/// Find the pull requests waiting for reviews in some repositories, indexed by repository
+/// key, with one GraphQL query per `BATCH_SIZE` repositories rather than one listing per
+/// repository.
+///
+/// We only look at the 100 most recent open PRs of each repository. Repositories that we
+/// couldn't query, e.g. because they're missing, are missing from the result.
+pub async fn batch_pending_reviews(
+    octocrab: &Octocrab,
+    projects: &[&Project],
+) -> Result<HashMap<String, Vec<PendingReview>>, anyhow::Error> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Repository {
+        pull_requests: Pulls,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Pulls {
+        page_info: PageInfo,
+        nodes: Vec<PullNode>,
+    }
+    let mut reviews: HashMap<String, Vec<PendingReview>> = HashMap::new();
+    for batch in projects.chunks(BATCH_SIZE) {
+        let (query, variables) = batch_query(batch);
+        // Missing repositories are `null`, and reported as errors, along with the others.
+        let (mut data, errors): (HashMap<String, Option<Repository>>, _) =
+            graphql_partial(octocrab, None, &query, variables)
+                .await
+                .context("Couldn't fetch pending reviews")?;
+        if errors.is_empty().not() {
+            warn!(
+                "Could not fetch the pending reviews of some repositories: {}",
+                errors.iter().format(", ")
+            );
+        }
+        for (i, project) in batch.iter().enumerate() {
+            let Some(repository) = data.remove(&format!("repo{i}")).flatten() else {
+                continue;
+            };
+            if repository.pull_requests.page_info.has_next_page {
+                warn!(
+                    "Project {} has more than 100 open PRs, only the most recent ones are checked",
+                    project.url
+                );
+            }
+            let pending = reviews
+                .entry(repository_key(&project.owner, project.repo.as_ref()))
+                .or_default();
+            pending.extend(
+                repository
+                    .pull_requests
+                    .nodes
+                    .into_iter()
+                    .filter_map(|node| node.into_pending_review().1),
+            );
+        }
+    }
+    Ok(reviews)
+}
+
+/// The issues of the same repository that a PR description closes, using GitHub's
+/// closing keywords, e.g. "Fixes #123".
+pub fn closing_references(body: &str) -> Vec<u64> {
+    static CLOSING: Lazy<Regex> =
+        lazy_regex! {r"(?i)\b(?:close[sd]?|fix(?:e[sd])?|resolve[sd]?):?\s+#(\d+)\b"};
+    CLOSING
+        .captures_iter(body)
+        .filter_map(|captures| captures[1].parse().ok())
+        .unique()
+        .collect()
+}
+
+/// Progress of a GitHub-flavored markdown task list, as `(done, total)`.
+///
+/// `None` if the text doesn't contain any task. Tasks within code blocks are ignored.
+pub fn task_list_progress(body: &str) -> Option<(usize, usize)> {
+    static TASK: Lazy<Regex> = lazy_regex! {r"^\s*[-*+]\s+\[([ xX])\](?:\s|$)"};
+    let mut in_code = false;
+    let (mut done, mut total) = (0, 0);
+    for line in body.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+        if let Some(captures) = TASK.captures(line) {
+            total += 1;
+            if &captures[1] != " " {
+                done += 1;
+            }
+        }
+    }
+    (total > 0).then_some((done, total))
+}
+
+/// The answers of an issue filed through an issue form, as `(label, answer)`, e.g.
+/// `("Severity", "High")`.
+///
+/// Forms render each field as a `### Label` heading followed by the answer. Fields left
+/// empty (`_No response_`) are omitted.
+pub fn issue_form_fields(body: &str) -> Vec<(String, String)> {
+    let mut fields = vec![];
+    let mut current: Option<(&str, Vec<&str>)> = None;
+    for line in body.lines() {
+        if let Some(label) = line.strip_prefix("### ") {
+            fields.extend(current.take());
+            current = Some((label.trim(), vec![]));
+        } else if let Some((_, ref mut lines)) = current {
+            lines.push(line);
+        }
+    }
+    fields.extend(current);
+    fields
+        .into_iter()
+        .filter_map(|(label, lines)| {
+            let answer = lines.join("\n").trim().to_string();
+            (answer.is_empty().not() && answer != "_No response_")
+                .then(|| (label.to_string(), answer))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use chrono::{TimeZone, Utc};
+    use itertools::Itertools;
+    use url::Url;
+
+    use super::{
+        batch_query, closing_references, failing_workflows, graphql, graphql_base,
+        issue_form_fields, label_changes, repository_key_from_api_url, reviewer_reaction,
+        roll_up_sub_issues, same_repository, search_owner, suggest_reviewers, task_list_progress,
+        unanswered_discussions, DiscussionNode, Host, IssueDetails, IssueEvent, PullNode, Reaction,
+        WorkflowRun, HOSTS,
+    };
+
+    /// Do we recognize renamed repositories (and only them)?
+    #[test]
+    fn test_same_repository() {
+        let configured = Url::parse("https://github.com/owner1/project1").unwrap();
+        for same in [
+            "https://github.com/owner1/project1",
+            "https://github.com/Owner1/Project1",
+            "https://github.com/owner1/project1/",
+        ] {
+            assert!(same_repository(&configured, &Url::parse(same).unwrap()));
+        }
+        for moved in [
+            "https://github.com/owner1/project2",
+            "https://github.com/owner2/project1",
+        ] {
+            assert!(!same_repository(&configured, &Url::parse(moved).unwrap()));
+        }
+    }
+
+    /// Do search results find their way back to their repository?
+    #[test]
+    fn test_repository_key_from_api_url() {
+        let url = Url::parse("https://api.github.com/repos/Owner1/Project1").unwrap();
+        assert_eq!(
+            repository_key_from_api_url(&url).as_deref(),
+            Some("owner1/project1")
+        );
+    }
+
+    /// Are PRs waiting for a team only pending too, with the team apart from the users?
+    #[test]
+    fn test_requested_teams() {
+        let node = |requested: serde_json::Value| -> PullNode {
+            serde_json::from_value(serde_json::json!({
+                "number": 1,
+                "title": "Fix",
+                "url": "https://github.com/Owner1/Project1/pull/1",
+                "body": null,
+                "createdAt": "2024-05-06T12:00:00Z",
+                "isDraft": false,
+                "author": { "__typename": "User", "login": "bob" },
+                "repository": { "nameWithOwner": "Owner1/Project1" },
+                "labels": { "nodes": [] },
+                "reviewRequests": { "nodes": requested },
+                "reviewThreads": { "nodes": [] },
+            }))
+            .unwrap()
+        };
+        let (key, review) = node(serde_json::json!([
+            { "requestedReviewer": { "combinedSlug": "owner1/reviewers" } },
+        ]))
+        .into_pending_review();
+        assert_eq!(key, "owner1/project1");
+        let review = review.unwrap();
+        assert!(review.reviewers.is_empty());
+        assert_eq!(review.teams, ["owner1/reviewers"]);
+        let (_, review) = node(serde_json::json!([
+            { "requestedReviewer": { "login": "alice" } },
+            { "requestedReviewer": { "combinedSlug": "owner1/reviewers" } },
+        ]))
+        .into_pending_review();
+        assert_eq!(
+            review.unwrap().requested().collect_vec(),
+            ["alice", "owner1/reviewers"]
+        );
+        let (_, review) =
+            node(serde_json::json!([{ "requestedReviewer": null }])).into_pending_review();
+        assert!(review.is_none());
+    }
+
+    /// Do searches use the credentials of the owner they are about, if any?
+    #[test]
+    fn test_search_owner() {
+        assert_eq!(
+            search_owner("is:pr review-requested:@me repo:owner1/project1"),
+            Some("owner1")
+        );
+        assert_eq!(
+            search_owner("is:issue org:owner2 label:bug"),
+            Some("owner2")
+        );
+        assert_eq!(search_owner("is:pr review-requested:@me"), None);
+        assert_eq!(search_owner("repo: is:open"), None);
+    }
+
+    /// Does a batch query one aliased field per repository, with its own variables?
+    #[test]
+    fn test_batch_query() {
+        let projects: Vec<crate::config::Project> = [
+            "https://github.com/owner1/project1",
+            "https://github.com/owner1/project2",
+        ]
+        .iter()
+        .map(|url| serde_yaml::from_str(&format!("url: {url}")).unwrap())
+        .collect();
+        let (query, variables) = batch_query(&projects.iter().collect::<Vec<_>>());
+        assert!(query.starts_with(
+            "query($owner0: String!, $name0: String!, $owner1: String!, $name1: String!)"
+        ));
+        assert!(query.contains("repo0: repository(owner: $owner0, name: $name0)"));
+        assert!(query.contains("repo1: repository(owner: $owner1, name: $name1)"));
+        assert!(query.contains("fragment pull on PullRequest"));
+        assert_eq!(
+            variables,
+            serde_json::json!({
+                "owner0": "owner1",
+                "name0": "project1",
+                "owner1": "owner1",
+                "name1": "project2",
+            })
+        );
+    }
+
+    /// Are sub-issues listed right after their parent?
+    #[test]
+    fn test_roll_up_sub_issues() {
+        let child_of = |parent| IssueDetails {
+            issue_type: None,
+            parent: Some(parent),
+        };
+        let details: HashMap<u64, IssueDetails> = [
+            (1, child_of(3)),
+            (4, child_of(1)),
+            (5, child_of(100)), // Parent not in the list.
+        ]
+        .into_iter()
+        .collect();
+        let rolled = roll_up_sub_issues(vec![1, 2, 3, 4, 5], |n| *n, &details);
+        assert_eq!(rolled, vec![(2, 0), (3, 0), (1, 1), (4, 2), (5, 0)]);
+    }
+
+    /// Do we keep the latest change of each label of interest, on issues only?
+    #[test]
+    fn test_label_changes() {
+        let event = |event: &str, label: &str, number: u64, pull: bool, at: &str| {
+            serde_json::json!({
+                "event": event,
+                "created_at": at,
+                "actor": { "login": "alice" },
+                "label": { "name": label },
+                "issue": {
+                    "number": number,
+                    "title": format!("Issue {number}"),
+                    "html_url": format!("https://github.com/owner/repo/issues/{number}"),
+                    "pull_request": if pull { Some(serde_json::json!({})) } else { None },
+                },
+            })
+        };
+        // Newest first.
+        let events: Vec<IssueEvent> = serde_json::from_value(serde_json::json!([
+            event("unlabeled", "Regression", 1, false, "2024-05-03T00:00:00Z"),
+            event(
+                "labeled",
+                "customer-reported",
+                2,
+                false,
+                "2024-05-02T00:00:00Z"
+            ),
+            event("labeled", "regression", 1, false, "2024-05-01T00:00:00Z"),
+            event("labeled", "regression", 3, true, "2024-05-01T00:00:00Z"),
+            event("labeled", "bug", 4, false, "2024-05-01T00:00:00Z"),
+            event("closed", "regression", 5, false, "2024-05-01T00:00:00Z"),
+        ]))
+        .unwrap();
+        let labels = ["regression".to_string(), "customer-reported".to_string()];
+        let changes = label_changes(events, &labels);
+        let summary: Vec<_> = changes
+            .iter()
+            .map(|change| (change.number, change.label.as_str(), change.added))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![(2, "customer-reported", true), (1, "Regression", false)]
+        );
+    }
+
+    /// Do we only alert about workflows whose latest run failed, and optionally only when
+    /// they turn red?
+    #[test]
+    fn test_failing_workflows() {
+        let run = |workflow: u64, conclusion: &str, at: &str| {
+            serde_json::json!({
+                "workflow_id": workflow,
+                "name": format!("Workflow {workflow}"),
+                "head_sha": "0123456789abcdef",
+                "conclusion": conclusion,
+                "html_url": format!("https://github.com/owner/repo/actions/runs/{workflow}"),
+                "updated_at": at,
+                "head_commit": { "message": "Fix tests" },
+            })
+        };
+        // Newest first.
+        let runs: Vec<WorkflowRun> = serde_json::from_value(serde_json::json!([
+            run(1, "failure", "2024-05-04T00:00:00Z"),
+            run(2, "timed_out", "2024-05-03T00:00:00Z"),
+            run(3, "success", "2024-05-03T00:00:00Z"),
+            run(4, "failure", "2024-05-01T00:00:00Z"),
+            run(1, "success", "2024-05-01T00:00:00Z"),
+            run(2, "failure", "2024-04-30T00:00:00Z"),
+            run(3, "failure", "2024-04-30T00:00:00Z"),
+        ]))
+        .unwrap();
+        let since = Some(Utc.with_ymd_and_hms(2024, 5, 2, 0, 0, 0).unwrap());
+        let workflows = |only_transitions| {
+            failing_workflows(runs.clone(), since, only_transitions)
+                .iter()
+                .map(|run| run.workflow_id)
+                .collect_vec()
+        };
+        assert_eq!(workflows(false), [2, 1]);
+        assert_eq!(workflows(true), [1]);
+    }
+
+    /// Are reviewers suggested by the number of files they touched, then of commits, without
+    /// the author of the PR and bots?
+    #[test]
+    fn test_suggest_reviewers() {
+        let authors = [
+            vec!["alice", "bob", "bob", "bob", "dependabot[bot]"],
+            vec!["carol", "alice", "carol"],
+            vec!["dave", "carol"],
+        ]
+        .map(|file| file.into_iter().map(str::to_string).collect_vec());
+        assert_eq!(
+            suggest_reviewers(&authors, |login| login == "dave", 2),
+            ["carol", "alice"]
+        );
+        assert_eq!(
+            suggest_reviewers(&authors, |login| login == "carol", 5),
+            ["alice", "bob", "dave"]
+        );
+    }
+
+    /// Do only reactions of requested reviewers count as acknowledgments?
+    #[test]
+    fn test_reviewer_reaction() {
+        let reactions: Vec<Reaction> = serde_json::from_value(serde_json::json!([
+            { "user": { "login": "mallory" }, "created_at": "2024-05-01T00:00:00Z" },
+            { "user": null, "created_at": "2024-05-01T00:00:00Z" },
+            { "user": { "login": "Bob" }, "created_at": "2024-05-03T00:00:00Z" },
+            { "user": { "login": "alice" }, "created_at": "2024-05-02T00:00:00Z" },
+        ]))
+        .unwrap();
+        let reviewers = ["alice".to_string(), "bob".to_string()];
+        let reaction = reviewer_reaction(&reviewers, &reactions).unwrap();
+        assert_eq!(reaction.login(), Some("alice"));
+        assert!(reviewer_reaction(&["carol".to_string()], &reactions).is_none());
+    }
+
+    /// Do we find the issues closed by a PR, and only them?
+    #[test]
+    fn test_closing_references() {
+        let body = "Fixes #12, closes #13 and resolved: #14.\nSee #15, prefix#16. Fix #12 again.";
+        assert_eq!(closing_references(body), vec![12, 13, 14]);
+    }
+
+    /// Are checked and unchecked tasks counted, outside of code blocks?
+    #[test]
+    fn test_task_list_progress() {
+        let body = "\
+## Checklist
+- [x] Tests
+- [X] Docs
+* [ ] Changelog
+- [ ]not a task
+```
+- [ ] not a task either
+```
+";
+        assert_eq!(task_list_progress(body), Some((2, 3)));
+        assert_eq!(task_list_progress("No checklist"), None);
+    }
+
+    /// Are the answers of issue forms extracted, without the empty ones?
+    #[test]
+    fn test_issue_form_fields() {
+        let body = "\
+### Severity
+
+High
+
+### Component
+
+_No response_
+
+### What happened?
+
+The SDK crashes
+on startup.
+";
+        assert_eq!(
+            issue_form_fields(body),
+            vec![
+                ("Severity".to_string(), "High".to_string()),
+                (
+                    "What happened?".to_string(),
+                    "The SDK crashes\non startup.".to_string()
+                ),
+            ]
+        );
+        assert!(issue_form_fields("Free-form issue").is_empty());
+    }
+
+    /// Are discussions with an answer or a reply from a maintainer left out, as well as
+    /// older ones?
+    #[test]
+    fn test_unanswered_discussions() {
+        let nodes: Vec<DiscussionNode> = serde_json::from_value(serde_json::json!([
+            {
+                "number": 4, "title": "Answered", "url": "https://github.com/owner1/project1/discussions/4",
+                "createdAt": "2024-05-06T12:00:00Z", "author": { "login": "alice" },
+                "category": { "name": "Q&A" }, "answer": { "id": "D_1" },
+                "comments": { "nodes": [{ "authorAssociation": "NONE" }] },
+            },
+            {
+                "number": 3, "title": "Replied", "url": "https://github.com/owner1/project1/discussions/3",
+                "createdAt": "2024-05-06T11:00:00Z", "author": { "login": "alice" },
+                "category": { "name": "Q&A" }, "answer": null,
+                "comments": { "nodes": [{ "authorAssociation": "MEMBER" }] },
+            },
+            {
+                "number": 2, "title": "Help", "url": "https://github.com/owner1/project1/discussions/2",
+                "createdAt": "2024-05-06T10:00:00Z", "author": null,
+                "category": { "name": "Q&A" }, "answer": null,
+                "comments": { "nodes": [{ "authorAssociation": "CONTRIBUTOR" }] },
+            },
+            {
+                "number": 1, "title": "Old", "url": "https://github.com/owner1/project1/discussions/1",
+                "createdAt": "2024-05-01T10:00:00Z", "author": { "login": "bob" },
+                "category": { "name": "Ideas" }, "answer": null,
+                "comments": { "nodes": [] },
+            },
+        ]))
+        .unwrap();
+        let since = Utc.with_ymd_and_hms(2024, 5, 6, 0, 0, 0).unwrap();
+        let discussions = unanswered_discussions(nodes, Some(since));
+        assert_eq!(
+            discussions
+                .iter()
+                .map(|discussion| discussion.number)
+                .collect_vec(),
+            vec![2]
+        );
+        assert_eq!(discussions[0].author, None);
+    }
+
+    /// Do GraphQL queries reach `/api/graphql` on GitHub Enterprise Server?
+    #[tokio::test]
+    async fn test_graphql_url() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let api_url = Url::parse("https://github.example.com/api/v3").unwrap();
+        assert_eq!(
+            graphql_base(&api_url).as_str(),
+            "https://github.example.com/api"
+        );
+
+        // A mock GitHub Enterprise Server, which answers a single query.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let api_url = Url::parse(&format!(
+            "http://{}/api/v3/",
+            listener.local_addr().unwrap()
+        ))
+        .unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 4096];
+            let read = socket.read(&mut request).await.unwrap();
+            let body = r#"{"data":{"viewer":{"login":"octocat"}}}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&request[..read]).into_owned()
+        });
+        let host = Host::connect(&api_url, Some("token")).unwrap();
+        HOSTS.lock().unwrap().insert(api_url.clone(), host.clone());
+        let data: serde_json::Value = graphql(
+            &host.rest,
+            Some(&api_url),
+            "query { viewer { login } }",
+            serde_json::json!({}),
+        )
+        .await
+        .unwrap();
+        assert_eq!(data["viewer"]["login"], "octocat");
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /api/graphql "), "{request}");
+    }
+}
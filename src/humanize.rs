@@ -0,0 +1,156 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+
+/// The language in which we display times and messages, see `i18n`.
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Locale {
+    #[default]
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "fr")]
+    French,
+}
+
+/// Units, from largest to smallest, with their length in seconds.
+const UNITS: [(Unit, i64); 5] = [
+    (Unit::Year, 365 * 24 * 3600),
+    (Unit::Month, 30 * 24 * 3600),
+    (Unit::Day, 24 * 3600),
+    (Unit::Hour, 3600),
+    (Unit::Minute, 60),
+];
+
+#[derive(Clone, Copy)]
+enum Unit {
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+}
+impl Unit {
+    fn name(self, count: i64, locale: Locale) -> &'static str {
+        let plural = count > 1;
+        match (locale, self, plural) {
+            (Locale::English, Unit::Year, false) => "year",
+            (Locale::English, Unit::Year, true) => "years",
+            (Locale::English, Unit::Month, false) => "month",
+            (Locale::English, Unit::Month, true) => "months",
+            (Locale::English, Unit::Day, false) => "day",
+            (Locale::English, Unit::Day, true) => "days",
+            (Locale::English, Unit::Hour, false) => "hour",
+            (Locale::English, Unit::Hour, true) => "hours",
+            (Locale::English, Unit::Minute, false) => "minute",
+            (Locale::English, Unit::Minute, true) => "minutes",
+            (Locale::French, Unit::Year, false) => "an",
+            (Locale::French, Unit::Year, true) => "ans",
+            (Locale::French, Unit::Month, _) => "mois",
+            (Locale::French, Unit::Day, false) => "jour",
+            (Locale::French, Unit::Day, true) => "jours",
+            (Locale::French, Unit::Hour, false) => "heure",
+            (Locale::French, Unit::Hour, true) => "heures",
+            (Locale::French, Unit::Minute, false) => "minute",
+            (Locale::French, Unit::Minute, true) => "minutes",
+        }
+    }
+}
+
+/// A duration, rounded down to its largest unit, e.g. "3 days" or "3 jours".
+///
+/// Durations shorter than a minute are displayed as "0 minutes".
+pub fn duration(duration: Duration, locale: Locale) -> String {
+    let seconds = duration.num_seconds().abs();
+    let (unit, count) = UNITS
+        .iter()
+        .find_map(|&(unit, length)| (seconds >= length).then_some((unit, seconds / length)))
+        .unwrap_or((Unit::Minute, 0));
+    format!("{count} {}", unit.name(count, locale))
+}
+
+/// A point in time, relative to `now`, e.g. "3 days ago" or "il y a 3 jours".
+pub fn relative(time: DateTime<Utc>, now: DateTime<Utc>, locale: Locale) -> String {
+    let delta = now - time;
+    if delta.num_seconds().abs() < 60 {
+        return match locale {
+            Locale::English => "just now".to_string(),
+            Locale::French => "à l'instant".to_string(),
+        };
+    }
+    let amount = duration(delta, locale);
+    match (locale, delta > Duration::zero()) {
+        (Locale::English, true) => format!("{amount} ago"),
+        (Locale::English, false) => format!("in {amount}"),
+        (Locale::French, true) => format!("il y a {amount}"),
+        (Locale::French, false) => format!("dans {amount}"),
+    }
+}
+
+/// A duration of working time, in working days of length `day`, then hours and minutes,
+/// e.g. "2 working days" or "3 heures ouvrées".
+pub fn working_time(duration: Duration, day: Duration, locale: Locale) -> String {
+    let seconds = duration.num_seconds().abs();
+    let day = day.num_seconds().max(1);
+    let (unit, count) = if seconds >= day {
+        (Unit::Day, seconds / day)
+    } else if seconds >= 3600 {
+        (Unit::Hour, seconds / 3600)
+    } else {
+        (Unit::Minute, seconds / 60)
+    };
+    let name = unit.name(count, locale);
+    match (locale, unit, count > 1) {
+        (Locale::English, _, _) => format!("{count} working {name}"),
+        (Locale::French, Unit::Day, false) => format!("{count} {name} ouvré"),
+        (Locale::French, Unit::Day, true) => format!("{count} {name} ouvrés"),
+        (Locale::French, _, false) => format!("{count} {name} ouvrée"),
+        (Locale::French, _, true) => format!("{count} {name} ouvrées"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::{relative, working_time, Locale};
+
+    /// Are times rounded to their largest unit, in both languages?
+    #[test]
+    fn test_relative() {
+        let now = Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap();
+        let cases = [
+            (Duration::seconds(10), "just now", "à l'instant"),
+            (Duration::minutes(1), "1 minute ago", "il y a 1 minute"),
+            (Duration::hours(2), "2 hours ago", "il y a 2 heures"),
+            (
+                Duration::days(3) + Duration::hours(5),
+                "3 days ago",
+                "il y a 3 jours",
+            ),
+            (Duration::days(65), "2 months ago", "il y a 2 mois"),
+            (Duration::days(400), "1 year ago", "il y a 1 an"),
+            (-Duration::days(2), "in 2 days", "dans 2 jours"),
+        ];
+        for (delta, english, french) in cases {
+            assert_eq!(relative(now - delta, now, Locale::English), english);
+            assert_eq!(relative(now - delta, now, Locale::French), french);
+        }
+    }
+
+    /// Is working time counted in working days, not calendar days?
+    #[test]
+    fn test_working_time() {
+        let day = Duration::hours(8);
+        assert_eq!(
+            working_time(Duration::hours(20), day, Locale::English),
+            "2 working days"
+        );
+        assert_eq!(
+            working_time(Duration::hours(3), day, Locale::French),
+            "3 heures ouvrées"
+        );
+        assert_eq!(
+            working_time(Duration::minutes(1), day, Locale::English),
+            "1 working minute"
+        );
+    }
+}
@@ -0,0 +1,446 @@
+use std::collections::HashMap;
+
+use chrono::format::{Item, StrftimeItems};
+use log::error;
+
+use crate::{humanize::Locale, template};
+
+/// The messages that we bundle, by key, in English and in French.
+///
+/// Messages may contain placeholders such as `{link}`, as templates do, except `date` and
+/// `date_time`, which are formats of dates, as in `strftime`.
+const MESSAGES: &[(&str, &str, &str)] = &[
+    ("date", "%d/%m/%Y", "%d/%m/%Y"),
+    ("date_time", "%Y-%m-%d %H:%M UTC", "%d/%m/%Y %H:%M UTC"),
+    (
+        "nothing_left",
+        "Nothing left to report about repo {link}",
+        "Plus rien à signaler sur le dépôt {link}",
+    ),
+    (
+        "backlog.title",
+        "Backlog of repo {link}",
+        "Travail en attente sur le dépôt {link}",
+    ),
+    ("backlog.updated", "updated {time}", "mis à jour le {time}"),
+    (
+        "backlog.pending_reviews",
+        "PRs waiting for a review",
+        "PR en attente de relecture",
+    ),
+    (
+        "backlog.updated_issues",
+        "Issues updated recently",
+        "Tickets mis à jour récemment",
+    ),
+    ("backlog.more", "…and {count} more", "…et {count} de plus"),
+    ("backlog.empty", "Nothing waiting ✨", "Rien en attente ✨"),
+    ("omitted.unknown", "…and more", "…et plus encore"),
+    (
+        "omitted.at_least",
+        "…and {count}+ more",
+        "…et au moins {count} de plus",
+    ),
+    ("omitted.see_all", "see all", "tout voir"),
+    ("by", "by {author}", "par {author}"),
+    (
+        "email.subject",
+        "Pending reviews and updated issues of {repo}",
+        "Relectures en attente et tickets mis à jour de {repo}",
+    ),
+    (
+        "force_push.dm",
+        "⚠️ {pull} was force-pushed since your review",
+        "⚠️ {pull} a été force-pushée depuis votre relecture",
+    ),
+    (
+        "dependency_updates.title",
+        "Dependency updates of repo {link}",
+        "Mises à jour des dépendances du dépôt {link}",
+    ),
+    (
+        "pending_reviews.title",
+        "PRs of repo {link} waiting for reviews",
+        "PR du dépôt {link} en attente de relecture",
+    ),
+    (
+        "pending_reviews.escalated",
+        "PRs of repo {link} waiting for reviews for over {span}",
+        "PR du dépôt {link} en attente de relecture depuis plus de {span}",
+    ),
+    (
+        "pending_reviews.force_pushed",
+        "⚠️ force-pushed",
+        "⚠️ force-pushée",
+    ),
+    (
+        "drafts.title",
+        "PRs of repo {link} waiting in draft",
+        "PR du dépôt {link} en attente à l'état de brouillon",
+    ),
+    (
+        "issues.all",
+        "Open issues of repo {link}",
+        "Tickets ouverts du dépôt {link}",
+    ),
+    (
+        "issues.since_last_report",
+        "Issues of repo {link} updated since the last report",
+        "Tickets du dépôt {link} mis à jour depuis le dernier rapport",
+    ),
+    (
+        "issues.last",
+        "Issues of repo {link} updated in the last {since}",
+        "Tickets du dépôt {link} mis à jour depuis {since}",
+    ),
+    (
+        "catch_up.title",
+        "Catch-up: issues of repo {link} updated since {since}",
+        "Rattrapage : tickets du dépôt {link} mis à jour depuis le {since}",
+    ),
+    (
+        "label_changes.title",
+        "🏷️ Labels of interest changed on issues of repo {link}",
+        "🏷️ Étiquettes suivies modifiées sur les tickets du dépôt {link}",
+    ),
+    (
+        "releases.title",
+        "New releases of repo {link}",
+        "Nouvelles versions du dépôt {link}",
+    ),
+    (
+        "security_alerts.title",
+        "🛡️ Vulnerable dependencies of repo {link}",
+        "🛡️ Dépendances vulnérables du dépôt {link}",
+    ),
+    (
+        "scanning_alerts.title",
+        "🔐 Open scanning alerts of repo {link}",
+        "🔐 Alertes d'analyse ouvertes du dépôt {link}",
+    ),
+    (
+        "ci_failures.title",
+        "🔴 Workflows failing on branch {branch} of repo {link}",
+        "🔴 Workflows en échec sur la branche {branch} du dépôt {link}",
+    ),
+    (
+        "popularity.title",
+        "⭐ Repo {link} is getting popular",
+        "⭐ Le dépôt {link} gagne en popularité",
+    ),
+    (
+        "popularity.stars",
+        "Crossed {milestone} stars",
+        "Cap des {milestone} étoiles franchi",
+    ),
+    (
+        "popularity.forks",
+        "Crossed {milestone} forks",
+        "Cap des {milestone} forks franchi",
+    ),
+    (
+        "popularity.spike",
+        "+{gained} stars since the last run",
+        "+{gained} étoiles depuis le dernier passage",
+    ),
+    ("popularity.total", "{count} in total", "{count} au total"),
+    (
+        "discussions.title",
+        "Discussions of repo {link} waiting for an answer",
+        "Discussions du dépôt {link} en attente de réponse",
+    ),
+    (
+        "sla_breaches.title",
+        "🚨 Issues of repo {link} without a timely response from maintainers",
+        "🚨 Tickets du dépôt {link} sans réponse des mainteneurs dans les délais",
+    ),
+    (
+        "sla_breaches.due",
+        "{label}: due {due}, {response}",
+        "{label} : échéance {due}, {response}",
+    ),
+    ("sla_breaches.answered", "answered {time}", "réponse {time}"),
+    (
+        "sla_breaches.unanswered",
+        "no answer yet",
+        "pas encore de réponse",
+    ),
+    (
+        "stuck_in_triage.title",
+        "🧊 Issues of repo {link} reported {after} times or more without triage",
+        "🧊 Tickets du dépôt {link} signalés {after} fois ou plus sans être triés",
+    ),
+    (
+        "stuck_in_triage.reported",
+        "{count} times already, first seen {time}",
+        "déjà {count} fois, vu pour la première fois {time}",
+    ),
+    (
+        "transferred_issues.title",
+        "Issues of repo {link} transferred to another repo",
+        "Tickets du dépôt {link} transférés vers un autre dépôt",
+    ),
+    (
+        "stale_pulls.title",
+        "PRs of repo {link} without activity for {idle}",
+        "PR du dépôt {link} sans activité depuis {idle}",
+    ),
+    (
+        "unreviewed_pulls.title",
+        "PRs of repo {link} that need a reviewer",
+        "PR du dépôt {link} sans relecteur",
+    ),
+    (
+        "weekly_digest.title",
+        "Weekly digest of repo {link}",
+        "Résumé hebdomadaire du dépôt {link}",
+    ),
+    ("weekly_digest.past_week", "Past week", "Semaine passée"),
+    (
+        "weekly_digest.activity",
+        "{merged} PRs merged, {opened} issues opened, {closed} closed",
+        "{merged} PR fusionnées, {opened} tickets ouverts, {closed} fermés",
+    ),
+    (
+        "weekly_digest.top_reviewers",
+        "Top reviewers",
+        "Meilleurs relecteurs",
+    ),
+    ("weekly_digest.nobody", "Nobody 😴", "Personne 😴"),
+    (
+        "weekly_digest.oldest",
+        "Oldest open PR",
+        "Plus ancienne PR ouverte",
+    ),
+    (
+        "weekly_digest.opened",
+        "{pull}, opened {age}",
+        "{pull}, ouverte {age}",
+    ),
+    (
+        "weekly_digest.by_age",
+        "{count} open PRs, by age",
+        "{count} PR ouvertes, par ancienneté",
+    ),
+    (
+        "weekly_digest.incomplete",
+        "Only the most recent PRs were counted.",
+        "Seules les PR les plus récentes ont été comptées.",
+    ),
+    (
+        "milestones.title",
+        "🎯 Progress of the milestones of repo {link}",
+        "🎯 Avancement des jalons du dépôt {link}",
+    ),
+    ("milestones.due", "due {due}", "échéance {due}"),
+    (
+        "milestones.counts",
+        "{closed} closed, {open} open",
+        "{closed} fermés, {open} ouverts",
+    ),
+    (
+        "due_milestone.title",
+        "⏰ Milestone {milestone} of repo {link}, due {due}, still has {count} open issues",
+        "⏰ Le jalon {milestone} du dépôt {link}, échéance {due}, a encore {count} tickets ouverts",
+    ),
+    ("due_milestone.soon", "soon", "bientôt"),
+    ("due_milestone.unassigned", "unassigned", "non assigné"),
+    (
+        "query.title",
+        "{count} results of `{query}`",
+        "{count} résultats pour `{query}`",
+    ),
+    (
+        "review_load.title",
+        "Review load across {count} projects over the last {days} days",
+        "Charge de relecture sur {count} projets ces {days} derniers jours",
+    ),
+    (
+        "review_dms.title",
+        "{count} PRs waiting for your review",
+        "{count} PR en attente de votre relecture",
+    ),
+    ("column.alert", "Alert", "Alerte"),
+    ("column.assignees", "Assignees", "Assignés"),
+    ("column.author", "Author", "Auteur"),
+    ("column.commit", "Commit", "Commit"),
+    ("column.compatibility", "Compatibility", "Compatibilité"),
+    ("column.dependencies", "Dependencies", "Dépendances"),
+    ("column.discussion", "Discussion", "Discussion"),
+    ("column.draft", "Draft", "Brouillon"),
+    ("column.issue", "Issue", "Ticket"),
+    ("column.label", "Label", "Étiquette"),
+    ("column.last_activity", "Last activity", "Dernière activité"),
+    ("column.milestone", "Milestone", "Jalon"),
+    ("column.notes", "Notes", "Notes"),
+    ("column.now_at", "Now at", "Désormais"),
+    ("column.pr", "PR", "PR"),
+    ("column.progress", "Progress", "Avancement"),
+    ("column.release", "Release", "Version"),
+    ("column.reported", "Reported", "Signalé"),
+    ("column.request", "Request", "Demande"),
+    (
+        "column.requested_reviewed",
+        "Requested · Reviewed",
+        "Demandées · Relues",
+    ),
+    ("column.reviewer", "Reviewer", "Relecteur"),
+    ("column.severity", "Severity", "Gravité"),
+    ("column.sla", "SLA", "SLA"),
+    (
+        "column.suggested_reviewers",
+        "Suggested reviewers",
+        "Relecteurs suggérés",
+    ),
+    ("column.update", "Update", "Mise à jour"),
+    ("column.updater", "Updater", "Mis à jour par"),
+    ("column.waiting", "Waiting", "En attente"),
+    ("column.workflow", "Workflow", "Workflow"),
+];
+
+/// Whether we bundle a message with this key, so that it may be overridden.
+pub fn is_known(key: &str) -> bool {
+    MESSAGES.iter().any(|(known, _, _)| *known == key)
+}
+
+/// Whether `message` may override the message with this key, e.g. is a valid format of dates
+/// for `date`.
+pub fn is_valid(key: &str, message: &str) -> bool {
+    match key {
+        "date" | "date_time" => StrftimeItems::new(message).all(|item| item != Item::Error),
+        _ => true,
+    }
+}
+
+/// The message with this key in `locale`, taken from `overrides` (see `Config::messages`) if
+/// possible, rendered with `vars`.
+///
+/// A key that isn't bundled is a bug, which we log rather than failing the report (or
+/// the daemon): the key stands for the message.
+pub fn message(
+    overrides: &HashMap<Locale, HashMap<String, String>>,
+    locale: Locale,
+    key: &str,
+    vars: &[(&str, String)],
+) -> String {
+    let message = match overrides
+        .get(&locale)
+        .and_then(|messages| messages.get(key))
+    {
+        Some(message) => message.as_str(),
+        None => match MESSAGES.iter().find(|(known, _, _)| *known == key) {
+            Some((_, en, _)) if locale == Locale::English => en,
+            Some((_, _, fr)) => fr,
+            None => {
+                error!("No message `{key}`");
+                key
+            }
+        },
+    };
+    template::render(message, &vars.iter().cloned().collect())
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashMap, ops::Not};
+
+    use crate::humanize::Locale;
+
+    use lazy_regex::{lazy_regex, Lazy};
+    use regex::Regex;
+
+    use super::{is_known, is_valid, message, MESSAGES};
+
+    /// Are messages translated, and can the config override them?
+    #[test]
+    fn test_message() {
+        let vars = [("link", "qastor".to_string())];
+        let overrides = HashMap::new();
+        assert_eq!(
+            message(&overrides, Locale::French, "drafts.title", &vars),
+            "PR du dépôt qastor en attente à l'état de brouillon"
+        );
+        let overrides = HashMap::from([(
+            Locale::English,
+            HashMap::from([("drafts.title".to_string(), "Drafts of {link}".to_string())]),
+        )]);
+        assert_eq!(
+            message(&overrides, Locale::English, "drafts.title", &vars),
+            "Drafts of qastor"
+        );
+        assert_eq!(
+            message(&overrides, Locale::French, "drafts.title", &vars),
+            "PR du dépôt qastor en attente à l'état de brouillon"
+        );
+    }
+
+    /// Do translations keep the placeholders of the original?
+    #[test]
+    fn test_placeholders() {
+        let placeholders = |message: &str| {
+            let mut found = lazy_regex::regex!(r"\{\w+\}")
+                .find_iter(message)
+                .map(|found| found.as_str().to_string())
+                .collect::<Vec<_>>();
+            found.sort();
+            found
+        };
+        for (key, en, fr) in MESSAGES {
+            assert_eq!(placeholders(en), placeholders(fr), "{key}");
+        }
+    }
+
+    /// Do we reject invalid formats of dates?
+    #[test]
+    fn test_is_valid() {
+        assert!(is_valid("date", "%d %B %Y"));
+        assert!(is_valid("date", "%Q").not());
+        assert!(is_valid("date_time", "%Y-%m-%d %").not());
+        assert!(is_valid("drafts.title", "%Q"));
+    }
+
+    /// Is every message that the sources ask for bundled?
+    #[test]
+    fn test_keys_bundled() {
+        static MESSAGE: Lazy<Regex> =
+            lazy_regex! {r#"\.(?:message|text)\(\s*[^"();]*?,\s*"([a-z_.]+)""#};
+        static COLUMNS: Lazy<Regex> =
+            lazy_regex! {r#"columns\(\s*[^;]*?\[\s*"([a-z_]+)",\s*"([a-z_]+)"\s*\]"#};
+        let mut used = vec![];
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut dirs = vec![dir];
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+                let source = std::fs::read_to_string(&path).unwrap();
+                for captures in MESSAGE.captures_iter(&source) {
+                    used.push(captures[1].to_string());
+                }
+                for captures in COLUMNS.captures_iter(&source) {
+                    used.push(format!("column.{}", &captures[1]));
+                    used.push(format!("column.{}", &captures[2]));
+                }
+            }
+        }
+        assert!(used.len() > 50, "the sources look unreadable");
+        let missing = used
+            .iter()
+            .filter(|key| is_known(key).not())
+            .collect::<Vec<_>>();
+        assert!(missing.is_empty(), "not bundled: {missing:?}");
+    }
+
+    /// Do missing keys stand for their message rather than panicking?
+    #[test]
+    fn test_message_missing() {
+        let overrides = HashMap::new();
+        assert_eq!(
+            message(&overrides, Locale::French, "no.such.key", &[]),
+            "no.such.key"
+        );
+    }
+}
@@ -1,2 +1,41 @@
+pub mod archive;
+pub mod business;
+pub mod calendar;
+pub mod catalog;
+pub mod codeowners;
 pub mod config;
+pub mod daemon;
+pub mod dependabot;
+pub mod digest;
+pub mod discord;
+pub mod email;
+pub mod enrich;
+pub mod error;
+pub mod fairness;
+pub mod fetch;
+pub mod github;
+pub mod humanize;
+pub mod i18n;
+pub mod lifecycle;
+pub mod matrix;
+pub mod metrics;
+pub mod outcome;
+pub mod popularity;
+pub mod release;
+pub mod render;
+pub mod replay;
+pub mod report;
+pub mod scanning;
+pub mod server;
+#[cfg(windows)]
+pub mod service;
+pub mod sinks;
+pub mod sla;
 pub mod slack;
+pub mod state;
+pub mod teams;
+pub mod telegram;
+pub mod template;
+pub mod tenant;
+pub mod tz;
+pub mod webhook;
@@ -0,0 +1,111 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How long we remember an issue that we haven't seen, e.g. because it was closed or nobody
+/// touched it since.
+const RETENTION: Duration = Duration::days(30);
+
+/// What happened to an issue across runs, since we first saw it, with `triage`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Lifecycle {
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+
+    /// How many times we have reported the issue.
+    #[serde(default)]
+    pub reported: u32,
+
+    /// When we first saw the issue labelled, if ever.
+    #[serde(default)]
+    pub labelled: Option<DateTime<Utc>>,
+
+    /// When we first saw the issue assigned, if ever.
+    #[serde(default)]
+    pub assigned: Option<DateTime<Utc>>,
+
+    /// When the issue was closed, unless it's open.
+    #[serde(default)]
+    pub closed: Option<DateTime<Utc>>,
+}
+impl Lifecycle {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Lifecycle {
+            first_seen: now,
+            last_seen: now,
+            reported: 0,
+            labelled: None,
+            assigned: None,
+            closed: None,
+        }
+    }
+
+    /// Record what we see of the issue now. Once labelled or assigned, an issue stays so,
+    /// even if someone removes its labels or assignees later.
+    pub fn observe(
+        &mut self,
+        labelled: bool,
+        assigned: bool,
+        closed_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) {
+        self.last_seen = now;
+        if labelled {
+            self.labelled.get_or_insert(now);
+        }
+        if assigned {
+            self.assigned.get_or_insert(now);
+        }
+        // Issues may be reopened.
+        self.closed = closed_at;
+    }
+
+    /// Whether we have reported the issue at least `after` times, and yet nobody has
+    /// labelled, assigned or closed it.
+    pub fn is_stuck(&self, after: u32) -> bool {
+        self.reported >= after
+            && self.labelled.is_none()
+            && self.assigned.is_none()
+            && self.closed.is_none()
+    }
+}
+
+/// Forget the issues that we haven't seen for `RETENTION`.
+pub fn prune(lifecycles: &mut BTreeMap<u64, Lifecycle>, now: DateTime<Utc>) {
+    lifecycles.retain(|_, lifecycle| now - lifecycle.last_seen < RETENTION);
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::BTreeMap, ops::Not};
+
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::{prune, Lifecycle};
+
+    /// Do issues get stuck once reported enough times without anyone acting on them?
+    #[test]
+    fn test_is_stuck() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 6, 12, 0, 0).unwrap();
+        let mut lifecycle = Lifecycle::new(now);
+        lifecycle.observe(false, false, None, now);
+        lifecycle.reported = 3;
+        assert!(lifecycle.is_stuck(3));
+        assert!(lifecycle.is_stuck(4).not());
+
+        let later = now + Duration::days(1);
+        lifecycle.observe(true, false, None, later);
+        assert_eq!(lifecycle.labelled, Some(later));
+        assert!(lifecycle.is_stuck(3).not());
+        // Removing the labels doesn't undo the triage.
+        lifecycle.observe(false, false, None, later + Duration::days(1));
+        assert_eq!(lifecycle.labelled, Some(later));
+
+        let mut lifecycles = BTreeMap::from([(1, lifecycle)]);
+        prune(&mut lifecycles, later + Duration::days(30));
+        assert_eq!(lifecycles.len(), 1);
+        prune(&mut lifecycles, later + Duration::days(32));
+        assert!(lifecycles.is_empty());
+    }
+}
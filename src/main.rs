@@ -1,181 +1,3504 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::ops::Not;
+use std::path::PathBuf;
+use std::time::Instant;
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, NaiveDate, Utc};
+use clap::{Parser, Subcommand};
 use itertools::Itertools;
 use log::{debug, error, info, warn};
-use octocrab::params::State;
+use octocrab::models::Repository;
+use octocrab::Octocrab;
 use reqwest::Client;
+use url::Url;
 
-use qastor::config::{Config, Project, ProjectToHook, Secrets};
+use qastor::archive::{self, Archive};
+use qastor::calendar;
+use qastor::catalog::{self, Ownership};
+use qastor::config::{
+    Config, Drafts, FetchMode, Hook, HookKind, HookTarget, Project, Secrets, SelfUpdate, SlaPolicy,
+    SlackHook, Smtp, StatusBoard, Window,
+};
+use qastor::daemon;
+use qastor::dependabot::{self, AlertSeverity};
+use qastor::digest::{self};
+use qastor::email;
+use qastor::enrich::Enricher;
+use qastor::error::{Error, ErrorClass};
+use qastor::fairness;
+use qastor::fetch::{
+    delivered_items, fetch_acks, fetch_code_owner_hooks, fetch_force_pushes, fetch_issue_details,
+    fetch_open_pulls, fetch_pending_requests, fetch_sections, fetch_weekly_digest, shows_request,
+    Scope, Sections,
+};
+use qastor::github::{self, Availability, ForcePush, PendingReview, SearchResults};
+use qastor::humanize;
+use qastor::lifecycle::{self};
+use qastor::metrics::{Metrics, ProjectMetrics};
+use qastor::outcome::{ProjectOutcome, RunSummary};
+use qastor::release::{self};
+use qastor::render::{
+    cap, has_waited, index_fixes, render_backlog, render_ci_failures, render_dependency_updates,
+    render_discussions, render_drafts, render_due_milestone, render_issues, render_label_changes,
+    render_milestone_progress, render_pending_requests, render_popularity, render_releases,
+    render_scanning_alerts, render_security_alerts, render_sla_breaches, render_stale_pulls,
+    render_status_board, render_stuck_in_triage, render_transferred_issues,
+    render_unreviewed_pulls, render_weekly_digest, route_issue, waiting, Omissions, Omitted,
+};
+use qastor::replay::Replay;
+use qastor::report::{self, Report, SectionKind, Severity};
+use qastor::server;
+use qastor::sinks::{self, HookSink, MatrixSink, Placement, Sink, SlackChannel, TelegramSink};
 use qastor::slack;
+use qastor::state::{
+    self, LiveMessages, Preview, RecentAuthors, ScheduledMessage, ScheduledMessages, State,
+};
+use qastor::template::{self, Templates};
+use qastor::tenant::Tenant;
+use qastor::webhook;
 
-/// All the machinery for a single project.
-async fn per_project(
+/// Patrol GitHub repositories and ping developers on Slack.
+#[derive(Parser, Clone)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Serve several tenants, one per subdirectory of this directory, each with its
+    /// own `config.yml`, `secrets.json` and state.
+    #[arg(long, global = true)]
+    tenants: Option<PathBuf>,
+
+    /// Write a summary of the run to this file, as JSON. With `--tenants`, relative to
+    /// the directory of each tenant.
+    #[arg(long, global = true)]
+    emit_report: Option<PathBuf>,
+
+    /// Run from this directory, which holds `config.yml`, `secrets.json` and the state,
+    /// e.g. as a Windows service, which starts in the system directory.
+    #[arg(long, global = true)]
+    dir: Option<PathBuf>,
+
+    /// Append the logs to this file rather than writing them to stderr, e.g. as a Windows
+    /// service, which has no console.
+    #[arg(long, global = true)]
+    log_file: Option<PathBuf>,
+
+    /// Deprecated, use `qastor serve`.
+    #[arg(long, hide = true)]
+    daemon: bool,
+
+    /// With `serve`, listen to HTTP requests at this address, e.g. `0.0.0.0:8080`: GitHub
+    /// webhooks at `/webhook`, which trigger a run of their repository, and the metrics of
+    /// the latest runs at `/metrics`.
+    #[arg(long, global = true)]
+    listen: Option<std::net::SocketAddr>,
+}
+
+#[derive(Subcommand, Clone)]
+enum Command {
+    /// Check all projects once and post the reports (the default).
+    Run {
+        /// Skip the projects checked less than `update_frequency` ago, e.g. when run by cron.
+        /// Otherwise, all projects are checked.
+        #[arg(long)]
+        scheduled: bool,
+    },
+
+    /// Keep running, checking each project every `update_frequency` (give or take 10%),
+    /// or as soon as a webhook arrives (see `--listen`), until SIGTERM.
+    Serve,
+
+    /// Run `serve` as a Windows service, under the Service Control Manager. Stopping the
+    /// service completes the run in progress, if any.
+    WindowsService {
+        /// The name of the service, as installed with `sc.exe create`.
+        #[arg(long, default_value = "qastor")]
+        name: String,
+    },
+
+    /// Print a launchd agent for macOS, running `serve` from the current directory (or
+    /// `--dir`) and logging to `--log-file` (by default, `qastor.log` in that directory).
+    Launchd {
+        /// The label of the agent.
+        #[arg(long, default_value = "io.pasqal.qastor")]
+        label: String,
+    },
+
+    /// Print the reports that `run` would post, without posting them or updating the
+    /// state.
+    Report,
+
+    /// Validate the config and the secrets, without contacting GitHub.
+    Check,
+
+    /// Check that the GitHub credentials give access to everything that the report of
+    /// each project needs.
+    CheckAccess,
+
+    /// Post a catch-up digest of everything that happened since a given date,
+    /// e.g. after the bot was down for a while.
+    Backfill {
+        /// The first day to replay, e.g. 2024-05-01.
+        #[arg(long)]
+        since: NaiveDate,
+    },
+
+    /// Post the weekly digest of each project now, whatever `weekly_digest` says, and
+    /// nothing else, e.g. from a weekly cron job.
+    Digest,
+
+    /// Print the PRs of all projects waiting for a review from a given user,
+    /// oldest first.
+    Mine {
+        /// The GitHub login of the reviewer.
+        #[arg(long)]
+        user: String,
+    },
+
+    /// Send each reviewer mapped in `user_map` a direct message on Slack with the PRs of
+    /// all projects waiting for their review, oldest first.
+    RemindReviewers,
+
+    /// Compare, for each reviewer, the review requests received across all projects with the
+    /// reviews completed, e.g. monthly from cron. Posted to the admin hook, if any, printed
+    /// otherwise.
+    Fairness {
+        /// How far back to look, in days.
+        #[arg(long, default_value_t = 30)]
+        days: u32,
+
+        /// Flag reviewers who received at least this share of all requests.
+        #[arg(long, default_value_t = 0.5)]
+        max_share: f64,
+    },
+
+    /// Suppress all non-critical sections about a project until a given day, e.g. during a
+    /// code freeze.
+    Snooze {
+        /// The url of the project, as in the config.
+        #[arg(long)]
+        project: Url,
+
+        /// The day on which reports resume, e.g. 2025-01-06.
+        #[arg(long)]
+        until: NaiveDate,
+    },
+
+    /// Stop checking a project until `qastor enable`, whatever `disabled` says in the
+    /// config.
+    Disable {
+        /// The url of the project, as in the config.
+        project: Url,
+    },
+
+    /// Undo `qastor disable`, so that `disabled` in the config applies again.
+    Enable {
+        /// The url of the project, as in the config.
+        project: Url,
+    },
+
+    /// Send again the messages that a run sent (or should have sent) to a sink, as recorded
+    /// with `replay`, without querying GitHub.
+    Resend {
+        /// The id of the run, e.g. 20250106T093000Z, as logged at the end of the run.
+        #[arg(long)]
+        run: String,
+
+        /// The name or alias of the hook (or the label of the sink) in the recording.
+        #[arg(long)]
+        hook: String,
+    },
+
+    /// Run an arbitrary GitHub search of issues and PRs with the credentials of the config,
+    /// e.g. `qastor query "is:pr review-requested:@me repo:owner/name"`, and print the
+    /// results as a report section, or post them to a hook.
+    Query {
+        /// The search, with GitHub's syntax.
+        query: String,
+
+        /// The name or alias of the hook (or the label of the sink) to post to, rather than
+        /// printing the results.
+        #[arg(long)]
+        hook: Option<String>,
+
+        /// The maximal number of results.
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+    },
+
+    /// Enable normal delivery for the current config, after checking its reports
+    /// on the preview hook.
+    Promote,
+
+    /// Make the members of the Slack user groups of the config those of their GitHub
+    /// teams, e.g. from a daily cron job.
+    SyncUsergroups,
+
+    /// Write the due dates of the open milestones of all projects to an iCalendar
+    /// file, e.g. to publish it for team calendars. With `--tenants`, relative to the
+    /// directory of each tenant.
+    Calendar {
+        /// Where to write the feed, e.g. `deadlines.ics`.
+        #[arg(long)]
+        output: PathBuf,
+    },
+}
+
+/// With `self_update`, tell the administrators about a newer release of qastor, once.
+async fn check_release(
+    client: &Client,
+    config: &Config,
+    secrets: &Secrets,
+    state: &mut State,
+) -> Result<(), anyhow::Error> {
+    let Some((owner, repo)) = config.self_update.as_ref().and_then(SelfUpdate::repo) else {
+        return Ok(());
+    };
+    // Releases of qastor are published on github.com, whatever the `api_url` of projects.
+    let latest = github::fetch_latest_release(&github::instance(owner), owner, repo).await?;
+    if release::is_newer(&latest.tag_name, release::VERSION).not()
+        || state.announced_release()?.as_ref() == Some(&latest.tag_name)
+    {
+        return Ok(());
+    }
+    let mut message = format!(
+        "qastor {} is out, this instance runs {}, see the {}",
+        latest.tag_name,
+        release::VERSION,
+        slack::link(&latest.html_url, Some("release notes")),
+    );
+    let excerpt = release::excerpt(latest.body.as_deref().unwrap_or_default());
+    if excerpt.is_empty().not() {
+        message.push_str(&format!("\n```\n{excerpt}\n```"));
+    }
+    notify_admin(client, secrets, message).await?;
+    state.set_announced_release(&latest.tag_name)
+}
+
+/// Post a message to the administrators of qastor, if we have a hook for them.
+async fn notify_admin(
+    client: &Client,
+    secrets: &Secrets,
+    message: String,
+) -> Result<(), anyhow::Error> {
+    if secrets.admin_hook.is_none() {
+        warn!("No admin hook, cannot notify: {message}");
+        return Ok(());
+    }
+    post_admin(client, secrets, &slack::Section::new(message)).await
+}
+
+/// Post a section to the admin hook, which must exist.
+async fn post_admin(
     client: &Client,
     secrets: &Secrets,
+    msg: &slack::Section,
+) -> Result<(), anyhow::Error> {
+    let Some(ref hook) = secrets.admin_hook else {
+        return Err(anyhow!("No admin hook"));
+    };
+    let hook = Hook::from(SlackHook::from(hook.as_ref().clone()));
+    sinks::post(&HookSink(&hook), client, msg, Placement::New)
+        .await
+        .context("Failed to notify admin")?;
+    Ok(())
+}
+
+/// Where the sections of the report of a project go.
+struct Outbox<'a> {
+    client: &'a Client,
+
+    /// How each kind of sink wants sections.
+    templates: &'a Templates,
+
+    /// If `true`, the project is snoozed (see `qastor snooze`), and we only send critical
+    /// sections.
+    snoozed: bool,
+
+    /// The hooks of the project.
+    hooks: Vec<&'a Hook>,
+
+    /// The SLA policies, whose `escalate_to` hooks only receive the breaches escalated to
+    /// them.
+    sla: &'a [SlaPolicy],
+
+    /// The other sinks of the project, e.g. its channel or the Matrix room that receives
+    /// the reports of all projects, which don't take part in routes.
+    sinks: Vec<Box<dyn Sink + 'a>>,
+
+    /// The channel of the project, if we keep a pinned summary of its backlog there.
+    summary: Option<SlackChannel<'a>>,
+
+    /// The recipients of the project by email, if any, with the sections to send them.
+    email: Option<Email<'a>>,
+
+    /// If the config is being previewed, the hook that receives the reports instead of
+    /// `hooks` and `sinks`.
+    preview: Option<&'a Hook>,
+
+    /// If `true`, print the reports to the terminal instead of posting them.
+    print: bool,
+    metrics: &'a mut ProjectMetrics,
+    archive: &'a mut Archive,
+    replay: &'a mut Replay,
+
+    /// Number of sections sent to each hook, by label.
+    sent: BTreeMap<String, usize>,
+
+    /// The kinds of sections that reached at least one hook, sink or recipient.
+    delivered: BTreeSet<SectionKind>,
+
+    /// With `thread_follow_ups`, the `ts` of today's parent message in each channel, by
+    /// channel id.
+    threads: Option<BTreeMap<String, String>>,
+
+    /// With `update_in_place`, the latest messages about each kind of section.
+    live: Option<Live>,
+
+    /// With `deliver_at`, when Slack bots deliver the sections of this run.
+    post_at: Option<DateTime<Utc>>,
+
+    /// With `deliver_at`, the messages scheduled during this run.
+    scheduled: ScheduledMessages,
+}
+
+/// The latest messages of Slack bots about each kind of section of a project.
+struct Live {
+    /// As of the previous run, then updated as we post.
+    messages: LiveMessages,
+
+    /// The messages posted (or updated) during this run, by channel id and kind.
+    current: BTreeSet<(String, SectionKind)>,
+}
+impl Outbox<'_> {
+    /// What happened to the project, once we're done with it, given the number of items
+    /// found for each kind of section.
+    fn outcome(self, sections: impl IntoIterator<Item = (SectionKind, usize)>) -> ProjectOutcome {
+        let sections: BTreeMap<SectionKind, usize> = sections
+            .into_iter()
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        ProjectOutcome {
+            items: sections.values().sum(),
+            sections,
+            sent: self.sent,
+            ..Default::default()
+        }
+    }
+
+    /// Post a section, rendered by `render` for the project, to every sink of the project
+    /// selected by `filter`, given the alias of the sink, if it subscribes to the severity of
+    /// the section.
+    ///
+    /// Sinks with a language of their own (see `Hook::locale`) receive the section rendered
+    /// for the project in that language.
+    async fn post_to(
+        &mut self,
+        project: &Project,
+        filter: impl Fn(Option<&str>) -> bool,
+        kind: SectionKind,
+        severity: Severity,
+        render: impl Fn(&Project) -> slack::Section,
+    ) -> Result<(), anyhow::Error> {
+        if self.snoozed && kind.sent_while_snoozed().not() {
+            debug!("Snoozed, not sending the {kind:?} section");
+            return Ok(());
+        }
+        let mut rendered = HashMap::new();
+        let default = render(project);
+        if self.print {
+            let restyled = template::restyle(project, kind, &default);
+            let msg = self.templates.apply("terminal", kind, project, &restyled);
+            println!("{}\n", msg.plain_text());
+            *self.sent.entry("terminal".to_string()).or_default() += 1;
+            return Ok(());
+        }
+        if let Some(ref mut email) = self.email {
+            if matches!(
+                kind,
+                SectionKind::PendingReviews | SectionKind::UpdatedIssues
+            ) {
+                let restyled = template::restyle(project, kind, &default);
+                let msg = self.templates.apply("email", kind, project, &restyled);
+                email.report.push(kind, msg.into_owned());
+            }
+        }
+        rendered.insert(None, default);
+        // JSON hooks receive the findings rather than the sections, see `send_payload`.
+        let hooks = self
+            .hooks
+            .iter()
+            .filter(|hook| hook.kind() != HookKind::Json)
+            .map(|hook| HookSink(hook))
+            .collect_vec();
+        let sinks: Vec<&dyn Sink> = hooks
+            .iter()
+            .map(|sink| sink as &dyn Sink)
+            .chain(self.sinks.iter().map(Box::as_ref))
+            .filter(|sink| filter(sink.alias()) && sink.accepts(severity))
+            .collect();
+        let preview = self.preview.map(HookSink);
+        let sinks = match preview {
+            Some(ref preview) if sinks.is_empty().not() => vec![preview as &dyn Sink],
+            Some(_) => vec![],
+            None => sinks,
+        };
+        for sink in sinks {
+            let label = sink.label();
+            let channel = sink.channel();
+            let previous = channel
+                .and_then(|channel| self.live.as_ref()?.messages.get(channel)?.get(&kind))
+                .cloned();
+            let parent = channel
+                .and_then(|channel| self.threads.as_ref()?.get(channel))
+                .cloned();
+            let placement = match (&previous, &parent) {
+                (Some(ts), _) => Placement::Replace(ts),
+                (None, Some(ts)) => Placement::Thread(ts),
+                (None, None) => match self.post_at {
+                    Some(at) if channel.is_some() => Placement::At(at),
+                    _ => Placement::New,
+                },
+            };
+            let section = rendered.entry(sink.locale()).or_insert_with(|| {
+                render(&Project {
+                    locale: sink.locale(),
+                    ..project.clone()
+                })
+            });
+            let restyled = template::restyle(project, kind, section);
+            let msg = self.templates.apply(sink.kind(), kind, project, &restyled);
+            let messages = sink.render(&msg);
+            self.replay
+                .record(&project.url, &label, Some(kind), &messages);
+            let sent = match sinks::post_rendered(sink, self.client, messages.clone(), placement)
+                .await
+            {
+                // Someone may have deleted the message.
+                Err(err) if previous.is_some() => {
+                    warn!("Could not update the latest message to {label}, posting anew: {err:?}");
+                    sinks::post_rendered(sink, self.client, messages, Placement::New).await
+                }
+                sent => sent,
+            };
+            let for_fallback = |sink: &dyn Sink| {
+                sink.render(&self.templates.apply(sink.kind(), kind, project, &restyled))
+            };
+            let sent = sinks::fall_back(sink, self.client, for_fallback, placement, sent)
+                .await
+                .with_context(|| format!("Failed to post update to {label}"))?;
+            self.metrics.posted_bytes += sent.bytes;
+            if let (Some(channel), Some(id), Some(post_at)) =
+                (channel, sent.scheduled, self.post_at)
+            {
+                self.scheduled
+                    .entry(channel.to_string())
+                    .or_default()
+                    .insert(kind, ScheduledMessage { id, post_at });
+            }
+            if let (Some(channel), Some(ts)) = (channel, sent.ts) {
+                if let Some(ref mut live) = self.live {
+                    live.messages
+                        .entry(channel.to_string())
+                        .or_default()
+                        .insert(kind, ts);
+                    live.current.insert((channel.to_string(), kind));
+                } else if let (None, Some(threads)) = (parent, self.threads.as_mut()) {
+                    threads.insert(channel.to_string(), ts);
+                }
+            }
+            self.archive.record(&project.url, &label, kind, &msg)?;
+            *self.sent.entry(label).or_default() += 1;
+            self.delivered.insert(kind);
+        }
+        Ok(())
+    }
+
+    /// Post the findings about the project to its JSON webhooks, if any.
+    async fn send_payload(
+        &mut self,
+        project: &Project,
+        payload: &webhook::Payload,
+    ) -> Result<(), anyhow::Error> {
+        if self.print || self.preview.is_some() {
+            return Ok(());
+        }
+        for hook in &self.hooks {
+            let (HookKind::Json, HookTarget::Url(url)) = (hook.kind(), &hook.target) else {
+                continue;
+            };
+            let findings = serde_json::to_value(payload).context("Could not serialize payload")?;
+            self.replay
+                .record(&project.url, &archive::hook_label(hook), None, &[findings]);
+            self.metrics.posted_bytes += webhook::send(self.client, url.as_ref(), payload)
+                .await
+                .with_context(|| format!("Failed to post findings about {}", project.url))?;
+            *self.sent.entry(archive::hook_label(hook)).or_default() += 1;
+        }
+        Ok(())
+    }
+
+    /// With `update_in_place`, replace the latest messages about kinds of sections that we
+    /// haven't posted during this run, since there's nothing left to report about them
+    /// (unless they aren't computed on every run), then record the latest messages.
+    async fn save_live(
+        &mut self,
+        config: &Config,
+        project: &Project,
+        configured: &Url,
+        state: &mut State,
+    ) -> Result<(), anyhow::Error> {
+        let snoozed = self.snoozed;
+        let Some(ref mut live) = self.live else {
+            return Ok(());
+        };
+        let hooks = self.hooks.iter().map(|hook| HookSink(hook)).collect_vec();
+        let sinks = hooks
+            .iter()
+            .map(|sink| sink as &dyn Sink)
+            .chain(self.sinks.iter().map(Box::as_ref));
+        for sink in sinks {
+            let Some(channel) = sink.channel() else {
+                continue;
+            };
+            let Some(messages) = live.messages.get_mut(channel) else {
+                continue;
+            };
+            let stale = messages
+                .keys()
+                .copied()
+                .filter(|kind| live.current.contains(&(channel.to_string(), *kind)).not())
+                .filter(|kind| kind.on_every_run())
+                // Snoozed sections may well have something to report.
+                .filter(|kind| snoozed.not() || kind.sent_while_snoozed())
+                .collect_vec();
+            for kind in stale {
+                let ts = messages.remove(&kind).expect("a stale message");
+                let msg = slack::Section::new(format!(
+                    "{} {}",
+                    kind.emoji(),
+                    config.text(
+                        project,
+                        "nothing_left",
+                        &[(
+                            "link",
+                            slack::link(&project.html_url(), Some(project.repo.as_ref()))
+                        )],
+                    ),
+                ));
+                match sinks::post(sink, self.client, &msg, Placement::Replace(&ts)).await {
+                    Ok(sent) => self.metrics.posted_bytes += sent.bytes,
+                    Err(err) => warn!(
+                        "Could not update the latest message to {}: {err:?}",
+                        sink.label()
+                    ),
+                }
+            }
+        }
+        state.set_live_messages(configured, &live.messages)
+    }
+
+    /// Email the sections collected for the recipients of the project, if any.
+    ///
+    /// Failing to send the email doesn't fail the project, as the hooks already received the
+    /// sections: we only log it and count it.
+    async fn send_email(
+        &mut self,
+        config: &Config,
+        project: &Project,
+    ) -> Result<(), anyhow::Error> {
+        let Some(email) = self.email.take() else {
+            return Ok(());
+        };
+        if email.report.is_empty() {
+            return Ok(());
+        }
+        let subject = config.text(
+            project,
+            "email.subject",
+            &[("repo", format!("{}/{}", project.owner, project.repo))],
+        );
+        match email::send(email.smtp, email.to, &subject, &email.report.sections()).await {
+            Ok(bytes) => self.metrics.posted_bytes += bytes,
+            Err(err) => {
+                warn!("Failed to email the report about {}: {err:?}", project.url);
+                self.metrics.failed_deliveries += 1;
+                return Ok(());
+            }
+        }
+        let label = "email".to_string();
+        for (kind, msg) in email.report.iter() {
+            self.archive.record(&project.url, &label, *kind, msg)?;
+            self.delivered.insert(*kind);
+        }
+        *self.sent.entry(label).or_default() += email.report.len();
+        Ok(())
+    }
+
+    /// Post a section, rendered by `render` for the project, to all the hooks that subscribe
+    /// to its kind.
+    async fn post(
+        &mut self,
+        project: &Project,
+        kind: SectionKind,
+        render: impl Fn(&Project) -> slack::Section,
+    ) -> Result<(), anyhow::Error> {
+        self.post_to(project, |_| true, kind, kind.severity(), render)
+            .await
+    }
+
+    /// Post items, split between hooks following the routes of the project.
+    ///
+    /// Items whose title matches a route go to the hooks with the route's alias, and items
+    /// with routed labels to the hooks of their labels (see `label_routes`), other items go
+    /// to the hooks that are not the target of any route. Each group of items is rendered
+    /// separately.
+    async fn post_routed<T: Clone>(
+        &mut self,
+        project: &Project,
+        kind: SectionKind,
+        items: Vec<T>,
+        title: impl Fn(&T) -> &str,
+        labels: impl Fn(&T) -> Vec<&str>,
+        render: impl Fn(&Project, Vec<T>) -> slack::Section,
+    ) -> Result<(), anyhow::Error> {
+        self.post_escalated(
+            project,
+            kind,
+            items,
+            |item| project.routes_for(title(item), &[], labels(item)),
+            render,
+        )
+        .await
+    }
+
+    /// Post items, split between the hooks with the aliases chosen for each item, if any
+    /// (e.g. those of their code owners), and the hooks that are not the target of any route
+    /// or escalation.
+    async fn post_escalated<T: Clone, A: IntoIterator<Item = String>>(
+        &mut self,
+        project: &Project,
+        kind: SectionKind,
+        items: Vec<T>,
+        aliases: impl Fn(&T) -> A,
+        render: impl Fn(&Project, Vec<T>) -> slack::Section,
+    ) -> Result<(), anyhow::Error> {
+        self.post_with_severity(project, kind, items, aliases, |_| kind.severity(), render)
+            .await
+    }
+
+    /// Post items as `post_escalated` does, with the severity that `severity` gives each group
+    /// of items rather than the one of `kind`, e.g. to reach the hooks that only want critical
+    /// sections when some items are critical.
+    async fn post_with_severity<T: Clone, A: IntoIterator<Item = String>>(
+        &mut self,
+        project: &Project,
+        kind: SectionKind,
+        items: Vec<T>,
+        aliases: impl Fn(&T) -> A,
+        severity: impl Fn(&[T]) -> Severity,
+        render: impl Fn(&Project, Vec<T>) -> slack::Section,
+    ) -> Result<(), anyhow::Error> {
+        let is_alias = |alias: &str| {
+            self.hooks
+                .iter()
+                .any(|hook| hook.alias.as_deref() == Some(alias))
+        };
+        let mut groups: BTreeMap<Option<String>, Vec<T>> = BTreeMap::new();
+        for item in items {
+            let mut aliases = aliases(&item)
+                .into_iter()
+                .unique()
+                .filter(|alias| {
+                    if is_alias(alias).not() {
+                        warn!("In project {}, no hook with alias {alias}", project.url);
+                    }
+                    is_alias(alias)
+                })
+                .collect_vec();
+            // Items for several aliases are rendered in each of their groups.
+            let Some(last) = aliases.pop() else {
+                groups.entry(None).or_default().push(item);
+                continue;
+            };
+            for alias in aliases {
+                groups.entry(Some(alias)).or_default().push(item.clone());
+            }
+            groups.entry(Some(last)).or_default().push(item);
+        }
+        for (alias, items) in groups {
+            // Sinks without an alias, e.g. the channel of the project, only receive items
+            // that have not been routed.
+            let filter = |sink_alias: Option<&str>| match alias {
+                Some(ref alias) => sink_alias == Some(alias.as_str()),
+                None => sink_alias
+                    .is_none_or(|sink_alias| project.is_target(sink_alias, self.sla).not()),
+            };
+            self.post_to(project, filter, kind, severity(&items), |project| {
+                render(project, items.clone())
+            })
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+/// The recipients of a project by email, along with the report to send them.
+struct Email<'a> {
+    smtp: &'a Smtp,
+    to: &'a [String],
+    report: Report,
+}
+
+/// Find (or create) the channel of a project, if the config asks for one.
+async fn project_channel<'a>(
+    client: &Client,
+    config: &Config,
+    secrets: &'a Secrets,
+    state: &mut State,
     project: &Project,
+) -> Result<Option<SlackChannel<'a>>, anyhow::Error> {
+    let (Some(channels), Some(bot)) = (&config.project_channels, &secrets.slack_bot) else {
+        return Ok(None);
+    };
+    let name = channels.name_for(project);
+    let cached = match state.channel_id(&name)? {
+        Some(id) if slack::is_open(client, &bot.token, &id).await? => Some(id),
+        Some(_) => {
+            warn!("Slack channel #{name} is gone or archived, looking for it again");
+            state.remove_channel_id(&name)?;
+            None
+        }
+        None => None,
+    };
+    let id = match cached {
+        Some(id) => id,
+        None => {
+            let Some(id) = slack::ensure_channel(
+                client,
+                &bot.token,
+                &name,
+                channels.create,
+                channels.invite_usergroup.as_deref(),
+            )
+            .await?
+            else {
+                warn!("No Slack channel #{name} for project {}", project.url);
+                return Ok(None);
+            };
+            info!("Posting the reports of {} to #{name}", project.url);
+            state.set_channel_id(&name, &id)?;
+            id
+        }
+    };
+    Ok(Some(SlackChannel {
+        token: &bot.token,
+        id,
+        name,
+    }))
+}
+
+/// A project that we have checked and may monitor.
+struct Checked {
+    /// The repository, as returned by GitHub.
+    repository: Box<Repository>,
+
+    /// The project at its current url, which may differ from the configured one
+    /// if the repository has moved.
+    current: Project,
+}
+
+/// Update a message in place, or post it (and pin it) if it doesn't exist yet, e.g. the
+/// status board.
+///
+/// Returns the `ts` of the message.
+async fn update_pinned(
+    client: &Client,
+    token: &str,
+    channel: &str,
+    ts: Option<String>,
+    msg: &slack::Section,
+    what: &str,
+) -> Result<String, anyhow::Error> {
+    if let Some(ts) = ts {
+        match msg.post_or_update(client, token, channel, Some(&ts)).await {
+            Ok(_) => return Ok(ts),
+            // e.g. the message was deleted, let's post a new one.
+            Err(err) => warn!("Could not update the {what}, posting a new one: {err:?}"),
+        }
+    }
+    let ts = msg.post_or_update(client, token, channel, None).await?;
+    // Best effort, the bot may lack scope `pins:write`.
+    let pinned = async {
+        client
+            .post("https://slack.com/api/pins.add")
+            .bearer_auth(token)
+            .json(&serde_json::json!({ "channel": channel, "timestamp": ts }))
+            .send()
+            .await?
+            .json::<serde_json::Value>()
+            .await
+    }
+    .await;
+    match pinned {
+        Ok(response) if response["ok"] == true => {}
+        Ok(response) => warn!("Could not pin the {what}: {}", response["error"]),
+        Err(err) => warn!("Could not pin the {what}: {err:?}"),
+    }
+    Ok(ts)
+}
+
+/// Update the status board in place, or post it (and pin it) if it doesn't exist yet.
+async fn update_status_board(
+    client: &Client,
+    board: &StatusBoard,
+    state: &mut State,
+    msg: &slack::Section,
+) -> Result<(), anyhow::Error> {
+    let ts = update_pinned(
+        client,
+        &board.token,
+        &board.channel,
+        state.status_board_ts(&board.channel)?,
+        msg,
+        "status board",
+    )
+    .await?;
+    state.set_status_board_ts(&board.channel, &ts)
+}
+
+/// Make sure that the repository is still alive and find out where it lives.
+///
+/// Returns `None` if the project cannot be monitored anymore. Unless `notify` is `false`
+/// (e.g. for `qastor report`), the administrators are told about such changes.
+async fn check_project(
+    client: &Client,
+    secrets: &Secrets,
+    octocrab: &Octocrab,
+    project: &Project,
+    state: &mut State,
+    notify: bool,
+) -> Result<Option<Checked>, anyhow::Error> {
+    // If the repository is gone, we'd fail on every single run.
+    let repository = match github::check_repository(octocrab, project).await? {
+        Availability::Available(repository) => repository,
+        Availability::Unavailable(reason) => {
+            warn!("Project {} is {reason}, disabling", project.url);
+            state.set_skipped(&project.url, reason)?;
+            if notify {
+                notify_admin(
+                    client,
+                    secrets,
+                    format!(
+                        "Repo {link} is {reason}, qastor will stop monitoring it",
+                        link = slack::link(&project.html_url(), Some(project.repo.as_ref())),
+                    ),
+                )
+                .await?;
+            }
+            return Ok(None);
+        }
+    };
+
+    // If the repository has been renamed, we keep sending to the hooks of the
+    // configured url, but fetch from (and link to) the new url.
+    let current = match github::moved_to(project, &repository) {
+        None => project.clone(),
+        Some(current) => {
+            if state.moved_to(&project.url)?.as_ref() != Some(&current) {
+                warn!("Project {} has moved to {current}", project.url);
+                state.set_moved_to(&project.url, &current)?;
+                if notify {
+                    notify_admin(
+                        client,
+                        secrets,
+                        format!(
+                            "Repo {} has moved to {}, please update config.yml",
+                            project.url, current
+                        ),
+                    )
+                    .await?;
+                }
+            }
+            project.moved_to(current)?
+        }
+    };
+    Ok(Some(Checked {
+        repository,
+        current,
+    }))
+}
+
+/// Tell the users who had reviewed force-pushed PRs, through Slack direct messages, that
+/// their reviews may no longer apply.
+///
+/// Each force-push is only told once, as recorded in the state.
+async fn dm_force_pushes(
+    client: &Client,
+    token: &str,
     config: &Config,
+    state: &mut State,
+    configured: &Url,
+    project: &Project,
+    pushed: Vec<(&PendingReview, &ForcePush)>,
 ) -> Result<(), anyhow::Error> {
-    let since = chrono::Local::now() - config.update_frequency;
+    for (pull, push) in pushed {
+        if state
+            .force_push_dm(configured, pull.number)?
+            .is_some_and(|told| told >= push.at)
+        {
+            continue;
+        }
+        for reviewer in &push.reviewers {
+            let Some(member) = config.slack_member(reviewer) else {
+                continue;
+            };
+            let msg = slack::Section::new(config.text(
+                project,
+                "force_push.dm",
+                &[(
+                    "pull",
+                    slack::link(&pull.url, Some(&format!("#{} {}", pull.number, pull.title))),
+                )],
+            ));
+            let payload = msg.payload(&slack::MessageOptions::default());
+            if let Err(err) = slack::post_message(client, token, member, None, payload).await {
+                warn!(
+                    "Could not tell {reviewer} about the force-push to #{}: {err:?}",
+                    pull.number
+                );
+            }
+        }
+        state.set_force_push_dm(configured, pull.number, push.at)?;
+    }
+    Ok(())
+}
+
+/// Find out who owns a project from its catalog file, if any, and add the hooks of
+/// its channel (or owning team) to the outbox.
+async fn resolve_ownership<'a>(
+    config: &Config,
+    secrets: &'a Secrets,
+    octocrab: &Octocrab,
+    project: &Project,
+    outbox: &mut Outbox<'a>,
+) -> Result<Ownership, anyhow::Error> {
+    let ownership = match config.catalog {
+        None => None,
+        Some(ref path) => catalog::fetch_ownership(octocrab, project, path)
+            .await
+            .unwrap_or_else(|err| {
+                warn!("Could not read catalog file {path}: {err:?}");
+                None
+            }),
+    }
+    .unwrap_or_default();
+    if let Some(channel) = ownership.channel.as_ref().or(ownership.team.as_ref()) {
+        match secrets.channels.get(channel) {
+            Some(hooks) => outbox.hooks.extend(hooks),
+            None => warn!("In project {}, no hooks for channel {channel}", project.url),
+        }
+    }
+    if outbox.hooks.is_empty() {
+        return Err(anyhow!("Missing secret"));
+    }
+    Ok(ownership)
+}
+
+/// All the machinery for a single project.
+async fn per_project<'a>(
+    secrets: &'a Secrets,
+    project: &Project,
+    config: &Config,
+    state: &mut State,
+    search: Option<&mut SearchResults>,
+    enricher: &Enricher,
+    mut outbox: Outbox<'a>,
+) -> Result<ProjectOutcome, anyhow::Error> {
+    let now = chrono::Utc::now();
+    let client = outbox.client;
+
+    let _update_frequency = config.update_frequency_for(project);
+    // With `deliver_at`, the report of a previous run may still be on its way, in which case
+    // its items would be scheduled twice.
+    if outbox.post_at.is_some() && state::is_pending(&state.scheduled_messages(&project.url)?, now)
+    {
+        debug!("A report is already scheduled, skipping");
+        return Ok(ProjectOutcome::skipped("scheduled"));
+    }
+
+    let octocrab = github::instance_for(project);
+    let fetch_started = Instant::now();
+
+    let Some(Checked {
+        repository,
+        current,
+    }) = check_project(
+        client,
+        secrets,
+        &octocrab,
+        project,
+        state,
+        outbox.print.not(),
+    )
+    .await?
+    else {
+        return Ok(ProjectOutcome::skipped("unavailable"));
+    };
+    let configured = &project.url;
+    let project = &current;
+    let ownership = resolve_ownership(config, secrets, &octocrab, project, &mut outbox).await?;
+    let _per_page = config.per_page(project).unwrap_or(100);
+
+    // The weekly digest is due regardless of activity.
+    let last_digest = state.last_digest(configured)?;
+    let digest_due = config
+        .weekly_digest
+        .is_some_and(|day| digest::is_due(now, day, last_digest));
+    let last_milestone_report = state.last_milestone_report(configured)?;
+    let milestone_report_due = project
+        .milestone_report
+        .is_some_and(|day| digest::is_due(now, day, last_milestone_report));
+
+    // Cheap pre-check: if nothing happened since the last run, don't bother
+    // listing issues and PRs.
+    let activity = github::last_activity(&repository);
+    if config.skip_unchanged
+        && digest_due.not()
+        && milestone_report_due.not()
+        && activity.is_some()
+        && activity == state.last_activity(configured)?
+    {
+        debug!("No activity since last run, skipping");
+        return Ok(ProjectOutcome::skipped("unchanged"));
+    }
+
+    let last_run = state.last_run(configured)?;
+    let issues_window = config.window(project, SectionKind::UpdatedIssues);
+    let mut sections = fetch_sections(
+        &Scope {
+            config,
+            octocrab: &octocrab,
+            project,
+            configured,
+            repository: &repository,
+            last_run,
+            now,
+        },
+        state,
+        search,
+        digest_due,
+        milestone_report_due,
+    )
+    .await?;
+
+    outbox.metrics.fetch += fetch_started.elapsed();
+
+    // Dashboards want the whole picture, even when there's nothing to report.
+    let payload = webhook::Payload::new(
+        &project.url,
+        now,
+        &sections.pending_requests,
+        &sections.pending_issues,
+    );
+    // Other hooks still deserve their report.
+    if let Err(err) = outbox.send_payload(project, &payload).await {
+        warn!("{err:?}");
+        outbox.metrics.failed_deliveries += 1;
+    }
+    if let Some(ref channel) = outbox.summary {
+        let msg = render_backlog(
+            config,
+            project,
+            &sections.pending_requests,
+            &sections.pending_issues,
+            now,
+        );
+        let ts = state.summary_ts(&channel.id)?;
+        match update_pinned(client, channel.token, &channel.id, ts, &msg, "summary").await {
+            Ok(ts) => state.set_summary_ts(&channel.id, &ts)?,
+            Err(err) => warn!("Could not update the summary of #{}: {err:?}", channel.name),
+        }
+    }
+
+    let counts = sections.counts();
+
+    if sections.is_empty() {
+        debug!("No issues to report");
+        outbox.save_live(config, project, configured, state).await?;
+        if let Some(activity) = activity {
+            state.set_last_activity(configured, activity)?;
+        }
+        if let Some(ref tags) = sections.tags {
+            state.set_tags(configured, tags)?;
+        }
+        if let Some(ref record) = sections.popularity_record {
+            state.set_popularity(configured, record)?;
+        }
+        if sections.milestone_progress.is_some() {
+            state.set_last_milestone_report(configured, now.date_naive())?;
+        }
+        if let Some(ref mut lifecycles) = sections.lifecycles {
+            lifecycle::prune(lifecycles, now);
+            state.set_lifecycles(configured, lifecycles)?;
+        }
+        state.set_last_run(configured, now)?;
+        state.set_reported(configured, &sections.reported)?;
+        return Ok(outbox.outcome(counts));
+    }
+
+    // Before dropping the PRs seen recently, since a force-push makes them worth another look.
+    let enrich_started = Instant::now();
+    let force_pushes = fetch_force_pushes(
+        config,
+        enricher,
+        &octocrab,
+        project,
+        last_run,
+        &sections.pending_requests,
+    )
+    .await;
+    outbox.metrics.fetch += enrich_started.elapsed();
+    if let (true, Some(bot)) = (config.force_push_dms, &secrets.slack_bot) {
+        if outbox.print.not() && outbox.preview.is_none() && outbox.snoozed.not() {
+            let pushed = sections
+                .pending_requests
+                .iter()
+                .filter_map(|pull| Some((pull, force_pushes.get(&pull.number)?)))
+                .collect_vec();
+            dm_force_pushes(
+                client, &bot.token, config, state, configured, project, pushed,
+            )
+            .await?;
+        }
+    }
+
+    // Past their cap, items are neither enriched nor displayed.
+    let fixes = index_fixes(&sections.pending_requests);
+    // Live messages list everything, and editing them doesn't ping anyone anyway.
+    if let Some(reping_after) = config.reping_after.filter(|_| config.update_in_place.not()) {
+        sections.drop_seen_since(state, configured, now - reping_after, &force_pushes)?;
+    }
+    let omitted = Omissions::cap(config, project, &mut sections);
+    let Sections {
+        pending_requests,
+        drafts,
+        pending_issues,
+        reported,
+        transferred,
+        mut lifecycles,
+        stuck,
+        due_milestones,
+        milestone_progress,
+        dependency_updates,
+        label_changes,
+        releases,
+        tags,
+        discussions,
+        popularity,
+        popularity_record,
+        security_alerts,
+        scanning_alerts,
+        ci_failures,
+        sla_breaches,
+        weekly_digest,
+        mut unreviewed,
+        stale_pulls,
+        ..
+    } = sections;
+    let milestone_report_sent = milestone_progress.is_some();
+    let milestone_progress = milestone_progress.unwrap_or_default();
+    let enrich_started = Instant::now();
+    // Suggestions only change with the head of a PR, so we fetch them once per head.
+    let mut authors = HashMap::new();
+    if let Some(ref suggest) = config.suggest_reviewers {
+        let mut missing = vec![];
+        for pull in &unreviewed {
+            match state.recent_authors(&project.url, pull.number)? {
+                Some(cached) if cached.head == pull.head.sha => {
+                    authors.insert(pull.number, cached.authors);
+                }
+                _ => missing.push(pull.number),
+            }
+        }
+        let fetched = enricher
+            .enrich(missing, |number| {
+                github::fetch_recent_authors(
+                    octocrab.as_ref().clone(),
+                    project.clone(),
+                    number,
+                    suggest.files,
+                    suggest.commits,
+                )
+            })
+            .await;
+        for pull in &unreviewed {
+            if let Some(fetched) = fetched.get(&pull.number) {
+                let recent = RecentAuthors {
+                    head: pull.head.sha.clone(),
+                    authors: fetched.clone(),
+                };
+                state.set_recent_authors(&project.url, pull.number, &recent)?;
+            }
+        }
+        authors.extend(fetched);
+        // Reviewed without a request, or after the requests were fulfilled.
+        unreviewed.retain(|pull| authors.get(&pull.number).is_none_or(Option::is_some));
+    }
+    let authors: HashMap<u64, Vec<Vec<String>>> = authors
+        .into_iter()
+        .filter_map(|(number, authors)| Some((number, authors?)))
+        .collect();
+    let notified = pending_requests
+        .iter()
+        .map(|pull| (SectionKind::PendingReviews, pull.number))
+        .chain(
+            drafts
+                .iter()
+                .map(|pull| (SectionKind::DraftReviews, pull.number)),
+        )
+        .chain(
+            unreviewed
+                .iter()
+                .map(|pull| (SectionKind::UnreviewedPulls, pull.number)),
+        )
+        .chain(
+            stale_pulls
+                .iter()
+                .map(|pull| (SectionKind::StalePulls, pull.number)),
+        )
+        .chain(
+            discussions
+                .iter()
+                .map(|discussion| (SectionKind::UnansweredDiscussions, discussion.number)),
+        )
+        .chain(
+            pending_issues
+                .iter()
+                .map(|issue| (SectionKind::UpdatedIssues, issue.number)),
+        )
+        .chain(
+            stuck
+                .iter()
+                .map(|issue| (SectionKind::StuckInTriage, issue.number)),
+        )
+        .collect_vec();
+    let reviews = enricher
+        .enrich(pending_requests.iter().map(|pull| pull.number), |number| {
+            github::fetch_review_summary(octocrab.as_ref().clone(), project.clone(), number)
+        })
+        .await;
+    let acks = fetch_acks(
+        config,
+        state,
+        enricher,
+        &octocrab,
+        project,
+        configured,
+        &pending_requests,
+    )
+    .await?;
+    let owner_hooks = fetch_code_owner_hooks(enricher, &octocrab, project, &pending_requests).await;
+    outbox.metrics.fetch += enrich_started.elapsed();
+    if pending_requests.is_empty().not() {
+        // PRs waiting for too long skip the routes, to reach those who can unblock them.
+        let escalation = |pull: &PendingReview| {
+            let created_at = pull.created_at?;
+            project
+                .escalations
+                .iter()
+                .filter(|escalation| has_waited(config, escalation.after, created_at, now))
+                .max_by_key(|escalation| escalation.after)
+        };
+        outbox
+            .post_with_severity(
+                project,
+                SectionKind::PendingReviews,
+                pending_requests,
+                |pull| {
+                    let labels = pull.labels.iter().map(String::as_str);
+                    let routes = project.routes_for(&pull.title, &[], labels);
+                    match (escalation(pull), routes.is_empty()) {
+                        (Some(escalation), _) => vec![escalation.hook.clone()],
+                        (None, false) => routes,
+                        (None, true) => owner_hooks.get(&pull.number).cloned().unwrap_or_default(),
+                    }
+                },
+                |pulls| {
+                    pulls
+                        .iter()
+                        .filter_map(escalation)
+                        .map(|escalation| escalation.severity)
+                        .fold(SectionKind::PendingReviews.severity(), std::cmp::max)
+                },
+                |project, pulls| {
+                    let escalation = pulls
+                        .iter()
+                        .filter_map(escalation)
+                        .max_by_key(|escalation| escalation.after);
+                    omitted.requests.mark(
+                        config,
+                        project,
+                        render_pending_requests(
+                            config,
+                            project,
+                            pulls,
+                            &reviews,
+                            &acks,
+                            &force_pushes,
+                            escalation,
+                        ),
+                    )
+                },
+            )
+            .await?;
+    }
+    if drafts.is_empty().not() {
+        outbox
+            .post_routed(
+                project,
+                SectionKind::DraftReviews,
+                drafts,
+                |pull| &pull.title,
+                |pull| pull.labels.iter().map(String::as_str).collect(),
+                |project, pulls| {
+                    omitted
+                        .drafts
+                        .mark(config, project, render_drafts(config, project, pulls))
+                },
+            )
+            .await?;
+    }
+    if let (false, Some(suggest)) = (unreviewed.is_empty(), &config.suggest_reviewers) {
+        outbox
+            .post_routed(
+                project,
+                SectionKind::UnreviewedPulls,
+                unreviewed,
+                |pull| pull.title.as_deref().unwrap_or_default(),
+                |pull| {
+                    pull.labels
+                        .iter()
+                        .flatten()
+                        .map(|label| label.name.as_str())
+                        .collect()
+                },
+                |project, pulls| {
+                    omitted.unreviewed.mark(
+                        config,
+                        project,
+                        render_unreviewed_pulls(config, project, pulls, &authors, suggest.max),
+                    )
+                },
+            )
+            .await?;
+    }
+    if let (false, Some(stale_after)) = (stale_pulls.is_empty(), config.stale_after) {
+        outbox
+            .post_routed(
+                project,
+                SectionKind::StalePulls,
+                stale_pulls,
+                |pull| pull.title.as_deref().unwrap_or_default(),
+                |pull| {
+                    pull.labels
+                        .iter()
+                        .flatten()
+                        .map(|label| label.name.as_str())
+                        .collect()
+                },
+                |project, pulls| {
+                    omitted.stale.mark(
+                        config,
+                        project,
+                        render_stale_pulls(config, project, pulls, stale_after, now),
+                    )
+                },
+            )
+            .await?;
+    }
+    if pending_issues.is_empty().not() {
+        let issues_title = |project: &Project| {
+            let link = slack::link(&project.html_url(), Some(project.repo.as_ref()));
+            match issues_window {
+                Window::All => config.text(project, "issues.all", &[("link", link)]),
+                Window::SinceLastRun => {
+                    config.text(project, "issues.since_last_report", &[("link", link)])
+                }
+                Window::Last(duration) => config.text(
+                    project,
+                    "issues.last",
+                    &[
+                        ("link", link),
+                        (
+                            "since",
+                            humanize::duration(duration, config.locale_for(project)),
+                        ),
+                    ],
+                ),
+            }
+        };
+        let details_started = Instant::now();
+        let details = fetch_issue_details(&octocrab, project, &pending_issues).await;
+        outbox.metrics.fetch += details_started.elapsed();
+        outbox
+            .post_escalated(
+                project,
+                SectionKind::UpdatedIssues,
+                pending_issues,
+                |issue| route_issue(project, issue),
+                |project, issues| {
+                    omitted.issues.mark(
+                        config,
+                        project,
+                        render_issues(
+                            config,
+                            Some(project),
+                            issues_title(project),
+                            issues,
+                            &details,
+                            &fixes,
+                            now,
+                        ),
+                    )
+                },
+            )
+            .await?;
+    }
+    if let (Some(triage), Some(lifecycles)) = (&config.triage, &lifecycles) {
+        if stuck.is_empty().not() {
+            outbox
+                .post_escalated(
+                    project,
+                    SectionKind::StuckInTriage,
+                    stuck,
+                    |issue| route_issue(project, issue),
+                    |project, issues| {
+                        omitted.stuck.mark(
+                            config,
+                            project,
+                            render_stuck_in_triage(
+                                config, project, issues, lifecycles, triage, now,
+                            ),
+                        )
+                    },
+                )
+                .await?;
+        }
+    }
+    if transferred.is_empty().not() {
+        outbox
+            .post(project, SectionKind::TransferredIssues, |project| {
+                omitted.transferred.mark(
+                    config,
+                    project,
+                    render_transferred_issues(config, project, transferred.clone()),
+                )
+            })
+            .await?;
+    }
+    let milestones_count = due_milestones.len();
+    for (i, (milestone, issues)) in due_milestones.into_iter().enumerate() {
+        let render = |project: &Project| {
+            let msg = render_due_milestone(
+                config,
+                project,
+                &milestone,
+                issues.clone(),
+                &ownership.escalation,
+                now,
+            );
+            match i + 1 == milestones_count {
+                true => omitted.milestones.mark(config, project, msg),
+                false => msg,
+            }
+        };
+        outbox
+            .post(project, SectionKind::DueMilestones, render)
+            .await?;
+    }
+    if dependency_updates.is_empty().not() {
+        let enrich_started = Instant::now();
+        let badges = dependency_updates
+            .iter()
+            .flat_map(|pull| &pull.updates)
+            .filter_map(|update| update.badge.clone())
+            .unique()
+            .collect_vec();
+        let scores = enricher
+            .enrich(badges, |badge| {
+                dependabot::fetch_score(client.clone(), badge)
+            })
+            .await;
+        outbox.metrics.fetch += enrich_started.elapsed();
+        outbox
+            .post(project, SectionKind::DependencyUpdates, |project| {
+                omitted.updates.mark(
+                    config,
+                    project,
+                    render_dependency_updates(config, project, &dependency_updates, &scores),
+                )
+            })
+            .await?;
+    }
+    let security_hook = project
+        .security
+        .as_ref()
+        .and_then(|security| security.hook.clone());
+    if security_alerts.is_empty().not() {
+        outbox
+            .post_with_severity(
+                project,
+                SectionKind::SecurityAlerts,
+                security_alerts,
+                |_| security_hook.clone(),
+                |alerts| match alerts
+                    .iter()
+                    .any(|alert| alert.security_advisory.severity == AlertSeverity::Critical)
+                {
+                    true => Severity::Critical,
+                    false => SectionKind::SecurityAlerts.severity(),
+                },
+                |project, alerts| {
+                    omitted.security.mark(
+                        config,
+                        project,
+                        render_security_alerts(config, project, alerts),
+                    )
+                },
+            )
+            .await?;
+    }
+    if scanning_alerts.is_empty().not() {
+        outbox
+            .post_with_severity(
+                project,
+                SectionKind::ScanningAlerts,
+                scanning_alerts,
+                |_| security_hook.clone(),
+                |alerts| match alerts
+                    .iter()
+                    .any(|alert| alert.severity == AlertSeverity::Critical)
+                {
+                    true => Severity::Critical,
+                    false => SectionKind::ScanningAlerts.severity(),
+                },
+                |project, alerts| {
+                    omitted.scanning.mark(
+                        config,
+                        project,
+                        render_scanning_alerts(config, project, alerts),
+                    )
+                },
+            )
+            .await?;
+    }
+    if label_changes.is_empty().not() {
+        outbox
+            .post_routed(
+                project,
+                SectionKind::LabelChanges,
+                label_changes,
+                |change| &change.title,
+                // Removing a label doesn't concern those who follow it any longer.
+                |change| match change.added {
+                    true => vec![change.label.as_str()],
+                    false => vec![],
+                },
+                |project, changes| {
+                    omitted.labels.mark(
+                        config,
+                        project,
+                        render_label_changes(config, project, changes, now),
+                    )
+                },
+            )
+            .await?;
+    }
+    if releases.is_empty().not() {
+        outbox
+            .post(project, SectionKind::Releases, |project| {
+                omitted.releases.mark(
+                    config,
+                    project,
+                    render_releases(config, project, releases.clone()),
+                )
+            })
+            .await?;
+    }
+    if milestone_progress.is_empty().not() {
+        outbox
+            .post(project, SectionKind::MilestoneProgress, |project| {
+                omitted.progress.mark(
+                    config,
+                    project,
+                    render_milestone_progress(config, project, milestone_progress.clone(), now),
+                )
+            })
+            .await?;
+    }
+    if popularity.is_empty().not() {
+        let hook = project.stars.as_ref().and_then(|stars| stars.hook.clone());
+        outbox
+            .post_escalated(
+                project,
+                SectionKind::Popularity,
+                popularity,
+                |_| hook.clone(),
+                |project, changes| render_popularity(config, project, changes),
+            )
+            .await?;
+    }
+    if ci_failures.is_empty().not() {
+        let branch = repository.default_branch.as_deref().unwrap_or_default();
+        outbox
+            .post(project, SectionKind::CiFailures, |project| {
+                omitted.ci.mark(
+                    config,
+                    project,
+                    render_ci_failures(config, project, branch, ci_failures.clone(), now),
+                )
+            })
+            .await?;
+    }
+    if discussions.is_empty().not() {
+        outbox
+            .post_routed(
+                project,
+                SectionKind::UnansweredDiscussions,
+                discussions,
+                |discussion| &discussion.title,
+                |_| vec![],
+                |project, discussions| {
+                    omitted.discussions.mark(
+                        config,
+                        project,
+                        render_discussions(config, project, discussions, now),
+                    )
+                },
+            )
+            .await?;
+    }
+    let escalated = sla_breaches
+        .iter()
+        .map(|breach| breach.issue.number)
+        .collect_vec();
+    if sla_breaches.is_empty().not() {
+        outbox
+            .post_escalated(
+                project,
+                SectionKind::SlaBreaches,
+                sla_breaches,
+                |breach| breach.policy.escalate_to.clone(),
+                |project, breaches| render_sla_breaches(config, project, breaches, now),
+            )
+            .await?;
+    }
+    if let Some(ref digest) = weekly_digest {
+        outbox
+            .post(project, SectionKind::WeeklyDigest, |project| {
+                render_weekly_digest(config, project, digest, now)
+            })
+            .await?;
+    }
+    // Only once everything has been sent, so that we retry otherwise. While snoozed, what we
+    // didn't send is still to be reported once the snooze ends.
+    let snoozed = outbox.snoozed;
+    let sent = |kind: SectionKind| snoozed.not() || kind.sent_while_snoozed();
+    if let Some(activity) = activity {
+        state.set_last_activity(configured, activity)?;
+    }
+    if let (Some(ref tags), true) = (&tags, sent(SectionKind::Releases)) {
+        state.set_tags(configured, tags)?;
+    }
+    if let (Some(ref record), true) = (&popularity_record, sent(SectionKind::Popularity)) {
+        state.set_popularity(configured, record)?;
+    }
+    if milestone_report_sent && sent(SectionKind::MilestoneProgress) {
+        state.set_last_milestone_report(configured, now.date_naive())?;
+    }
+    if weekly_digest.is_some() && sent(SectionKind::WeeklyDigest) {
+        state.set_last_digest(configured, now.date_naive())?;
+    }
+    if let Some(ref threads) = outbox.threads {
+        state.set_threads(configured, now.date_naive(), threads)?;
+    }
+    outbox.save_live(config, project, configured, state).await?;
+    if outbox.post_at.is_some() {
+        state.set_scheduled_messages(configured, &outbox.scheduled)?;
+    }
+    for number in escalated {
+        state.set_escalated(configured, number, now)?;
+    }
+    outbox.send_email(config, project).await?;
+    // Items only count as notified once their section has reached someone, and none of them
+    // are in critical sections, which go out while snoozed.
+    let notified = match snoozed {
+        true => vec![],
+        false => delivered_items(notified, &outbox.delivered),
+    };
+    if let Some(ref mut lifecycles) = lifecycles {
+        for number in &notified {
+            if let Some(lifecycle) = lifecycles.get_mut(number) {
+                lifecycle.reported += 1;
+            }
+        }
+        lifecycle::prune(lifecycles, now);
+        state.set_lifecycles(configured, lifecycles)?;
+    }
+    if config.reping_after.is_some() {
+        for number in notified {
+            state.set_seen(configured, number, now)?;
+        }
+    }
+    if snoozed.not() {
+        state.set_last_run(configured, now)?;
+        state.set_reported(configured, &reported)?;
+    }
+    Ok(outbox.outcome(counts))
+}
+
+/// Replay a single project since a given day and post a catch-up digest.
+async fn backfill_project<'a>(
+    secrets: &'a Secrets,
+    project: &Project,
+    config: &Config,
+    state: &mut State,
+    since: NaiveDate,
+    enricher: &Enricher,
+    mut outbox: Outbox<'a>,
+) -> Result<ProjectOutcome, anyhow::Error> {
+    /// Leave some API calls to other users of the same credentials.
+    const MIN_REMAINING_CALLS: usize = 10;
+    let now = chrono::Utc::now();
+    let client = outbox.client;
+
+    let octocrab = github::instance_for(project);
+    let fetch_started = Instant::now();
+    let Some(Checked { current, .. }) =
+        check_project(client, secrets, &octocrab, project, state, true).await?
+    else {
+        return Ok(ProjectOutcome::skipped("unavailable"));
+    };
+    let project = &current;
+    resolve_ownership(config, secrets, &octocrab, project, &mut outbox).await?;
+    let per_page = config.per_page(project).unwrap_or(100);
+
+    // Replay day by day, so that we can pause whenever we run low on API calls.
+    let mut issues = vec![];
+    let mut day = since;
+    while day <= now.date_naive() {
+        github::wait_for_rate_limit(&octocrab, MIN_REMAINING_CALLS).await?;
+        let start = day.and_time(chrono::NaiveTime::MIN).and_utc();
+        let end = start + chrono::Duration::days(1);
+        debug!("Replaying {} on {day}", project.url);
+        issues.extend(
+            github::fetch_issues_updated_between(&octocrab, project, start, end, per_page).await?,
+        );
+        day = day.succ_opt().context("Invalid date")?;
+    }
+    github::wait_for_rate_limit(&octocrab, MIN_REMAINING_CALLS).await?;
+    let (pending_requests, requests_complete) =
+        fetch_pending_requests(&octocrab, config, project).await?;
+    let mut pending_requests = pending_requests
+        .into_iter()
+        .filter(|pull| project.shows(pull.labels.iter().map(String::as_str)))
+        .filter(|pull| {
+            pull.author
+                .as_deref()
+                .is_none_or(|author| config.ignores_author(author).not())
+        })
+        .collect_vec();
+    report::sort(&mut pending_requests);
+
+    // Drafts aren't ready for reviews yet, whatever their reviewers.
+    let (mut drafts, mut pending_requests): (Vec<_>, Vec<_>) = match project.drafts {
+        Drafts::Include => (vec![], pending_requests),
+        Drafts::Separate => pending_requests.into_iter().partition(|pull| pull.draft),
+        Drafts::Ignore => (
+            vec![],
+            pending_requests
+                .into_iter()
+                .filter(|pull| pull.draft.not())
+                .collect(),
+        ),
+    };
+
+    // PRs are listed separately.
+    let mut issues = issues
+        .into_iter()
+        .filter(|issue| issue.pull_request.is_none())
+        .filter(|issue| project.shows(issue.labels.iter().map(|label| label.name.as_str())))
+        .filter(|issue| config.ignores_author(&issue.user.login).not())
+        .collect_vec();
+    report::sort(&mut issues);
+    outbox.metrics.fetch += fetch_started.elapsed();
+    let sections = [
+        (SectionKind::PendingReviews, pending_requests.len()),
+        (SectionKind::DraftReviews, drafts.len()),
+        (SectionKind::UpdatedIssues, issues.len()),
+    ];
+
+    let fixes = index_fixes(&pending_requests);
+    let omitted_requests = Omitted::new(
+        project,
+        "pulls",
+        cap(
+            config,
+            project,
+            SectionKind::PendingReviews,
+            &mut pending_requests,
+        ),
+        requests_complete,
+    );
+    let omitted_drafts = Omitted::new(
+        project,
+        "pulls",
+        cap(config, project, SectionKind::DraftReviews, &mut drafts),
+        requests_complete,
+    );
+    let omitted_issues = Omitted::new(
+        project,
+        "issues",
+        cap(config, project, SectionKind::UpdatedIssues, &mut issues),
+        true,
+    );
+    let enrich_started = Instant::now();
+    let reviews = enricher
+        .enrich(pending_requests.iter().map(|pull| pull.number), |number| {
+            github::fetch_review_summary(octocrab.as_ref().clone(), project.clone(), number)
+        })
+        .await;
+    outbox.metrics.fetch += enrich_started.elapsed();
+    if pending_requests.is_empty().not() {
+        outbox
+            .post_routed(
+                project,
+                SectionKind::PendingReviews,
+                pending_requests,
+                |pull| &pull.title,
+                |pull| pull.labels.iter().map(String::as_str).collect(),
+                |project, pulls| {
+                    omitted_requests.mark(
+                        config,
+                        project,
+                        render_pending_requests(
+                            config,
+                            project,
+                            pulls,
+                            &reviews,
+                            &HashMap::new(),
+                            &HashMap::new(),
+                            None,
+                        ),
+                    )
+                },
+            )
+            .await?;
+    }
+    if drafts.is_empty().not() {
+        outbox
+            .post_routed(
+                project,
+                SectionKind::DraftReviews,
+                drafts,
+                |pull| &pull.title,
+                |pull| pull.labels.iter().map(String::as_str).collect(),
+                |project, pulls| {
+                    omitted_drafts.mark(config, project, render_drafts(config, project, pulls))
+                },
+            )
+            .await?;
+    }
+    if issues.is_empty().not() {
+        let title = |project: &Project| {
+            config.text(
+                project,
+                "catch_up.title",
+                &[
+                    (
+                        "link",
+                        slack::link(&project.html_url(), Some(project.repo.as_ref())),
+                    ),
+                    (
+                        "since",
+                        since.format(&config.text(project, "date", &[])).to_string(),
+                    ),
+                ],
+            )
+        };
+        let details = fetch_issue_details(&octocrab, project, &issues).await;
+        outbox
+            .post_escalated(
+                project,
+                SectionKind::UpdatedIssues,
+                issues,
+                |issue| route_issue(project, issue),
+                |project, issues| {
+                    omitted_issues.mark(
+                        config,
+                        project,
+                        render_issues(
+                            config,
+                            Some(project),
+                            title(project),
+                            issues,
+                            &details,
+                            &fixes,
+                            now,
+                        ),
+                    )
+                },
+            )
+            .await?;
+    }
+    outbox.send_email(config, project).await?;
+    Ok(outbox.outcome(sections))
+}
+
+/// Post the weekly digest of a project now, whatever the day, and nothing else.
+async fn digest_project<'a>(
+    secrets: &'a Secrets,
+    project: &Project,
+    config: &Config,
+    state: &mut State,
+    mut outbox: Outbox<'a>,
+) -> Result<ProjectOutcome, anyhow::Error> {
+    let now = chrono::Utc::now();
+    let client = outbox.client;
+
+    let octocrab = github::instance_for(project);
+    let fetch_started = Instant::now();
+    let Some(Checked { current, .. }) =
+        check_project(client, secrets, &octocrab, project, state, true).await?
+    else {
+        return Ok(ProjectOutcome::skipped("unavailable"));
+    };
+    let configured = &project.url;
+    let project = &current;
+    resolve_ownership(config, secrets, &octocrab, project, &mut outbox).await?;
+    let (pulls, complete) = fetch_open_pulls(&octocrab, config, project).await?;
+    let digest = fetch_weekly_digest(&octocrab, config, project, &pulls, complete, now).await?;
+    outbox.metrics.fetch += fetch_started.elapsed();
+    let sections = [(SectionKind::WeeklyDigest, digest.histogram.total())];
+
+    outbox
+        .post(project, SectionKind::WeeklyDigest, |project| {
+            render_weekly_digest(config, project, &digest, now)
+        })
+        .await?;
+    outbox.send_email(config, project).await?;
+    state.set_last_digest(configured, now.date_naive())?;
+    Ok(outbox.outcome(sections))
+}
+
+/// In search (or batch) mode, find the issues and PRs of all the projects of each owner at once.
+///
+/// Returns search results indexed by lowercase owner. Owners that could not be
+/// searched are missing, so that we fall back to listing their projects, as we do for
+/// projects with an `api_url`, see `searched`.
+async fn search_all(
+    config: &Config,
+    state: &State,
+) -> Result<HashMap<String, SearchResults>, anyhow::Error> {
+    let mut searches = HashMap::new();
+    if config.fetch == FetchMode::PerRepository {
+        return Ok(searches);
+    }
+    let now = chrono::Utc::now();
+    let mut enabled = vec![];
+    for project in &config.projects {
+        if is_disabled(state, project)?.not() {
+            enabled.push(project);
+        }
+    }
+    for owner in enabled
+        .iter()
+        .filter(|project| project.api_url.is_none())
+        .map(|project| project.owner.to_lowercase())
+        .unique()
+    {
+        // Cover the windows of all the projects of this owner, each project then
+        // filters its own issues.
+        let mut since = Some(now);
+        let projects = enabled
+            .iter()
+            .copied()
+            .filter(|project| project.api_url.is_none() && project.owner.to_lowercase() == owner)
+            .collect_vec();
+        for project in &projects {
+            let last_run = state.last_run(&project.url)?;
+            let window = config.window(project, SectionKind::UpdatedIssues);
+            since = since.min(window.since(now, last_run, config.update_frequency_for(project)));
+        }
+        info!("Searching projects of {owner}");
+        let per_page = config
+            .per_page
+            .map_or(100, |per_page| per_page.clamp(1, 100));
+        let batch = (config.fetch == FetchMode::Batch).then_some(projects.as_slice());
+        match github::search(&github::instance(&owner), &owner, since, per_page, batch).await {
+            Ok(results) => {
+                searches.insert(owner, results);
+            }
+            Err(err) => {
+                warn!("Could not search projects of {owner}, listing them per repository: {err:?}")
+            }
+        }
+    }
+    Ok(searches)
+}
+
+/// The search results of the owner of a project, see `search_all`.
+fn searched<'a>(
+    searches: &'a mut HashMap<String, SearchResults>,
+    project: &Project,
+) -> Option<&'a mut SearchResults> {
+    match project.api_url {
+        Some(_) => None,
+        None => searches.get_mut(&project.owner.to_lowercase()),
+    }
+}
+
+/// Make the members of each Slack user group of the config those of its GitHub team,
+/// mapped with `user_map`.
+async fn sync_usergroups(config: &Config, secrets: &Secrets) -> Result<(), anyhow::Error> {
+    let Some(ref bot) = secrets.slack_bot else {
+        return Err(anyhow!(
+            "Syncing user groups requires a `slack_bot` in the secrets"
+        ));
+    };
+    let client = reqwest::Client::new();
+    for sync in &config.usergroups {
+        let Some((org, slug)) = sync.team() else {
+            error!("Invalid team {}, expected org/slug, skipping", sync.team);
+            continue;
+        };
+        let logins = github::fetch_team_members(&github::instance(org), org, slug).await?;
+        let mut users = vec![];
+        for login in logins {
+            match config.slack_member(&login) {
+                Some(member) => users.push(member.to_string()),
+                None => warn!(
+                    "No Slack member for {login} of team {}, see `user_map`",
+                    sync.team
+                ),
+            }
+        }
+        // Slack refuses to empty a user group.
+        if users.is_empty() {
+            warn!(
+                "No Slack member for team {}, leaving {} as is",
+                sync.team, sync.usergroup
+            );
+            continue;
+        }
+        if slack::sync_usergroup(&client, &bot.token, &sync.usergroup, &users)
+            .await
+            .with_context(|| format!("Couldn't update user group {}", sync.usergroup))?
+        {
+            info!(
+                "User group {} now follows team {}",
+                sync.usergroup, sync.team
+            );
+        }
+    }
+    Ok(())
+}
 
-    // Instantiate the slack hook.
-    let slack_hooks = secrets
-        .repo_to_hook
-        .get(&project.url)
-        .context("Missing secret")?;
-
-    // List issues and pull requests.
-    //
-    // Note that the API could return more than one page, but we're not interested
-    // in so many issues/PRs.
-    let octocrab = octocrab::instance();
-    let issues = octocrab
-        .issues(&project.owner, &project.repo)
-        .list()
-        .since(since)
-        .send()
-        .await
-        .context("Couldn't download recent issues")?;
+/// The PRs of all projects waiting for a review, with their project, oldest first.
+async fn all_pending_requests<'a>(
+    config: &'a Config,
+    state: &State,
+) -> Result<Vec<(&'a Project, PendingReview)>, anyhow::Error> {
+    let mut searches = search_all(config, state).await?;
+    let mut all = vec![];
+    for project in &config.projects {
+        if state.skip_reason(&project.url)?.is_some() || is_disabled(state, project)? {
+            continue;
+        }
+        let searched = searched(&mut searches, project).and_then(|search| search.take(project).1);
+        let pending_requests = match searched {
+            Some(pending_requests) => pending_requests,
+            None => fetch_pending_requests(&github::instance_for(project), config, project)
+                .await
+                .map(|(pending_requests, _)| pending_requests)
+                .with_context(|| format!("Couldn't fetch pending reviews of {}", project.url))?,
+        };
+        all.extend(pending_requests.into_iter().map(|pull| (project, pull)));
+    }
+    // Oldest first, PRs without a date last.
+    all.sort_by_key(|(_, pull)| (pull.created_at.is_none(), pull.created_at));
+    Ok(all)
+}
 
-    let requests = octocrab
-        .pulls(&project.owner, &project.repo)
-        .list()
-        .state(State::Open)
-        .send()
-        .await
-        .context("Couldn't download open pull requests")?;
+/// A team requested to review a PR of a project, lowercased as `org/slug`. Teams without
+/// an organization belong to the owner of the project.
+fn qualified_team(project: &Project, team: &str) -> String {
+    match team.contains('/') {
+        true => team.to_lowercase(),
+        false => format!("{}/{team}", project.owner).to_lowercase(),
+    }
+}
 
-    // We're only interested in pending requests (i.e. requests with
-    // a pending review).
-    let pending_requests: HashMap<_, _> = requests
-        .into_iter()
-        .filter_map(|pr| match pr.requested_reviewers {
-            Some(ref reviewers) if reviewers.is_empty().not() => Some((*pr.id, pr)),
-            _ => None,
-        })
+/// The members of the teams requested to review these PRs, by `qualified_team`.
+///
+/// Teams that we can't list are taken to be empty, with a warning.
+async fn requested_team_members(all: &[(&Project, PendingReview)]) -> HashMap<String, Vec<String>> {
+    let mut members = HashMap::new();
+    for (project, pull) in all {
+        for team in &pull.teams {
+            let team = qualified_team(project, team);
+            if members.contains_key(&team) {
+                continue;
+            }
+            let (org, slug) = team.split_once('/').expect("teams are qualified");
+            let octocrab = github::instance_for(project);
+            let logins = match github::fetch_team_members(&octocrab, org, slug).await {
+                Ok(logins) => logins,
+                Err(err) => {
+                    warn!("Ignoring the review requests of team {team}: {err:?}");
+                    vec![]
+                }
+            };
+            members.insert(team, logins);
+        }
+    }
+    members
+}
+
+/// Everyone asked to review a PR, lowercased: its reviewers and the members of its teams.
+fn requested_reviewers(
+    project: &Project,
+    pull: &PendingReview,
+    team_members: &HashMap<String, Vec<String>>,
+) -> BTreeSet<String> {
+    let members = pull
+        .teams
+        .iter()
+        .filter_map(|team| team_members.get(&qualified_team(project, team)))
+        .flatten();
+    pull.reviewers
+        .iter()
+        .chain(members)
+        .map(|login| login.to_lowercase())
+        .collect()
+}
+
+/// Print the PRs waiting for a review from `user` (or one of their teams) across all
+/// projects, oldest first.
+async fn mine(config: &Config, state: &State, user: &str) -> Result<(), anyhow::Error> {
+    let all = all_pending_requests(config, state).await?;
+    let team_members = requested_team_members(&all).await;
+    let user = user.to_lowercase();
+    let mine = all.into_iter().filter(|(project, pull)| {
+        requested_reviewers(project, pull, &team_members).contains(&user)
+    });
+    let now = chrono::Utc::now();
+    for (project, pull) in mine {
+        let age = match pull.created_at {
+            Some(created_at) => waiting(config, config.locale_for(project), created_at, now),
+            None => "?".to_string(),
+        };
+        println!(
+            "{age}\t{}/{}#{}\t{}\t{}",
+            project.owner, project.repo, pull.number, pull.title, pull.url
+        );
+    }
+    Ok(())
+}
+
+/// Find a sink of the config by name or alias (or label, e.g. `#reviews` for the channel
+/// of a project), e.g. for `qastor resend --hook`.
+fn find_sink<'a>(
+    secrets: &'a Secrets,
+    state: &State,
+    label: &str,
+) -> Result<Box<dyn Sink + 'a>, anyhow::Error> {
+    let mut candidates: Vec<Box<dyn Sink>> = secrets
+        .repo_to_hook
+        .values()
+        .chain(secrets.channels.values())
+        .flatten()
+        .map(|hook| Box::new(HookSink(hook)) as Box<dyn Sink>)
         .collect();
+    candidates.extend(
+        secrets
+            .matrix
+            .as_ref()
+            .map(|room| Box::new(MatrixSink(room)) as Box<dyn Sink>),
+    );
+    candidates.extend(
+        secrets
+            .telegram
+            .as_ref()
+            .map(|chat| Box::new(TelegramSink(chat)) as Box<dyn Sink>),
+    );
+    if let (Some(name), Some(bot)) = (label.strip_prefix('#'), &secrets.slack_bot) {
+        if let Some(id) = state.channel_id(name)? {
+            candidates.push(Box::new(SlackChannel {
+                token: &bot.token,
+                id,
+                name: name.to_string(),
+            }));
+        }
+    }
+    candidates
+        .into_iter()
+        .find(|sink| sink.label() == label || sink.alias() == Some(label))
+        .ok_or_else(|| anyhow!("No hook {label} in the secrets"))
+}
+
+/// Run an arbitrary GitHub search, e.g. `is:pr review-requested:@me org:owner`, and print the
+/// results as a report section, or post them to a sink.
+///
+/// The search must name an owner with `repo:`, `org:` or `user:`, whose credentials we use,
+/// rather than searching the whole of GitHub.
+async fn query(
+    config: &Config,
+    secrets: &Secrets,
+    state: &State,
+    query: &str,
+    hook: Option<&str>,
+    limit: usize,
+) -> Result<(), anyhow::Error> {
+    // With the client of the projects of the owner, which may be on GitHub Enterprise Server.
+    let Some(owner) = github::search_owner(query) else {
+        return Err(anyhow!(
+            "The search must name an owner with `repo:`, `org:` or `user:`"
+        ));
+    };
+    let octocrab = match config
+        .projects
+        .iter()
+        .find(|project| project.owner.eq_ignore_ascii_case(owner))
+    {
+        Some(project) => github::instance_for(project),
+        None => github::instance(owner),
+    };
+    let issues = github::search_issues(&octocrab, query, limit).await?;
+    let title = config.message(
+        config.locale,
+        "query.title",
+        &[
+            ("count", issues.len().to_string()),
+            ("query", html_escape::encode_text(query).to_string()),
+        ],
+    );
+    let msg = render_issues(
+        config,
+        None,
+        title,
+        issues,
+        &HashMap::new(),
+        &HashMap::new(),
+        Utc::now(),
+    );
+    match hook {
+        Some(label) => {
+            let sink = find_sink(secrets, state, label)?;
+            sinks::post(sink.as_ref(), &Client::new(), &msg, Placement::New)
+                .await
+                .with_context(|| format!("Failed to post the results to {label}"))?;
+            info!("Posted the results to {label}");
+        }
+        None => println!("{}", msg.plain_text()),
+    }
+    Ok(())
+}
 
-    // ...and since requests are also issues, let's make sure that we
-    // don't display them twice.
-    let pending_issues = issues
+/// Send again the messages recorded for a sink during a run, see `replay`.
+async fn resend(
+    config: &Config,
+    secrets: &Secrets,
+    state: &State,
+    run: &str,
+    label: &str,
+) -> Result<(), anyhow::Error> {
+    let Some(ref dir) = config.replay else {
+        return Err(anyhow!("Resending requires `replay` in the config"));
+    };
+    let entries = Replay::load(dir, run)?
         .into_iter()
-        .filter(|issue| pending_requests.contains_key(&*issue.id).not())
+        .filter(|entry| entry.sink == label)
         .collect_vec();
-
-    if pending_issues.is_empty() && pending_requests.is_empty() {
-        debug!("No issues to report");
-        return Ok(());
+    if entries.is_empty() {
+        return Err(anyhow!("Nothing was sent to {label} during run {run}"));
     }
+    let sink = find_sink(secrets, state, label)?;
+    let client = Client::new();
+    let mut count = 0;
+    for entry in entries {
+        for message in &entry.messages {
+            sink.send(&client, message, None)
+                .await
+                .with_context(|| format!("Failed to resend a message about {}", entry.project))?;
+            count += 1;
+        }
+    }
+    println!("Sent {count} messages to {label} again");
+    Ok(())
+}
 
-    if pending_requests.is_empty().not() {
-        let title = format!(
-            "PRs of repo {link} waiting for reviews",
-            link = slack::link(&project.url, Some(project.repo.as_ref())),
-        );
-        let mut msg = slack::Section::new(title);
-        msg.append_fields(&["*Request*".to_string(), "*Reviewer*".to_string()]);
-        for pull in pending_requests.into_values() {
-            let Some(reviewers) = pull.requested_reviewers else {
-                panic!("Inconsistency: we just checked that reviewers as not-None")
-            };
-            let Some(url) = pull.html_url else {
-                error!(
-                    "In project {}, PR {} missing a URL, skipping",
-                    project.url, pull.id
-                );
-                continue;
-            };
-            let Some(title) = pull.title else {
-                error!(
-                    "In project {}, PR {} missing a title, skipping",
-                    project.url, pull.id
-                );
-                continue;
-            };
-            let reviewers = format!(
-                "{}",
-                reviewers
-                    .into_iter()
-                    .map(|reviewer| reviewer.login)
-                    .format(", ")
-            );
-            msg.append_fields(&[slack::link(&url, Some(title.as_str())), reviewers])
+/// Compare the review requests received by each reviewer across all projects over the last
+/// `days` with the reviews they completed, flagging those who received at least `max_share`
+/// of all requests.
+async fn fairness(
+    config: &Config,
+    secrets: &Secrets,
+    state: &State,
+    days: u32,
+    max_share: f64,
+) -> Result<(), anyhow::Error> {
+    let since = Utc::now() - chrono::Duration::days(days.into());
+    let mut activity = vec![];
+    for project in &config.projects {
+        if is_disabled(state, project)? {
+            continue;
         }
-        for hook in slack_hooks {
-            msg.send(client, hook.as_ref())
-                .await
-                .context("Failed to post udpdate on Slack")?;
+        let octocrab = github::instance_for(project);
+        match github::fetch_review_activity(&octocrab, project, since).await {
+            Ok(found) => activity.extend(found),
+            Err(err) => warn!("Could not fetch the reviews of {}: {err:?}", project.url),
         }
     }
-    if pending_issues.is_empty().not() {
-        let title = format!(
-            "Issues of repo {link} updated since {since}",
-            link = slack::link(&project.url, Some(project.repo.as_ref())),
-            since = since.format("%d/%m/%Y %H:%M"),
-        );
-        let mut msg = slack::Section::new(title);
-        msg.append_fields(&["*Issue*".to_string(), "*Updater*".to_string()]);
-        for issue in pending_issues.into_iter() {
+    let tallies = fairness::tally(activity);
+    let imbalances = fairness::imbalances(&tallies, max_share);
+    let mut msg = slack::Section::new(config.message(
+        config.locale,
+        "review_load.title",
+        &[
+            ("count", config.projects.len().to_string()),
+            ("days", days.to_string()),
+        ],
+    ));
+    msg.append_headers(&config.columns(config.locale, ["reviewer", "requested_reviewed"]));
+    for (login, tally) in tallies
+        .iter()
+        .sorted_by_key(|(_, tally)| std::cmp::Reverse(tally.requested))
+    {
+        let flag = match imbalances
+            .iter()
+            .find(|(overloaded, _)| overloaded == login)
+        {
+            Some((_, share)) => format!(" ⚠️ {:.0}% of requests", share * 100.0),
+            None => String::new(),
+        };
+        msg.append_fields(&[
+            format!("{login}{flag}"),
+            format!("{} · {}", tally.requested, tally.reviewed),
+        ]);
+    }
+    match secrets.admin_hook {
+        Some(_) => post_admin(&Client::new(), secrets, &msg).await,
+        None => {
+            println!("{}", msg.plain_text());
+            Ok(())
+        }
+    }
+}
+
+/// Send each reviewer mapped in `user_map` a direct message with the PRs of all projects
+/// waiting for their review (or that of one of their teams).
+async fn remind_reviewers(
+    config: &Config,
+    state: &State,
+    secrets: &Secrets,
+) -> Result<(), anyhow::Error> {
+    let Some(ref bot) = secrets.slack_bot else {
+        return Err(anyhow!(
+            "Reminding reviewers requires a `slack_bot` in the secrets"
+        ));
+    };
+    // By reviewer, case-insensitively, as GitHub logins are.
+    let mut by_reviewer = BTreeMap::<String, Vec<(&Project, &PendingReview)>>::new();
+    let all = all_pending_requests(config, state)
+        .await?
+        .into_iter()
+        .filter(|(project, pull)| is_remindable(config, project, pull))
+        .collect_vec();
+    let team_members = requested_team_members(&all).await;
+    for (project, pull) in &all {
+        for reviewer in requested_reviewers(project, pull, &team_members) {
+            by_reviewer
+                .entry(reviewer)
+                .or_default()
+                .push((project, pull));
+        }
+    }
+    let client = reqwest::Client::new();
+    let now = chrono::Utc::now();
+    for (reviewer, pulls) in by_reviewer {
+        let Some(member) = config.slack_member(&reviewer) else {
+            debug!("No Slack member for {reviewer}, see `user_map`");
+            continue;
+        };
+        let mut msg = slack::Section::new(config.message(
+            config.locale,
+            "review_dms.title",
+            &[("count", pulls.len().to_string())],
+        ));
+        msg.append_headers(&config.columns(config.locale, ["request", "waiting"]));
+        for (project, pull) in pulls {
+            let age = match pull.created_at {
+                Some(created_at) => waiting(config, config.locale, created_at, now),
+                None => "?".to_string(),
+            };
             msg.append_fields(&[
-                slack::link(&issue.html_url, Some(issue.title.as_str())),
                 format!(
-                    "{} on {}",
-                    issue.user.login,
-                    issue.updated_at.format("%d/%m/%Y %H:%M")
+                    "{}/{} {}",
+                    project.owner,
+                    project.repo,
+                    slack::link(&pull.url, Some(&format!("#{} {}", pull.number, pull.title)))
                 ),
-            ])
+                age,
+            ]);
         }
-        for hook in slack_hooks {
-            msg.send(client, hook.as_ref())
-                .await
-                .context("Failed to post udpdate on Slack")?;
+        // Posting to a member id lands in their direct messages with the bot.
+        let mut payload = msg.payload(&slack::MessageOptions::default());
+        let sent = match config.reminder_time(&reviewer, now) {
+            Some(at) => {
+                payload["post_at"] = at.timestamp().into();
+                slack::schedule_message(&client, &bot.token, member, payload).await
+            }
+            None => slack::post_message(&client, &bot.token, member, None, payload).await,
+        };
+        match sent {
+            Ok(_) => info!("Reminded {reviewer} of their pending reviews"),
+            Err(err) => error!("Could not remind {reviewer} of their pending reviews: {err:?}"),
         }
     }
     Ok(())
 }
 
-#[tokio::main]
-async fn main() -> Result<(), anyhow::Error> {
-    env_logger::init();
-    let _ = dotenv::dotenv(); // If there's no .env, let's not load one!
+/// Whether reviewers are reminded of a PR, as the reports of its project list it as waiting
+/// for their review: drafts aren't ready for reviews yet, even those listed in a section of
+/// their own.
+fn is_remindable(config: &Config, project: &Project, pull: &PendingReview) -> bool {
+    shows_request(config, project, pull) && (pull.draft.not() || project.drafts == Drafts::Include)
+}
 
-    // Load secrets.
-    info!("Loading secrets");
-    // Source 1: Big variable `QASTOR_SECRETS`.
-    let env_secrets = std::env::var("QASTOR_SECRETS").unwrap_or_else(|_| "{}".to_string());
-    let mut secrets: Secrets =
-        serde_json::from_str(&env_secrets).context("Invalid env QASTOR_SECRETS")?;
-
-    // Source 2: any variable `QASTOR_HOOK.*` can contain a mapping
-    for (key, value) in std::env::vars() {
-        if key.starts_with("QASTOR_HOOK") {
-            let project_to_hook = ProjectToHook::from_env_var(&value)
-                .with_context(|| format!("Invalid env variable {key}:{value}"))?;
-            secrets
-                .repo_to_hook
-                .entry(project_to_hook.project)
-                .or_default()
-                .push(project_to_hook.hook);
+/// Whether a project is disabled, by `qastor disable` or else by the config.
+fn is_disabled(state: &State, project: &Project) -> Result<bool, anyhow::Error> {
+    Ok(state.disabled(&project.url)?.unwrap_or(project.disabled))
+}
+
+/// Write the due dates of the open milestones of all projects to an iCalendar file.
+async fn calendar(
+    config: &Config,
+    state: &State,
+    output: &std::path::Path,
+) -> Result<(), anyhow::Error> {
+    let mut events = vec![];
+    for project in &config.projects {
+        if state.skip_reason(&project.url)?.is_some() || is_disabled(state, project)? {
+            continue;
+        }
+        let per_page = config.per_page(project).unwrap_or(100);
+        let milestones =
+            github::fetch_milestones(&github::instance_for(project), project, per_page)
+                .await
+                .with_context(|| format!("Couldn't fetch milestones of {}", project.url))?;
+        events.extend(milestones.into_iter().filter_map(|milestone| {
+            let date = milestone.due_on?.date_naive();
+            Some(calendar::Event {
+                uid: format!(
+                    "{}-{}-milestone-{}@qastor",
+                    project.owner, project.repo, milestone.number
+                ),
+                summary: format!("{}: {}", project.repo, milestone.title),
+                description: format!(
+                    "{} open issues, {} closed",
+                    milestone.open_issues.unwrap_or_default(),
+                    milestone.closed_issues.unwrap_or_default()
+                ),
+                url: milestone.html_url,
+                date,
+            })
+        }));
+    }
+    events.sort_by(|a, b| (a.date, &a.uid).cmp(&(b.date, &b.uid)));
+    std::fs::write(output, calendar::render(&events, Utc::now()))
+        .with_context(|| format!("Could not write {}", output.display()))?;
+    info!("Wrote {} deadlines to {}", events.len(), output.display());
+    Ok(())
+}
+
+/// If the config has changed and hasn't been promoted yet, the preview hook, which should
+/// receive all the reports instead of the usual hooks.
+async fn preview_hook<'a>(
+    client: &Client,
+    config: &Config,
+    secrets: &'a Secrets,
+    state: &mut State,
+) -> Result<Option<&'a Hook>, anyhow::Error> {
+    let Some(ref hook) = secrets.preview_hook else {
+        return Ok(None);
+    };
+    if state.promoted()?.as_ref() == Some(&config.fingerprint) {
+        return Ok(None);
+    }
+    let now = Utc::now();
+    let preview = match state.preview()? {
+        Some(preview) if preview.config == config.fingerprint => preview,
+        _ => {
+            let preview = Preview {
+                config: config.fingerprint.clone(),
+                since: now,
+            };
+            state.set_preview(&preview)?;
+            notify_admin(
+                client,
+                secrets,
+                format!(
+                    "The config has changed, reports go to the preview hook until it's promoted with `qastor promote` (config {})",
+                    config.fingerprint
+                ),
+            )
+            .await?;
+            preview
+        }
+    };
+    if let Some(timeout) = config.preview_timeout {
+        if now - preview.since >= timeout {
+            info!("Promoting config {} after preview", config.fingerprint);
+            state.set_promoted(&config.fingerprint)?;
+            return Ok(None);
+        }
+    }
+    info!(
+        "Previewing config {}, run `qastor promote` to enable normal delivery",
+        config.fingerprint
+    );
+    Ok(Some(hook))
+}
+
+/// Check all the projects of an app, with its state.
+async fn run(command: &Command, app: &App, state: &mut State) -> Result<RunSummary, anyhow::Error> {
+    let App {
+        ref config,
+        ref secrets,
+        ref templates,
+        ..
+    } = *app;
+    github::authenticate(secrets, &config.projects).await?;
+    match command {
+        Command::Mine { ref user } => {
+            mine(config, state, user).await?;
+            return Ok(RunSummary::default());
+        }
+        Command::Calendar { ref output } => {
+            calendar(config, state, &app.path(output)).await?;
+            return Ok(RunSummary::default());
+        }
+        Command::SyncUsergroups => {
+            sync_usergroups(config, secrets).await?;
+            return Ok(RunSummary::default());
+        }
+        Command::RemindReviewers => {
+            remind_reviewers(config, state, secrets).await?;
+            return Ok(RunSummary::default());
+        }
+        Command::Resend { ref run, ref hook } => {
+            resend(config, secrets, state, run, hook).await?;
+            return Ok(RunSummary::default());
+        }
+        Command::Query {
+            ref query,
+            ref hook,
+            limit,
+        } => {
+            self::query(config, secrets, state, query, hook.as_deref(), *limit).await?;
+            return Ok(RunSummary::default());
+        }
+        Command::Fairness { days, max_share } => {
+            fairness(config, secrets, state, *days, *max_share).await?;
+            return Ok(RunSummary::default());
+        }
+        Command::Snooze { ref project, until } => {
+            if config
+                .projects
+                .iter()
+                .all(|configured| &configured.url != project)
+            {
+                return Err(anyhow!("Project {project} is not in the config"));
+            }
+            let until = until.and_time(chrono::NaiveTime::MIN).and_utc();
+            state.set_snoozed_until(project, None, until)?;
+            state.save()?;
+            println!("Snoozed {project} until {until}");
+            return Ok(RunSummary::default());
+        }
+        Command::Disable { ref project } | Command::Enable { ref project } => {
+            if config
+                .projects
+                .iter()
+                .all(|configured| &configured.url != project)
+            {
+                return Err(anyhow!("Project {project} is not in the config"));
+            }
+            match command {
+                Command::Disable { .. } => {
+                    state.set_disabled(project, true)?;
+                    println!("Disabled {project}");
+                }
+                _ => {
+                    state.clear_disabled(project)?;
+                    println!("Enabled {project}, unless the config disables it");
+                }
+            }
+            state.save()?;
+            return Ok(RunSummary::default());
+        }
+        Command::Promote => {
+            state.set_promoted(&config.fingerprint)?;
+            state.save()?;
+            println!("Promoted config {}", config.fingerprint);
+            return Ok(RunSummary::default());
+        }
+        Command::Run { .. } | Command::Report | Command::Backfill { .. } | Command::Digest => {}
+        Command::Serve
+        | Command::WindowsService { .. }
+        | Command::Launchd { .. }
+        | Command::Check
+        | Command::CheckAccess => {
+            unreachable!("not a single run")
         }
     }
+    // Reports printed on demand don't post anything nor touch the state.
+    let print = matches!(command, Command::Report);
 
-    // Load config.
-    info!("Loading config");
-    let file_config = std::fs::File::open("config.yml").context("Could not open config.yml")?;
-    let config: Config = serde_yaml::from_reader(file_config).context("Invalid config.yml")?;
+    if secrets.github_token.is_none() && secrets.github_app.is_none() {
+        info!("No GitHub token, only public repositories are visible");
+    }
 
     let client = reqwest::Client::new();
+    let started = Instant::now();
+    let mut metrics = Metrics::default();
+    let mut archive = Archive::new(config.archive.clone());
+    let mut replay = Replay::new(config.replay.clone(), Utc::now());
+    let enricher = Enricher::new(&config.enrichment);
+
+    let preview = match command {
+        Command::Run { .. } => preview_hook(&client, config, secrets, state).await?,
+        _ => None,
+    };
+    // Previews and printed reports leave the state alone, so that their items are reported
+    // as usual afterwards, whatever the backend of the state.
+    state.set_scratch(preview.is_some() || print);
+    if matches!(command, Command::Run { .. }) {
+        if let Err(err) = check_release(&client, config, secrets, state).await {
+            warn!("Could not check for new releases of qastor: {err:?}");
+        }
+    }
+
+    let mut searches = match command {
+        Command::Run { .. } | Command::Report => search_all(config, state).await?,
+        _ => HashMap::new(),
+    };
 
+    let mut summary = RunSummary::default();
     for project in &config.projects {
+        if let Some(reason) = state.skip_reason(&project.url)? {
+            info!(
+                "Skipping project {}, which is {reason} (see state {})",
+                project.url, config.state
+            );
+            summary.record(&project.url, ProjectOutcome::skipped(reason));
+            continue;
+        }
+        if is_disabled(state, project)? {
+            info!("Skipping project {}, which is disabled", project.url);
+            summary.record(&project.url, ProjectOutcome::skipped("disabled"));
+            continue;
+        }
+        // Projects may be checked less often than others, but runs on demand are always up
+        // to date.
+        if matches!(command, Command::Run { scheduled: true })
+            && server::take_request(&project.owner, project.repo.as_ref()).not()
+            && daemon::is_due(
+                Utc::now(),
+                state.last_run(&project.url)?,
+                config.update_frequency_for(project),
+            )
+            .not()
+        {
+            debug!("Project {} is not due yet, skipping", project.url);
+            summary.record(&project.url, ProjectOutcome::skipped("not due"));
+            continue;
+        }
         info!("Checking project {}", project.url);
-        if let Err(err) = per_project(&client, &secrets, project, &config).await {
+        let channel = match print {
+            true => None,
+            false => project_channel(&client, config, secrets, state, project)
+                .await
+                .unwrap_or_else(|err| {
+                    error!(
+                        "Could not set up the Slack channel of {}: {err:?}",
+                        project.url
+                    );
+                    None
+                }),
+        };
+        let pinned = channel.clone().filter(|_| {
+            config
+                .project_channels
+                .as_ref()
+                .is_some_and(|channels| channels.pinned_summary)
+                && preview.is_none()
+        });
+        let snoozed = state
+            .snoozed_until(&project.url, None)?
+            .is_some_and(|until| until > chrono::Utc::now());
+        if snoozed {
+            info!(
+                "Project {} is snoozed, only critical sections go out",
+                project.url
+            );
+        }
+        let outbox = Outbox {
+            client: &client,
+            templates,
+            snoozed,
+            summary: pinned,
+            sla: &config.sla,
+            hooks: secrets
+                .repo_to_hook
+                .get(&project.url)
+                .into_iter()
+                .flatten()
+                .collect(),
+            sinks: channel
+                .map(|channel| Box::new(channel) as Box<dyn Sink>)
+                .into_iter()
+                .chain(
+                    secrets
+                        .matrix
+                        .as_ref()
+                        .map(|room| Box::new(MatrixSink(room)) as Box<dyn Sink>),
+                )
+                .chain(
+                    secrets
+                        .telegram
+                        .as_ref()
+                        .map(|chat| Box::new(TelegramSink(chat)) as Box<dyn Sink>),
+                )
+                .collect(),
+            // While previewing, the preview hook receives the sections instead.
+            email: match (&secrets.smtp, preview, print) {
+                (Some(smtp), None, false) if project.email.is_empty().not() => Some(Email {
+                    smtp,
+                    to: &project.email,
+                    report: Report::default(),
+                }),
+                _ => None,
+            },
+            preview,
+            print,
+            metrics: metrics.project(&project.url),
+            archive: &mut archive,
+            replay: &mut replay,
+            sent: BTreeMap::new(),
+            delivered: BTreeSet::new(),
+            threads: match command {
+                Command::Run { .. } | Command::Report
+                    if config.thread_follow_ups
+                        && config.update_in_place.not()
+                        && config.deliver_at.is_none()
+                        && preview.is_none()
+                        && print.not() =>
+                {
+                    Some(state.threads(&project.url, chrono::Utc::now().date_naive())?)
+                }
+                _ => None,
+            },
+            live: match command {
+                Command::Run { .. } | Command::Report
+                    if config.update_in_place
+                        && config.deliver_at.is_none()
+                        && preview.is_none()
+                        && print.not() =>
+                {
+                    Some(Live {
+                        messages: state.live_messages(&project.url)?,
+                        current: BTreeSet::new(),
+                    })
+                }
+                _ => None,
+            },
+            // Previews are meant to be seen right away.
+            post_at: match preview {
+                Some(_) => None,
+                None => config
+                    .deliver_at
+                    .as_ref()
+                    .map(|delivery| delivery.next(chrono::Utc::now())),
+            },
+            scheduled: ScheduledMessages::new(),
+        };
+        let result = match command {
+            Command::Run { .. } | Command::Report => {
+                per_project(
+                    secrets,
+                    project,
+                    config,
+                    state,
+                    searched(&mut searches, project),
+                    &enricher,
+                    outbox,
+                )
+                .await
+            }
+            Command::Mine { .. }
+            | Command::Promote
+            | Command::Calendar { .. }
+            | Command::SyncUsergroups
+            | Command::Snooze { .. }
+            | Command::Disable { .. }
+            | Command::Enable { .. }
+            | Command::RemindReviewers
+            | Command::Fairness { .. }
+            | Command::Resend { .. }
+            | Command::Query { .. }
+            | Command::Serve
+            | Command::WindowsService { .. }
+            | Command::Launchd { .. }
+            | Command::Check
+            | Command::CheckAccess => unreachable!("handled above"),
+            Command::Backfill { since } => {
+                backfill_project(secrets, project, config, state, *since, &enricher, outbox).await
+            }
+            Command::Digest => digest_project(secrets, project, config, state, outbox).await,
+        };
+        let outcome = result.unwrap_or_else(|err| {
             warn!(
                 "Error handling project {}/{}: {:?}",
                 project.owner, project.repo, err
-            )
+            );
+            ProjectOutcome::failed(&err)
+        });
+        let project_metrics = metrics.project(&project.url);
+        project_metrics.items = outcome.items;
+        project_metrics.error = outcome.error.as_ref().map(|error| error.class);
+        summary.record(&project.url, outcome);
+        // Project by project, so that what we sent can be resent whatever happens next.
+        if let Err(err) = replay.save() {
+            warn!("Could not record messages: {err:?}");
+        }
+        if print.not() {
+            state.save()?;
         }
     }
-    info!("Done");
+    state.set_scratch(false);
+    if print {
+        info!("{}", metrics.summary(started.elapsed()));
+        return Ok(summary);
+    }
+    let alerts = summary
+        .admin_alerts()
+        .map(|(url, error)| format!("• {url}: {}", error.message))
+        .join("\n");
+    if alerts.is_empty().not() {
+        notify_admin(
+            &client,
+            secrets,
+            format!("Could not handle some repos, please check the configuration:\n{alerts}"),
+        )
+        .await?;
+    }
+    if let (Some(board), Command::Run { .. }) = (&secrets.status_board, command) {
+        let mut previous = HashMap::new();
+        for (url, outcome) in &summary.projects {
+            match (&outcome.skipped, &outcome.error) {
+                (Some(_), _) => {
+                    if let Some(counts) = state.board_counts(url)? {
+                        previous.insert(url.clone(), counts);
+                    }
+                }
+                (None, None) => state.set_board_counts(url, &outcome.sections)?,
+                (None, Some(_)) => {}
+            }
+        }
+        let msg = render_status_board(config, &summary, &previous, Utc::now());
+        if let Err(err) = update_status_board(&client, board, state, &msg).await {
+            warn!("Could not update the status board: {err:?}");
+        }
+        state.save()?;
+    }
+    archive.commit().context("Could not archive reports")?;
+    if config.replay.is_some() {
+        info!(
+            "Messages recorded as run {}, see `qastor resend`",
+            replay.run()
+        );
+    }
+    info!("{}", metrics.summary(started.elapsed()));
+    server::publish(app.tenant.as_deref(), metrics.samples(started.elapsed()));
+    Ok(summary)
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    if let Err(err) = init(&cli) {
+        eprintln!("Error: {err:?}");
+        std::process::exit(Error::class(&err).exit_code());
+    }
+    match try_main(cli).await {
+        Ok(0) => {}
+        Ok(code) => std::process::exit(code),
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            std::process::exit(Error::class(&err).exit_code());
+        }
+    }
+}
+
+/// Move to `--dir`, load its `.env`, if any, and set up logging, to `--log-file` if
+/// specified.
+fn init(cli: &Cli) -> Result<(), anyhow::Error> {
+    if let Some(ref dir) = cli.dir {
+        std::env::set_current_dir(dir)
+            .map_err(|err| Error::Config(format!("Could not move to {}: {err}", dir.display())))?;
+    }
+    let _ = dotenv::dotenv(); // If there's no .env, let's not load one!
+    let mut logger = env_logger::Builder::from_default_env();
+    if let Some(ref path) = cli.log_file {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|err| Error::Config(format!("Could not open {}: {err}", path.display())))?;
+        logger.target(env_logger::Target::Pipe(Box::new(file)));
+    }
+    logger.init();
     Ok(())
 }
+
+/// Load `config.yml`, in single-tenant mode.
+fn load_config() -> Result<Config, anyhow::Error> {
+    let source = std::fs::read_to_string("config.yml").context("Could not open config.yml")?;
+    Config::parse(&source)
+        .map_err(|err| Error::Config(format!("{err:#}")))
+        .context("Invalid config.yml")
+}
+
+/// The config and secrets of a tenant (or of the single-tenant setup), which every
+/// command starts from.
+struct App {
+    /// The name of the tenant, `None` in single-tenant mode.
+    tenant: Option<String>,
+
+    /// The directory of the tenant, in which relative output paths are resolved.
+    dir: Option<PathBuf>,
+    config: Config,
+    secrets: Secrets,
+
+    /// The templates of `config.templates` and of the projects, if any.
+    templates: Templates,
+}
+impl App {
+    /// Load `config.yml` and the secrets from the environment, in single-tenant mode.
+    fn load() -> Result<Self, anyhow::Error> {
+        info!("Loading secrets");
+        let secrets = Secrets::from_env()?;
+
+        info!("Loading config");
+        let config = load_config()?;
+        App {
+            tenant: None,
+            dir: None,
+            config,
+            secrets,
+            templates: Templates::default(),
+        }
+        .load_templates()
+    }
+
+    /// Load the config and the secrets of a tenant.
+    fn load_tenant(tenant: &Tenant) -> Result<Self, anyhow::Error> {
+        info!("Loading tenant {}", tenant.name);
+        App {
+            tenant: Some(tenant.name.clone()),
+            dir: Some(tenant.dir.clone()),
+            config: tenant.load_config()?,
+            secrets: tenant.load_secrets()?,
+            templates: Templates::default(),
+        }
+        .load_templates()
+    }
+
+    /// Read the templates of the config, if any.
+    fn load_templates(mut self) -> Result<Self, anyhow::Error> {
+        if let Some(ref dir) = self.config.templates {
+            info!("Loading templates");
+            self.templates = Templates::load(&self.path(dir)).context("Invalid templates")?;
+        }
+        for project in &self.config.projects {
+            if let Some(ref dir) = project.templates {
+                let templates = Templates::load(&self.path(dir))
+                    .with_context(|| format!("Invalid templates of {}", project.url))?;
+                self.templates.set_project(dir, templates);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Resolve an output path, e.g. `--emit-report`, within the directory of the tenant.
+    fn path(&self, path: &std::path::Path) -> PathBuf {
+        match self.dir {
+            Some(ref dir) => dir.join(path),
+            None => path.to_path_buf(),
+        }
+    }
+}
+
+/// Returns the exit code of the process.
+async fn try_main(cli: Cli) -> Result<i32, anyhow::Error> {
+    match cli
+        .command
+        .clone()
+        .unwrap_or(Command::Run { scheduled: false })
+    {
+        Command::Run { .. } if cli.daemon => {
+            warn!("`--daemon` is deprecated, use `qastor serve`");
+            serve(&cli).await
+        }
+        _ if cli.daemon => Err(Error::Config("--daemon only applies to `run`".to_string()).into()),
+        Command::Serve => serve(&cli).await,
+        Command::WindowsService { ref name } => windows_service(&cli, name).await,
+        Command::Launchd { ref label } => launchd(&cli, label),
+        Command::Check => check(&cli),
+        Command::CheckAccess => check_access(&cli).await,
+        command => run_once(&cli, &command).await,
+    }
+}
+
+/// A tenant (or the single-tenant setup) by name, if it could be loaded.
+type NamedApp = (String, Result<App, anyhow::Error>);
+
+/// Load each tenant (or the single-tenant setup).
+///
+/// Tenants that fail to load don't prevent loading the others.
+fn load_apps(cli: &Cli) -> Result<Vec<NamedApp>, anyhow::Error> {
+    Ok(match cli.tenants {
+        None => vec![("config.yml".to_string(), App::load())],
+        Some(ref root) => Tenant::list(root)?
+            .iter()
+            .map(|tenant| (tenant.name.clone(), App::load_tenant(tenant)))
+            .collect(),
+    })
+}
+
+/// Validate the config and the secrets of each tenant (or of the single-tenant setup).
+///
+/// Returns the exit code of the process.
+fn check(cli: &Cli) -> Result<i32, anyhow::Error> {
+    let mut exit_code = 0;
+    for (name, app) in load_apps(cli)? {
+        let problems = match app {
+            Ok(app) => app.secrets.problems(&app.config),
+            Err(err) => vec![format!("{err:#}")],
+        };
+        if problems.is_empty() {
+            println!("{name}: ok");
+            continue;
+        }
+        for problem in problems {
+            println!("{name}: {problem}");
+        }
+        exit_code = ErrorClass::Config.exit_code();
+    }
+    Ok(exit_code)
+}
+
+/// The API calls that the report of a project depends on, given the config.
+fn access_probes(config: &Config) -> Vec<github::Probe> {
+    let mut probes = vec![
+        github::Probe {
+            what: "list issues",
+            path: "issues",
+            permission: "Issues: read",
+        },
+        github::Probe {
+            what: "list pull requests",
+            path: "pulls",
+            permission: "Pull requests: read",
+        },
+    ];
+    if config.milestone_alarm_days.is_some() {
+        probes.push(github::Probe {
+            what: "list milestones",
+            path: "milestones",
+            permission: "Issues: read",
+        });
+    }
+    if config.label_alerts.is_empty().not() {
+        probes.push(github::Probe {
+            what: "list issue events",
+            path: "issues/events",
+            permission: "Issues: read",
+        });
+    }
+    if config.ci.is_some() {
+        probes.push(github::Probe {
+            what: "list workflow runs",
+            path: "actions/runs",
+            permission: "Actions: read",
+        });
+    }
+    let security = config
+        .projects
+        .iter()
+        .filter_map(|project| project.security.as_ref())
+        .collect_vec();
+    if security.is_empty().not() {
+        probes.push(github::Probe {
+            what: "list Dependabot alerts",
+            path: "dependabot/alerts",
+            permission: "Dependabot alerts: read",
+        });
+    }
+    if security.iter().any(|security| security.code_scanning) {
+        probes.push(github::Probe {
+            what: "list code scanning alerts",
+            path: "code-scanning/alerts",
+            permission: "Code scanning alerts: read",
+        });
+    }
+    if security.iter().any(|security| security.secret_scanning) {
+        probes.push(github::Probe {
+            what: "list secret scanning alerts",
+            path: "secret-scanning/alerts",
+            permission: "Secret scanning alerts: read",
+        });
+    }
+    probes
+}
+
+/// Check that the credentials of an app give access to everything that the reports of
+/// its projects need.
+///
+/// Returns the problems of each project, if any.
+async fn access_problems(app: &App) -> Result<Vec<(&Project, Vec<String>)>, anyhow::Error> {
+    github::authenticate(&app.secrets, &app.config.projects).await?;
+    let probes = access_probes(&app.config);
+    let mut problems = vec![];
+    for project in &app.config.projects {
+        let octocrab = github::instance_for(project);
+        problems.push((
+            project,
+            github::check_access(&octocrab, project, &probes).await,
+        ));
+    }
+    Ok(problems)
+}
+
+/// Check the access of each tenant (or of the single-tenant setup) to its projects.
+///
+/// Returns the exit code of the process.
+async fn check_access(cli: &Cli) -> Result<i32, anyhow::Error> {
+    let mut exit_code = 0;
+    for (name, app) in load_apps(cli)? {
+        let problems = match app {
+            Ok(ref app) => access_problems(app).await,
+            Err(err) => Err(err),
+        };
+        let problems = match problems {
+            Ok(problems) => problems,
+            Err(err) => {
+                println!("{name}: {err:#}");
+                exit_code = Error::class(&err).exit_code();
+                continue;
+            }
+        };
+        for (project, problems) in problems {
+            if problems.is_empty() {
+                println!("{name}: {}: ok", project.url);
+            }
+            for problem in problems {
+                println!("{name}: {}: {problem}", project.url);
+                exit_code = ErrorClass::Auth.exit_code();
+            }
+        }
+    }
+    Ok(exit_code)
+}
+
+/// Run `serve` as a Windows service, until the service is stopped.
+#[cfg(windows)]
+async fn windows_service(cli: &Cli, name: &str) -> Result<i32, anyhow::Error> {
+    let runtime = tokio::runtime::Handle::current();
+    let cli = cli.clone();
+    let name = name.to_string();
+    // The Service Control Manager blocks the thread that connects to it, and runs the
+    // service on a thread of its own.
+    tokio::task::spawn_blocking(move || {
+        qastor::service::run(&name, move || {
+            runtime.block_on(serve(&cli)).unwrap_or_else(|err| {
+                error!("Service failed: {err:?}");
+                Error::class(&err).exit_code()
+            })
+        })
+    })
+    .await
+    .context("The service panicked")?
+}
+
+#[cfg(not(windows))]
+async fn windows_service(_cli: &Cli, _name: &str) -> Result<i32, anyhow::Error> {
+    Err(Error::Config("`windows-service` is only available on Windows".to_string()).into())
+}
+
+/// Print a launchd agent running `serve` from the current directory.
+fn launchd(cli: &Cli, label: &str) -> Result<i32, anyhow::Error> {
+    let exe = std::env::current_exe().context("Could not find the qastor executable")?;
+    let dir = std::env::current_dir().context("Could not find the current directory")?;
+    let log = dir.join(cli.log_file.as_deref().unwrap_or("qastor.log".as_ref()));
+    print!("{}", daemon::launchd_plist(label, &exe, &dir, &log));
+    Ok(0)
+}
+
+/// Run until SIGTERM, once every `update_frequency`.
+///
+/// The config and secrets are reloaded before each run, so that they may change
+/// without a restart. A run in progress is completed before shutting down.
+async fn serve(cli: &Cli) -> Result<i32, anyhow::Error> {
+    /// In multi-tenant mode, how often we check which tenants are due.
+    const TENANTS_TICK: chrono::Duration = chrono::Duration::minutes(5);
+    let (stop, mut stopping) = tokio::sync::watch::channel(false);
+    tokio::spawn(async move {
+        daemon::shutdown_signal().await;
+        let _ = stop.send(true);
+    });
+    // Better to find out now than with opaque errors in the middle of a run.
+    for (name, app) in load_apps(cli)? {
+        let Ok(app) = app else {
+            continue;
+        };
+        match access_problems(&app).await {
+            Ok(problems) => {
+                for (project, problem) in problems
+                    .into_iter()
+                    .flat_map(|(project, problems)| problems.into_iter().map(move |p| (project, p)))
+                {
+                    warn!("{name}: {}: {problem}", project.url);
+                }
+            }
+            Err(err) => warn!("{name}: could not check access: {err:?}"),
+        }
+    }
+    if let Some(address) = cli.listen {
+        let secrets = load_apps(cli)?
+            .into_iter()
+            .filter_map(|(_, app)| app.ok()?.secrets.webhook_secret)
+            .collect();
+        server::listen(address, secrets).await?;
+    }
+    loop {
+        match run_once(cli, &Command::Run { scheduled: true }).await {
+            Ok(0) => {}
+            Ok(code) => warn!("Run failed with exit code {code}, trying again later"),
+            Err(err) => error!("Run failed, trying again later: {err:?}"),
+        }
+        if *stopping.borrow() {
+            break;
+        }
+        let period = match cli.tenants {
+            Some(_) => TENANTS_TICK,
+            None => load_config()
+                .map(|config| config.shortest_update_frequency())
+                .unwrap_or(TENANTS_TICK),
+        };
+        let delay = daemon::next_delay(period);
+        info!("Next run in {}s", delay.as_secs());
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = server::requested() => info!("Running early for a webhook"),
+            _ = stopping.changed() => break,
+        }
+    }
+    info!("Shutting down");
+    Ok(0)
+}
+
+/// Run a command once for an app.
+///
+/// Returns the exit code of the run.
+async fn run_app(cli: &Cli, command: &Command, app: &App) -> Result<i32, anyhow::Error> {
+    let mut state = State::open(&app.config.state)?;
+    let now = Utc::now();
+    // Each tenant runs on its own schedule.
+    let scheduled = app.tenant.is_some() && matches!(command, Command::Run { scheduled: true });
+    let requested = app
+        .config
+        .projects
+        .iter()
+        .any(|project| server::is_requested(&project.owner, project.repo.as_ref()));
+    if scheduled
+        && requested.not()
+        && daemon::is_due(
+            now,
+            state.last_full_run()?,
+            app.config.shortest_update_frequency(),
+        )
+        .not()
+    {
+        info!(
+            "Skipping tenant {}, which is not due yet",
+            app.tenant.as_deref().unwrap_or_default()
+        );
+        return Ok(0);
+    }
+    let summary = run(command, app, &mut state).await?;
+    if scheduled {
+        state.set_last_full_run(now)?;
+        state.save()?;
+    }
+    if let Some(ref path) = cli.emit_report {
+        summary.write(&app.path(path))?;
+    }
+    Ok(summary.exit_code())
+}
+
+/// Run a command once for all projects (or all tenants).
+///
+/// Returns the exit code of the run.
+async fn run_once(cli: &Cli, command: &Command) -> Result<i32, anyhow::Error> {
+    let Some(ref root) = cli.tenants else {
+        let exit_code = run_app(cli, command, &App::load()?).await?;
+        info!("Done");
+        return Ok(exit_code);
+    };
+
+    // Tenants are handled one after the other, each with its own config, secrets and
+    // state, so that an error in one of them doesn't affect the others.
+    let mut states = HashSet::new();
+    let mut exit_code = 0;
+    for tenant in Tenant::list(root)? {
+        let result = async {
+            let app = App::load_tenant(&tenant)?;
+            if states.insert(format!("{:?}", app.config.state)).not() {
+                return Err(anyhow!(
+                    "State {} is shared with another tenant",
+                    app.config.state
+                ));
+            }
+            run_app(cli, command, &app).await
+        }
+        .await;
+        match result {
+            Ok(code) if exit_code == 0 => exit_code = code,
+            Ok(_) => {}
+            Err(err) => {
+                error!("Error handling tenant {}: {:?}", tenant.name, err);
+                if exit_code == 0 {
+                    exit_code = Error::class(&err).exit_code();
+                }
+            }
+        }
+    }
+    info!("Done");
+    Ok(exit_code)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::ops::Not;
+
+    use qastor::config::Config;
+    use qastor::github::PendingReview;
+
+    use super::{is_remindable, requested_reviewers};
+
+    /// Are the members of requested teams asked to review, whether or not teams name their
+    /// organization?
+    #[test]
+    fn test_requested_reviewers() {
+        let config = Config::parse(
+            r#"
+projects:
+  - url: "https://github.com/owner1/project1"
+"#,
+        )
+        .unwrap();
+        let project = &config.projects[0];
+        let pull = PendingReview {
+            number: 1,
+            url: "https://github.com/owner1/project1/pull/1".parse().unwrap(),
+            title: "Fix".to_string(),
+            reviewers: vec!["Alice".to_string()],
+            teams: vec!["sdk".to_string(), "other/docs".to_string()],
+            labels: vec![],
+            body: None,
+            created_at: None,
+            updated_at: None,
+            author: None,
+            draft: false,
+            review_comments: None,
+        };
+        let team_members = HashMap::from([
+            (
+                "owner1/sdk".to_string(),
+                vec!["Bob".to_string(), "alice".to_string()],
+            ),
+            ("other/docs".to_string(), vec!["carol".to_string()]),
+        ]);
+        assert_eq!(
+            requested_reviewers(project, &pull, &team_members)
+                .into_iter()
+                .collect::<Vec<_>>(),
+            ["alice", "bob", "carol"]
+        );
+    }
+
+    /// Are reviewers only reminded of the PRs that the reports list as waiting for them?
+    #[test]
+    fn test_is_remindable() {
+        let config = Config::parse(
+            r#"
+projects:
+  - url: "https://github.com/owner1/project1"
+    exclude_labels: [wontfix]
+    drafts: separate
+  - url: "https://github.com/owner1/project2"
+ignore_authors: ["renovate[bot]"]
+"#,
+        )
+        .unwrap();
+        let pull = PendingReview {
+            number: 1,
+            url: "https://github.com/owner1/project1/pull/1".parse().unwrap(),
+            title: "Fix".to_string(),
+            reviewers: vec!["alice".to_string()],
+            teams: vec![],
+            labels: vec![],
+            body: None,
+            created_at: None,
+            updated_at: None,
+            author: Some("bob".to_string()),
+            draft: false,
+            review_comments: None,
+        };
+        let (project1, project2) = (&config.projects[0], &config.projects[1]);
+        assert!(is_remindable(&config, project1, &pull));
+        let excluded = PendingReview {
+            labels: vec!["wontfix".to_string()],
+            ..pull.clone()
+        };
+        assert!(is_remindable(&config, project1, &excluded).not());
+        let ignored = PendingReview {
+            author: Some("renovate[bot]".to_string()),
+            ..pull.clone()
+        };
+        assert!(is_remindable(&config, project1, &ignored).not());
+        let draft = PendingReview {
+            draft: true,
+            ..pull.clone()
+        };
+        assert!(is_remindable(&config, project1, &draft).not());
+        assert!(is_remindable(&config, project2, &draft));
+    }
+}
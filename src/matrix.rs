@@ -0,0 +1,96 @@
+use std::{
+    ops::Not,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use anyhow::{anyhow, Context};
+use itertools::Itertools;
+use log::debug;
+use reqwest::Client;
+use serde_json::json;
+
+use crate::{
+    config::MatrixRoom,
+    error::Error,
+    slack::{self, MessageOptions},
+};
+
+/// Render a section as plain text, for clients that don't display HTML.
+fn plain(section: &slack::Section) -> String {
+    let options = &MessageOptions::default();
+    let mut text = slack::to_markdown(section.title(), options);
+    for row in &section.fields().chunks(2) {
+        text.push('\n');
+        text.push_str(
+            &row.map(|field| slack::to_markdown(field, options))
+                .join(" · "),
+        );
+    }
+    text
+}
+
+/// Render a section as a notice, formatted as HTML.
+pub fn payload(section: &slack::Section) -> serde_json::Value {
+    json!({
+        "msgtype": "m.notice",
+        "body": plain(section),
+        "format": "org.matrix.custom.html",
+        "formatted_body": section.to_html(),
+    })
+}
+
+/// Send a message (see `payload`) to a Matrix room.
+///
+/// Returns the number of bytes posted.
+pub async fn send(
+    client: &Client,
+    room: &MatrixRoom,
+    payload: &serde_json::Value,
+) -> Result<usize, anyhow::Error> {
+    // Transaction ids let the homeserver deduplicate retries, they must be unique per token.
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let txn_id = format!(
+        "qastor-{}-{}",
+        chrono::Utc::now().timestamp_micros(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    let mut url = room.homeserver.clone();
+    url.path_segments_mut()
+        .map_err(|()| anyhow!("Invalid homeserver {}", room.homeserver))?
+        .pop_if_empty()
+        .extend([
+            "_matrix",
+            "client",
+            "v3",
+            "rooms",
+            &room.room_id,
+            "send",
+            "m.room.message",
+            &txn_id,
+        ]);
+    debug!(
+        "Sending: {}",
+        serde_json::to_string_pretty(payload).unwrap()
+    );
+    let body = serde_json::to_vec(payload).context("Could not serialize message")?;
+    let posted_bytes = body.len();
+    let response = client
+        .put(url)
+        .bearer_auth(&room.access_token)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body)
+        .send()
+        .await
+        .context("Error while posting message to Matrix")?;
+    let status = response.status();
+    if status.is_success().not() {
+        let body = response.text().await.context("Could not gather response")?;
+        return Err(Error::SinkRejected {
+            service: "Matrix".to_string(),
+            status,
+            body,
+        }
+        .into());
+    }
+    Ok(posted_bytes)
+}
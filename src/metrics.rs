@@ -0,0 +1,213 @@
+use std::{fmt::Write, ops::Not, time::Duration};
+
+use itertools::Itertools;
+use url::Url;
+
+use crate::error::ErrorClass;
+
+/// What we measured while handling a single project.
+#[derive(Default, Debug, Clone)]
+pub struct ProjectMetrics {
+    /// Time spent fetching data from GitHub.
+    pub fetch: Duration,
+
+    /// Number of issues and PRs reported.
+    pub items: usize,
+
+    /// Number of bytes posted to hooks.
+    pub posted_bytes: usize,
+
+    /// Number of deliveries that failed without failing the project, e.g. to JSON webhooks.
+    pub failed_deliveries: usize,
+
+    /// If we failed to handle the project, the class of error.
+    pub error: Option<ErrorClass>,
+}
+
+/// A measure, in the Prometheus data model, e.g. for `/metrics`, see `server`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub labels: Vec<(&'static str, String)>,
+    pub value: f64,
+}
+
+/// What we measured during a run.
+///
+/// Logged at the end of the run and, with `qastor serve --listen`, served at `/metrics`.
+#[derive(Default)]
+pub struct Metrics {
+    projects: Vec<(Url, ProjectMetrics)>,
+}
+impl Metrics {
+    /// The metrics of a project, created if necessary.
+    pub fn project(&mut self, url: &Url) -> &mut ProjectMetrics {
+        let index = match self.projects.iter().position(|(key, _)| key == url) {
+            Some(index) => index,
+            None => {
+                self.projects.push((url.clone(), ProjectMetrics::default()));
+                self.projects.len() - 1
+            }
+        };
+        &mut self.projects[index].1
+    }
+
+    /// A single line summarizing the run, as `key=value` pairs.
+    pub fn summary(&self, elapsed: Duration) -> String {
+        let sum = |f: fn(&ProjectMetrics) -> usize| -> usize {
+            self.projects.iter().map(|(_, metrics)| f(metrics)).sum()
+        };
+        let mut result = format!(
+            "run_stats duration_ms={} projects={} errors={} items={} posted_bytes={} failed_deliveries={}",
+            elapsed.as_millis(),
+            self.projects.len(),
+            sum(|metrics| metrics.error.is_some() as usize),
+            sum(|metrics| metrics.items),
+            sum(|metrics| metrics.posted_bytes),
+            sum(|metrics| metrics.failed_deliveries),
+        );
+        let errors = self
+            .projects
+            .iter()
+            .filter_map(|(_, metrics)| metrics.error)
+            .counts();
+        if errors.is_empty().not() {
+            let _ = write!(
+                result,
+                " error_classes={}",
+                errors
+                    .into_iter()
+                    .sorted()
+                    .map(|(class, count)| format!("{class}:{count}"))
+                    .format(",")
+            );
+        }
+        if self.projects.is_empty().not() {
+            let _ = write!(
+                result,
+                " fetch_ms={}",
+                self.projects
+                    .iter()
+                    .map(|(url, metrics)| format!(
+                        "{}:{}",
+                        url.path().trim_matches('/'),
+                        metrics.fetch.as_millis()
+                    ))
+                    .format(",")
+            );
+        }
+        result
+    }
+
+    /// The measures of the run, e.g. for `/metrics`.
+    pub fn samples(&self, elapsed: Duration) -> Vec<Sample> {
+        let sum = |f: fn(&ProjectMetrics) -> usize| -> f64 {
+            self.projects
+                .iter()
+                .map(|(_, metrics)| f(metrics))
+                .sum::<usize>() as f64
+        };
+        let total = |name, help, value| Sample {
+            name,
+            help,
+            labels: vec![],
+            value,
+        };
+        let mut samples = vec![
+            total(
+                "qastor_run_duration_seconds",
+                "How long the latest run took.",
+                elapsed.as_secs_f64(),
+            ),
+            total(
+                "qastor_projects",
+                "Projects handled by the latest run.",
+                self.projects.len() as f64,
+            ),
+            total(
+                "qastor_items",
+                "Issues and PRs reported by the latest run.",
+                sum(|metrics| metrics.items),
+            ),
+            total(
+                "qastor_posted_bytes",
+                "Bytes posted to hooks by the latest run.",
+                sum(|metrics| metrics.posted_bytes),
+            ),
+            total(
+                "qastor_failed_deliveries",
+                "Deliveries that failed without failing their project in the latest run.",
+                sum(|metrics| metrics.failed_deliveries),
+            ),
+        ];
+        let errors = self
+            .projects
+            .iter()
+            .filter_map(|(_, metrics)| metrics.error)
+            .counts();
+        samples.extend(errors.into_iter().sorted().map(|(class, count)| Sample {
+            name: "qastor_project_errors",
+            help: "Projects that failed in the latest run, by class of error.",
+            labels: vec![("class", class.to_string())],
+            value: count as f64,
+        }));
+        samples.extend(self.projects.iter().map(|(url, metrics)| Sample {
+            name: "qastor_fetch_seconds",
+            help: "Time spent fetching data from GitHub in the latest run, by project.",
+            labels: vec![("project", url.path().trim_matches('/').to_string())],
+            value: metrics.fetch.as_secs_f64(),
+        }));
+        samples
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use url::Url;
+
+    use super::{Metrics, Sample};
+    use crate::error::ErrorClass;
+
+    /// Does the summary aggregate all projects?
+    #[test]
+    fn test_summary() {
+        let mut metrics = Metrics::default();
+        let project1 = Url::parse("https://github.com/owner1/project1").unwrap();
+        let project2 = Url::parse("https://github.com/owner2/project2").unwrap();
+        metrics.project(&project1).fetch = Duration::from_millis(120);
+        metrics.project(&project1).items = 3;
+        metrics.project(&project1).posted_bytes = 1000;
+        metrics.project(&project2).fetch = Duration::from_millis(80);
+        metrics.project(&project2).error = Some(ErrorClass::Auth);
+        metrics.project(&project2).failed_deliveries = 1;
+        assert_eq!(
+            metrics.summary(Duration::from_secs(2)),
+            "run_stats duration_ms=2000 projects=2 errors=1 items=3 posted_bytes=1000 failed_deliveries=1 error_classes=auth:1 fetch_ms=owner1/project1:120,owner2/project2:80"
+        );
+    }
+
+    /// Are the measures those of the summary?
+    #[test]
+    fn test_samples() {
+        let mut metrics = Metrics::default();
+        let project = Url::parse("https://github.com/owner1/project1").unwrap();
+        metrics.project(&project).items = 3;
+        metrics.project(&project).error = Some(ErrorClass::Auth);
+        let samples = metrics.samples(Duration::from_secs(2));
+        let value = |name| {
+            samples
+                .iter()
+                .find(|sample: &&Sample| sample.name == name)
+                .map(|sample| sample.value)
+        };
+        assert_eq!(value("qastor_run_duration_seconds"), Some(2.0));
+        assert_eq!(value("qastor_items"), Some(3.0));
+        assert_eq!(value("qastor_project_errors"), Some(1.0));
+        assert!(samples
+            .iter()
+            .any(|sample| sample.labels == [("project", "owner1/project1".to_string())]));
+    }
+}
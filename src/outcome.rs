@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+
+use anyhow::Context;
+use serde::Serialize;
+use url::Url;
+
+use crate::{
+    error::{Error, ErrorClass},
+    report::SectionKind,
+};
+
+/// Why we failed to handle a project.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ProjectError {
+    pub class: ErrorClass,
+    pub message: String,
+
+    /// Whether the administrators need to step in, e.g. to renew a token.
+    pub needs_admin: bool,
+}
+
+/// What happened to a single project during a run.
+#[derive(Serialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct ProjectOutcome {
+    /// Number of issues and PRs found.
+    pub items: usize,
+
+    /// Number of items found for each kind of section.
+    pub sections: BTreeMap<SectionKind, usize>,
+
+    /// Number of sections sent to each hook, by alias (or hash of the url).
+    pub sent: BTreeMap<String, usize>,
+
+    /// If we didn't check the project, why, e.g. `archived` or `unchanged`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<ProjectError>,
+}
+impl ProjectOutcome {
+    pub fn skipped(reason: impl ToString) -> Self {
+        ProjectOutcome {
+            skipped: Some(reason.to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn failed(err: &anyhow::Error) -> Self {
+        ProjectOutcome {
+            error: Some(ProjectError {
+                class: Error::class(err),
+                message: format!("{err:#}"),
+                needs_admin: Error::find(err).is_some_and(Error::needs_admin),
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+/// What happened to all the projects during a run.
+#[derive(Serialize, Default, Debug)]
+pub struct RunSummary {
+    pub projects: BTreeMap<Url, ProjectOutcome>,
+}
+impl RunSummary {
+    pub fn record(&mut self, project: &Url, outcome: ProjectOutcome) {
+        self.projects.insert(project.clone(), outcome);
+    }
+
+    /// The projects that the administrators need to look at, with the reason.
+    pub fn admin_alerts(&self) -> impl Iterator<Item = (&Url, &ProjectError)> {
+        self.projects.iter().filter_map(|(url, outcome)| {
+            outcome
+                .error
+                .as_ref()
+                .filter(|error| error.needs_admin)
+                .map(|error| (url, error))
+        })
+    }
+
+    /// The exit code of the process: errors that need the administrators (e.g. an
+    /// expired token) fail the run, while other errors (e.g. rate limits) are retried
+    /// on the next run.
+    pub fn exit_code(&self) -> i32 {
+        self.admin_alerts()
+            .map(|(_, error)| error.class.exit_code())
+            .next()
+            .unwrap_or(0)
+    }
+
+    /// Write the summary as JSON, e.g. for `--emit-report`.
+    pub fn write(&self, path: &std::path::Path) -> Result<(), anyhow::Error> {
+        let file = std::fs::File::create(path)
+            .with_context(|| format!("Could not create {}", path.display()))?;
+        serde_json::to_writer_pretty(file, self).context("Could not write run summary")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use url::Url;
+
+    use super::{ProjectOutcome, RunSummary};
+    use crate::error::Error;
+
+    /// Do only errors that need the administrators fail the run?
+    #[test]
+    fn test_exit_code() {
+        let mut summary = RunSummary::default();
+        let project1 = Url::parse("https://github.com/owner/project1").unwrap();
+        let project2 = Url::parse("https://github.com/owner/project2").unwrap();
+        summary.record(&project1, ProjectOutcome::skipped("archived"));
+        summary.record(
+            &project2,
+            ProjectOutcome::failed(&Error::RateLimited("slow down".to_string()).into()),
+        );
+        assert_eq!(summary.admin_alerts().count(), 0);
+        assert_eq!(summary.exit_code(), 0);
+
+        summary.record(
+            &project2,
+            ProjectOutcome::failed(&Error::Auth("Bad credentials".to_string()).into()),
+        );
+        assert_eq!(summary.admin_alerts().count(), 1);
+        assert_eq!(summary.exit_code(), 3);
+        let json = serde_json::to_value(&summary).unwrap();
+        assert_eq!(
+            json["projects"]["https://github.com/owner/project2"]["error"]["class"],
+            "auth"
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use chrono::{DateTime, Utc};
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+
+/// The stars and forks of a project, as of a run.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Counts {
+    pub stars: u64,
+    pub forks: u64,
+}
+
+/// What we remember of the popularity of a project from one run to the next.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    /// The counts as of the last run.
+    #[serde(flatten)]
+    pub counts: Counts,
+
+    /// The highest milestones celebrated so far for stars and forks (0 if none), so that we
+    /// don't celebrate them again after a dip. Missing from older states.
+    #[serde(default)]
+    pub celebrated: Option<Counts>,
+
+    /// The stars counted at each run within the window of spikes, oldest first.
+    #[serde(default)]
+    pub samples: Vec<(DateTime<Utc>, u64)>,
+}
+
+/// What we count.
+#[derive(Display, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    #[display("stars")]
+    Stars,
+    #[display("forks")]
+    Forks,
+}
+
+/// Something worth celebrating since the last run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// The project crossed a milestone, e.g. 1000 stars.
+    Milestone {
+        metric: Metric,
+        milestone: u64,
+        count: u64,
+    },
+
+    /// The project gained at least `spike` stars within the window.
+    Spike { gained: u64, count: u64 },
+}
+
+/// The milestones crossed (only the highest one for each metric, and only once) and the spike
+/// of stars, if any, along with what to remember for the next run.
+///
+/// Spikes are measured over `window`, i.e. since the oldest run within it, or since the last
+/// run without a window. Once announced, a spike starts a new window, so that we don't announce
+/// it again at every run.
+///
+/// The first time, there's nothing to compare with, so nothing to say.
+pub fn changes(
+    previous: Option<&Record>,
+    current: Counts,
+    now: DateTime<Utc>,
+    milestones: &[u64],
+    spike: Option<u64>,
+    window: Option<chrono::Duration>,
+) -> (Vec<Change>, Record) {
+    let highest = |count: u64| {
+        milestones
+            .iter()
+            .copied()
+            .filter(|milestone| *milestone <= count)
+            .max()
+            .unwrap_or_default()
+    };
+    let sample = (now, current.stars);
+    let Some(previous) = previous else {
+        let record = Record {
+            counts: current,
+            celebrated: Some(Counts {
+                stars: highest(current.stars),
+                forks: highest(current.forks),
+            }),
+            samples: vec![sample],
+        };
+        return (vec![], record);
+    };
+    let mut celebrated = previous.celebrated.unwrap_or(Counts {
+        stars: highest(previous.counts.stars),
+        forks: highest(previous.counts.forks),
+    });
+    let mut changes = vec![];
+    for (metric, celebrated, now) in [
+        (Metric::Stars, &mut celebrated.stars, current.stars),
+        (Metric::Forks, &mut celebrated.forks, current.forks),
+    ] {
+        let milestone = highest(now);
+        if milestone > *celebrated {
+            *celebrated = milestone;
+            changes.push(Change::Milestone {
+                metric,
+                milestone,
+                count: now,
+            });
+        }
+    }
+    let mut samples: Vec<_> = match window {
+        Some(window) => previous
+            .samples
+            .iter()
+            .copied()
+            .filter(|(at, _)| *at >= now - window)
+            .collect(),
+        None => vec![],
+    };
+    let baseline = samples
+        .first()
+        .map_or(previous.counts.stars, |(_, stars)| *stars);
+    let gained = current.stars.saturating_sub(baseline);
+    if spike.is_some_and(|spike| gained >= spike) {
+        changes.push(Change::Spike {
+            gained,
+            count: current.stars,
+        });
+        samples.clear();
+    }
+    samples.push(sample);
+    let record = Record {
+        counts: current,
+        celebrated: Some(celebrated),
+        samples,
+    };
+    (changes, record)
+}
+
+/// A round number, e.g. `1k` or `1.5k`.
+pub fn round(count: u64) -> String {
+    match count {
+        0..1_000 => count.to_string(),
+        _ if count.is_multiple_of(1_000) => format!("{}k", count / 1_000),
+        _ => format!("{:.1}k", count as f64 / 1_000.0),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::{changes, round, Change, Counts, Metric, Record};
+
+    /// Do we celebrate the highest milestone crossed and spikes, but not the first run?
+    #[test]
+    fn test_changes() {
+        let counts = |stars, forks| Counts { stars, forks };
+        let record = |stars, forks| Record {
+            counts: counts(stars, forks),
+            celebrated: None,
+            samples: vec![],
+        };
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let milestones = [1_000, 5_000, 10_000];
+        let (found, first) = changes(None, counts(6_000, 10), now, &milestones, Some(50), None);
+        assert!(found.is_empty());
+        assert_eq!(first.celebrated, Some(counts(5_000, 0)));
+        let (found, _) = changes(
+            Some(&record(900, 990)),
+            counts(5_100, 1_000),
+            now,
+            &milestones,
+            Some(50),
+            None,
+        );
+        assert_eq!(
+            found,
+            vec![
+                Change::Milestone {
+                    metric: Metric::Stars,
+                    milestone: 5_000,
+                    count: 5_100
+                },
+                Change::Milestone {
+                    metric: Metric::Forks,
+                    milestone: 1_000,
+                    count: 1_000
+                },
+                Change::Spike {
+                    gained: 4_200,
+                    count: 5_100
+                },
+            ]
+        );
+        let (found, _) = changes(
+            Some(&record(1_000, 0)),
+            counts(1_020, 0),
+            now,
+            &milestones,
+            Some(50),
+            None,
+        );
+        assert!(found.is_empty());
+        assert_eq!(round(999), "999");
+        assert_eq!(round(5_000), "5k");
+        assert_eq!(round(1_500), "1.5k");
+    }
+
+    /// Do we celebrate a milestone only once, even after a dip below it?
+    #[test]
+    fn test_changes_dip() {
+        let counts = |stars, forks| Counts { stars, forks };
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let milestones = [1_000, 5_000];
+        let (found, record) = changes(
+            Some(&Record {
+                counts: counts(990, 0),
+                celebrated: None,
+                samples: vec![],
+            }),
+            counts(1_001, 0),
+            now,
+            &milestones,
+            None,
+            None,
+        );
+        assert_eq!(found.len(), 1);
+        let (found, record) = changes(Some(&record), counts(998, 0), now, &milestones, None, None);
+        assert!(found.is_empty());
+        let (found, _) = changes(
+            Some(&record),
+            counts(1_002, 0),
+            now,
+            &milestones,
+            None,
+            None,
+        );
+        assert!(found.is_empty());
+    }
+
+    /// Do we measure spikes over the window, rather than between two runs, and only once?
+    #[test]
+    fn test_changes_window() {
+        let counts = |stars| Counts { stars, forks: 0 };
+        let start = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let window = Some(Duration::days(7));
+        let (_, mut record) = changes(None, counts(100), start, &[], Some(50), window);
+        for day in 1..=3 {
+            let (found, next) = changes(
+                Some(&record),
+                counts(100 + day * 20),
+                start + Duration::days(day as i64),
+                &[],
+                Some(50),
+                window,
+            );
+            record = next;
+            match day {
+                3 => assert_eq!(
+                    found,
+                    vec![Change::Spike {
+                        gained: 60,
+                        count: 160
+                    }]
+                ),
+                _ => assert!(found.is_empty()),
+            }
+        }
+        let (found, _) = changes(
+            Some(&record),
+            counts(180),
+            start + Duration::days(4),
+            &[],
+            Some(50),
+            window,
+        );
+        assert!(found.is_empty());
+
+        // Without a window, we compare with the last run.
+        let (_, record) = changes(None, counts(100), start, &[], Some(50), None);
+        let (found, record) = changes(
+            Some(&record),
+            counts(140),
+            start + Duration::days(1),
+            &[],
+            Some(50),
+            None,
+        );
+        assert!(found.is_empty());
+        let (found, _) = changes(
+            Some(&record),
+            counts(180),
+            start + Duration::days(2),
+            &[],
+            Some(50),
+            None,
+        );
+        assert!(found.is_empty());
+    }
+}
@@ -0,0 +1,178 @@
+use std::ops::Not;
+
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use octocrab::models::repos::Release;
+use url::Url;
+
+/// The version of this build of qastor.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The number of lines of the changelog of a release that we quote.
+const EXCERPT_LINES: usize = 10;
+
+/// Parse a version or the tag of a release, e.g. `v1.2.3` or `1.2`, ignoring pre-release
+/// and build metadata.
+fn parse(version: &str) -> Option<[u64; 3]> {
+    let version = version.trim().trim_start_matches('v');
+    let core = version.split(['-', '+']).next()?;
+    let mut parts = [0; 3];
+    for (i, part) in core.split('.').enumerate() {
+        *parts.get_mut(i)? = part.parse().ok()?;
+    }
+    Some(parts)
+}
+
+/// Whether the tag of a release is a newer version than `current`.
+///
+/// Tags that aren't versions are never newer.
+pub fn is_newer(tag: &str, current: &str) -> bool {
+    match (parse(tag), parse(current)) {
+        (Some(tag), Some(current)) => tag > current,
+        _ => false,
+    }
+}
+
+/// The first lines of the changelog of a release, skipping blank lines.
+pub fn excerpt(body: &str) -> String {
+    excerpt_lines(body, EXCERPT_LINES)
+}
+
+/// The first `count` lines of the changelog of a release, skipping blank lines.
+pub fn excerpt_lines(body: &str, count: usize) -> String {
+    let lines = body
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| line.is_empty().not())
+        .collect_vec();
+    let mut excerpt = lines.iter().take(count).join("\n");
+    if lines.len() > count {
+        excerpt.push_str("\n…");
+    }
+    excerpt
+}
+
+/// A release or a tag of a project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Published {
+    pub tag: String,
+    pub name: Option<String>,
+    pub url: Url,
+    pub notes: Option<String>,
+
+    /// When the release was published, `None` for tags without releases.
+    pub at: Option<DateTime<Utc>>,
+}
+impl From<Release> for Published {
+    fn from(release: Release) -> Self {
+        Published {
+            tag: release.tag_name,
+            name: release.name.filter(|name| name.is_empty().not()),
+            url: release.html_url,
+            notes: release.body,
+            at: release.published_at,
+        }
+    }
+}
+
+/// The releases published since `since`, then the tags of `project` missing from `known`
+/// that have no release.
+pub fn published_since(
+    project: &Url,
+    releases: Vec<Published>,
+    tags: &[String],
+    known: &[String],
+    since: DateTime<Utc>,
+) -> Vec<Published> {
+    let mut tags_only = tags
+        .iter()
+        .filter(|tag| known.contains(tag).not())
+        .filter(|tag| releases.iter().all(|release| &&release.tag != tag))
+        .map(|tag| {
+            let mut url = project.clone();
+            if let Ok(mut segments) = url.path_segments_mut() {
+                segments
+                    .pop_if_empty()
+                    .extend(["releases", "tag", tag.as_str()]);
+            }
+            Published {
+                tag: tag.clone(),
+                name: None,
+                url,
+                notes: None,
+                at: None,
+            }
+        })
+        .collect_vec();
+    let mut published = releases
+        .into_iter()
+        .filter(|release| release.at.is_some_and(|at| at > since))
+        .sorted_by_key(|release| release.at)
+        .collect_vec();
+    // Tags are listed newest first.
+    tags_only.reverse();
+    published.append(&mut tags_only);
+    published
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, TimeZone, Utc};
+    use url::Url;
+
+    use super::{excerpt, is_newer, published_since, Published};
+
+    /// Are tags compared as versions?
+    #[test]
+    fn test_is_newer() {
+        assert!(is_newer("v0.2.0", "0.1.0"));
+        assert!(is_newer("0.10.0", "0.9.3"));
+        assert!(is_newer("v1", "0.9.3"));
+        assert!(!is_newer("v0.1.0", "0.1.0"));
+        assert!(!is_newer("v0.1.0-rc.1", "0.1.0"));
+        assert!(!is_newer("nightly", "0.1.0"));
+    }
+
+    /// Are long changelogs cut?
+    #[test]
+    fn test_excerpt() {
+        assert_eq!(excerpt("## Changes\n\n- Fix\n"), "## Changes\n- Fix");
+        let long = (0..20).map(|i| format!("- Change {i}")).collect::<Vec<_>>();
+        assert!(excerpt(&long.join("\n")).ends_with("- Change 9\n…"));
+    }
+
+    /// Are new releases listed, then new tags without releases?
+    #[test]
+    fn test_published_since() {
+        let project = Url::parse("https://github.com/owner1/project1").unwrap();
+        let since = Utc.with_ymd_and_hms(2024, 5, 6, 12, 0, 0).unwrap();
+        let release = |tag: &str, at| Published {
+            tag: tag.to_string(),
+            name: None,
+            url: project
+                .join(&format!("project1/releases/tag/{tag}"))
+                .unwrap(),
+            notes: None,
+            at: Some(at),
+        };
+        let releases = vec![
+            release("v1.1", since + Duration::hours(1)),
+            release("v1.0", since - Duration::days(7)),
+        ];
+        let tags = ["nightly", "v1.1", "v1.0", "v0.9"].map(str::to_string);
+        let known = ["v1.0", "v0.9"].map(str::to_string);
+        let published = published_since(&project, releases, &tags, &known, since);
+        assert_eq!(
+            published
+                .iter()
+                .map(|item| item.tag.as_str())
+                .collect::<Vec<_>>(),
+            vec!["v1.1", "nightly"]
+        );
+        assert_eq!(
+            published[1].url.as_str(),
+            "https://github.com/owner1/project1/releases/tag/nightly"
+        );
+        assert_eq!(published[1].at, None);
+    }
+}
@@ -0,0 +1,1557 @@
+//! Rendering each section of a report, as Slack sections.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Not,
+};
+
+use chrono::{DateTime, Utc};
+use itertools::Itertools;
+use octocrab::models::{issues::Issue, pulls::PullRequest, Milestone};
+use url::Url;
+
+use crate::{
+    business,
+    config::{Config, Escalation, Project, Triage},
+    dependabot::{self, Compatibility, DependabotAlert, DependabotPull},
+    digest,
+    fetch::{Sections, WeeklyDigest},
+    github::{
+        self, Discussion, ForcePush, IssueDetails, LabelChange, PendingReview, ReviewSummary,
+        WorkflowRun,
+    },
+    humanize::{self, Locale},
+    lifecycle::Lifecycle,
+    outcome::RunSummary,
+    popularity::{self, Change},
+    release::{self, Published},
+    report::SectionKind,
+    scanning::ScanningAlert,
+    sla::Breach,
+    slack,
+    state::Ack,
+};
+
+/// Render the open Dependabot PRs of a project, one row per update.
+pub fn render_dependency_updates(
+    config: &Config,
+    project: &Project,
+    pulls: &[DependabotPull],
+    scores: &HashMap<Url, u8>,
+) -> slack::Section {
+    let locale = config.locale_for(project);
+    let title = config.text(
+        project,
+        "dependency_updates.title",
+        &[(
+            "link",
+            slack::link(&project.html_url(), Some(project.repo.as_ref())),
+        )],
+    );
+    let mut msg = slack::Section::new(title);
+    msg.append_headers(&config.columns(locale, ["update", "compatibility"]));
+    for pull in pulls {
+        let pr = slack::link(&pull.url, Some(&format!("#{}", pull.number)));
+        if pull.updates.is_empty() {
+            msg.append_fields(&[
+                slack::link(&pull.url, Some(pull.title.as_str())),
+                Compatibility::Unknown.to_string(),
+            ]);
+        }
+        for update in &pull.updates {
+            let version = format!("{} {}→{}", update.name, update.from, update.to);
+            let version = match update.changes {
+                Some(ref changes) => slack::link(changes, Some(&version)),
+                None => version,
+            };
+            let score = match update.badge.as_ref().and_then(|badge| scores.get(badge)) {
+                Some(score) => format!(", score {score}%"),
+                None => String::new(),
+            };
+            msg.append_fields(&[
+                format!("{version} ({pr})"),
+                format!("{}{score}", update.compatibility()),
+            ]);
+        }
+    }
+    msg
+}
+
+/// Render one line per project, with the number of items of each kind, e.g.
+/// `qadence 🔍 4 🐛 7 ⏳ 2`.
+///
+/// Projects skipped during the run keep their counts of the last run that checked them,
+/// taken from `previous`.
+pub fn render_status_board(
+    config: &Config,
+    summary: &RunSummary,
+    previous: &HashMap<Url, BTreeMap<SectionKind, usize>>,
+    now: DateTime<Utc>,
+) -> slack::Section {
+    /// Room for the "…and N more".
+    const RESERVED: usize = 100;
+    let title = format!(
+        "*Status board* (updated {})",
+        now.format("%Y-%m-%d %H:%M UTC")
+    );
+    let counts = |sections: &BTreeMap<SectionKind, usize>| {
+        sections
+            .iter()
+            .map(|(kind, count)| format!("{} {count}", kind.emoji()))
+            .join("  ")
+    };
+    let mut lines = vec![];
+    for project in &config.projects {
+        let Some(outcome) = summary.projects.get(&project.url) else {
+            continue;
+        };
+        let status = if let Some(ref error) = outcome.error {
+            format!("⚠️ {}", error.class)
+        } else if let Some(ref reason) = outcome.skipped {
+            match previous.get(&project.url) {
+                Some(sections) if sections.is_empty().not() => {
+                    format!("{}  💤 {reason}", counts(sections))
+                }
+                _ => format!("💤 {reason}"),
+            }
+        } else if outcome.sections.is_empty() {
+            "✨".to_string()
+        } else {
+            counts(&outcome.sections)
+        };
+        lines.push(format!(
+            "{}  {status}",
+            slack::link(&project.html_url(), Some(project.repo.as_ref()))
+        ));
+    }
+    // Beyond this, Slack rejects the section.
+    let budget = (slack::MAX_TEXT - RESERVED).saturating_sub(title.chars().count());
+    let (mut lines, more) = fit(lines, budget);
+    if more > 0 {
+        lines.push(config.message(
+            config.locale,
+            "backlog.more",
+            &[("count", more.to_string())],
+        ));
+    }
+    lines.insert(0, title);
+    slack::Section::new(lines.join("\n"))
+}
+
+/// Render the backlog of a project for the pinned summary of its channel: the PRs waiting
+/// for a review and the issues updated recently, one line each.
+pub fn render_backlog(
+    config: &Config,
+    project: &Project,
+    pending_requests: &[PendingReview],
+    pending_issues: &[Issue],
+    now: DateTime<Utc>,
+) -> slack::Section {
+    /// Room for the headings of the groups and their "…and N more".
+    const RESERVED: usize = 200;
+    let locale = config.locale_for(project);
+    let mut lines = vec![format!(
+        "📌 *{}* ({})",
+        config.text(
+            project,
+            "backlog.title",
+            &[(
+                "link",
+                slack::link(&project.html_url(), Some(project.repo.as_ref()))
+            )],
+        ),
+        config.text(
+            project,
+            "backlog.updated",
+            &[(
+                "time",
+                now.format(&config.text(project, "date_time", &[]))
+                    .to_string(),
+            )],
+        ),
+    )];
+    // Beyond this, Slack rejects the section: each group gets half of what's left.
+    let budget = (slack::MAX_TEXT - RESERVED).saturating_sub(lines[0].chars().count()) / 2;
+    let mut push = |kind: SectionKind, heading: &str, items: Vec<String>| {
+        if items.is_empty() {
+            return;
+        }
+        lines.push(format!("*{} {heading}*", kind.emoji()));
+        let (items, more) = fit(items, budget);
+        lines.extend(items);
+        if more > 0 {
+            lines.push(config.text(project, "backlog.more", &[("count", more.to_string())]));
+        }
+    };
+    push(
+        SectionKind::PendingReviews,
+        &config.text(project, "backlog.pending_reviews", &[]),
+        pending_requests
+            .iter()
+            .map(|pull| {
+                let age = match pull.created_at {
+                    Some(created_at) => format!(", {}", waiting(config, locale, created_at, now)),
+                    None => String::new(),
+                };
+                format!(
+                    "• #{} {} ({}){age}",
+                    pull.number,
+                    slack::link(&pull.url, Some(pull.title.as_str())),
+                    pull.requested().join(", ")
+                )
+            })
+            .collect(),
+    );
+    push(
+        SectionKind::UpdatedIssues,
+        &config.text(project, "backlog.updated_issues", &[]),
+        pending_issues
+            .iter()
+            .map(|issue| {
+                format!(
+                    "• #{} {}",
+                    issue.number,
+                    slack::link(&issue.html_url, Some(issue.title.as_str()))
+                )
+            })
+            .collect(),
+    );
+    if lines.len() == 1 {
+        lines.push(config.text(project, "backlog.empty", &[]));
+    }
+    slack::Section::new(lines.join("\n"))
+}
+
+/// Keep the first lines that fit in `budget` characters once joined, e.g. in a section of
+/// Slack.
+///
+/// Returns these lines, and how many were left out.
+fn fit(lines: Vec<String>, budget: usize) -> (Vec<String>, usize) {
+    let count = lines.len();
+    let mut used = 0;
+    let fitting = lines
+        .into_iter()
+        .take_while(|line| {
+            used += line.chars().count() + 1;
+            used <= budget
+        })
+        .collect_vec();
+    let more = count - fitting.len();
+    (fitting, more)
+}
+
+/// Drop the items of a section beyond its cap, if any.
+///
+/// Returns the number of items dropped.
+pub fn cap<T>(config: &Config, project: &Project, kind: SectionKind, items: &mut Vec<T>) -> usize {
+    match config.item_cap(project, kind) {
+        Some(cap) if items.len() > cap => {
+            let dropped = items.len() - cap;
+            items.truncate(cap);
+            dropped
+        }
+        _ => 0,
+    }
+}
+
+/// Items left out of a section, because of `item_caps` or `max_items`.
+pub struct Omitted {
+    /// The number of items that we have found but not displayed.
+    count: usize,
+
+    /// Whether we have found all the items, or stopped listing them at `max_items`.
+    complete: bool,
+
+    /// Where readers can find all the items.
+    all: Url,
+}
+
+impl Omitted {
+    pub fn new(project: &Project, path: &str, count: usize, complete: bool) -> Self {
+        let mut all = project.html_url();
+        if let Ok(mut segments) = all.path_segments_mut() {
+            segments.pop_if_empty().push(path);
+        }
+        Omitted {
+            count,
+            complete,
+            all,
+        }
+    }
+
+    /// Drop the items of a section beyond its cap, if any, see `cap`.
+    pub fn capped<T>(
+        config: &Config,
+        project: &Project,
+        kind: SectionKind,
+        path: &str,
+        items: &mut Vec<T>,
+        complete: bool,
+    ) -> Self {
+        Omitted::new(project, path, cap(config, project, kind, items), complete)
+    }
+
+    /// Append an explicit "and N more" to a section, if we have left out some items.
+    pub fn mark(
+        &self,
+        config: &Config,
+        project: &Project,
+        mut msg: slack::Section,
+    ) -> slack::Section {
+        let count = [("count", self.count.to_string())];
+        let more = match (self.count, self.complete) {
+            (0, true) => return msg,
+            (0, false) => config.text(project, "omitted.unknown", &[]),
+            (_, true) => config.text(project, "backlog.more", &count),
+            (_, false) => config.text(project, "omitted.at_least", &count),
+        };
+        let all = config.text(project, "omitted.see_all", &[]);
+        msg.append_fields(&[format!("_{more}_"), slack::link(&self.all, Some(&all))]);
+        msg
+    }
+}
+
+/// What we leave out of each section of a report, see `Omitted`.
+pub struct Omissions {
+    pub requests: Omitted,
+    pub drafts: Omitted,
+    pub unreviewed: Omitted,
+    pub stale: Omitted,
+    pub issues: Omitted,
+    pub stuck: Omitted,
+    pub transferred: Omitted,
+    pub milestones: Omitted,
+    pub updates: Omitted,
+    pub labels: Omitted,
+    pub releases: Omitted,
+    pub security: Omitted,
+    pub scanning: Omitted,
+    pub progress: Omitted,
+    pub ci: Omitted,
+    pub discussions: Omitted,
+}
+impl Omissions {
+    /// Drop the items of each section beyond its cap, if any.
+    pub fn cap(config: &Config, project: &Project, sections: &mut Sections) -> Self {
+        Omissions {
+            requests: Omitted::capped(
+                config,
+                project,
+                SectionKind::PendingReviews,
+                "pulls",
+                &mut sections.pending_requests,
+                sections.requests_complete,
+            ),
+            drafts: Omitted::capped(
+                config,
+                project,
+                SectionKind::DraftReviews,
+                "pulls",
+                &mut sections.drafts,
+                sections.requests_complete,
+            ),
+            unreviewed: Omitted::capped(
+                config,
+                project,
+                SectionKind::UnreviewedPulls,
+                "pulls",
+                &mut sections.unreviewed,
+                sections.open_complete,
+            ),
+            stale: Omitted::capped(
+                config,
+                project,
+                SectionKind::StalePulls,
+                "pulls",
+                &mut sections.stale_pulls,
+                sections.open_complete,
+            ),
+            issues: Omitted::capped(
+                config,
+                project,
+                SectionKind::UpdatedIssues,
+                "issues",
+                &mut sections.pending_issues,
+                sections.issues_complete,
+            ),
+            stuck: Omitted::capped(
+                config,
+                project,
+                SectionKind::StuckInTriage,
+                "issues",
+                &mut sections.stuck,
+                sections.issues_complete,
+            ),
+            transferred: Omitted::capped(
+                config,
+                project,
+                SectionKind::TransferredIssues,
+                "issues",
+                &mut sections.transferred,
+                true,
+            ),
+            milestones: Omitted::capped(
+                config,
+                project,
+                SectionKind::DueMilestones,
+                "milestones",
+                &mut sections.due_milestones,
+                true,
+            ),
+            updates: Omitted::capped(
+                config,
+                project,
+                SectionKind::DependencyUpdates,
+                "pulls",
+                &mut sections.dependency_updates,
+                true,
+            ),
+            labels: Omitted::capped(
+                config,
+                project,
+                SectionKind::LabelChanges,
+                "issues",
+                &mut sections.label_changes,
+                true,
+            ),
+            releases: Omitted::capped(
+                config,
+                project,
+                SectionKind::Releases,
+                "releases",
+                &mut sections.releases,
+                true,
+            ),
+            security: Omitted::capped(
+                config,
+                project,
+                SectionKind::SecurityAlerts,
+                "security/dependabot",
+                &mut sections.security_alerts,
+                true,
+            ),
+            scanning: Omitted::capped(
+                config,
+                project,
+                SectionKind::ScanningAlerts,
+                "security",
+                &mut sections.scanning_alerts,
+                true,
+            ),
+            progress: match sections.milestone_progress {
+                Some(ref mut progress) => Omitted::capped(
+                    config,
+                    project,
+                    SectionKind::MilestoneProgress,
+                    "milestones",
+                    progress,
+                    true,
+                ),
+                None => Omitted::new(project, "milestones", 0, true),
+            },
+            ci: Omitted::capped(
+                config,
+                project,
+                SectionKind::CiFailures,
+                "actions",
+                &mut sections.ci_failures,
+                true,
+            ),
+            discussions: Omitted::capped(
+                config,
+                project,
+                SectionKind::UnansweredDiscussions,
+                "discussions",
+                &mut sections.discussions,
+                true,
+            ),
+        }
+    }
+}
+
+/// Render the PRs waiting for a review.
+///
+/// Acknowledged PRs are listed last, with the reviewer who is on it rather than all of them.
+pub fn render_pending_requests(
+    config: &Config,
+    project: &Project,
+    pulls: impl IntoIterator<Item = PendingReview>,
+    reviews: &HashMap<u64, ReviewSummary>,
+    acks: &HashMap<u64, Ack>,
+    force_pushes: &HashMap<u64, ForcePush>,
+    escalation: Option<&Escalation>,
+) -> slack::Section {
+    let locale = config.locale_for(project);
+    let link = slack::link(&project.html_url(), Some(project.repo.as_ref()));
+    let title = match escalation {
+        None => config.text(project, "pending_reviews.title", &[("link", link)]),
+        Some(escalation) => {
+            let mut title = config.text(
+                project,
+                "pending_reviews.escalated",
+                &[
+                    ("link", link),
+                    ("span", span(config, locale, escalation.after)),
+                ],
+            );
+            if escalation.mention.is_empty().not() {
+                let mentions = escalation
+                    .mention
+                    .iter()
+                    .map(|login| config.mention(login))
+                    .join(", ");
+                title.push_str(&format!(", cc {mentions}"));
+            }
+            title
+        }
+    };
+    let mut msg = slack::Section::new(title);
+    msg.append_headers(&config.columns(locale, ["request", "reviewer"]));
+    let mut pulls = pulls.into_iter().collect_vec();
+    pulls.sort_by_key(|pull| acks.contains_key(&pull.number));
+    for pull in pulls {
+        let emojis = config.label_emojis(pull.labels.iter().map(String::as_str));
+        let checklist = match pull.body.as_deref().and_then(github::task_list_progress) {
+            Some((done, total)) => format!(" ☑ {done}/{total}"),
+            None => String::new(),
+        };
+        let closes = github::closing_references(pull.body.as_deref().unwrap_or_default());
+        let closes = if closes.is_empty() {
+            String::new()
+        } else {
+            format!(
+                " (fixes {})",
+                closes
+                    .iter()
+                    .map(|number| format!("#{number}"))
+                    .format(", ")
+            )
+        };
+        let description = match config.description_check {
+            None => String::new(),
+            Some(ref check) => match pull.body.as_deref().map(str::trim) {
+                None | Some("") => " 📝 no description".to_string(),
+                Some(body) => {
+                    let missing = check.missing_sections(body);
+                    if missing.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" 📝 missing {}", missing.join(", "))
+                    }
+                }
+            },
+        };
+        // Heavy PRs take longer to review.
+        let comments = match pull.review_comments {
+            Some(count) if count > 0 => format!(" 💬 {count}"),
+            _ => String::new(),
+        };
+        // Omitted if we couldn't afford to fetch reviews.
+        let reviews = match reviews.get(&pull.number) {
+            Some(summary) => {
+                let mut reviews = String::new();
+                if summary.approvals > 0 {
+                    reviews.push_str(&format!(" ✅ {}", summary.approvals));
+                }
+                if summary.changes_requested > 0 {
+                    reviews.push_str(&format!(" ❌ {}", summary.changes_requested));
+                }
+                reviews
+            }
+            None => String::new(),
+        };
+        // Previous reviews may no longer apply.
+        let pushed = match force_pushes.contains_key(&pull.number) {
+            true => format!(
+                " {}",
+                config.text(project, "pending_reviews.force_pushed", &[])
+            ),
+            false => String::new(),
+        };
+        msg.append_fields(&[
+            format!(
+                "{emojis}{}{pushed}{checklist}{comments}{reviews}{closes}{description}",
+                slack::link(&pull.url, Some(pull.title.as_str()))
+            ),
+            match acks.get(&pull.number) {
+                Some(ack) => format!("👀 {}", ack.by),
+                None => pull
+                    .reviewers
+                    .iter()
+                    .map(|reviewer| config.mention(reviewer))
+                    .chain(pull.teams.iter().map(|team| config.mention_team(team)))
+                    .join(", "),
+            },
+        ])
+    }
+    msg
+}
+
+/// How long an item has been waiting since `since`, in working time with `business_hours`,
+/// e.g. "2 working days", or else relative to `now`, e.g. "3 days ago".
+pub fn waiting(
+    config: &Config,
+    locale: Locale,
+    since: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> String {
+    match config.business_hours {
+        Some(ref hours) => {
+            humanize::working_time(business::elapsed(hours, since, now), hours.day(), locale)
+        }
+        None => humanize::relative(since, now, locale),
+    }
+}
+
+/// Render the draft PRs that have requested reviewers, with `drafts: separate`.
+///
+/// Reviewers aren't mentioned, as there's nothing for them to do yet.
+pub fn render_drafts(
+    config: &Config,
+    project: &Project,
+    pulls: impl IntoIterator<Item = PendingReview>,
+) -> slack::Section {
+    let locale = config.locale_for(project);
+    let title = config.text(
+        project,
+        "drafts.title",
+        &[(
+            "link",
+            slack::link(&project.html_url(), Some(project.repo.as_ref())),
+        )],
+    );
+    let mut msg = slack::Section::new(title);
+    msg.append_headers(&config.columns(locale, ["draft", "reviewer"]));
+    for pull in pulls {
+        let emojis = config.label_emojis(pull.labels.iter().map(String::as_str));
+        msg.append_fields(&[
+            format!(
+                "{emojis}{}",
+                slack::link(&pull.url, Some(pull.title.as_str()))
+            ),
+            pull.requested().join(", "),
+        ]);
+    }
+    msg
+}
+
+/// Index the PRs waiting for a review by the issues that they close.
+pub fn index_fixes(pulls: &[PendingReview]) -> HashMap<u64, Vec<u64>> {
+    let mut fixes: HashMap<u64, Vec<u64>> = HashMap::new();
+    for pull in pulls {
+        for issue in github::closing_references(pull.body.as_deref().unwrap_or_default()) {
+            fixes.entry(issue).or_default().push(pull.number);
+        }
+    }
+    fixes
+}
+
+/// Render a list of issues, of a project unless they come from a search.
+pub fn render_issues(
+    config: &Config,
+    project: Option<&Project>,
+    title: String,
+    issues: Vec<Issue>,
+    details: &HashMap<u64, IssueDetails>,
+    fixes: &HashMap<u64, Vec<u64>>,
+    now: DateTime<Utc>,
+) -> slack::Section {
+    let locale = project.map_or(config.locale, |project| config.locale_for(project));
+    let issue_fields = project.map_or(&[][..], |project| &project.issue_fields);
+    let mut msg = slack::Section::new(title);
+    msg.append_headers(&config.columns(locale, ["issue", "updater"]));
+    for (issue, depth) in github::roll_up_sub_issues(issues, |issue| issue.number, details) {
+        let emojis = config.label_emojis(issue.labels.iter().map(|label| label.name.as_str()));
+        let details = details.get(&issue.number).cloned().unwrap_or_default();
+        let indent = if depth == 0 {
+            String::new()
+        } else {
+            format!("{}↳ ", "  ".repeat(depth - 1))
+        };
+        let badge = match details.issue_type {
+            Some(issue_type) => format!("*[{issue_type}]* "),
+            None => String::new(),
+        };
+        let parent = match details.parent {
+            // The parent is displayed just above.
+            Some(_) if depth > 0 => String::new(),
+            Some(parent) => format!(" (sub-issue of #{parent})"),
+            None => String::new(),
+        };
+        // Let readers know that a fix is on its way.
+        let fixed_by = match fixes.get(&issue.number) {
+            Some(pulls) => format!(
+                " ← PR {} awaiting review",
+                pulls.iter().map(|number| format!("#{number}")).format(", ")
+            ),
+            None => String::new(),
+        };
+        // The answers of issue forms, first line only.
+        let answers = match issue_fields.is_empty() {
+            true => vec![],
+            false => github::issue_form_fields(issue.body.as_deref().unwrap_or_default()),
+        };
+        let answers = issue_fields
+            .iter()
+            .filter_map(|wanted| {
+                let (label, answer) = answers
+                    .iter()
+                    .find(|(label, _)| label.eq_ignore_ascii_case(wanted))?;
+                Some(format!(
+                    "{}: {}",
+                    slack::escape(label),
+                    slack::escape(answer.lines().next().unwrap_or_default())
+                ))
+            })
+            .join(", ");
+        let answers = match answers.is_empty() {
+            true => String::new(),
+            false => format!(" ({answers})"),
+        };
+        msg.append_fields(&[
+            format!(
+                "{indent}{badge}{emojis}{}{answers}{parent}{fixed_by}",
+                slack::link(&issue.html_url, Some(issue.title.as_str()))
+            ),
+            format!(
+                "{} {}",
+                issue.user.login,
+                humanize::relative(issue.updated_at, now, locale)
+            ),
+        ])
+    }
+    msg
+}
+
+/// The alias of the hooks that should receive an issue, by title and issue form fields.
+pub fn route_issue(project: &Project, issue: &Issue) -> Vec<String> {
+    let fields = github::issue_form_fields(issue.body.as_deref().unwrap_or_default());
+    project.routes_for(
+        &issue.title,
+        &fields,
+        issue.labels.iter().map(|label| label.name.as_str()),
+    )
+}
+
+/// Render the labels of interest added to or removed from issues.
+pub fn render_label_changes(
+    config: &Config,
+    project: &Project,
+    changes: Vec<LabelChange>,
+    now: DateTime<Utc>,
+) -> slack::Section {
+    let locale = config.locale_for(project);
+    let title = config.text(
+        project,
+        "label_changes.title",
+        &[(
+            "link",
+            slack::link(&project.html_url(), Some(project.repo.as_ref())),
+        )],
+    );
+    let mut msg = slack::Section::new(title);
+    msg.append_headers(&config.columns(locale, ["issue", "label"]));
+    for change in changes {
+        let emojis = config.label_emojis([change.label.as_str()]);
+        let sign = if change.added { "+" } else { "−" };
+        let actor = match change.actor {
+            Some(ref actor) => format!(
+                " {}",
+                config.text(project, "by", &[("author", actor.clone())])
+            ),
+            None => String::new(),
+        };
+        msg.append_fields(&[
+            format!(
+                "#{} {}",
+                change.number,
+                slack::link(&change.url, Some(change.title.as_str()))
+            ),
+            format!(
+                "{sign}{emojis}{}{actor} {}",
+                change.label,
+                humanize::relative(change.at, now, locale)
+            ),
+        ])
+    }
+    msg
+}
+
+/// Render the releases and tags published since the last run.
+pub fn render_releases(
+    config: &Config,
+    project: &Project,
+    releases: Vec<Published>,
+) -> slack::Section {
+    let locale = config.locale_for(project);
+    /// The number of lines of release notes that we quote.
+    const NOTES_LINES: usize = 3;
+    let title = config.text(
+        project,
+        "releases.title",
+        &[(
+            "link",
+            slack::link(&project.html_url(), Some(project.repo.as_ref())),
+        )],
+    );
+    let mut msg = slack::Section::new(title);
+    msg.append_headers(&config.columns(locale, ["release", "notes"]));
+    for published in releases {
+        let release = match published.name {
+            Some(ref name) if name != &published.tag => format!(
+                "{} ({})",
+                slack::link(&published.url, Some(name.as_str())),
+                slack::escape(&published.tag)
+            ),
+            _ => slack::link(&published.url, Some(published.tag.as_str())),
+        };
+        // Release notes are markdown from anyone with push access, not mrkdwn.
+        let notes = published
+            .notes
+            .as_deref()
+            .map(|notes| slack::escape(&release::excerpt_lines(notes, NOTES_LINES)))
+            .unwrap_or_default();
+        msg.append_fields(&[release, notes]);
+    }
+    msg
+}
+
+/// Render the open Dependabot alerts of a project, by severity, most severe first.
+pub fn render_security_alerts(
+    config: &Config,
+    project: &Project,
+    alerts: Vec<DependabotAlert>,
+) -> slack::Section {
+    let locale = config.locale_for(project);
+    let title = config.text(
+        project,
+        "security_alerts.title",
+        &[(
+            "link",
+            slack::link(&project.html_url(), Some(project.repo.as_ref())),
+        )],
+    );
+    let mut msg = slack::Section::new(title);
+    msg.append_headers(&config.columns(locale, ["severity", "dependencies"]));
+    for (severity, alerts) in dependabot::by_severity(alerts) {
+        let dependencies = alerts
+            .iter()
+            .map(|alert| {
+                let package = &alert.dependency.package;
+                format!(
+                    "{} ({}): {}",
+                    slack::link(&alert.html_url, Some(package.name.as_str())),
+                    package.ecosystem,
+                    alert.security_advisory.summary
+                )
+            })
+            .join("\n");
+        msg.append_fields(&[format!("{severity} ({})", alerts.len()), dependencies]);
+    }
+    msg
+}
+
+/// Render the open alerts of code scanning and secret scanning, most severe first.
+pub fn render_scanning_alerts(
+    config: &Config,
+    project: &Project,
+    alerts: Vec<ScanningAlert>,
+) -> slack::Section {
+    let locale = config.locale_for(project);
+    let title = config.text(
+        project,
+        "scanning_alerts.title",
+        &[(
+            "link",
+            slack::link(&project.html_url(), Some(project.repo.as_ref())),
+        )],
+    );
+    let mut msg = slack::Section::new(title);
+    msg.append_headers(&config.columns(locale, ["alert", "severity"]));
+    for alert in alerts {
+        let location = match alert.location {
+            Some(ref location) => format!(" in `{location}`"),
+            None => String::new(),
+        };
+        msg.append_fields(&[
+            format!(
+                "#{} {}",
+                alert.number,
+                slack::link(&alert.url, Some(alert.title.as_str()))
+            ),
+            format!("{} ({}){location}", alert.severity, alert.scanner),
+        ]);
+    }
+    msg
+}
+
+/// Render the workflows whose latest run on the default branch failed.
+pub fn render_ci_failures(
+    config: &Config,
+    project: &Project,
+    branch: &str,
+    runs: Vec<WorkflowRun>,
+    now: DateTime<Utc>,
+) -> slack::Section {
+    let locale = config.locale_for(project);
+    let title = config.text(
+        project,
+        "ci_failures.title",
+        &[
+            (
+                "link",
+                slack::link(&project.html_url(), Some(project.repo.as_ref())),
+            ),
+            ("branch", branch.to_string()),
+        ],
+    );
+    let mut msg = slack::Section::new(title);
+    msg.append_headers(&config.columns(locale, ["workflow", "commit"]));
+    for run in runs {
+        let name = run.name.as_deref().unwrap_or("workflow");
+        let sha = run.head_sha.get(..7).unwrap_or(&run.head_sha);
+        let commit = match run.head_commit {
+            Some(ref commit) => format!(
+                "`{sha}` {}",
+                slack::escape(commit.message.lines().next().unwrap_or_default())
+            ),
+            None => format!("`{sha}`"),
+        };
+        msg.append_fields(&[
+            slack::link(&run.html_url, Some(name)),
+            format!(
+                "{commit} {}",
+                humanize::relative(run.updated_at, now, locale)
+            ),
+        ])
+    }
+    msg
+}
+
+/// Render the star and fork milestones crossed, and the spike of stars, since the last run.
+pub fn render_popularity(
+    config: &Config,
+    project: &Project,
+    changes: Vec<Change>,
+) -> slack::Section {
+    let title = config.text(
+        project,
+        "popularity.title",
+        &[(
+            "link",
+            slack::link(&project.html_url(), Some(project.repo.as_ref())),
+        )],
+    );
+    let mut msg = slack::Section::new(title);
+    for change in changes {
+        let (news, count) = match change {
+            Change::Milestone {
+                metric,
+                milestone,
+                count,
+            } => (
+                config.text(
+                    project,
+                    match metric {
+                        popularity::Metric::Stars => "popularity.stars",
+                        popularity::Metric::Forks => "popularity.forks",
+                    },
+                    &[("milestone", popularity::round(milestone))],
+                ),
+                count,
+            ),
+            Change::Spike { gained, count } => (
+                config.text(
+                    project,
+                    "popularity.spike",
+                    &[("gained", gained.to_string())],
+                ),
+                count,
+            ),
+        };
+        msg.append_fields(&[
+            news,
+            config.text(project, "popularity.total", &[("count", count.to_string())]),
+        ]);
+    }
+    msg
+}
+
+/// Render the discussions without an answer or a reply from a maintainer.
+pub fn render_discussions(
+    config: &Config,
+    project: &Project,
+    discussions: Vec<Discussion>,
+    now: DateTime<Utc>,
+) -> slack::Section {
+    let locale = config.locale_for(project);
+    let title = config.text(
+        project,
+        "discussions.title",
+        &[(
+            "link",
+            slack::link(&project.html_url(), Some(project.repo.as_ref())),
+        )],
+    );
+    let mut msg = slack::Section::new(title);
+    msg.append_headers(&config.columns(locale, ["discussion", "author"]));
+    for discussion in discussions {
+        msg.append_fields(&[
+            format!(
+                "{}: {}",
+                discussion.category,
+                slack::link(&discussion.url, Some(discussion.title.as_str()))
+            ),
+            format!(
+                "{} {}",
+                discussion.author.as_deref().unwrap_or("ghost"),
+                humanize::relative(discussion.created_at, now, locale)
+            ),
+        ])
+    }
+    msg
+}
+
+/// Render the issues that didn't receive a first response within their SLA.
+pub fn render_sla_breaches(
+    config: &Config,
+    project: &Project,
+    breaches: Vec<Breach>,
+    now: DateTime<Utc>,
+) -> slack::Section {
+    let locale = config.locale_for(project);
+    let title = config.text(
+        project,
+        "sla_breaches.title",
+        &[(
+            "link",
+            slack::link(&project.html_url(), Some(project.repo.as_ref())),
+        )],
+    );
+    let mut msg = slack::Section::new(title);
+    msg.append_headers(&config.columns(locale, ["issue", "sla"]));
+    for breach in breaches {
+        let response = match breach.responded_at {
+            Some(at) => config.text(
+                project,
+                "sla_breaches.answered",
+                &[("time", humanize::relative(at, now, locale))],
+            ),
+            None => config.text(project, "sla_breaches.unanswered", &[]),
+        };
+        msg.append_fields(&[
+            format!(
+                "#{} {}",
+                breach.issue.number,
+                slack::link(&breach.issue.html_url, Some(breach.issue.title.as_str()))
+            ),
+            config.text(
+                project,
+                "sla_breaches.due",
+                &[
+                    ("label", breach.policy.label.clone()),
+                    ("due", humanize::relative(breach.deadline, now, locale)),
+                    ("response", response),
+                ],
+            ),
+        ])
+    }
+    msg
+}
+
+/// Render the issues that we keep reporting while nobody labels or assigns them, with
+/// `triage`.
+pub fn render_stuck_in_triage(
+    config: &Config,
+    project: &Project,
+    issues: Vec<Issue>,
+    lifecycles: &BTreeMap<u64, Lifecycle>,
+    triage: &Triage,
+    now: DateTime<Utc>,
+) -> slack::Section {
+    let locale = config.locale_for(project);
+    let title = config.text(
+        project,
+        "stuck_in_triage.title",
+        &[
+            (
+                "link",
+                slack::link(&project.html_url(), Some(project.repo.as_ref())),
+            ),
+            ("after", triage.after.to_string()),
+        ],
+    );
+    let mut msg = slack::Section::new(title);
+    msg.append_headers(&config.columns(locale, ["issue", "reported"]));
+    for issue in issues {
+        let reported = match lifecycles.get(&issue.number) {
+            Some(lifecycle) => config.text(
+                project,
+                "stuck_in_triage.reported",
+                &[
+                    ("count", lifecycle.reported.to_string()),
+                    (
+                        "time",
+                        humanize::relative(lifecycle.first_seen, now, locale),
+                    ),
+                ],
+            ),
+            None => String::new(),
+        };
+        msg.append_fields(&[
+            format!(
+                "#{} {}",
+                issue.number,
+                slack::link(&issue.html_url, Some(issue.title.as_str()))
+            ),
+            reported,
+        ])
+    }
+    msg
+}
+
+/// Render issues that have been transferred away from a project.
+pub fn render_transferred_issues(
+    config: &Config,
+    project: &Project,
+    issues: Vec<(u64, Issue)>,
+) -> slack::Section {
+    let locale = config.locale_for(project);
+    let title = config.text(
+        project,
+        "transferred_issues.title",
+        &[(
+            "link",
+            slack::link(&project.html_url(), Some(project.repo.as_ref())),
+        )],
+    );
+    let mut msg = slack::Section::new(title);
+    msg.append_headers(&config.columns(locale, ["issue", "now_at"]));
+    for (number, issue) in issues {
+        let destination = issue
+            .html_url
+            .path()
+            .trim_start_matches('/')
+            .replace("/issues/", "#");
+        msg.append_fields(&[
+            format!("#{number} {}", html_escape::encode_text(&issue.title)),
+            slack::link(&issue.html_url, Some(destination.as_str())),
+        ])
+    }
+    msg
+}
+
+/// Whether an item has waited for longer than `duration` since `at`, in working time with
+/// `business_hours`.
+pub fn has_waited(
+    config: &Config,
+    duration: chrono::Duration,
+    at: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> bool {
+    match config.business_hours {
+        Some(ref hours) => business::elapsed(hours, at, now) > business::threshold(hours, duration),
+        None => now - at > duration,
+    }
+}
+
+/// A duration of the config, e.g. `5d`, in working time with `business_hours`.
+fn span(config: &Config, locale: Locale, duration: chrono::Duration) -> String {
+    match config.business_hours {
+        Some(ref hours) => {
+            humanize::working_time(business::threshold(hours, duration), hours.day(), locale)
+        }
+        None => humanize::duration(duration, locale),
+    }
+}
+
+/// Render the open PRs without activity for `stale_after`.
+pub fn render_stale_pulls(
+    config: &Config,
+    project: &Project,
+    pulls: impl IntoIterator<Item = PullRequest>,
+    stale_after: chrono::Duration,
+    now: DateTime<Utc>,
+) -> slack::Section {
+    let locale = config.locale_for(project);
+    let title = config.text(
+        project,
+        "stale_pulls.title",
+        &[
+            (
+                "link",
+                slack::link(&project.html_url(), Some(project.repo.as_ref())),
+            ),
+            ("idle", span(config, locale, stale_after)),
+        ],
+    );
+    let mut msg = slack::Section::new(title);
+    msg.append_headers(&config.columns(locale, ["pr", "last_activity"]));
+    for pull in pulls {
+        let Some(ref url) = pull.html_url else {
+            continue;
+        };
+        let labels = pull
+            .labels
+            .iter()
+            .flatten()
+            .map(|label| label.name.as_str());
+        let author = match pull.user {
+            Some(ref user) => format!(
+                " {}",
+                config.text(project, "by", &[("author", user.login.clone())])
+            ),
+            None => String::new(),
+        };
+        msg.append_fields(&[
+            format!(
+                "{}{}{author}",
+                config.label_emojis(labels),
+                slack::link(url, pull.title.as_deref()),
+            ),
+            pull.updated_at
+                .map(|at| waiting(config, locale, at, now))
+                .unwrap_or_default(),
+        ]);
+    }
+    msg
+}
+
+/// Render the open PRs that nobody was asked to review, with the reviewers we suggest,
+/// if we could afford to look for them.
+pub fn render_unreviewed_pulls(
+    config: &Config,
+    project: &Project,
+    pulls: impl IntoIterator<Item = PullRequest>,
+    authors: &HashMap<u64, Vec<Vec<String>>>,
+    max: usize,
+) -> slack::Section {
+    let locale = config.locale_for(project);
+    let title = config.text(
+        project,
+        "unreviewed_pulls.title",
+        &[(
+            "link",
+            slack::link(&project.html_url(), Some(project.repo.as_ref())),
+        )],
+    );
+    let mut msg = slack::Section::new(title);
+    msg.append_headers(&config.columns(locale, ["pr", "suggested_reviewers"]));
+    for pull in pulls {
+        let Some(ref url) = pull.html_url else {
+            continue;
+        };
+        let labels = pull
+            .labels
+            .iter()
+            .flatten()
+            .map(|label| label.name.as_str());
+        let author = pull.user.as_ref().map(|user| user.login.as_str());
+        let suggestions = match authors.get(&pull.number) {
+            Some(authors) => github::suggest_reviewers(
+                authors,
+                |login| {
+                    author.is_some_and(|author| author.eq_ignore_ascii_case(login))
+                        || config.ignores_author(login)
+                },
+                max,
+            )
+            .iter()
+            .map(|login| config.mention(login))
+            .join(", "),
+            None => String::new(),
+        };
+        msg.append_fields(&[
+            format!(
+                "{}{}{}",
+                config.label_emojis(labels),
+                slack::link(url, pull.title.as_deref()),
+                author
+                    .map(|author| format!(
+                        " {}",
+                        config.text(project, "by", &[("author", author.to_string())])
+                    ))
+                    .unwrap_or_default(),
+            ),
+            suggestions,
+        ]);
+    }
+    msg
+}
+
+/// Render the weekly digest of a project.
+pub fn render_weekly_digest(
+    config: &Config,
+    project: &Project,
+    digest: &WeeklyDigest,
+    now: DateTime<Utc>,
+) -> slack::Section {
+    let locale = config.locale_for(project);
+    let title = config.text(
+        project,
+        "weekly_digest.title",
+        &[(
+            "link",
+            slack::link(&project.html_url(), Some(project.repo.as_ref())),
+        )],
+    );
+    let mut msg = slack::Section::new(title);
+    let activity = &digest.activity;
+    msg.append_fields(&[
+        format!("*{}*", config.text(project, "weekly_digest.past_week", &[])),
+        config.text(
+            project,
+            "weekly_digest.activity",
+            &[
+                ("merged", activity.merged.to_string()),
+                ("opened", activity.opened.to_string()),
+                ("closed", activity.closed.to_string()),
+            ],
+        ),
+    ]);
+    if let Some(ref reviewers) = activity.reviewers {
+        msg.append_fields(&[
+            format!(
+                "*{}*",
+                config.text(project, "weekly_digest.top_reviewers", &[])
+            ),
+            match reviewers.is_empty() {
+                true => config.text(project, "weekly_digest.nobody", &[]),
+                false => reviewers
+                    .iter()
+                    .map(|(login, count)| format!("{} ({count})", config.mention(login)))
+                    .join(", "),
+            },
+        ]);
+    }
+    if let Some(ref pull) = digest.oldest {
+        msg.append_fields(&[
+            format!("*{}*", config.text(project, "weekly_digest.oldest", &[])),
+            config.text(
+                project,
+                "weekly_digest.opened",
+                &[
+                    (
+                        "pull",
+                        match pull.html_url {
+                            Some(ref url) => slack::link(url, pull.title.as_deref()),
+                            None => format!("#{}", pull.number),
+                        },
+                    ),
+                    (
+                        "age",
+                        pull.created_at
+                            .map(|at| waiting(config, locale, at, now))
+                            .unwrap_or_default(),
+                    ),
+                ],
+            ),
+        ]);
+    }
+    msg.append_fields(&[
+        format!(
+            "*{}*",
+            config.text(
+                project,
+                "weekly_digest.by_age",
+                &[("count", digest.histogram.total().to_string())],
+            )
+        ),
+        String::new(),
+    ]);
+    msg.append_fields(&[
+        format!("```\n{}\n```", digest.histogram.render()),
+        String::new(),
+    ]);
+    if digest.complete.not() {
+        msg.append_fields(&[
+            format!(
+                "_{}_",
+                config.text(project, "weekly_digest.incomplete", &[])
+            ),
+            String::new(),
+        ]);
+    }
+    msg
+}
+
+/// Render the progress of the open milestones of a project.
+pub fn render_milestone_progress(
+    config: &Config,
+    project: &Project,
+    milestones: Vec<Milestone>,
+    now: DateTime<Utc>,
+) -> slack::Section {
+    let locale = config.locale_for(project);
+    let title = config.text(
+        project,
+        "milestones.title",
+        &[(
+            "link",
+            slack::link(&project.html_url(), Some(project.repo.as_ref())),
+        )],
+    );
+    let mut msg = slack::Section::new(title);
+    msg.append_headers(&config.columns(locale, ["milestone", "progress"]));
+    for milestone in milestones {
+        let due = match milestone.due_on {
+            Some(due_on) => format!(
+                ", {}",
+                config.text(
+                    project,
+                    "milestones.due",
+                    &[("due", humanize::relative(due_on, now, locale))]
+                )
+            ),
+            None => String::new(),
+        };
+        let open = milestone.open_issues.unwrap_or_default().max(0) as u64;
+        let closed = milestone.closed_issues.unwrap_or_default().max(0) as u64;
+        msg.append_fields(&[
+            format!(
+                "{}{due}",
+                slack::link(&milestone.html_url, Some(milestone.title.as_str()))
+            ),
+            format!(
+                "`{}` {}",
+                digest::progress_bar(closed, open),
+                config.text(
+                    project,
+                    "milestones.counts",
+                    &[("closed", closed.to_string()), ("open", open.to_string())]
+                )
+            ),
+        ]);
+    }
+    msg
+}
+
+/// Render a milestone due soon, along with its open issues.
+pub fn render_due_milestone(
+    config: &Config,
+    project: &Project,
+    milestone: &Milestone,
+    issues: Vec<Issue>,
+    escalation: &[String],
+    now: DateTime<Utc>,
+) -> slack::Section {
+    let locale = config.locale_for(project);
+    let due = match milestone.due_on {
+        Some(due_on) => humanize::relative(due_on, now, locale),
+        None => config.text(project, "due_milestone.soon", &[]),
+    };
+    let title = config.text(
+        project,
+        "due_milestone.title",
+        &[
+            (
+                "milestone",
+                slack::link(&milestone.html_url, Some(milestone.title.as_str())),
+            ),
+            (
+                "link",
+                slack::link(&project.html_url(), Some(project.repo.as_ref())),
+            ),
+            ("due", due),
+            ("count", issues.len().to_string()),
+        ],
+    );
+    let title = if escalation.is_empty() {
+        title
+    } else {
+        format!("{title} (cc {})", escalation.join(", "))
+    };
+    let mut msg = slack::Section::new(title);
+    msg.append_headers(&config.columns(locale, ["issue", "assignees"]));
+    for issue in issues {
+        let emojis = config.label_emojis(issue.labels.iter().map(|label| label.name.as_str()));
+        let assignees = if issue.assignees.is_empty() {
+            config.text(project, "due_milestone.unassigned", &[])
+        } else {
+            format!(
+                "{}",
+                issue
+                    .assignees
+                    .iter()
+                    .map(|assignee| &assignee.login)
+                    .format(", ")
+            )
+        };
+        msg.append_fields(&[
+            format!(
+                "{emojis}{}",
+                slack::link(&issue.html_url, Some(issue.title.as_str()))
+            ),
+            assignees,
+        ])
+    }
+    msg
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::{config::Config, github::PendingReview, report::SectionKind, template::Templates};
+
+    use super::{fit, render_pending_requests};
+
+    /// Do we keep the lines that fit, and count the others?
+    #[test]
+    fn test_fit() {
+        let lines = vec!["é".repeat(10), "a".repeat(10), "b".repeat(10)];
+        assert_eq!(fit(lines.clone(), 22), (lines[..2].to_vec(), 1));
+        assert_eq!(fit(lines.clone(), 5), (vec![], 3));
+        assert_eq!(fit(lines.clone(), 100), (lines, 0));
+    }
+
+    /// Do row templates render the PRs, without the headers of the columns?
+    #[test]
+    fn test_row_templates() {
+        let dir = std::env::temp_dir().join("qastor-test-row-templates");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("pending_reviews.row.tmpl"), "• {item} ({details})").unwrap();
+        let templates = Templates::load(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let config = Config::parse(
+            r#"
+projects:
+  - url: "https://github.com/owner1/project1"
+"#,
+        )
+        .unwrap();
+        let project = &config.projects[0];
+        let pull = PendingReview {
+            number: 1,
+            url: "https://github.com/owner1/project1/pull/1".parse().unwrap(),
+            title: "Fix".to_string(),
+            reviewers: vec!["alice".to_string()],
+            teams: vec![],
+            labels: vec![],
+            body: None,
+            created_at: None,
+            updated_at: None,
+            author: None,
+            draft: false,
+            review_comments: None,
+        };
+        let section = render_pending_requests(
+            &config,
+            project,
+            [pull],
+            &HashMap::new(),
+            &HashMap::new(),
+            &HashMap::new(),
+            None,
+        );
+        assert_eq!(section.fields().count(), 4);
+        let templated = templates.apply("slack", SectionKind::PendingReviews, project, &section);
+        let rows = templated.title().lines().skip(1).collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            ["• <https://github.com/owner1/project1/pull/1|Fix> (alice)"]
+        );
+    }
+}
@@ -0,0 +1,151 @@
+use std::{
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::report::SectionKind;
+
+/// The messages rendered for a sink, as sent (or as they should have been sent).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub at: DateTime<Utc>,
+    pub project: Url,
+
+    /// The label of the sink, see `Sink::label`.
+    pub sink: String,
+
+    /// `None` for the findings sent to JSON webhooks.
+    pub kind: Option<SectionKind>,
+    pub messages: Vec<serde_json::Value>,
+}
+
+/// The messages rendered for each sink during a run, kept so that `qastor resend` can
+/// send them again, e.g. to debug delivery problems without querying GitHub again.
+pub struct Replay {
+    dir: Option<PathBuf>,
+    run: String,
+    entries: Vec<Entry>,
+}
+impl Replay {
+    /// Keep the messages of a run starting `now` in `dir`, if specified.
+    pub fn new(dir: Option<PathBuf>, now: DateTime<Utc>) -> Self {
+        Replay {
+            dir,
+            run: now.format("%Y%m%dT%H%M%SZ").to_string(),
+            entries: vec![],
+        }
+    }
+
+    /// The id of the run, e.g. `20250106T093000Z`.
+    pub fn run(&self) -> &str {
+        &self.run
+    }
+
+    /// Record the messages rendered for a sink, before sending them.
+    pub fn record(
+        &mut self,
+        project: &Url,
+        sink: &str,
+        kind: Option<SectionKind>,
+        messages: &[serde_json::Value],
+    ) {
+        if self.dir.is_none() {
+            return;
+        }
+        self.entries.push(Entry {
+            at: Utc::now(),
+            project: project.clone(),
+            sink: sink.to_string(),
+            kind,
+            messages: messages.to_vec(),
+        });
+    }
+
+    /// Append the messages recorded since the last call to `<dir>/<run>.jsonl`.
+    pub fn save(&mut self) -> Result<(), anyhow::Error> {
+        let Some(ref dir) = self.dir else {
+            return Ok(());
+        };
+        if self.entries.is_empty() {
+            return Ok(());
+        }
+        std::fs::create_dir_all(dir).context("Could not create replay directory")?;
+        let mut out = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(Self::path(dir, &self.run))
+            .context("Could not open replay file")?;
+        for entry in &self.entries {
+            let line = serde_json::to_string(entry).context("Could not serialize entry")?;
+            writeln!(out, "{line}").context("Could not write replay file")?;
+        }
+        self.entries.clear();
+        Ok(())
+    }
+
+    /// Read the messages recorded during a run.
+    pub fn load(dir: &Path, run: &str) -> Result<Vec<Entry>, anyhow::Error> {
+        let path = Self::path(dir, run);
+        let file = std::fs::File::open(&path)
+            .with_context(|| format!("Could not open {}", path.display()))?;
+        let mut entries = vec![];
+        for line in BufReader::new(file).lines() {
+            let line = line.with_context(|| format!("Could not read {}", path.display()))?;
+            entries.push(
+                serde_json::from_str(&line)
+                    .with_context(|| format!("Invalid entry in {}", path.display()))?,
+            );
+        }
+        Ok(entries)
+    }
+
+    fn path(dir: &Path, run: &str) -> PathBuf {
+        dir.join(format!("{run}.jsonl"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{TimeZone, Utc};
+    use url::Url;
+
+    use super::Replay;
+    use crate::report::SectionKind;
+
+    /// Can the messages of a run be read back?
+    #[test]
+    fn test_replay_roundtrip() {
+        let dir = std::env::temp_dir().join("qastor-test-replay");
+        let _ = std::fs::remove_dir_all(&dir);
+        let project = Url::parse("https://github.com/owner1/project1").unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 1, 6, 9, 30, 0).unwrap();
+
+        let mut replay = Replay::new(Some(dir.clone()), now);
+        assert_eq!(replay.run(), "20250106T093000Z");
+        let messages = [serde_json::json!({ "text": "PRs of repo project1" })];
+        replay.record(
+            &project,
+            "sdk-team",
+            Some(SectionKind::PendingReviews),
+            &messages,
+        );
+        replay.save().unwrap();
+        // Saved project by project.
+        replay.record(&project, "docs-team", None, &messages);
+        replay.save().unwrap();
+        replay.save().unwrap();
+
+        let entries = Replay::load(&dir, "20250106T093000Z").unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].sink, "sdk-team");
+        assert_eq!(entries[0].messages, messages);
+        assert_eq!(entries[1].sink, "docs-team");
+    }
+}
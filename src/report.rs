@@ -0,0 +1,339 @@
+use chrono::{DateTime, Utc};
+use derive_more::Display;
+use octocrab::models::{issues::Issue, pulls::PullRequest};
+use serde::{Deserialize, Serialize};
+
+use crate::{github::PendingReview, sla::Breach, slack};
+
+/// An item listed in a report, e.g. a PR or an issue.
+pub trait Item {
+    /// When the item was opened, if we know.
+    fn opened_at(&self) -> Option<DateTime<Utc>>;
+
+    fn number(&self) -> u64;
+}
+impl Item for PendingReview {
+    fn opened_at(&self) -> Option<DateTime<Utc>> {
+        self.created_at
+    }
+
+    fn number(&self) -> u64 {
+        self.number
+    }
+}
+impl Item for Issue {
+    fn opened_at(&self) -> Option<DateTime<Utc>> {
+        Some(self.created_at)
+    }
+
+    fn number(&self) -> u64 {
+        self.number
+    }
+}
+impl Item for PullRequest {
+    fn opened_at(&self) -> Option<DateTime<Utc>> {
+        self.created_at
+    }
+
+    fn number(&self) -> u64 {
+        self.number
+    }
+}
+impl Item for Breach {
+    fn opened_at(&self) -> Option<DateTime<Utc>> {
+        Some(self.issue.created_at)
+    }
+
+    fn number(&self) -> u64 {
+        self.issue.number
+    }
+}
+
+/// Sort items the way reports list them: oldest first, then by number, items without a
+/// date last.
+///
+/// Reports shouldn't depend on the order in which GitHub (or our maps) return items, which
+/// varies e.g. between `fetch` modes, so that unchanged reports look unchanged.
+pub fn sort<T: Item>(items: &mut [T]) {
+    items.sort_by_key(|item| (item.opened_at().is_none(), item.opened_at(), item.number()));
+}
+
+/// The sections of the report of a project, in the order in which reports list them: by
+/// kind, in the order of `SectionKind`, then in the order they were added.
+///
+/// Items within each section are expected in the order of `sort`.
+#[derive(Default)]
+pub struct Report {
+    sections: Vec<(SectionKind, slack::Section)>,
+}
+impl Report {
+    /// Add a section at its place in the report.
+    pub fn push(&mut self, kind: SectionKind, section: slack::Section) {
+        let index = self.sections.partition_point(|(other, _)| *other <= kind);
+        self.sections.insert(index, (kind, section));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sections.len()
+    }
+
+    /// The sections, in report order, along with their kind.
+    pub fn iter(&self) -> impl Iterator<Item = &(SectionKind, slack::Section)> {
+        self.sections.iter()
+    }
+
+    /// The sections, in report order.
+    pub fn sections(&self) -> Vec<slack::Section> {
+        self.sections
+            .iter()
+            .map(|(_, section)| section.clone())
+            .collect()
+    }
+}
+
+/// How urgent a section of a report is.
+///
+/// Hooks may subscribe to only some severities.
+#[derive(Deserialize, Display, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    #[display("info")]
+    Info,
+    #[display("warning")]
+    Warning,
+    #[display("critical")]
+    Critical,
+}
+
+/// The kinds of sections that may appear in a report.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SectionKind {
+    /// PRs waiting for a review.
+    PendingReviews,
+
+    /// Draft PRs with requested reviewers, with `drafts: separate`.
+    DraftReviews,
+
+    /// Issues updated recently.
+    UpdatedIssues,
+
+    /// Open PRs that nobody was asked to review, with `suggest_reviewers`.
+    UnreviewedPulls,
+
+    /// Open PRs without activity for `stale_after`, whether or not they wait for reviews.
+    StalePulls,
+
+    /// Issues previously reported, which have since been transferred to another repository.
+    TransferredIssues,
+
+    /// Milestones due soon, with open issues.
+    DueMilestones,
+
+    /// The progress of each open milestone, weekly with `milestone_report`.
+    MilestoneProgress,
+
+    /// Open Dependabot PRs, with the compatibility of their updates.
+    DependencyUpdates,
+
+    /// Open Dependabot alerts, by severity, with `security`.
+    SecurityAlerts,
+
+    /// Open alerts of code scanning and secret scanning, with `security`.
+    ScanningAlerts,
+
+    /// Labels of interest added to or removed from issues.
+    LabelChanges,
+
+    /// Discussions without an answer or a reply from a maintainer.
+    UnansweredDiscussions,
+
+    /// Releases and tags published since the last run, with `releases`.
+    Releases,
+
+    /// Star and fork milestones crossed, and spikes of stars, since the last run, with
+    /// `stars`.
+    Popularity,
+
+    /// Workflows whose latest run on the default branch failed, with `ci`.
+    CiFailures,
+
+    /// The weekly summary of a project, e.g. the ages of open PRs.
+    WeeklyDigest,
+
+    /// Issues that didn't receive a first response within their SLA.
+    SlaBreaches,
+
+    /// Issues reported several times without being labelled or assigned, with `triage`.
+    StuckInTriage,
+}
+impl SectionKind {
+    /// How urgent this kind of section is.
+    pub fn severity(self) -> Severity {
+        match self {
+            SectionKind::PendingReviews => Severity::Info,
+            SectionKind::DraftReviews => Severity::Info,
+            SectionKind::UpdatedIssues => Severity::Info,
+            SectionKind::UnreviewedPulls => Severity::Info,
+            SectionKind::StalePulls => Severity::Warning,
+            SectionKind::TransferredIssues => Severity::Info,
+            SectionKind::DueMilestones => Severity::Warning,
+            SectionKind::MilestoneProgress => Severity::Info,
+            SectionKind::DependencyUpdates => Severity::Info,
+            SectionKind::SecurityAlerts => Severity::Warning,
+            SectionKind::ScanningAlerts => Severity::Warning,
+            SectionKind::LabelChanges => Severity::Warning,
+            SectionKind::UnansweredDiscussions => Severity::Info,
+            SectionKind::Releases => Severity::Info,
+            SectionKind::Popularity => Severity::Info,
+            SectionKind::CiFailures => Severity::Warning,
+            SectionKind::WeeklyDigest => Severity::Info,
+            SectionKind::SlaBreaches => Severity::Critical,
+            SectionKind::StuckInTriage => Severity::Warning,
+        }
+    }
+
+    /// Whether sections of this kind still go out while their project is snoozed, see
+    /// `qastor snooze`, so that we may record them as reported.
+    pub fn sent_while_snoozed(self) -> bool {
+        self.severity() == Severity::Critical
+    }
+
+    /// Whether sections of this kind are computed on every run, so that their absence means
+    /// that there's nothing left to report, rather than e.g. that the week isn't over.
+    pub fn on_every_run(self) -> bool {
+        match self {
+            SectionKind::WeeklyDigest
+            | SectionKind::MilestoneProgress
+            | SectionKind::Releases
+            | SectionKind::Popularity
+            | SectionKind::SecurityAlerts
+            | SectionKind::ScanningAlerts => false,
+            SectionKind::PendingReviews
+            | SectionKind::DraftReviews
+            | SectionKind::UpdatedIssues
+            | SectionKind::UnreviewedPulls
+            | SectionKind::StalePulls
+            | SectionKind::TransferredIssues
+            | SectionKind::DueMilestones
+            | SectionKind::DependencyUpdates
+            | SectionKind::LabelChanges
+            | SectionKind::UnansweredDiscussions
+            | SectionKind::CiFailures
+            | SectionKind::SlaBreaches
+            | SectionKind::StuckInTriage => true,
+        }
+    }
+
+    /// The emoji standing for this kind of section, e.g. on the status board.
+    pub fn emoji(self) -> &'static str {
+        match self {
+            SectionKind::PendingReviews => "🔍",
+            SectionKind::DraftReviews => "✏️",
+            SectionKind::UpdatedIssues => "🐛",
+            SectionKind::UnreviewedPulls => "🙋",
+            SectionKind::StalePulls => "🕸️",
+            SectionKind::TransferredIssues => "🚚",
+            SectionKind::DueMilestones => "⏳",
+            SectionKind::MilestoneProgress => "🎯",
+            SectionKind::DependencyUpdates => "📦",
+            SectionKind::SecurityAlerts => "🛡️",
+            SectionKind::ScanningAlerts => "🔐",
+            SectionKind::LabelChanges => "🏷️",
+            SectionKind::UnansweredDiscussions => "💬",
+            SectionKind::Releases => "🚀",
+            SectionKind::Popularity => "⭐",
+            SectionKind::CiFailures => "🔴",
+            SectionKind::WeeklyDigest => "📊",
+            SectionKind::SlaBreaches => "🚨",
+            SectionKind::StuckInTriage => "🧊",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::ops::Not;
+
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use crate::slack::Section;
+
+    use super::{sort, Item, Report, SectionKind};
+
+    struct Numbered(u64, Option<DateTime<Utc>>);
+    impl Item for Numbered {
+        fn opened_at(&self) -> Option<DateTime<Utc>> {
+            self.1
+        }
+
+        fn number(&self) -> u64 {
+            self.0
+        }
+    }
+
+    /// Are items listed oldest first, then by number, regardless of the order we got them in?
+    #[test]
+    fn test_sort() {
+        let monday = Utc.with_ymd_and_hms(2024, 5, 6, 12, 0, 0).unwrap();
+        let tuesday = Utc.with_ymd_and_hms(2024, 5, 7, 12, 0, 0).unwrap();
+        let mut items = vec![
+            Numbered(1, None),
+            Numbered(4, Some(tuesday)),
+            Numbered(3, Some(monday)),
+            Numbered(2, Some(tuesday)),
+        ];
+        sort(&mut items);
+        assert_eq!(
+            items.iter().map(Item::number).collect::<Vec<_>>(),
+            [3, 2, 4, 1]
+        );
+    }
+
+    /// Are sections listed by kind, then in the order they were added?
+    #[test]
+    fn test_report_order() {
+        let mut report = Report::default();
+        report.push(
+            SectionKind::UpdatedIssues,
+            Section::new("Issues".to_string()),
+        );
+        report.push(
+            SectionKind::PendingReviews,
+            Section::new("Reviews".to_string()),
+        );
+        report.push(SectionKind::UpdatedIssues, Section::new("More".to_string()));
+        assert_eq!(report.len(), 3);
+        assert_eq!(
+            report
+                .iter()
+                .map(|(kind, section)| (*kind, section.plain_text()))
+                .collect::<Vec<_>>(),
+            [
+                (SectionKind::PendingReviews, "Reviews".to_string()),
+                (SectionKind::UpdatedIssues, "Issues".to_string()),
+                (SectionKind::UpdatedIssues, "More".to_string()),
+            ]
+        );
+    }
+
+    /// Do only critical sections go out while snoozed?
+    #[test]
+    fn test_sent_while_snoozed() {
+        assert!(SectionKind::SlaBreaches.sent_while_snoozed());
+        assert!(SectionKind::WeeklyDigest.sent_while_snoozed().not());
+        assert!(SectionKind::UpdatedIssues.sent_while_snoozed().not());
+    }
+
+    /// Are the messages about occasional sections left alone when they don't come back?
+    #[test]
+    fn test_on_every_run() {
+        assert!(SectionKind::PendingReviews.on_every_run());
+        assert!(SectionKind::WeeklyDigest.on_every_run().not());
+        assert!(SectionKind::SecurityAlerts.on_every_run().not());
+    }
+}
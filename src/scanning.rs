@@ -0,0 +1,216 @@
+use anyhow::Context;
+use derive_more::Display;
+use octocrab::{Octocrab, Page};
+use serde::{de::DeserializeOwned, Deserialize};
+use url::Url;
+
+use crate::{config::Project, dependabot::AlertSeverity, error::Error, github};
+
+/// What found an alert.
+#[derive(Display, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Scanner {
+    /// Code scanning, e.g. CodeQL.
+    #[display("code scanning")]
+    Code,
+
+    /// Secret scanning, i.e. a leaked token or password.
+    #[display("secret scanning")]
+    Secret,
+}
+
+/// An open alert of code scanning or secret scanning.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanningAlert {
+    pub scanner: Scanner,
+    pub number: u64,
+    pub url: Url,
+
+    /// What was found, e.g. the description of the rule or the type of secret.
+    pub title: String,
+
+    /// Where, e.g. `src/main.rs:12`, for code scanning.
+    pub location: Option<String>,
+
+    /// Leaked secrets are always critical.
+    pub severity: AlertSeverity,
+}
+
+/// An alert of code scanning, as returned by the REST API.
+#[derive(Deserialize)]
+struct CodeScanningAlert {
+    number: u64,
+    html_url: Url,
+    rule: Rule,
+    most_recent_instance: Option<Instance>,
+}
+#[derive(Deserialize)]
+struct Rule {
+    description: String,
+
+    /// E.g. `error` or `warning`.
+    severity: Option<String>,
+
+    /// For security rules, e.g. `high`.
+    security_severity_level: Option<AlertSeverity>,
+}
+#[derive(Deserialize)]
+struct Instance {
+    location: Option<Location>,
+}
+#[derive(Deserialize)]
+struct Location {
+    path: String,
+    start_line: Option<u64>,
+}
+impl From<CodeScanningAlert> for ScanningAlert {
+    fn from(alert: CodeScanningAlert) -> Self {
+        // Rules that are not about security (e.g. code quality) only have a severity.
+        let fallback = match alert.rule.severity.as_deref() {
+            Some("error") => AlertSeverity::High,
+            Some("warning") => AlertSeverity::Medium,
+            _ => AlertSeverity::Low,
+        };
+        let severity = alert.rule.security_severity_level.unwrap_or(fallback);
+        let location = alert
+            .most_recent_instance
+            .and_then(|instance| instance.location)
+            .map(|location| match location.start_line {
+                Some(line) => format!("{}:{line}", location.path),
+                None => location.path,
+            });
+        ScanningAlert {
+            scanner: Scanner::Code,
+            number: alert.number,
+            url: alert.html_url,
+            title: alert.rule.description,
+            location,
+            severity,
+        }
+    }
+}
+
+/// An alert of secret scanning, as returned by the REST API.
+#[derive(Deserialize)]
+struct SecretScanningAlert {
+    number: u64,
+    html_url: Url,
+    secret_type: String,
+
+    /// E.g. `GitHub Personal Access Token`.
+    secret_type_display_name: Option<String>,
+}
+impl From<SecretScanningAlert> for ScanningAlert {
+    fn from(alert: SecretScanningAlert) -> Self {
+        ScanningAlert {
+            scanner: Scanner::Secret,
+            number: alert.number,
+            url: alert.html_url,
+            title: alert.secret_type_display_name.unwrap_or(alert.secret_type),
+            location: None,
+            severity: AlertSeverity::Critical,
+        }
+    }
+}
+
+/// List the open alerts of a scanner on a project.
+///
+/// Requires authentication, with "Code scanning alerts: read" or "Secret scanning alerts:
+/// read" for GitHub apps.
+pub async fn fetch_alerts(
+    octocrab: &Octocrab,
+    project: &Project,
+    scanner: Scanner,
+    per_page: u8,
+) -> Result<Vec<ScanningAlert>, anyhow::Error> {
+    let route = format!(
+        "/repos/{owner}/{repo}/{path}/alerts",
+        owner = project.owner,
+        repo = project.repo,
+        path = match scanner {
+            Scanner::Code => "code-scanning",
+            Scanner::Secret => "secret-scanning",
+        }
+    );
+    let params = serde_json::json!({
+        "state": "open",
+        "per_page": per_page,
+    });
+    Ok(match scanner {
+        Scanner::Code => fetch_pages::<CodeScanningAlert>(octocrab, route, &params)
+            .await
+            .context("Couldn't download code scanning alerts")?
+            .into_iter()
+            .map(ScanningAlert::from)
+            .collect(),
+        Scanner::Secret => fetch_pages::<SecretScanningAlert>(octocrab, route, &params)
+            .await
+            .context("Couldn't download secret scanning alerts")?
+            .into_iter()
+            .map(ScanningAlert::from)
+            .collect(),
+    })
+}
+
+/// Read the alerts of a scanner, page by page.
+async fn fetch_pages<T: DeserializeOwned>(
+    octocrab: &Octocrab,
+    route: String,
+    params: &serde_json::Value,
+) -> Result<Vec<T>, anyhow::Error> {
+    /// Enough to find the most severe ones among the alerts of any sane project.
+    const MAX_ALERTS: usize = 1000;
+    let first: Page<T> = octocrab
+        .get(route, Some(params))
+        .await
+        .map_err(Error::from_github)?;
+    let (alerts, _) = github::collect_pages(octocrab, first, MAX_ALERTS).await?;
+    Ok(alerts)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CodeScanningAlert, ScanningAlert, SecretScanningAlert};
+    use crate::dependabot::AlertSeverity;
+
+    /// Do alerts get the severity of their rule, and leaked secrets the highest one?
+    #[test]
+    fn test_severity() {
+        let code = |rule: serde_json::Value| -> ScanningAlert {
+            serde_json::from_value::<CodeScanningAlert>(serde_json::json!({
+                "number": 1,
+                "html_url": "https://github.com/owner/repo/security/code-scanning/1",
+                "rule": rule,
+                "most_recent_instance": {
+                    "location": { "path": "src/main.rs", "start_line": 12 },
+                },
+            }))
+            .unwrap()
+            .into()
+        };
+        let alert = code(serde_json::json!({
+            "description": "SQL injection",
+            "severity": "error",
+            "security_severity_level": "critical",
+        }));
+        assert_eq!(alert.severity, AlertSeverity::Critical);
+        assert_eq!(alert.location.as_deref(), Some("src/main.rs:12"));
+        let alert = code(serde_json::json!({
+            "description": "Unused variable",
+            "severity": "warning",
+            "security_severity_level": null,
+        }));
+        assert_eq!(alert.severity, AlertSeverity::Medium);
+
+        let secret: ScanningAlert =
+            serde_json::from_value::<SecretScanningAlert>(serde_json::json!({
+                "number": 2,
+                "html_url": "https://github.com/owner/repo/security/secret-scanning/2",
+                "secret_type": "github_personal_access_token",
+                "secret_type_display_name": "GitHub Personal Access Token",
+            }))
+            .unwrap()
+            .into();
+        assert_eq!(secret.severity, AlertSeverity::Critical);
+        assert_eq!(secret.title, "GitHub Personal Access Token");
+    }
+}
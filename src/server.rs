@@ -0,0 +1,337 @@
+//! The HTTP endpoints of `qastor serve --listen`: GitHub webhooks (`POST /webhook`), which
+//! make the next run check their repository right away, and the metrics of the latest runs
+//! (`GET /metrics`), in the Prometheus text format.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Write,
+    net::SocketAddr,
+    ops::Not,
+    sync::Mutex,
+};
+
+use anyhow::Context;
+use chrono::Utc;
+use itertools::Itertools;
+use log::{debug, info, warn};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::metrics::Sample;
+
+/// The metrics of the latest run of each tenant (`""` in single-tenant mode).
+static METRICS: Mutex<BTreeMap<String, Vec<Sample>>> = Mutex::new(BTreeMap::new());
+
+/// The repositories, as lowercased `owner/repo`, that webhooks asked to check.
+static REQUESTED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Wakes `serve` up when a webhook arrives.
+static WAKE: tokio::sync::Notify = tokio::sync::Notify::const_new();
+
+/// Record the metrics of the latest run of a tenant, for `/metrics`.
+pub fn publish(tenant: Option<&str>, mut samples: Vec<Sample>) {
+    samples.push(Sample {
+        name: "qastor_last_run_timestamp_seconds",
+        help: "When the latest run ended.",
+        labels: vec![],
+        value: Utc::now().timestamp() as f64,
+    });
+    METRICS
+        .lock()
+        .unwrap()
+        .insert(tenant.unwrap_or_default().to_string(), samples);
+}
+
+/// Whether a webhook asked to check a repository since it was last checked.
+pub fn is_requested(owner: &str, repo: &str) -> bool {
+    REQUESTED
+        .lock()
+        .unwrap()
+        .contains(&format!("{owner}/{repo}").to_lowercase())
+}
+
+/// Whether a webhook asked to check a repository, forgetting it, as we're about to.
+pub fn take_request(owner: &str, repo: &str) -> bool {
+    REQUESTED
+        .lock()
+        .unwrap()
+        .remove(&format!("{owner}/{repo}").to_lowercase())
+}
+
+/// Wait until a webhook asks to check a repository.
+pub async fn requested() {
+    WAKE.notified().await
+}
+
+/// Listen to HTTP requests, in the background.
+///
+/// Webhooks must be signed with one of `secrets`, see `Secrets::webhook_secret`, and are
+/// rejected if there is none.
+pub async fn listen(address: SocketAddr, secrets: Vec<String>) -> Result<(), anyhow::Error> {
+    let listener = TcpListener::bind(address)
+        .await
+        .with_context(|| format!("Could not listen on {address}"))?;
+    info!("Listening on {address}, see /webhook and /metrics");
+    if secrets.is_empty() {
+        warn!("No `webhook_secret` in the secrets, webhooks will be rejected");
+    }
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, peer)) => {
+                    let secrets = secrets.clone();
+                    tokio::spawn(async move {
+                        if let Err(err) = handle(stream, &secrets).await {
+                            debug!("Could not answer {peer}: {err:?}");
+                        }
+                    });
+                }
+                Err(err) => warn!("Could not accept a connection: {err:?}"),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// A request, as far as we care.
+struct Request {
+    method: String,
+    path: String,
+
+    /// By lowercased name.
+    headers: HashMap<String, String>,
+}
+
+/// Parse the head of a request, up to the empty line.
+fn parse_head(head: &str) -> Option<Request> {
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+    Some(Request {
+        method,
+        path,
+        headers,
+    })
+}
+
+async fn handle(mut stream: TcpStream, secrets: &[String]) -> Result<(), anyhow::Error> {
+    /// Heads are small, unlike some webhook payloads.
+    const MAX_HEAD: usize = 16 * 1024;
+    /// GitHub caps the payloads of webhooks at 25 MB.
+    const MAX_BODY: usize = 25 * 1024 * 1024;
+    let mut buffer = vec![];
+    let head_len = loop {
+        if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end + 4;
+        }
+        if buffer.len() > MAX_HEAD {
+            return respond(&mut stream, 431, "text/plain", "Request header too large").await;
+        }
+        let mut chunk = [0; 4096];
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+    let Some(request) = parse_head(&String::from_utf8_lossy(&buffer[..head_len])) else {
+        return respond(&mut stream, 400, "text/plain", "Bad request").await;
+    };
+    let length = request
+        .headers
+        .get("content-length")
+        .and_then(|length| length.parse::<usize>().ok())
+        .unwrap_or_default();
+    if length > MAX_BODY {
+        return respond(&mut stream, 413, "text/plain", "Payload too large").await;
+    }
+    let mut body = buffer.split_off(head_len);
+    if body.len() < length {
+        let mut rest = vec![0; length - body.len()];
+        stream.read_exact(&mut rest).await?;
+        body.extend(rest);
+    }
+    body.truncate(length);
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/metrics") => {
+            let metrics = render_metrics(&METRICS.lock().unwrap());
+            respond(&mut stream, 200, "text/plain; version=0.0.4", &metrics).await
+        }
+        ("POST", "/webhook") => {
+            let signature = request.headers.get("x-hub-signature-256");
+            let signed = signature.is_some_and(|signature| {
+                secrets
+                    .iter()
+                    .any(|secret| verify(secret, &body, signature))
+            });
+            if signed.not() {
+                return respond(&mut stream, 401, "text/plain", "Invalid signature").await;
+            }
+            let event = request.headers.get("x-github-event").cloned();
+            match webhook_repository(&body) {
+                Some(repository) if event.as_deref() != Some("ping") => {
+                    info!("Webhook {event:?} about {repository}, checking it on the next run");
+                    REQUESTED.lock().unwrap().insert(repository.to_lowercase());
+                    WAKE.notify_one();
+                    respond(&mut stream, 202, "text/plain", "Accepted").await
+                }
+                _ => respond(&mut stream, 200, "text/plain", "Ignored").await,
+            }
+        }
+        _ => respond(&mut stream, 404, "text/plain", "Not found").await,
+    }
+}
+
+async fn respond(
+    stream: &mut TcpStream,
+    status: u16,
+    content_type: &str,
+    body: &str,
+) -> Result<(), anyhow::Error> {
+    let reason = match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        413 => "Payload Too Large",
+        _ => "Request Header Fields Too Large",
+    };
+    let response = format!(
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Whether a payload was signed with a secret, as in GitHub's header
+/// `X-Hub-Signature-256: sha256=<hex>`.
+fn verify(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Some(signature) = signature
+        .strip_prefix("sha256=")
+        .and_then(|hex| decode_hex(hex.trim()))
+    else {
+        return false;
+    };
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, secret.as_bytes());
+    ring::hmac::verify(&key, body, &signature).is_ok()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len().is_multiple_of(2).not() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|at| u8::from_str_radix(hex.get(at..at + 2)?, 16).ok())
+        .collect()
+}
+
+/// The repository of a webhook payload, as `owner/repo`, if any.
+fn webhook_repository(body: &[u8]) -> Option<String> {
+    let payload: serde_json::Value = serde_json::from_slice(body).ok()?;
+    Some(payload["repository"]["full_name"].as_str()?.to_string())
+}
+
+/// Render the metrics of each tenant in the Prometheus text format, labelled by tenant in
+/// multi-tenant mode.
+fn render_metrics(metrics: &BTreeMap<String, Vec<Sample>>) -> String {
+    let samples = metrics
+        .iter()
+        .flat_map(|(tenant, samples)| samples.iter().map(move |sample| (tenant, sample)))
+        .into_group_map_by(|(_, sample)| sample.name);
+    let mut result = String::new();
+    for (name, samples) in samples.into_iter().sorted_by_key(|(name, _)| *name) {
+        let _ = writeln!(result, "# HELP {name} {}", samples[0].1.help);
+        let _ = writeln!(result, "# TYPE {name} gauge");
+        for (tenant, sample) in samples {
+            let labels = std::iter::once(("tenant", tenant.as_str()))
+                .filter(|(_, tenant)| tenant.is_empty().not())
+                .chain(
+                    sample
+                        .labels
+                        .iter()
+                        .map(|(label, value)| (*label, value.as_str())),
+                )
+                .map(|(label, value)| format!("{label}=\"{}\"", value.replace('"', "\\\"")))
+                .join(",");
+            let _ = match labels.is_empty() {
+                true => writeln!(result, "{name} {}", sample.value),
+                false => writeln!(result, "{name}{{{labels}}} {}", sample.value),
+            };
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use crate::metrics::Sample;
+
+    use super::{parse_head, render_metrics, verify, webhook_repository};
+
+    /// Do we check signatures as GitHub computes them?
+    #[test]
+    fn test_verify() {
+        // The example of GitHub's documentation.
+        let secret = "It's a Secret to Everybody";
+        let signature = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+        assert!(verify(secret, b"Hello, World!", signature));
+        assert!(!verify(secret, b"Hello, World?", signature));
+        assert!(!verify("Another secret", b"Hello, World!", signature));
+        assert!(!verify(secret, b"Hello, World!", "sha1=757107"));
+    }
+
+    /// Do we find the method, the path, the headers and the repository of a webhook?
+    #[test]
+    fn test_parse() {
+        let request = parse_head(
+            "POST /webhook HTTP/1.1\r\nX-GitHub-Event: pull_request\r\nContent-Length: 2\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path, "/webhook");
+        assert_eq!(request.headers["x-github-event"], "pull_request");
+        assert_eq!(
+            webhook_repository(
+                br#"{"action": "opened", "repository": {"full_name": "owner1/project1"}}"#
+            ),
+            Some("owner1/project1".to_string())
+        );
+        assert_eq!(webhook_repository(b"{}"), None);
+    }
+
+    /// Are metrics grouped by name, and labelled by tenant?
+    #[test]
+    fn test_render_metrics() {
+        let sample = |value, labels: Vec<(&'static str, String)>| Sample {
+            name: "qastor_items",
+            help: "Items reported.",
+            labels,
+            value,
+        };
+        let metrics = BTreeMap::from([
+            ("".to_string(), vec![sample(3.0, vec![])]),
+            (
+                "physics".to_string(),
+                vec![sample(2.0, vec![("class", "auth".to_string())])],
+            ),
+        ]);
+        assert_eq!(
+            render_metrics(&metrics),
+            "# HELP qastor_items Items reported.\n# TYPE qastor_items gauge\nqastor_items 3\nqastor_items{tenant=\"physics\",class=\"auth\"} 2\n"
+        );
+    }
+}
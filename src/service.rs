@@ -0,0 +1,139 @@
+//! Running as a Windows service, under the Service Control Manager.
+
+use std::{
+    ffi::c_void,
+    ptr,
+    sync::{
+        atomic::{AtomicIsize, Ordering},
+        Mutex,
+    },
+};
+
+use anyhow::anyhow;
+use log::{error, info};
+use windows_sys::{
+    core::PWSTR,
+    Win32::{
+        Foundation::{ERROR_CALL_NOT_IMPLEMENTED, ERROR_SERVICE_SPECIFIC_ERROR, NO_ERROR},
+        System::Services::{
+            RegisterServiceCtrlHandlerExW, SetServiceStatus, StartServiceCtrlDispatcherW,
+            SERVICE_ACCEPT_SHUTDOWN, SERVICE_ACCEPT_STOP, SERVICE_CONTROL_INTERROGATE,
+            SERVICE_CONTROL_SHUTDOWN, SERVICE_CONTROL_STOP, SERVICE_RUNNING, SERVICE_STATUS,
+            SERVICE_STATUS_CURRENT_STATE, SERVICE_STOPPED, SERVICE_STOP_PENDING,
+            SERVICE_TABLE_ENTRYW, SERVICE_WIN32_OWN_PROCESS,
+        },
+    },
+};
+
+use crate::daemon;
+
+/// The body of the service, called once the Service Control Manager has started it.
+type Body = Box<dyn FnOnce() -> i32 + Send>;
+
+/// The name of the service, as UTF-16, and its body, until the service starts.
+static SERVICE: Mutex<Option<(Vec<u16>, Body)>> = Mutex::new(None);
+
+/// The handle through which we report the status of the service.
+static STATUS_HANDLE: AtomicIsize = AtomicIsize::new(0);
+
+/// The exit code of the body of the service, once it is done.
+static EXIT_CODE: Mutex<Option<i32>> = Mutex::new(None);
+
+/// How long we may take to stop, i.e. to complete a run in progress, in milliseconds.
+const STOP_WAIT_HINT: u32 = 5 * 60 * 1000;
+
+/// Run `body` as the Windows service `name`, returning its exit code once it is done.
+///
+/// Stopping the service (or shutting down the machine) asks `body` to stop through
+/// `daemon::shutdown_signal`. Fails if the process was not started by the Service Control
+/// Manager, e.g. from a console.
+pub fn run(name: &str, body: impl FnOnce() -> i32 + Send + 'static) -> Result<i32, anyhow::Error> {
+    let mut wide = name.encode_utf16().collect::<Vec<_>>();
+    wide.push(0);
+    let table = [
+        SERVICE_TABLE_ENTRYW {
+            lpServiceName: wide.as_mut_ptr(),
+            lpServiceProc: Some(service_main),
+        },
+        SERVICE_TABLE_ENTRYW {
+            lpServiceName: ptr::null_mut(),
+            lpServiceProc: None,
+        },
+    ];
+    *SERVICE.lock().unwrap() = Some((wide.clone(), Box::new(body)));
+    // Returns once the service has stopped.
+    if unsafe { StartServiceCtrlDispatcherW(table.as_ptr()) } == 0 {
+        return Err(anyhow!(
+            "Could not connect to the Service Control Manager: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(EXIT_CODE.lock().unwrap().unwrap_or_default())
+}
+
+unsafe extern "system" fn service_main(_argc: u32, _argv: *mut PWSTR) {
+    let Some((name, body)) = SERVICE.lock().unwrap().take() else {
+        return;
+    };
+    let handle = RegisterServiceCtrlHandlerExW(name.as_ptr(), Some(handler), ptr::null());
+    if handle == 0 {
+        error!(
+            "Could not register the service control handler: {}",
+            std::io::Error::last_os_error()
+        );
+        return;
+    }
+    STATUS_HANDLE.store(handle, Ordering::SeqCst);
+    set_status(SERVICE_RUNNING, 0);
+    info!("Service started");
+    let code = body();
+    *EXIT_CODE.lock().unwrap() = Some(code);
+    set_status(SERVICE_STOPPED, code);
+}
+
+unsafe extern "system" fn handler(
+    control: u32,
+    _event_type: u32,
+    _event_data: *mut c_void,
+    _context: *mut c_void,
+) -> u32 {
+    match control {
+        SERVICE_CONTROL_STOP | SERVICE_CONTROL_SHUTDOWN => {
+            info!("Service asked to stop");
+            set_status(SERVICE_STOP_PENDING, 0);
+            daemon::request_stop();
+            NO_ERROR
+        }
+        SERVICE_CONTROL_INTERROGATE => NO_ERROR,
+        _ => ERROR_CALL_NOT_IMPLEMENTED,
+    }
+}
+
+/// Tell the Service Control Manager where we are.
+fn set_status(state: SERVICE_STATUS_CURRENT_STATE, code: i32) {
+    let status = SERVICE_STATUS {
+        dwServiceType: SERVICE_WIN32_OWN_PROCESS,
+        dwCurrentState: state,
+        dwControlsAccepted: match state {
+            SERVICE_RUNNING => SERVICE_ACCEPT_STOP | SERVICE_ACCEPT_SHUTDOWN,
+            _ => 0,
+        },
+        dwWin32ExitCode: match code {
+            0 => NO_ERROR,
+            _ => ERROR_SERVICE_SPECIFIC_ERROR,
+        },
+        dwServiceSpecificExitCode: code as u32,
+        dwCheckPoint: 0,
+        dwWaitHint: match state {
+            SERVICE_STOP_PENDING => STOP_WAIT_HINT,
+            _ => 0,
+        },
+    };
+    let handle = STATUS_HANDLE.load(Ordering::SeqCst);
+    if unsafe { SetServiceStatus(handle, &status) } == 0 {
+        error!(
+            "Could not report the status of the service: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+}
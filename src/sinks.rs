@@ -0,0 +1,633 @@
+use std::{future::Future, ops::Not, pin::Pin};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use derive_more::Display;
+use log::{debug, warn};
+use reqwest::Client;
+use serde_json::json;
+use url::Url;
+
+use crate::{
+    archive,
+    config::{Hook, HookKind, HookTarget, MatrixRoom, TelegramChat},
+    discord,
+    error::Error,
+    humanize::Locale,
+    matrix,
+    report::Severity,
+    slack::{self, MessageFormat},
+    teams, telegram, webhook,
+};
+
+/// A message, once sent.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Sent {
+    /// The number of bytes posted.
+    pub bytes: usize,
+
+    /// The `ts` of the message, if a Slack bot posted it.
+    pub ts: Option<String>,
+
+    /// The id of the message, if a Slack bot scheduled it, see `Placement::At`.
+    pub scheduled: Option<String>,
+}
+
+/// Why a sink only received the first messages of a section, e.g. a long section split
+/// in several messages, see `post_rendered`.
+#[derive(Debug, Display)]
+#[display("Sent {delivered} of {total} messages")]
+pub struct Undelivered {
+    /// How many messages went out before the failure.
+    pub delivered: usize,
+    pub total: usize,
+}
+
+/// Where a message goes, for sinks that post to a Slack channel as a bot. Other sinks
+/// always post new messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement<'a> {
+    /// A new message.
+    New,
+
+    /// A reply in the thread of a message, given its `ts`.
+    Thread(&'a str),
+
+    /// In place of a message, given its `ts`.
+    Replace(&'a str),
+
+    /// A new message, that Slack delivers later, see `deliver_at` in the config.
+    At(DateTime<Utc>),
+}
+
+/// The result of sending a message.
+pub type SendFuture<'a> = Pin<Box<dyn Future<Output = Result<Sent, anyhow::Error>> + Send + 'a>>;
+
+/// Somewhere we post the sections of reports, e.g. a Slack hook or a Matrix room.
+///
+/// Sections are written as Slack mrkdwn, each sink renders them in the format of its
+/// service, then sends them.
+pub trait Sink: Send + Sync {
+    /// How the sink appears in the archive and in the outcome of a run.
+    fn label(&self) -> String;
+
+    /// The kind of the sink, e.g. `slack` or `matrix`, which selects its templates.
+    fn kind(&self) -> &'static str;
+
+    /// The alias of the sink, if items may be routed to it.
+    fn alias(&self) -> Option<&str> {
+        None
+    }
+
+    /// Whether the sink wants sections with this severity.
+    fn accepts(&self, _severity: Severity) -> bool {
+        true
+    }
+
+    /// The id of the Slack channel, if a bot posts there, so that we can reply in threads.
+    fn channel(&self) -> Option<&str> {
+        None
+    }
+
+    /// The language of the sections sent to this sink, if not the one of the project.
+    fn locale(&self) -> Option<Locale> {
+        None
+    }
+
+    /// The hooks to try in turn if sending to this sink keeps failing, see `fall_back`.
+    fn fallbacks(&self) -> Vec<Hook> {
+        vec![]
+    }
+
+    /// Render a section as the bodies of the messages to send.
+    fn render(&self, section: &slack::Section) -> Vec<serde_json::Value>;
+
+    /// Send a rendered message, in place of the message with `ts` `replace` if specified
+    /// (Slack bots only).
+    fn send<'a>(
+        &'a self,
+        client: &'a Client,
+        message: &'a serde_json::Value,
+        replace: Option<&'a str>,
+    ) -> SendFuture<'a>;
+}
+
+/// Render a section for a sink, then send it where `placement` says.
+///
+/// Returns the `ts` of the first message, if any.
+pub async fn post(
+    sink: &dyn Sink,
+    client: &Client,
+    section: &slack::Section,
+    placement: Placement<'_>,
+) -> Result<Sent, anyhow::Error> {
+    let posted = post_rendered(sink, client, sink.render(section), placement).await;
+    fall_back(sink, client, |sink| sink.render(section), placement, posted).await
+}
+
+/// If `posted`, the outcome of sending a section to a sink, is a failure, send the section
+/// to the fallbacks of the sink in turn, until one accepts it.
+///
+/// Each fallback renders the section with `render`, in the format of its own service. If
+/// some messages of the section went out before the failure, fallbacks that render them
+/// alike only receive the others. Fallbacks that render them differently, e.g. on another
+/// service, receive the whole section, so readers may see its first messages twice.
+pub async fn fall_back(
+    sink: &dyn Sink,
+    client: &Client,
+    render: impl Fn(&dyn Sink) -> Vec<serde_json::Value>,
+    placement: Placement<'_>,
+    mut posted: Result<Sent, anyhow::Error>,
+) -> Result<Sent, anyhow::Error> {
+    // The messages of the latest attempt, the first of which went out.
+    let mut attempted = render(sink);
+    let mut skipped = 0;
+    for hook in sink.fallbacks() {
+        let Err(ref err) = posted else {
+            break;
+        };
+        warn!(
+            "Giving up on {}, trying the next fallback: {err:?}",
+            sink.label()
+        );
+        let delivered = err
+            .downcast_ref::<Undelivered>()
+            .map_or(0, |undelivered| undelivered.delivered);
+        attempted.truncate(skipped + delivered);
+        let fallback = HookSink(&hook);
+        let messages = render(&fallback);
+        skipped = already_sent(&messages, &attempted);
+        if skipped > 0 {
+            debug!(
+                "Only sending the last {} messages to {}",
+                messages.len() - skipped,
+                fallback.label()
+            );
+        }
+        posted = post_rendered(&fallback, client, messages[skipped..].to_vec(), placement).await;
+        attempted = messages;
+    }
+    posted
+}
+
+/// How many of the first `messages` have already been sent, as the first of `sent`.
+fn already_sent(messages: &[serde_json::Value], sent: &[serde_json::Value]) -> usize {
+    messages
+        .iter()
+        .zip(sent)
+        .take_while(|(message, sent)| message == sent)
+        .count()
+}
+
+/// Send the messages rendered by a sink (see `Sink::render`) where `placement` says.
+///
+/// Returns the `ts` of the first message, if any. If some messages went out before a
+/// failure, the error says how many, see `Undelivered`.
+pub async fn post_rendered(
+    sink: &dyn Sink,
+    client: &Client,
+    messages: Vec<serde_json::Value>,
+    placement: Placement<'_>,
+) -> Result<Sent, anyhow::Error> {
+    let mut posted = Sent::default();
+    let total = messages.len();
+    for (delivered, mut message) in messages.into_iter().enumerate() {
+        let replace = match placement {
+            Placement::New => None,
+            Placement::Thread(ts) => {
+                message["thread_ts"] = ts.into();
+                None
+            }
+            Placement::Replace(ts) => Some(ts),
+            Placement::At(at) => {
+                message["post_at"] = at.timestamp().into();
+                None
+            }
+        };
+        let sent = match sink.send(client, &message, replace).await {
+            Ok(sent) => sent,
+            Err(err) if delivered == 0 => return Err(err),
+            Err(err) => return Err(err.context(Undelivered { delivered, total })),
+        };
+        posted.bytes += sent.bytes;
+        posted.ts = posted.ts.or(sent.ts);
+        posted.scheduled = posted.scheduled.or(sent.scheduled);
+    }
+    Ok(posted)
+}
+
+/// Post a message as a Slack bot, in place of the message with `ts` `replace` if specified,
+/// or schedule it if it has a `post_at` (see `Placement::At`).
+///
+/// Scheduled messages have no `ts` yet, only the id of the schedule.
+async fn post_as_bot(
+    client: &Client,
+    token: &str,
+    channel: &str,
+    message: &serde_json::Value,
+    replace: Option<&str>,
+) -> Result<Sent, anyhow::Error> {
+    let bytes = message.to_string().len();
+    match message.get("post_at") {
+        Some(_) => Ok(Sent {
+            bytes,
+            ts: None,
+            scheduled: Some(
+                slack::schedule_message(client, token, channel, message.clone()).await?,
+            ),
+        }),
+        None => Ok(Sent {
+            bytes,
+            ts: Some(slack::post_message(client, token, channel, replace, message.clone()).await?),
+            scheduled: None,
+        }),
+    }
+}
+
+/// A message for a hook url, which can't schedule messages, so that a message scheduled for
+/// a Slack bot falls back to a hook right away.
+fn for_url(message: &serde_json::Value) -> std::borrow::Cow<'_, serde_json::Value> {
+    match message.get("post_at") {
+        Some(_) => {
+            let mut message = message.clone();
+            if let Some(fields) = message.as_object_mut() {
+                fields.remove("post_at");
+            }
+            std::borrow::Cow::Owned(message)
+        }
+        None => std::borrow::Cow::Borrowed(message),
+    }
+}
+
+/// POST a JSON body to a hook, e.g. of Slack or Discord.
+///
+/// Returns the number of bytes posted.
+pub async fn post_json(
+    client: &Client,
+    hook: &Url,
+    payload: &serde_json::Value,
+    service: &str,
+) -> Result<usize, anyhow::Error> {
+    debug!(
+        "Sending: {}",
+        serde_json::to_string_pretty(payload).unwrap()
+    );
+    let body = serde_json::to_vec(payload).context("Could not serialize message")?;
+    let posted_bytes = body.len();
+    let response = client
+        .post(hook.to_string())
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body)
+        .send()
+        .await
+        .with_context(|| format!("Error while posting message to {service}"))?;
+    let status = response.status();
+    if status.is_success().not() {
+        let body = response.text().await.context("Could not gather response")?;
+        return Err(Error::SinkRejected {
+            service: service.to_string(),
+            status,
+            body,
+        }
+        .into());
+    }
+    Ok(posted_bytes)
+}
+
+/// A Slack, Discord, Teams or JSON hook (or a Slack bot), with its fallbacks.
+pub struct HookSink<'a>(pub &'a Hook);
+impl HookSink<'_> {
+    /// Post a message to the hook, retrying a few times. Fallbacks are tried by `fall_back`,
+    /// since they may need another format.
+    async fn send_with_retries(
+        &self,
+        client: &Client,
+        message: &serde_json::Value,
+        replace: Option<&str>,
+    ) -> Result<Sent, anyhow::Error> {
+        const ATTEMPTS: u32 = 3;
+        let hook = self.0;
+        let service = match hook.kind() {
+            HookKind::Slack => "Slack",
+            HookKind::Discord => "Discord",
+            HookKind::Teams => "Teams",
+            HookKind::Json => "JSON webhook",
+        };
+        let mut last_error = None;
+        for attempt in 1..=ATTEMPTS {
+            let posted = match hook.target {
+                HookTarget::Url(ref url) => {
+                    post_json(client, url.as_ref(), &for_url(message), service)
+                        .await
+                        .map(|bytes| Sent {
+                            bytes,
+                            ..Sent::default()
+                        })
+                }
+                HookTarget::Bot {
+                    ref token,
+                    ref channel,
+                } => post_as_bot(client, token, channel, message, replace).await,
+            };
+            match posted {
+                Ok(sent) => return Ok(sent),
+                Err(err) => {
+                    warn!("Attempt {attempt}/{ATTEMPTS} to post on {service} failed: {err:?}");
+                    // No need to insist if the message has been rejected for good.
+                    let transient = Error::find(&err).is_none_or(Error::is_transient);
+                    last_error = Some(err);
+                    if transient.not() {
+                        break;
+                    }
+                }
+            }
+            if attempt < ATTEMPTS {
+                tokio::time::sleep(std::time::Duration::from_secs(1 << attempt)).await;
+            }
+        }
+        Err(last_error.expect("we have made at least one attempt"))
+    }
+}
+impl Sink for HookSink<'_> {
+    fn label(&self) -> String {
+        archive::hook_label(self.0)
+    }
+
+    fn kind(&self) -> &'static str {
+        match self.0.kind() {
+            HookKind::Slack => "slack",
+            HookKind::Discord => "discord",
+            HookKind::Teams => "teams",
+            HookKind::Json => "json",
+        }
+    }
+
+    fn alias(&self) -> Option<&str> {
+        self.0.alias.as_deref()
+    }
+
+    fn accepts(&self, severity: Severity) -> bool {
+        self.0.accepts(severity)
+    }
+
+    fn locale(&self) -> Option<Locale> {
+        self.0.locale
+    }
+
+    fn fallbacks(&self) -> Vec<Hook> {
+        self.0.fallback_hooks()
+    }
+
+    fn channel(&self) -> Option<&str> {
+        match self.0.target {
+            HookTarget::Bot { ref channel, .. } => Some(channel),
+            HookTarget::Url(_) => None,
+        }
+    }
+
+    fn render(&self, section: &slack::Section) -> Vec<serde_json::Value> {
+        let options = &self.0.options;
+        vec![match (self.0.kind(), options.format) {
+            (HookKind::Slack, MessageFormat::Rich) => section.payload(options),
+            (HookKind::Slack, MessageFormat::Plain) => json!({ "text": section.plain_text() }),
+            (HookKind::Discord, MessageFormat::Rich) => discord::payload(section, options),
+            (HookKind::Discord, MessageFormat::Plain) => discord::plain_payload(section),
+            (HookKind::Teams, MessageFormat::Rich) => teams::payload(section, options),
+            // Legacy Teams connectors accept `{"text": ...}`, as Slack does.
+            (HookKind::Teams, MessageFormat::Plain) => json!({ "text": section.plain_text() }),
+            (HookKind::Json, _) => webhook::section_payload(section),
+        }]
+    }
+
+    fn send<'a>(
+        &'a self,
+        client: &'a Client,
+        message: &'a serde_json::Value,
+        replace: Option<&'a str>,
+    ) -> SendFuture<'a> {
+        Box::pin(self.send_with_retries(client, message, replace))
+    }
+}
+
+/// A Slack channel, to which a Slack bot posts.
+#[derive(Clone)]
+pub struct SlackChannel<'a> {
+    pub token: &'a str,
+    pub id: String,
+    pub name: String,
+}
+impl Sink for SlackChannel<'_> {
+    fn label(&self) -> String {
+        format!("#{}", self.name)
+    }
+
+    fn kind(&self) -> &'static str {
+        "slack"
+    }
+
+    fn channel(&self) -> Option<&str> {
+        Some(&self.id)
+    }
+
+    fn render(&self, section: &slack::Section) -> Vec<serde_json::Value> {
+        vec![section.payload(&slack::MessageOptions::default())]
+    }
+
+    fn send<'a>(
+        &'a self,
+        client: &'a Client,
+        message: &'a serde_json::Value,
+        replace: Option<&'a str>,
+    ) -> SendFuture<'a> {
+        Box::pin(post_as_bot(client, self.token, &self.id, message, replace))
+    }
+}
+
+/// A Matrix room, to which we post notices.
+pub struct MatrixSink<'a>(pub &'a MatrixRoom);
+impl Sink for MatrixSink<'_> {
+    fn label(&self) -> String {
+        self.0.room_id.clone()
+    }
+
+    fn kind(&self) -> &'static str {
+        "matrix"
+    }
+
+    fn render(&self, section: &slack::Section) -> Vec<serde_json::Value> {
+        vec![matrix::payload(section)]
+    }
+
+    fn send<'a>(
+        &'a self,
+        client: &'a Client,
+        message: &'a serde_json::Value,
+        _replace: Option<&'a str>,
+    ) -> SendFuture<'a> {
+        Box::pin(async move {
+            let bytes = matrix::send(client, self.0, message).await?;
+            Ok(Sent {
+                bytes,
+                ..Sent::default()
+            })
+        })
+    }
+}
+
+/// A Telegram chat, to which a bot posts.
+pub struct TelegramSink<'a>(pub &'a TelegramChat);
+impl Sink for TelegramSink<'_> {
+    fn label(&self) -> String {
+        format!("telegram:{}", self.0.chat_id)
+    }
+
+    fn kind(&self) -> &'static str {
+        "telegram"
+    }
+
+    fn render(&self, section: &slack::Section) -> Vec<serde_json::Value> {
+        telegram::payloads(self.0, section)
+    }
+
+    fn send<'a>(
+        &'a self,
+        client: &'a Client,
+        message: &'a serde_json::Value,
+        _replace: Option<&'a str>,
+    ) -> SendFuture<'a> {
+        Box::pin(async move {
+            let bytes = telegram::send(client, self.0, message).await?;
+            Ok(Sent {
+                bytes,
+                ..Sent::default()
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use url::Url;
+
+    use anyhow::anyhow;
+    use reqwest::Client;
+    use serde_json::json;
+
+    use super::{
+        already_sent, post_rendered, HookSink, Placement, SendFuture, Sent, Sink, Undelivered,
+    };
+    use crate::{
+        config::{Hook, HookKind, SlackHook},
+        report::Severity,
+        slack::{self, MessageFormat},
+    };
+
+    /// Do hooks render sections in the format of their service?
+    #[test]
+    fn test_hook_sink() {
+        let section = slack::Section::new("PRs of repo project1 waiting for reviews".to_string());
+        let url = Url::parse("https://discord.com/api/webhooks/1/abc").unwrap();
+        let mut hook = Hook::from(SlackHook::from(url));
+        hook.severities = vec![Severity::Critical];
+        let sink = HookSink(&hook);
+        assert!(sink.render(&section)[0]["embeds"].is_array());
+        assert!(sink.accepts(Severity::Critical));
+        assert!(!sink.accepts(Severity::Info));
+
+        hook.options.format = MessageFormat::Plain;
+        assert_eq!(
+            HookSink(&hook).render(&section)[0]["content"],
+            "PRs of repo project1 waiting for reviews"
+        );
+
+        hook.kind = Some(HookKind::Slack);
+        hook.options.format = MessageFormat::Rich;
+        hook.alias = Some("sdk".to_string());
+        let sink = HookSink(&hook);
+        assert_eq!(
+            sink.render(&section)[0]["text"],
+            "PRs of repo project1 waiting for reviews"
+        );
+        assert!(sink.render(&section)[0]["blocks"].is_array());
+        assert_eq!(sink.alias(), Some("sdk"));
+        assert_eq!(sink.label(), "sdk");
+
+        hook.name = Some("#sdk on Slack".to_string());
+        assert_eq!(HookSink(&hook).label(), "#sdk on Slack");
+    }
+
+    /// A sink that only accepts its first messages.
+    struct Flaky {
+        accepted: usize,
+    }
+    impl Sink for Flaky {
+        fn label(&self) -> String {
+            "flaky".to_string()
+        }
+
+        fn kind(&self) -> &'static str {
+            "slack"
+        }
+
+        fn render(&self, _section: &slack::Section) -> Vec<serde_json::Value> {
+            vec![]
+        }
+
+        fn send<'a>(
+            &'a self,
+            _client: &'a Client,
+            message: &'a serde_json::Value,
+            _replace: Option<&'a str>,
+        ) -> SendFuture<'a> {
+            let accepted = message["index"].as_u64().unwrap() < self.accepted as u64;
+            Box::pin(async move {
+                match accepted {
+                    true => Ok(Sent::default()),
+                    false => Err(anyhow!("Rate limited")),
+                }
+            })
+        }
+    }
+
+    /// Do failures say how many messages of a section went out, so that fallbacks only
+    /// receive the others?
+    #[tokio::test]
+    async fn test_undelivered() {
+        let client = Client::new();
+        let messages = (0..3)
+            .map(|index| json!({ "index": index }))
+            .collect::<Vec<_>>();
+        let err = post_rendered(
+            &Flaky { accepted: 2 },
+            &client,
+            messages.clone(),
+            Placement::New,
+        )
+        .await
+        .unwrap_err();
+        let undelivered = err.downcast_ref::<Undelivered>().unwrap();
+        assert_eq!((undelivered.delivered, undelivered.total), (2, 3));
+        let err = post_rendered(
+            &Flaky { accepted: 0 },
+            &client,
+            messages.clone(),
+            Placement::New,
+        )
+        .await
+        .unwrap_err();
+        assert!(err.downcast_ref::<Undelivered>().is_none());
+        assert!(post_rendered(
+            &Flaky { accepted: 3 },
+            &client,
+            messages.clone(),
+            Placement::New
+        )
+        .await
+        .is_ok());
+
+        // Fallbacks that render the section alike skip what went out, others get it all.
+        assert_eq!(already_sent(&messages, &messages[..2]), 2);
+        assert_eq!(already_sent(&messages, &[]), 0);
+        assert_eq!(already_sent(&messages, &[json!({ "text": "0" })]), 0);
+    }
+}
@@ -0,0 +1,152 @@
+use std::ops::Not;
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use octocrab::models::{issues::Comment, issues::Issue, AuthorAssociation};
+
+use crate::{business, config::SlaPolicy};
+
+/// The moment `days` business days after `start`, skipping weekends and `holidays` (in
+/// UTC).
+///
+/// An issue opened on a day off starts counting the next business day, in the morning.
+pub fn add_business_days(start: DateTime<Utc>, days: u32, holidays: &[NaiveDate]) -> DateTime<Utc> {
+    let is_business_day = |at: DateTime<Utc>| business::is_working_day(at.date_naive(), holidays);
+    let mut at = start;
+    if is_business_day(at).not() {
+        while is_business_day(at).not() {
+            at += Duration::days(1);
+        }
+        at = at.with_time(NaiveTime::MIN).single().unwrap_or(at);
+    }
+    for _ in 0..days {
+        at += Duration::days(1);
+        while is_business_day(at).not() {
+            at += Duration::days(1);
+        }
+    }
+    at
+}
+
+/// Whether a comment comes from a maintainer, i.e. someone with write access or a member
+/// of the organization.
+pub fn is_maintainer(association: &AuthorAssociation) -> bool {
+    matches!(
+        association,
+        AuthorAssociation::Owner | AuthorAssociation::Member | AuthorAssociation::Collaborator
+    )
+}
+
+/// When a maintainer first commented, if ever.
+pub fn first_response(comments: &[Comment]) -> Option<DateTime<Utc>> {
+    comments
+        .iter()
+        .filter(|comment| is_maintainer(&comment.author_association))
+        .map(|comment| comment.created_at)
+        .min()
+}
+
+/// An issue that didn't receive a first response from a maintainer in time.
+#[derive(Debug, Clone)]
+pub struct Breach {
+    pub issue: Issue,
+    pub policy: SlaPolicy,
+    pub deadline: DateTime<Utc>,
+
+    /// When a maintainer eventually commented, if they did.
+    pub responded_at: Option<DateTime<Utc>>,
+}
+
+/// Whether the first response to an issue opened at `opened`, if any, breaches `policy`,
+/// given the days off of the team besides weekends.
+///
+/// Returns the deadline if so.
+pub fn breach(
+    policy: &SlaPolicy,
+    opened: DateTime<Utc>,
+    first_response: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    holidays: &[NaiveDate],
+) -> Option<DateTime<Utc>> {
+    let deadline = add_business_days(opened, policy.first_response_business_days, holidays);
+    match first_response {
+        Some(at) if at <= deadline => None,
+        Some(_) => Some(deadline),
+        None if now > deadline => Some(deadline),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, NaiveDate, TimeZone, Utc};
+
+    use super::{add_business_days, breach};
+    use crate::config::SlaPolicy;
+
+    /// Are weekends skipped?
+    #[test]
+    fn test_add_business_days() {
+        // A Friday afternoon.
+        let friday = Utc.with_ymd_and_hms(2024, 5, 3, 15, 0, 0).unwrap();
+        assert_eq!(
+            add_business_days(friday, 1, &[]),
+            Utc.with_ymd_and_hms(2024, 5, 6, 15, 0, 0).unwrap()
+        );
+        assert_eq!(add_business_days(friday, 0, &[]), friday);
+        // Saturday counts from Monday morning.
+        let saturday = friday + Duration::days(1);
+        assert_eq!(
+            add_business_days(saturday, 1, &[]),
+            Utc.with_ymd_and_hms(2024, 5, 7, 0, 0, 0).unwrap()
+        );
+        assert_eq!(
+            add_business_days(friday, 5, &[]),
+            Utc.with_ymd_and_hms(2024, 5, 10, 15, 0, 0).unwrap()
+        );
+        // Holidays are skipped too.
+        let monday = NaiveDate::from_ymd_opt(2024, 5, 6).unwrap();
+        assert_eq!(
+            add_business_days(friday, 1, &[monday]),
+            Utc.with_ymd_and_hms(2024, 5, 7, 15, 0, 0).unwrap()
+        );
+    }
+
+    /// Are late or missing responses breaches, but only once the deadline has passed?
+    #[test]
+    fn test_breach() {
+        let policy = SlaPolicy {
+            label: "customer".to_string(),
+            first_response_business_days: 1,
+            escalate_to: None,
+        };
+        // A Monday.
+        let opened = Utc.with_ymd_and_hms(2024, 5, 6, 9, 0, 0).unwrap();
+        let deadline = opened + Duration::days(1);
+        let later = opened + Duration::days(3);
+        assert_eq!(
+            breach(&policy, opened, None, opened + Duration::hours(2), &[]),
+            None
+        );
+        assert_eq!(breach(&policy, opened, None, later, &[]), Some(deadline));
+        assert_eq!(
+            breach(
+                &policy,
+                opened,
+                Some(opened + Duration::hours(3)),
+                later,
+                &[]
+            ),
+            None
+        );
+        assert_eq!(
+            breach(
+                &policy,
+                opened,
+                Some(opened + Duration::days(2)),
+                later,
+                &[]
+            ),
+            Some(deadline)
+        );
+    }
+}
@@ -1,15 +1,75 @@
-use std::{ops::Not, sync::Arc};
+use std::{borrow::Cow, collections::BTreeSet, ops::Not, sync::Arc};
 
-use anyhow::{anyhow, Context};
+use anyhow::Context;
+use itertools::Itertools;
+use lazy_regex::{lazy_regex, Lazy};
 use log::debug;
+use regex::Regex;
 use reqwest::Client;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use url::Url;
 
-#[derive(Serialize)]
+use crate::error::Error;
+
+/// Slack's limit on the text of a section, in characters.
+pub const MAX_TEXT: usize = 3000;
+
+/// How links are displayed.
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkStyle {
+    /// Titles link to their page.
+    #[default]
+    Full,
+
+    /// Titles only, without links, to keep reports short.
+    Compact,
+}
+
+/// How sections are laid out.
+#[derive(Deserialize, Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MessageFormat {
+    /// With the formatting of the service, e.g. Slack blocks or Teams cards.
+    #[default]
+    Rich,
+
+    /// Plain text, one line per row, e.g. for bridges to other services.
+    Plain,
+}
+
+/// Per-hook options for the messages we post.
+#[derive(Deserialize, Default, Debug, Clone, PartialEq, Eq)]
+pub struct MessageOptions {
+    /// If `Some(false)`, ask Slack not to display previews for links and media.
+    #[serde(default)]
+    pub unfurl_links: Option<bool>,
+
+    #[serde(default)]
+    pub link_style: LinkStyle,
+
+    #[serde(default)]
+    pub format: MessageFormat,
+}
+
+#[derive(Serialize, Clone)]
 pub struct Section {
     title: Text,
     fields: Vec<Text>,
+
+    /// Whether the first two fields are the headers of the columns, see `append_headers`.
+    #[serde(skip)]
+    headers: bool,
+}
+
+/// A section, as sent to Slack.
+#[derive(Serialize)]
+struct Block {
+    #[serde(rename = "type")]
+    typ_: &'static str,
+    text: Text,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fields: Vec<Text>,
 }
 
 #[derive(Serialize, Clone)]
@@ -27,9 +87,79 @@ impl Section {
                 text: title.into(),
             },
             fields: vec![],
+            headers: false,
+        }
+    }
+
+    /// The same section, with another title.
+    pub fn retitled(&self, title: String) -> Self {
+        Section {
+            title: Text {
+                typ: "mrkdwn",
+                text: title.into(),
+            },
+            ..self.clone()
         }
     }
 
+    /// The title, as Slack mrkdwn.
+    pub fn title(&self) -> &str {
+        &self.title.text
+    }
+
+    /// The fields, as Slack mrkdwn, displayed on two columns.
+    pub fn fields(&self) -> impl Iterator<Item = &str> {
+        self.fields.iter().map(|field| &*field.text)
+    }
+
+    /// The fields without the headers of the columns, if any.
+    pub fn rows(&self) -> impl Iterator<Item = &str> {
+        let headers = match self.headers {
+            true => 2,
+            false => 0,
+        };
+        self.fields().skip(headers)
+    }
+
+    /// The section as plain text, e.g. for the terminal: the title, then one line per row
+    /// of fields, without links.
+    pub fn plain_text(&self) -> String {
+        let plain = |text: &str| strip_links(&strip_mentions(text));
+        let mut text = plain(self.title());
+        for row in &self.fields().chunks(2) {
+            text.push('\n');
+            text.push_str(&row.map(plain).join("\t"));
+        }
+        html_escape::decode_html_entities(&text).into_owned()
+    }
+
+    /// The section as HTML, e.g. for Matrix or email: the title, then a table of the fields.
+    pub fn to_html(&self) -> String {
+        let mut html = format!("<p>{}</p>", to_html(self.title()));
+        let rows = self
+            .fields()
+            .chunks(2)
+            .into_iter()
+            .map(|row| {
+                format!(
+                    "<tr>{}</tr>",
+                    row.map(|field| format!("<td>{}</td>", to_html(field)))
+                        .join("")
+                )
+            })
+            .join("");
+        if rows.is_empty().not() {
+            html.push_str(&format!("<table>{rows}</table>"));
+        }
+        html
+    }
+
+    /// Append the headers of the columns, before any other field.
+    pub fn append_headers(&mut self, headers: &[String; 2]) {
+        self.headers = self.fields.is_empty();
+        self.append_fields(headers);
+    }
+
     pub fn append_fields(&mut self, headers: &[String]) {
         self.fields.extend(headers.iter().map(|header| Text {
             typ: "mrkdwn",
@@ -37,46 +167,439 @@ impl Section {
         }));
     }
 
-    pub async fn send(&self, client: &Client, hook: &Url) -> Result<(), anyhow::Error> {
-        #[derive(Serialize)]
-        struct Payload {
-            blocks: [Section; 1],
+    fn block(&self, options: &MessageOptions) -> Block {
+        let style = |text: &Text| match options.link_style {
+            LinkStyle::Full => text.clone(),
+            LinkStyle::Compact => Text {
+                typ: text.typ,
+                text: strip_links(&text.text).into(),
+            },
+        };
+        Block {
+            typ_: "section",
+            text: style(&self.title),
+            fields: self.fields.iter().map(style).collect(),
         }
+    }
+
+    /// Render this section as the body of a message, for a hook or for the Web API.
+    pub fn payload(&self, options: &MessageOptions) -> serde_json::Value {
         #[derive(Serialize)]
-        struct Section {
-            #[serde(rename = "type")]
-            typ_: &'static str,
-            text: Text,
-            fields: Vec<Text>,
+        struct Payload<'a> {
+            /// Used in notifications.
+            text: &'a str,
+            blocks: [Block; 1],
+            #[serde(skip_serializing_if = "Option::is_none")]
+            unfurl_links: Option<bool>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            unfurl_media: Option<bool>,
         }
         let payload = Payload {
-            blocks: [Section {
-                typ_: "section",
-                text: self.title.clone(),
-                fields: self.fields.clone(),
-            }],
+            text: &self.title.text,
+            blocks: [self.block(options)],
+            unfurl_links: options.unfurl_links,
+            unfurl_media: options.unfurl_links,
         };
-        debug!(
-            "Sending: {}",
-            serde_json::to_string_pretty(&payload).unwrap()
-        );
-        let response = client
-            .post(hook.to_string())
-            .json(&payload)
-            .send()
-            .await
-            .context("Error while posting message to Slack")?;
-        let status = response.status();
-        if status.is_success().not() {
-            let text = response.text().await.context("Could not gather response")?;
-            return Err(anyhow!(
-                "Slack responded with an error {}: {}",
-                status,
-                text
-            ));
+        serde_json::to_value(payload).expect("sections can be serialized")
+    }
+
+    /// Post this section to a channel with the Slack Web API, or, if we know the `ts` of
+    /// a previous message, replace that message.
+    ///
+    /// Unlike messages posted to incoming webhooks, these messages can be updated later.
+    /// Returns the `ts` of the message.
+    pub async fn post_or_update(
+        &self,
+        client: &Client,
+        token: &str,
+        channel: &str,
+        ts: Option<&str>,
+    ) -> Result<String, anyhow::Error> {
+        post_message(
+            client,
+            token,
+            channel,
+            ts,
+            self.payload(&MessageOptions::default()),
+        )
+        .await
+    }
+}
+
+/// Post a message (see `Section::payload`) to a channel with the Slack Web API, or, if we
+/// know the `ts` of a previous message, replace that message.
+///
+/// Returns the `ts` of the message.
+pub async fn post_message(
+    client: &Client,
+    token: &str,
+    channel: &str,
+    ts: Option<&str>,
+    mut payload: serde_json::Value,
+) -> Result<String, anyhow::Error> {
+    #[derive(Deserialize)]
+    struct Response {
+        ok: bool,
+        #[serde(default)]
+        ts: Option<String>,
+        #[serde(default)]
+        error: Option<String>,
+    }
+    let method = match ts {
+        Some(_) => "chat.update",
+        None => "chat.postMessage",
+    };
+    payload["channel"] = channel.into();
+    if let Some(ts) = ts {
+        payload["ts"] = ts.into();
+    }
+    debug!(
+        "Sending: {}",
+        serde_json::to_string_pretty(&payload).unwrap()
+    );
+    let response = client
+        .post(format!("https://slack.com/api/{method}"))
+        .bearer_auth(token)
+        .json(&payload)
+        .send()
+        .await
+        .with_context(|| format!("Error while calling Slack's {method}"))?;
+    let status = response.status();
+    if status.is_success().not() {
+        let body = response.text().await.context("Could not gather response")?;
+        return Err(Error::SinkRejected {
+            service: "Slack".to_string(),
+            status,
+            body,
+        }
+        .into());
+    }
+    let response: Response = response
+        .json()
+        .await
+        .with_context(|| format!("Invalid response to Slack's {method}"))?;
+    match (response.ok, response.ts) {
+        (true, Some(ts)) => Ok(ts),
+        _ => Err(Error::SinkRejected {
+            service: "Slack".to_string(),
+            status,
+            body: response.error.unwrap_or_default(),
+        }
+        .into()),
+    }
+}
+
+/// Schedule a message (see `Section::payload`, with a `post_at` timestamp) for a channel
+/// with the Slack Web API, see `deliver_at` in the config.
+///
+/// Scheduled messages can't be updated or replied to before they're posted. Returns the id
+/// of the scheduled message.
+pub async fn schedule_message(
+    client: &Client,
+    token: &str,
+    channel: &str,
+    mut payload: serde_json::Value,
+) -> Result<String, anyhow::Error> {
+    payload["channel"] = channel.into();
+    debug!(
+        "Scheduling: {}",
+        serde_json::to_string_pretty(&payload).unwrap()
+    );
+    let response = client
+        .post("https://slack.com/api/chat.scheduleMessage")
+        .bearer_auth(token)
+        .json(&payload)
+        .send()
+        .await
+        .context("Error while calling Slack's chat.scheduleMessage")?;
+    let status = response.status();
+    if status.is_success().not() {
+        let body = response.text().await.context("Could not gather response")?;
+        return Err(Error::SinkRejected {
+            service: "Slack".to_string(),
+            status,
+            body,
+        }
+        .into());
+    }
+    let response: serde_json::Value = response
+        .json()
+        .await
+        .context("Invalid response to Slack's chat.scheduleMessage")?;
+    match response["scheduled_message_id"].as_str() {
+        Some(id) if response["ok"].as_bool() == Some(true) => Ok(id.to_string()),
+        _ => Err(Error::SinkRejected {
+            service: "Slack".to_string(),
+            status,
+            body: response["error"].as_str().unwrap_or_default().to_string(),
         }
-        Ok(())
+        .into()),
+    }
+}
+
+/// Make the members of a user group exactly `users`, given their member ids, unless they
+/// already are.
+///
+/// Returns whether we have updated the user group.
+pub async fn sync_usergroup(
+    client: &Client,
+    token: &str,
+    usergroup: &str,
+    users: &[String],
+) -> Result<bool, anyhow::Error> {
+    let response = call(
+        client,
+        token,
+        "usergroups.users.list",
+        &[("usergroup", usergroup)],
+    )
+    .await?;
+    let current: BTreeSet<&str> = response["users"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|user| user.as_str())
+        .collect();
+    let wanted: BTreeSet<&str> = users.iter().map(String::as_str).collect();
+    if current == wanted {
+        return Ok(false);
+    }
+    call(
+        client,
+        token,
+        "usergroups.users.update",
+        &[
+            ("usergroup", usergroup),
+            ("users", &wanted.iter().join(",")),
+        ],
+    )
+    .await?;
+    Ok(true)
+}
+
+/// Call a method of the Slack Web API with a bot token, e.g. `conversations.create`.
+///
+/// Slack reports most errors with `"ok": false` and an `error`, rejected as well.
+pub async fn call(
+    client: &Client,
+    token: &str,
+    method: &str,
+    params: &[(&str, &str)],
+) -> Result<serde_json::Value, anyhow::Error> {
+    let response = client
+        .post(format!("https://slack.com/api/{method}"))
+        .bearer_auth(token)
+        .form(params)
+        .send()
+        .await
+        .with_context(|| format!("Error while calling Slack's {method}"))?;
+    let status = response.status();
+    if status.is_success().not() {
+        let body = response.text().await.context("Could not gather response")?;
+        return Err(Error::SinkRejected {
+            service: "Slack".to_string(),
+            status,
+            body,
+        }
+        .into());
+    }
+    let response: serde_json::Value = response
+        .json()
+        .await
+        .with_context(|| format!("Invalid response to Slack's {method}"))?;
+    if response["ok"].as_bool() != Some(true) {
+        return Err(Error::SinkRejected {
+            service: "Slack".to_string(),
+            status,
+            body: response["error"].as_str().unwrap_or_default().to_string(),
+        })
+        .with_context(|| format!("Slack's {method} failed"));
     }
+    Ok(response)
+}
+
+/// Whether we may still post to the channel with this id, i.e. it exists and isn't archived.
+pub async fn is_open(client: &Client, token: &str, id: &str) -> Result<bool, anyhow::Error> {
+    match call(client, token, "conversations.info", &[("channel", id)]).await {
+        Ok(response) => Ok(response["channel"]["is_archived"].as_bool() != Some(true)),
+        Err(err)
+            if matches!(
+                Error::find(&err),
+                Some(Error::SinkRejected { body, .. }) if body == "channel_not_found"
+            ) =>
+        {
+            Ok(false)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// A valid Slack channel name, e.g. `reviews-my-repo` for `Reviews-My.Repo`.
+pub fn channel_name(name: &str) -> String {
+    /// Slack's limit.
+    const MAX_LENGTH: usize = 80;
+    name.trim_start_matches('#')
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '-' | '_' => c,
+            _ => '-',
+        })
+        .take(MAX_LENGTH)
+        .collect()
+}
+
+/// Find a public channel by name, creating it if it doesn't exist and `create` is set.
+/// The members of `invite_usergroup`, if any, are invited to channels we create.
+///
+/// Returns the id of the channel, which the bot has joined, if the channel exists.
+pub async fn ensure_channel(
+    client: &Client,
+    token: &str,
+    name: &str,
+    create: bool,
+    invite_usergroup: Option<&str>,
+) -> Result<Option<String>, anyhow::Error> {
+    let mut cursor = String::new();
+    loop {
+        let response = call(
+            client,
+            token,
+            "conversations.list",
+            &[
+                ("types", "public_channel"),
+                ("exclude_archived", "true"),
+                ("limit", "1000"),
+                ("cursor", &cursor),
+            ],
+        )
+        .await?;
+        let found = response["channels"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find(|channel| channel["name"].as_str() == Some(name))
+            .and_then(|channel| channel["id"].as_str());
+        if let Some(id) = found {
+            // Bots may only post to channels they are members of.
+            call(client, token, "conversations.join", &[("channel", id)]).await?;
+            return Ok(Some(id.to_string()));
+        }
+        match response["response_metadata"]["next_cursor"].as_str() {
+            Some(next) if next.is_empty().not() => cursor = next.to_string(),
+            _ => break,
+        }
+    }
+    if create.not() {
+        return Ok(None);
+    }
+    let response = call(client, token, "conversations.create", &[("name", name)]).await?;
+    let id = response["channel"]["id"]
+        .as_str()
+        .context("Slack created a channel without an id")?
+        .to_string();
+    if let Some(usergroup) = invite_usergroup {
+        let response = call(
+            client,
+            token,
+            "usergroups.users.list",
+            &[("usergroup", usergroup)],
+        )
+        .await?;
+        let users = response["users"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|user| user.as_str())
+            .collect::<Vec<_>>()
+            .join(",");
+        if users.is_empty().not() {
+            call(
+                client,
+                token,
+                "conversations.invite",
+                &[("channel", &id), ("users", &users)],
+            )
+            .await?;
+        }
+    }
+    Ok(Some(id))
+}
+
+/// Replace Slack links `<url|text>` with their text, keeping mentions (`<@U0123456789|login>`
+/// or `<!subteam^S0123456789|org/team>`), so that compact Slack messages still ping people.
+pub fn strip_links(text: &str) -> String {
+    static LINK: Lazy<Regex> = lazy_regex! {r"<([^<>|@!][^<>|]*)\|([^<>]*)>"};
+    LINK.replace_all(text, "$2").into_owned()
+}
+
+/// Mention a Slack user, given their member id, e.g. `U0123456789`, and their GitHub
+/// login, which other services display instead, see `strip_mentions`.
+pub fn mention(member: &str, login: &str) -> String {
+    format!("<@{member}|{}>", html_escape::encode_text(login))
+}
+
+/// Mention a Slack user group, given its id, e.g. `S0123456789`, and the GitHub team it
+/// follows, which other services display instead, see `strip_mentions`.
+pub fn mention_usergroup(usergroup: &str, team: &str) -> String {
+    format!("<!subteam^{usergroup}|{}>", html_escape::encode_text(team))
+}
+
+/// Replace Slack mentions `<@U0123456789|login>` (or `<!subteam^S0123456789|org/team>`)
+/// with `@login` (or `@org/team`), for other services.
+pub fn strip_mentions(text: &str) -> Cow<'_, str> {
+    static MENTION: Lazy<Regex> = lazy_regex! {r"<(?:@|!subteam\^)[A-Z0-9]+\|([^<>]*)>"};
+    MENTION.replace_all(text, "@$1")
+}
+
+/// Convert Slack mrkdwn to HTML, e.g. `<url|text>` to `<a href="url">text</a>`.
+///
+/// Everything else is escaped, so that titles can't inject markup.
+fn to_html(text: &str) -> String {
+    static LINK: Lazy<Regex> = lazy_regex! {r"<([^<>|]+)\|([^<>]*)>"};
+    static BOLD: Lazy<Regex> = lazy_regex! {r"(^|\s)\*([^*\n]+)\*"};
+    let text = &strip_mentions(text);
+    let escape = |text: &str| {
+        let text = html_escape::decode_html_entities(text);
+        let text = html_escape::encode_text(&text);
+        BOLD.replace_all(&text, "$1<strong>$2</strong>")
+            .replace('\n', "<br>")
+    };
+    let mut html = String::new();
+    let mut last = 0;
+    for link in LINK.captures_iter(text) {
+        let whole = link.get(0).expect("the whole match");
+        html.push_str(&escape(&text[last..whole.start()]));
+        html.push_str(&format!(
+            "<a href=\"{}\">{}</a>",
+            html_escape::encode_double_quoted_attribute(&html_escape::decode_html_entities(
+                &link[1]
+            )),
+            escape(&link[2]),
+        ));
+        last = whole.end();
+    }
+    html.push_str(&escape(&text[last..]));
+    html
+}
+
+/// Convert Slack mrkdwn to standard markdown, e.g. `<url|text>` to `[text](url)`, for
+/// Discord or Teams.
+pub fn to_markdown(text: &str, options: &MessageOptions) -> String {
+    static LINK: Lazy<Regex> = lazy_regex! {r"<([^<>|]+)\|([^<>]*)>"};
+    static BOLD: Lazy<Regex> = lazy_regex! {r"(^|\s)\*([^*\n]+)\*"};
+    let text = &strip_mentions(text);
+    let text = match options.link_style {
+        LinkStyle::Full => LINK.replace_all(text, "[$2]($1)").into_owned(),
+        LinkStyle::Compact => strip_links(text),
+    };
+    let text = BOLD.replace_all(&text, "$1**$2**");
+    html_escape::decode_html_entities(&text).into_owned()
+}
+
+/// Escape text from users for mrkdwn, so that it can't e.g. mention `<!channel>` or hide links.
+pub fn escape(text: &str) -> String {
+    html_escape::encode_text(text).into_owned()
 }
 
 pub fn link(url: &Url, text: Option<&str>) -> String {
@@ -90,3 +613,56 @@ pub fn link(url: &Url, text: Option<&str>) -> String {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use url::Url;
+
+    use super::{channel_name, link, mention, mention_usergroup, strip_links, Section};
+
+    /// Are sections rendered as HTML, without letting titles inject markup?
+    #[test]
+    fn test_to_html() {
+        let url = Url::parse("https://github.com/owner1/project1/pull/1?a=1&b=2").unwrap();
+        let mut section = Section::new(format!(
+            "PRs of repo {} waiting for reviews",
+            link(&url, Some("project1"))
+        ));
+        section.append_fields(&["*Request*".to_string(), "*Reviewer*".to_string()]);
+        section.append_fields(&[link(&url, Some("Fix <script>")), "alice <b>".to_string()]);
+        assert_eq!(
+            section.to_html(),
+            "<p>PRs of repo <a href=\"https://github.com/owner1/project1/pull/1?a=1&amp;b=2\">project1</a> waiting for reviews</p>\
+            <table>\
+            <tr><td><strong>Request</strong></td><td><strong>Reviewer</strong></td></tr>\
+            <tr><td><a href=\"https://github.com/owner1/project1/pull/1?a=1&amp;b=2\">Fix &lt;script&gt;</a></td><td>alice &lt;b&gt;</td></tr>\
+            </table>"
+        );
+        assert_eq!(
+            Section::new("Nothing to see".to_string()).to_html(),
+            "<p>Nothing to see</p>"
+        );
+    }
+
+    /// Do compact links keep only their text, and mentions stay mentions?
+    #[test]
+    fn test_strip_links() {
+        let url = Url::parse("https://github.com/owner1/project1/pull/1").unwrap();
+        let text = format!("🐞 {} ☑ 1/2", link(&url, Some("Fix <b>")));
+        assert_eq!(strip_links(&text), "🐞 Fix &lt;b&gt; ☑ 1/2");
+        let text = format!(
+            "{}, {}, bob",
+            mention("U0123456789", "alice"),
+            mention_usergroup("S0123456789", "owner1/sdk")
+        );
+        assert_eq!(strip_links(&text), text);
+        assert_eq!(Section::new(text).plain_text(), "@alice, @owner1/sdk, bob");
+    }
+
+    /// Do we turn names into valid Slack channel names?
+    #[test]
+    fn test_channel_name() {
+        assert_eq!(channel_name("#reviews-My.Repo"), "reviews-my-repo");
+        assert_eq!(channel_name(&"x".repeat(100)).len(), 80);
+    }
+}
@@ -0,0 +1,932 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use chrono::{DateTime, NaiveDate, Utc};
+use derive_more::Display;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use url::Url;
+
+use crate::{lifecycle::Lifecycle, popularity::Record, report::SectionKind};
+
+pub mod file;
+#[cfg(feature = "redis")]
+pub mod redis;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+/// A backend able to persist the state between two runs.
+///
+/// The state is organized as namespaces (e.g. "skipped") of key/value pairs.
+pub trait StateStore: Send {
+    /// Read a value, if it exists.
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<serde_json::Value>, anyhow::Error>;
+
+    /// Write a value, replacing any previous value.
+    fn put(
+        &mut self,
+        namespace: &str,
+        key: &str,
+        value: serde_json::Value,
+    ) -> Result<(), anyhow::Error>;
+
+    /// Remove a value, if it exists.
+    fn remove(&mut self, namespace: &str, key: &str) -> Result<(), anyhow::Error>;
+
+    /// Make sure that all changes are persisted.
+    fn flush(&mut self) -> Result<(), anyhow::Error>;
+}
+
+/// Where the state is stored.
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "backend", rename_all = "snake_case")]
+pub enum StateConfig {
+    /// A JSON file.
+    File { path: PathBuf },
+
+    /// A SQLite database (requires feature `sqlite`).
+    Sqlite { path: PathBuf },
+
+    /// A Redis server (requires feature `redis`), e.g. `redis://127.0.0.1/`.
+    Redis { url: String },
+}
+impl Default for StateConfig {
+    fn default() -> Self {
+        StateConfig::File {
+            path: PathBuf::from("qastor-state.json"),
+        }
+    }
+}
+impl StateConfig {
+    /// Resolve relative paths against a directory, e.g. that of a tenant, then canonicalize
+    /// them, so that two tenants can't share a state through different paths.
+    pub fn relative_to(&self, dir: &Path) -> Self {
+        match self {
+            StateConfig::File { path } => StateConfig::File {
+                path: canonical(&dir.join(path)),
+            },
+            StateConfig::Sqlite { path } => StateConfig::Sqlite {
+                path: canonical(&dir.join(path)),
+            },
+            StateConfig::Redis { .. } => self.clone(),
+        }
+    }
+}
+
+/// The canonical form of a path, or of its directory if the file doesn't exist yet, e.g.
+/// before the first run.
+fn canonical(path: &Path) -> PathBuf {
+    if let Ok(path) = path.canonicalize() {
+        return path;
+    }
+    match (path.parent().map(Path::canonicalize), path.file_name()) {
+        (Some(Ok(dir)), Some(name)) => dir.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+impl std::fmt::Display for StateConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StateConfig::File { path } => write!(f, "file {}", path.display()),
+            StateConfig::Sqlite { path } => write!(f, "database {}", path.display()),
+            StateConfig::Redis { .. } => write!(f, "redis server"),
+        }
+    }
+}
+
+/// Why a project is not monitored anymore.
+#[derive(Serialize, Deserialize, Display, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    #[display("archived")]
+    Archived,
+    #[display("disabled")]
+    Disabled,
+    #[display("not found")]
+    NotFound,
+}
+
+/// A config being previewed, until it's promoted.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Preview {
+    /// The fingerprint of the config.
+    pub config: String,
+
+    /// When we first ran with this config.
+    pub since: DateTime<Utc>,
+}
+
+/// An acknowledgment of an item, e.g. a reviewer telling us that they're on it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Ack {
+    /// Who acknowledged the item.
+    pub by: String,
+
+    /// When the item was acknowledged.
+    pub at: DateTime<Utc>,
+}
+
+/// The `ts` of messages, by channel id and kind of section.
+pub type LiveMessages = BTreeMap<String, BTreeMap<SectionKind, String>>;
+
+/// A message that Slack delivers later, see `deliver_at` in the config.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledMessage {
+    /// The `scheduled_message_id` of the message.
+    pub id: String,
+
+    /// When Slack delivers the message.
+    pub post_at: DateTime<Utc>,
+}
+
+/// Scheduled messages, by channel id and kind of section.
+pub type ScheduledMessages = BTreeMap<String, BTreeMap<SectionKind, ScheduledMessage>>;
+
+/// Whether Slack has yet to deliver some of these messages, in which case the report that
+/// they belong to is still to come, and we shouldn't schedule another one.
+pub fn is_pending(messages: &ScheduledMessages, now: DateTime<Utc>) -> bool {
+    messages
+        .values()
+        .flat_map(BTreeMap::values)
+        .any(|message| message.post_at > now)
+}
+
+/// The authors of the recent commits of the files touched by a PR, as of a head commit,
+/// see `github::fetch_recent_authors`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct RecentAuthors {
+    /// The SHA of the head of the PR.
+    pub head: String,
+
+    /// The authors by file, or `None` if the PR had been reviewed already.
+    pub authors: Option<Vec<Vec<String>>>,
+}
+
+/// The parent messages of the threads of a project, see `State::threads`.
+#[derive(Serialize, Deserialize)]
+struct Threads {
+    day: NaiveDate,
+    parents: BTreeMap<String, String>,
+}
+
+/// Projects that we have stopped monitoring.
+///
+/// Remove an entry to resume monitoring the project.
+const SKIPPED: &str = "skipped";
+
+/// Projects that have moved, from their configured url to their current url.
+const MOVED: &str = "moved";
+
+/// The latest activity (push or update) of each project, as of the last run.
+const LAST_ACTIVITY: &str = "last_activity";
+
+/// When each project was last handled successfully.
+const LAST_RUN: &str = "last_run";
+
+/// When we last notified about each item.
+const SEEN: &str = "seen";
+
+/// The open issues of each project listed in the latest report.
+const REPORTED: &str = "reported";
+
+/// Identifiers of the messages we have posted, e.g. Slack's `ts`.
+const MESSAGE_TS: &str = "message_ts";
+
+/// Items that have been acknowledged.
+const ACKS: &str = "acks";
+
+/// Items or projects that should not be reported until a given date.
+const SNOOZES: &str = "snoozes";
+
+/// Projects disabled (`true`) or enabled (`false`) from the command line, overriding
+/// `disabled` in the config.
+const DISABLED: &str = "disabled";
+
+/// The latest release of qastor that we have announced (`announced`), and the tags of each
+/// project as of its last run.
+const RELEASES: &str = "releases";
+
+/// The stars and forks of each project as of its last run, the milestones celebrated and the
+/// stars counted within the window of spikes.
+const POPULARITY: &str = "popularity";
+
+/// When all the projects of the config were last checked.
+const LAST_FULL_RUN: &str = "last_full_run";
+
+/// The config being previewed (`pending`) and the latest promoted config (`promoted`).
+const PREVIEW: &str = "preview";
+
+/// The ids of the Slack channels of projects, by name.
+const CHANNELS: &str = "channels";
+
+/// The day we last sent the weekly digest of each project.
+const DIGESTS: &str = "digests";
+
+/// The day we last sent the milestone report of each project.
+const MILESTONE_REPORTS: &str = "milestone_reports";
+
+/// When we escalated the SLA breach of an issue, by project and issue number.
+const SLA_BREACHES: &str = "sla_breaches";
+
+/// When we started checking each SLA policy of each project, by project and label.
+const SLA_POLICIES: &str = "sla_policies";
+
+/// The lifecycle of the issues of each project, by issue number, with `triage`.
+const LIFECYCLES: &str = "lifecycles";
+
+/// The authors from whom we suggest reviewers for PRs, by project and PR number.
+const RECENT_AUTHORS: &str = "recent_authors";
+
+/// The number of items of each kind of each project, as of the last run that checked it,
+/// for the status board.
+const STATUS_BOARD: &str = "status_board";
+
+/// The latest force-push to each PR that we told its reviewers about, by project and PR
+/// number, with `force_push_dms`.
+const FORCE_PUSH_DMS: &str = "force_push_dms";
+
+/// Information persisted from one run to the next.
+pub struct State {
+    store: Box<dyn StateStore>,
+
+    /// While previewing or printing reports, the changes to the state, which we keep in
+    /// memory rather than in the store, see `set_scratch`.
+    scratch: Option<BTreeMap<(String, String), serde_json::Value>>,
+}
+impl State {
+    pub fn new(store: Box<dyn StateStore>) -> Self {
+        State {
+            store,
+            scratch: None,
+        }
+    }
+
+    /// If `true`, keep the changes to the state in memory from now on, until `set_scratch`
+    /// is called with `false`, which forgets them, e.g. so that the items shown to the
+    /// preview hook are reported as usual once the config is promoted.
+    pub fn set_scratch(&mut self, scratch: bool) {
+        self.scratch = scratch.then(BTreeMap::new);
+    }
+
+    /// Open the state with the backend selected in the config.
+    pub fn open(config: &StateConfig) -> Result<Self, anyhow::Error> {
+        let store: Box<dyn StateStore> = match config {
+            StateConfig::File { path } => Box::new(file::FileStore::open(path)?),
+            #[cfg(feature = "sqlite")]
+            StateConfig::Sqlite { path } => Box::new(sqlite::SqliteStore::open(path)?),
+            #[cfg(feature = "redis")]
+            StateConfig::Redis { url } => Box::new(redis::RedisStore::open(url)?),
+            #[allow(unreachable_patterns)]
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "This build of qastor does not support state backend {config}, please rebuild with the corresponding feature"
+                ))
+            }
+        };
+        Ok(State::new(store))
+    }
+
+    /// Make sure that all changes are persisted.
+    pub fn save(&mut self) -> Result<(), anyhow::Error> {
+        self.store.flush()
+    }
+
+    fn get<T: DeserializeOwned>(
+        &self,
+        namespace: &str,
+        key: &str,
+    ) -> Result<Option<T>, anyhow::Error> {
+        let scratched = self
+            .scratch
+            .as_ref()
+            .and_then(|scratch| scratch.get(&(namespace.to_string(), key.to_string())));
+        let value = match scratched {
+            // Removed, see `remove`.
+            Some(serde_json::Value::Null) => None,
+            Some(value) => Some(value.clone()),
+            None => self.store.get(namespace, key)?,
+        };
+        let Some(value) = value else {
+            return Ok(None);
+        };
+        let value = serde_json::from_value(value)
+            .with_context(|| format!("Invalid state entry {namespace}/{key}"))?;
+        Ok(Some(value))
+    }
+
+    fn put<T: Serialize + ?Sized>(
+        &mut self,
+        namespace: &str,
+        key: &str,
+        value: &T,
+    ) -> Result<(), anyhow::Error> {
+        let value = serde_json::to_value(value).context("Could not serialize state entry")?;
+        match self.scratch {
+            Some(ref mut scratch) => {
+                scratch.insert((namespace.to_string(), key.to_string()), value);
+                Ok(())
+            }
+            None => self.store.put(namespace, key, value),
+        }
+    }
+
+    fn remove(&mut self, namespace: &str, key: &str) -> Result<(), anyhow::Error> {
+        match self.scratch {
+            Some(ref mut scratch) => {
+                scratch.insert(
+                    (namespace.to_string(), key.to_string()),
+                    serde_json::Value::Null,
+                );
+                Ok(())
+            }
+            None => self.store.remove(namespace, key),
+        }
+    }
+
+    /// The key for an item (issue or PR) within a project.
+    fn item_key(project: &Url, item: u64) -> String {
+        format!("{project}#{item}")
+    }
+
+    /// If we have stopped monitoring a project, the reason why.
+    pub fn skip_reason(&self, project: &Url) -> Result<Option<SkipReason>, anyhow::Error> {
+        self.get(SKIPPED, project.as_str())
+    }
+
+    /// Stop monitoring a project in future runs.
+    pub fn set_skipped(&mut self, project: &Url, reason: SkipReason) -> Result<(), anyhow::Error> {
+        self.put(SKIPPED, project.as_str(), &reason)
+    }
+
+    /// Whether a project has been disabled (or enabled) from the command line, if ever.
+    pub fn disabled(&self, project: &Url) -> Result<Option<bool>, anyhow::Error> {
+        self.get(DISABLED, project.as_str())
+    }
+
+    /// Disable (or enable) a project, whatever the config says.
+    pub fn set_disabled(&mut self, project: &Url, disabled: bool) -> Result<(), anyhow::Error> {
+        self.put(DISABLED, project.as_str(), &disabled)
+    }
+
+    /// Let the config say whether a project is disabled again.
+    pub fn clear_disabled(&mut self, project: &Url) -> Result<(), anyhow::Error> {
+        self.remove(DISABLED, project.as_str())
+    }
+
+    /// If a project has moved away from its configured url, its current url.
+    pub fn moved_to(&self, project: &Url) -> Result<Option<Url>, anyhow::Error> {
+        self.get(MOVED, project.as_str())
+    }
+
+    /// Record that a project has moved away from its configured url.
+    pub fn set_moved_to(&mut self, project: &Url, current: &Url) -> Result<(), anyhow::Error> {
+        self.put(MOVED, project.as_str(), current)
+    }
+
+    /// The latest activity of a project, as of the last run.
+    pub fn last_activity(&self, project: &Url) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+        self.get(LAST_ACTIVITY, project.as_str())
+    }
+
+    /// Record the latest activity of a project.
+    pub fn set_last_activity(
+        &mut self,
+        project: &Url,
+        activity: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error> {
+        self.put(LAST_ACTIVITY, project.as_str(), &activity)
+    }
+
+    /// When a project was last handled successfully.
+    pub fn last_run(&self, project: &Url) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+        self.get(LAST_RUN, project.as_str())
+    }
+
+    /// Record that a project was handled successfully.
+    pub fn set_last_run(&mut self, project: &Url, at: DateTime<Utc>) -> Result<(), anyhow::Error> {
+        self.put(LAST_RUN, project.as_str(), &at)
+    }
+
+    /// When all the projects were last checked, e.g. to schedule tenants.
+    pub fn last_full_run(&self) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+        self.get(LAST_FULL_RUN, "all")
+    }
+
+    /// Record that all the projects were checked.
+    pub fn set_last_full_run(&mut self, at: DateTime<Utc>) -> Result<(), anyhow::Error> {
+        self.put(LAST_FULL_RUN, "all", &at)
+    }
+
+    /// The config being previewed, if any.
+    pub fn preview(&self) -> Result<Option<Preview>, anyhow::Error> {
+        self.get(PREVIEW, "pending")
+    }
+
+    /// Start previewing a config.
+    pub fn set_preview(&mut self, preview: &Preview) -> Result<(), anyhow::Error> {
+        self.put(PREVIEW, "pending", preview)
+    }
+
+    /// The fingerprint of the latest promoted config.
+    pub fn promoted(&self) -> Result<Option<String>, anyhow::Error> {
+        self.get(PREVIEW, "promoted")
+    }
+
+    /// Enable normal delivery for a config.
+    pub fn set_promoted(&mut self, config: &str) -> Result<(), anyhow::Error> {
+        self.put(PREVIEW, "promoted", config)
+    }
+
+    /// The tag of the latest release of qastor that we have announced, if any.
+    pub fn announced_release(&self) -> Result<Option<String>, anyhow::Error> {
+        self.get(RELEASES, "announced")
+    }
+
+    /// Record that we have announced a release of qastor.
+    pub fn set_announced_release(&mut self, tag: &str) -> Result<(), anyhow::Error> {
+        self.put(RELEASES, "announced", tag)
+    }
+
+    /// The tags of a project as of its last run, if we have ever listed them.
+    pub fn tags(&self, project: &Url) -> Result<Option<Vec<String>>, anyhow::Error> {
+        self.get(RELEASES, project.as_str())
+    }
+
+    /// Record the tags of a project.
+    pub fn set_tags(&mut self, project: &Url, tags: &[String]) -> Result<(), anyhow::Error> {
+        self.put(RELEASES, project.as_str(), tags)
+    }
+
+    /// What we remember of the popularity of a project, if we have ever counted its stars.
+    pub fn popularity(&self, project: &Url) -> Result<Option<Record>, anyhow::Error> {
+        self.get(POPULARITY, project.as_str())
+    }
+
+    /// Record the popularity of a project.
+    pub fn set_popularity(&mut self, project: &Url, record: &Record) -> Result<(), anyhow::Error> {
+        self.put(POPULARITY, project.as_str(), record)
+    }
+
+    /// When we last notified about an item.
+    pub fn seen(&self, project: &Url, item: u64) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+        self.get(SEEN, &Self::item_key(project, item))
+    }
+
+    /// Record that we have notified about an item.
+    pub fn set_seen(
+        &mut self,
+        project: &Url,
+        item: u64,
+        at: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error> {
+        self.put(SEEN, &Self::item_key(project, item), &at)
+    }
+
+    /// When we escalated the SLA breach of an issue, if ever.
+    pub fn escalated(
+        &self,
+        project: &Url,
+        item: u64,
+    ) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+        self.get(SLA_BREACHES, &Self::item_key(project, item))
+    }
+
+    /// Record that we have escalated the SLA breach of an issue.
+    pub fn set_escalated(
+        &mut self,
+        project: &Url,
+        item: u64,
+        at: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error> {
+        self.put(SLA_BREACHES, &Self::item_key(project, item), &at)
+    }
+
+    /// The day we last sent the weekly digest of a project, if ever.
+    pub fn last_digest(&self, project: &Url) -> Result<Option<NaiveDate>, anyhow::Error> {
+        self.get(DIGESTS, project.as_str())
+    }
+
+    /// Record that we have sent the weekly digest of a project.
+    pub fn set_last_digest(&mut self, project: &Url, day: NaiveDate) -> Result<(), anyhow::Error> {
+        self.put(DIGESTS, project.as_str(), &day)
+    }
+
+    /// The day we last sent the milestone report of a project, if ever.
+    pub fn last_milestone_report(&self, project: &Url) -> Result<Option<NaiveDate>, anyhow::Error> {
+        self.get(MILESTONE_REPORTS, project.as_str())
+    }
+
+    /// Record that we have sent the milestone report of a project.
+    pub fn set_last_milestone_report(
+        &mut self,
+        project: &Url,
+        day: NaiveDate,
+    ) -> Result<(), anyhow::Error> {
+        self.put(MILESTONE_REPORTS, project.as_str(), &day)
+    }
+
+    /// The open issues listed in the latest report about a project.
+    pub fn reported(&self, project: &Url) -> Result<Vec<u64>, anyhow::Error> {
+        Ok(self.get(REPORTED, project.as_str())?.unwrap_or_default())
+    }
+
+    /// Record the open issues listed in a report about a project.
+    pub fn set_reported(&mut self, project: &Url, issues: &[u64]) -> Result<(), anyhow::Error> {
+        self.put(REPORTED, project.as_str(), issues)
+    }
+
+    /// The lifecycle of the issues of a project that we have seen lately, by number.
+    pub fn lifecycles(&self, project: &Url) -> Result<BTreeMap<u64, Lifecycle>, anyhow::Error> {
+        Ok(self.get(LIFECYCLES, project.as_str())?.unwrap_or_default())
+    }
+
+    /// Record the lifecycle of the issues of a project.
+    pub fn set_lifecycles(
+        &mut self,
+        project: &Url,
+        lifecycles: &BTreeMap<u64, Lifecycle>,
+    ) -> Result<(), anyhow::Error> {
+        self.put(LIFECYCLES, project.as_str(), lifecycles)
+    }
+
+    /// The identifier of the status board in a channel, if we have posted it.
+    pub fn status_board_ts(&self, channel: &str) -> Result<Option<String>, anyhow::Error> {
+        self.get(MESSAGE_TS, &format!("status_board {channel}"))
+    }
+
+    /// Record the identifier of the status board in a channel.
+    pub fn set_status_board_ts(&mut self, channel: &str, ts: &str) -> Result<(), anyhow::Error> {
+        self.put(MESSAGE_TS, &format!("status_board {channel}"), ts)
+    }
+
+    /// When the latest force-push to a PR that we told its reviewers about happened.
+    pub fn force_push_dm(
+        &self,
+        project: &Url,
+        number: u64,
+    ) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+        self.get(FORCE_PUSH_DMS, &Self::item_key(project, number))
+    }
+
+    /// Record that we have told the reviewers of a PR about a force-push.
+    pub fn set_force_push_dm(
+        &mut self,
+        project: &Url,
+        number: u64,
+        at: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error> {
+        self.put(FORCE_PUSH_DMS, &Self::item_key(project, number), &at)
+    }
+
+    /// When we started checking an SLA policy of a project, by label, if ever.
+    pub fn sla_enabled(
+        &self,
+        project: &Url,
+        label: &str,
+    ) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+        self.get(SLA_POLICIES, &format!("{project} {label}"))
+    }
+
+    /// Record when we started checking an SLA policy of a project.
+    pub fn set_sla_enabled(
+        &mut self,
+        project: &Url,
+        label: &str,
+        at: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error> {
+        self.put(SLA_POLICIES, &format!("{project} {label}"), &at)
+    }
+
+    /// The number of items of each kind of a project, as of the last run that checked it.
+    pub fn board_counts(
+        &self,
+        project: &Url,
+    ) -> Result<Option<BTreeMap<SectionKind, usize>>, anyhow::Error> {
+        self.get(STATUS_BOARD, project.as_str())
+    }
+
+    /// Record the number of items of each kind of a project.
+    pub fn set_board_counts(
+        &mut self,
+        project: &Url,
+        counts: &BTreeMap<SectionKind, usize>,
+    ) -> Result<(), anyhow::Error> {
+        self.put(STATUS_BOARD, project.as_str(), counts)
+    }
+
+    /// The identifier of the pinned summary in the channel of a project, if we have posted it.
+    pub fn summary_ts(&self, channel: &str) -> Result<Option<String>, anyhow::Error> {
+        self.get(MESSAGE_TS, &format!("summary {channel}"))
+    }
+
+    /// Record the identifier of the pinned summary in the channel of a project.
+    pub fn set_summary_ts(&mut self, channel: &str, ts: &str) -> Result<(), anyhow::Error> {
+        self.put(MESSAGE_TS, &format!("summary {channel}"), ts)
+    }
+
+    /// The identifiers of the parent messages of the threads of a project on a day, by
+    /// channel id. Threads of previous days are not continued.
+    pub fn threads(
+        &self,
+        project: &Url,
+        day: NaiveDate,
+    ) -> Result<BTreeMap<String, String>, anyhow::Error> {
+        let threads: Option<Threads> = self.get(MESSAGE_TS, &format!("threads {project}"))?;
+        Ok(threads
+            .filter(|threads| threads.day == day)
+            .map(|threads| threads.parents)
+            .unwrap_or_default())
+    }
+
+    /// The identifiers of the latest messages about each kind of section of a project, by
+    /// channel id, see `update_in_place` in the config.
+    pub fn live_messages(&self, project: &Url) -> Result<LiveMessages, anyhow::Error> {
+        Ok(self
+            .get(MESSAGE_TS, &format!("live {project}"))?
+            .unwrap_or_default())
+    }
+
+    /// Record the latest messages about each kind of section of a project.
+    pub fn set_live_messages(
+        &mut self,
+        project: &Url,
+        messages: &LiveMessages,
+    ) -> Result<(), anyhow::Error> {
+        self.put(MESSAGE_TS, &format!("live {project}"), messages)
+    }
+
+    /// The messages about a project that we have scheduled during its latest run, see
+    /// `deliver_at` in the config.
+    pub fn scheduled_messages(&self, project: &Url) -> Result<ScheduledMessages, anyhow::Error> {
+        Ok(self
+            .get(MESSAGE_TS, &format!("scheduled {project}"))?
+            .unwrap_or_default())
+    }
+
+    /// Record the messages about a project that we have scheduled during a run.
+    pub fn set_scheduled_messages(
+        &mut self,
+        project: &Url,
+        messages: &ScheduledMessages,
+    ) -> Result<(), anyhow::Error> {
+        self.put(MESSAGE_TS, &format!("scheduled {project}"), messages)
+    }
+
+    /// Record the parent messages of the threads of a project on a day.
+    pub fn set_threads(
+        &mut self,
+        project: &Url,
+        day: NaiveDate,
+        parents: &BTreeMap<String, String>,
+    ) -> Result<(), anyhow::Error> {
+        let threads = Threads {
+            day,
+            parents: parents.clone(),
+        };
+        self.put(MESSAGE_TS, &format!("threads {project}"), &threads)
+    }
+
+    /// The authors from whom we last suggested reviewers for a PR.
+    pub fn recent_authors(
+        &self,
+        project: &Url,
+        item: u64,
+    ) -> Result<Option<RecentAuthors>, anyhow::Error> {
+        self.get(RECENT_AUTHORS, &Self::item_key(project, item))
+    }
+
+    /// Record the authors from whom we suggest reviewers for a PR.
+    pub fn set_recent_authors(
+        &mut self,
+        project: &Url,
+        item: u64,
+        authors: &RecentAuthors,
+    ) -> Result<(), anyhow::Error> {
+        self.put(RECENT_AUTHORS, &Self::item_key(project, item), authors)
+    }
+
+    /// The id of a Slack channel, if we have already found (or created) it.
+    pub fn channel_id(&self, name: &str) -> Result<Option<String>, anyhow::Error> {
+        self.get(CHANNELS, name)
+    }
+
+    /// Record the id of a Slack channel.
+    pub fn set_channel_id(&mut self, name: &str, id: &str) -> Result<(), anyhow::Error> {
+        self.put(CHANNELS, name, id)
+    }
+
+    /// Forget the id of a Slack channel, e.g. once it has been archived.
+    pub fn remove_channel_id(&mut self, name: &str) -> Result<(), anyhow::Error> {
+        self.remove(CHANNELS, name)
+    }
+
+    /// If an item has been acknowledged, by whom and when.
+    pub fn ack(&self, project: &Url, item: u64) -> Result<Option<Ack>, anyhow::Error> {
+        self.get(ACKS, &Self::item_key(project, item))
+    }
+
+    /// Record that an item has been acknowledged.
+    pub fn set_ack(&mut self, project: &Url, item: u64, ack: &Ack) -> Result<(), anyhow::Error> {
+        self.put(ACKS, &Self::item_key(project, item), ack)
+    }
+
+    /// If an item (or with `item == None`, a whole project) is snoozed, until when.
+    pub fn snoozed_until(
+        &self,
+        project: &Url,
+        item: Option<u64>,
+    ) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+        match item {
+            Some(item) => self.get(SNOOZES, &Self::item_key(project, item)),
+            None => self.get(SNOOZES, project.as_str()),
+        }
+    }
+
+    /// Snooze an item (or with `item == None`, a whole project) until a given date.
+    pub fn set_snoozed_until(
+        &mut self,
+        project: &Url,
+        item: Option<u64>,
+        until: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error> {
+        match item {
+            Some(item) => self.put(SNOOZES, &Self::item_key(project, item), &until),
+            None => self.put(SNOOZES, project.as_str(), &until),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use chrono::{Duration, NaiveDate, TimeZone, Utc};
+    use url::Url;
+
+    use super::{
+        file::FileStore, is_pending, LiveMessages, ScheduledMessage, ScheduledMessages, SkipReason,
+        State,
+    };
+    use crate::{
+        popularity::{Counts, Record},
+        report::SectionKind,
+    };
+
+    /// Is the state preserved across a save/load?
+    #[test]
+    fn test_state_roundtrip() {
+        let path = std::env::temp_dir().join("qastor-test-state-roundtrip.json");
+        let _ = std::fs::remove_file(&path);
+        let project = Url::parse("https://github.com/owner1/project1").unwrap();
+
+        let mut state = State::new(Box::new(FileStore::open(&path).unwrap()));
+        assert_eq!(state.skip_reason(&project).unwrap(), None);
+        assert_eq!(state.disabled(&project).unwrap(), None);
+        state.set_skipped(&project, SkipReason::Archived).unwrap();
+        state.set_disabled(&project, false).unwrap();
+        let record = Record {
+            counts: Counts {
+                stars: 1_024,
+                forks: 12,
+            },
+            celebrated: Some(Counts {
+                stars: 1_000,
+                forks: 0,
+            }),
+            samples: vec![],
+        };
+        state.set_popularity(&project, &record).unwrap();
+        state.save().unwrap();
+
+        let state = State::new(Box::new(FileStore::open(&path).unwrap()));
+        assert_eq!(
+            state.skip_reason(&project).unwrap(),
+            Some(SkipReason::Archived)
+        );
+        assert_eq!(state.disabled(&project).unwrap(), Some(false));
+        assert_eq!(state.popularity(&project).unwrap(), Some(record));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    /// Can we find the latest messages about each kind of section?
+    #[test]
+    fn test_live_messages() {
+        let path = std::env::temp_dir().join("qastor-test-state-live.json");
+        let _ = std::fs::remove_file(&path);
+        let project = Url::parse("https://github.com/owner1/project1").unwrap();
+        let mut messages = LiveMessages::new();
+        messages
+            .entry("C0123".to_string())
+            .or_default()
+            .insert(SectionKind::PendingReviews, "1715000000.000100".to_string());
+
+        let mut state = State::new(Box::new(FileStore::open(&path).unwrap()));
+        assert!(state.live_messages(&project).unwrap().is_empty());
+        state.set_live_messages(&project, &messages).unwrap();
+        state.save().unwrap();
+
+        let state = State::new(Box::new(FileStore::open(&path).unwrap()));
+        assert_eq!(state.live_messages(&project).unwrap(), messages);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    /// Are threads only continued on the day they were started?
+    #[test]
+    fn test_threads() {
+        let path = std::env::temp_dir().join("qastor-test-state-threads.json");
+        let _ = std::fs::remove_file(&path);
+        let project = Url::parse("https://github.com/owner1/project1").unwrap();
+        let today = NaiveDate::from_ymd_opt(2024, 5, 6).unwrap();
+        let parents: BTreeMap<String, String> =
+            [("C0123".to_string(), "1715000000.000100".to_string())]
+                .into_iter()
+                .collect();
+
+        let mut state = State::new(Box::new(FileStore::open(&path).unwrap()));
+        assert!(state.threads(&project, today).unwrap().is_empty());
+        state.set_threads(&project, today, &parents).unwrap();
+        assert_eq!(state.threads(&project, today).unwrap(), parents);
+        assert!(state
+            .threads(&project, today.succ_opt().unwrap())
+            .unwrap()
+            .is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    /// Is a single report scheduled, however often we run before it's delivered?
+    #[test]
+    fn test_scheduled_messages() {
+        let path = std::env::temp_dir().join("qastor-test-state-scheduled.json");
+        let _ = std::fs::remove_file(&path);
+        let project = Url::parse("https://github.com/owner1/project1").unwrap();
+        let post_at = Utc.with_ymd_and_hms(2024, 5, 7, 7, 0, 0).unwrap();
+        let mut state = State::new(Box::new(FileStore::open(&path).unwrap()));
+        let mut schedules = 0;
+        // Runs every 15 minutes during the night, then after the delivery.
+        for run in 0..30 {
+            let now =
+                Utc.with_ymd_and_hms(2024, 5, 7, 0, 0, 0).unwrap() + Duration::minutes(15 * run);
+            if is_pending(&state.scheduled_messages(&project).unwrap(), now) {
+                continue;
+            }
+            schedules += 1;
+            let mut messages = ScheduledMessages::new();
+            messages.entry("C0123".to_string()).or_default().insert(
+                SectionKind::PendingReviews,
+                ScheduledMessage {
+                    id: format!("Q{run}"),
+                    post_at: if now < post_at {
+                        post_at
+                    } else {
+                        post_at + Duration::days(1)
+                    },
+                },
+            );
+            state.set_scheduled_messages(&project, &messages).unwrap();
+        }
+        assert_eq!(schedules, 2);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    /// Are the items shown to the preview hook reported once the config is promoted?
+    #[test]
+    fn test_scratch() {
+        let path = std::env::temp_dir().join("qastor-test-state-scratch.json");
+        let _ = std::fs::remove_file(&path);
+        let project = Url::parse("https://github.com/owner1/project1").unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 5, 6, 12, 0, 0).unwrap();
+
+        let mut state = State::new(Box::new(FileStore::open(&path).unwrap()));
+        // Previewing.
+        state.set_scratch(true);
+        assert_eq!(state.seen(&project, 1).unwrap(), None);
+        state.set_seen(&project, 1, now).unwrap();
+        state.set_last_run(&project, now).unwrap();
+        assert_eq!(state.seen(&project, 1).unwrap(), Some(now));
+        state.save().unwrap();
+        state.set_scratch(false);
+
+        // Promoted.
+        let mut state = State::new(Box::new(FileStore::open(&path).unwrap()));
+        assert_eq!(state.seen(&project, 1).unwrap(), None);
+        assert_eq!(state.last_run(&project).unwrap(), None);
+        state.set_seen(&project, 1, now).unwrap();
+        assert_eq!(state.seen(&project, 1).unwrap(), Some(now));
+
+        // Removals are kept in memory as well.
+        state.set_channel_id("reviews", "C0123456789").unwrap();
+        state.set_scratch(true);
+        state.remove_channel_id("reviews").unwrap();
+        assert_eq!(state.channel_id("reviews").unwrap(), None);
+        state.set_scratch(false);
+        assert_eq!(
+            state.channel_id("reviews").unwrap().as_deref(),
+            Some("C0123456789")
+        );
+        state.remove_channel_id("reviews").unwrap();
+        assert_eq!(state.channel_id("reviews").unwrap(), None);
+        let _ = std::fs::remove_file(&path);
+    }
+}
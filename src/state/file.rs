@@ -0,0 +1,72 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use super::StateStore;
+
+/// A state stored as a JSON file, read entirely on startup and written on flush.
+///
+/// The file is a JSON object of namespaces, each of them a JSON object of entries.
+pub struct FileStore {
+    path: PathBuf,
+    data: BTreeMap<String, BTreeMap<String, serde_json::Value>>,
+}
+impl FileStore {
+    /// Load the state from a file.
+    ///
+    /// If the file doesn't exist yet, start with an empty state.
+    pub fn open(path: &Path) -> Result<Self, anyhow::Error> {
+        let data = match std::fs::read_to_string(path) {
+            Ok(source) => serde_json::from_str(&source)
+                .with_context(|| format!("Invalid state file {}", path.display()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("Could not read state file {}", path.display()))
+            }
+        };
+        Ok(FileStore {
+            path: path.to_path_buf(),
+            data,
+        })
+    }
+}
+impl StateStore for FileStore {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<serde_json::Value>, anyhow::Error> {
+        Ok(self
+            .data
+            .get(namespace)
+            .and_then(|entries| entries.get(key))
+            .cloned())
+    }
+
+    fn put(
+        &mut self,
+        namespace: &str,
+        key: &str,
+        value: serde_json::Value,
+    ) -> Result<(), anyhow::Error> {
+        self.data
+            .entry(namespace.to_string())
+            .or_default()
+            .insert(key.to_string(), value);
+        Ok(())
+    }
+
+    fn remove(&mut self, namespace: &str, key: &str) -> Result<(), anyhow::Error> {
+        if let Some(entries) = self.data.get_mut(namespace) {
+            entries.remove(key);
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), anyhow::Error> {
+        let source =
+            serde_json::to_string_pretty(&self.data).context("Could not serialize state")?;
+        std::fs::write(&self.path, source)
+            .with_context(|| format!("Could not write state file {}", self.path.display()))
+    }
+}
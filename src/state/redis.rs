@@ -0,0 +1,69 @@
+use std::cell::RefCell;
+
+use anyhow::Context;
+use redis::Commands;
+
+use super::StateStore;
+
+/// A state stored in a Redis server, as one hash `qastor:<namespace>` per namespace.
+///
+/// Changes are written immediately.
+pub struct RedisStore {
+    // Reading from Redis requires a mutable connection, but not a mutable state.
+    connection: RefCell<redis::Connection>,
+}
+impl RedisStore {
+    /// Connect to the server, e.g. `redis://127.0.0.1/`.
+    pub fn open(url: &str) -> Result<Self, anyhow::Error> {
+        let connection = redis::Client::open(url)
+            .context("Invalid redis url")?
+            .get_connection()
+            .context("Could not connect to redis")?;
+        Ok(RedisStore {
+            connection: RefCell::new(connection),
+        })
+    }
+
+    fn hash(namespace: &str) -> String {
+        format!("qastor:{namespace}")
+    }
+}
+impl StateStore for RedisStore {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<serde_json::Value>, anyhow::Error> {
+        let value: Option<String> = self
+            .connection
+            .borrow_mut()
+            .hget(Self::hash(namespace), key)
+            .context("Could not read state")?;
+        value
+            .map(|value| serde_json::from_str(&value).context("Invalid state entry"))
+            .transpose()
+    }
+
+    fn put(
+        &mut self,
+        namespace: &str,
+        key: &str,
+        value: serde_json::Value,
+    ) -> Result<(), anyhow::Error> {
+        let () = self
+            .connection
+            .get_mut()
+            .hset(Self::hash(namespace), key, value.to_string())
+            .context("Could not write state")?;
+        Ok(())
+    }
+
+    fn remove(&mut self, namespace: &str, key: &str) -> Result<(), anyhow::Error> {
+        let () = self
+            .connection
+            .get_mut()
+            .hdel(Self::hash(namespace), key)
+            .context("Could not write state")?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+}
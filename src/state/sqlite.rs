@@ -0,0 +1,77 @@
+use std::path::Path;
+
+use anyhow::Context;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::StateStore;
+
+/// A state stored in a SQLite database, as a single table of namespaced entries.
+///
+/// Changes are written immediately.
+pub struct SqliteStore {
+    connection: Connection,
+}
+impl SqliteStore {
+    /// Open (or create) the database.
+    pub fn open(path: &Path) -> Result<Self, anyhow::Error> {
+        let connection = Connection::open(path)
+            .with_context(|| format!("Could not open state database {}", path.display()))?;
+        connection
+            .execute(
+                "CREATE TABLE IF NOT EXISTS state (
+                    namespace TEXT NOT NULL,
+                    key TEXT NOT NULL,
+                    value TEXT NOT NULL,
+                    PRIMARY KEY (namespace, key)
+                )",
+                (),
+            )
+            .context("Could not create state table")?;
+        Ok(SqliteStore { connection })
+    }
+}
+impl StateStore for SqliteStore {
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<serde_json::Value>, anyhow::Error> {
+        let value: Option<String> = self
+            .connection
+            .query_row(
+                "SELECT value FROM state WHERE namespace = ?1 AND key = ?2",
+                params![namespace, key],
+                |row| row.get(0),
+            )
+            .optional()
+            .context("Could not read state")?;
+        value
+            .map(|value| serde_json::from_str(&value).context("Invalid state entry"))
+            .transpose()
+    }
+
+    fn put(
+        &mut self,
+        namespace: &str,
+        key: &str,
+        value: serde_json::Value,
+    ) -> Result<(), anyhow::Error> {
+        self.connection
+            .execute(
+                "INSERT OR REPLACE INTO state (namespace, key, value) VALUES (?1, ?2, ?3)",
+                params![namespace, key, value.to_string()],
+            )
+            .context("Could not write state")?;
+        Ok(())
+    }
+
+    fn remove(&mut self, namespace: &str, key: &str) -> Result<(), anyhow::Error> {
+        self.connection
+            .execute(
+                "DELETE FROM state WHERE namespace = ?1 AND key = ?2",
+                params![namespace, key],
+            )
+            .context("Could not write state")?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), anyhow::Error> {
+        Ok(())
+    }
+}
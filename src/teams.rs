@@ -0,0 +1,111 @@
+use itertools::Itertools;
+use serde_json::json;
+use url::Url;
+
+use crate::slack::{self, MessageOptions};
+
+/// Whether a hook is a Teams incoming webhook (or a Power Automate workflow), e.g.
+/// `https://xxx.webhook.office.com/webhookb2/...`.
+pub fn is_teams(hook: &Url) -> bool {
+    hook.host_str().is_some_and(|host| {
+        host == "outlook.office.com"
+            || host.ends_with(".webhook.office.com")
+            || host.ends_with(".logic.azure.com")
+    })
+}
+
+/// A text block of an Adaptive Card.
+fn text_block(text: String, bold: bool) -> serde_json::Value {
+    let mut block = json!({
+        "type": "TextBlock",
+        "text": text,
+        "wrap": true,
+    });
+    if bold {
+        block["weight"] = json!("Bolder");
+    }
+    block
+}
+
+/// Render a section as an Adaptive Card: the title, then a two-column table of the fields.
+pub fn card(section: &slack::Section, options: &MessageOptions) -> serde_json::Value {
+    let mut body = vec![text_block(
+        slack::to_markdown(section.title(), options),
+        true,
+    )];
+    for row in &section.fields().chunks(2) {
+        let columns = row
+            .map(|field| {
+                json!({
+                    "type": "Column",
+                    "width": "stretch",
+                    "items": [text_block(slack::to_markdown(field, options), false)],
+                })
+            })
+            .collect_vec();
+        body.push(json!({
+            "type": "ColumnSet",
+            "separator": true,
+            "columns": columns,
+        }));
+    }
+    json!({
+        "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+        "type": "AdaptiveCard",
+        "version": "1.4",
+        "msteams": { "width": "Full" },
+        "body": body,
+    })
+}
+
+/// Render a section as the body of a message to a Teams webhook.
+pub fn payload(section: &slack::Section, options: &MessageOptions) -> serde_json::Value {
+    json!({
+        "type": "message",
+        "attachments": [{
+            "contentType": "application/vnd.microsoft.card.adaptive",
+            "content": card(section, options),
+        }],
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use url::Url;
+
+    use super::{card, is_teams};
+    use crate::slack::{self, MessageOptions};
+
+    /// Do reports become a title and a two-column table?
+    #[test]
+    fn test_card() {
+        let url = Url::parse("https://github.com/owner1/project1/pull/1").unwrap();
+        let mut section = slack::Section::new(format!(
+            "PRs of repo {} waiting for reviews",
+            slack::link(&url, Some("project1"))
+        ));
+        section.append_fields(&["*Request*".to_string(), "*Reviewer*".to_string()]);
+        section.append_fields(&[slack::link(&url, Some("Fix")), "alice".to_string()]);
+        let card = card(&section, &MessageOptions::default());
+        assert_eq!(
+            card["body"][0]["text"],
+            "PRs of repo [project1](https://github.com/owner1/project1/pull/1) waiting for reviews"
+        );
+        assert_eq!(card["body"][0]["weight"], "Bolder");
+        assert_eq!(
+            card["body"][1]["columns"][1]["items"][0]["text"],
+            "**Reviewer**"
+        );
+        assert_eq!(
+            card["body"][2]["columns"][0]["items"][0]["text"],
+            "[Fix](https://github.com/owner1/project1/pull/1)"
+        );
+
+        assert!(is_teams(
+            &Url::parse("https://contoso.webhook.office.com/webhookb2/abc").unwrap()
+        ));
+        assert!(!is_teams(
+            &Url::parse("https://hooks.slack.com/services/A/B/C").unwrap()
+        ));
+    }
+}
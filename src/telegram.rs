@@ -0,0 +1,208 @@
+use std::ops::Not;
+
+use anyhow::Context;
+use itertools::Itertools;
+use lazy_regex::{lazy_regex, Lazy};
+use log::debug;
+use regex::Regex;
+use reqwest::Client;
+use serde_json::json;
+
+use crate::{config::TelegramChat, error::Error, slack};
+
+/// Telegram's limit on the length of a message, in characters, once entities are parsed.
+///
+/// We count the markup too, to stay on the safe side.
+const MAX_MESSAGE: usize = 4096;
+
+/// Characters that must be escaped anywhere in MarkdownV2 text.
+const SPECIAL: &[char] = &[
+    '\\', '_', '*', '[', ']', '(', ')', '~', '`', '>', '#', '+', '-', '=', '|', '{', '}', '.', '!',
+];
+
+/// Escape text for Telegram's MarkdownV2, so that it's displayed verbatim.
+pub fn escape(text: &str) -> String {
+    escape_only(text, SPECIAL)
+}
+
+/// Escape some characters only, e.g. in code blocks or the url of a link.
+fn escape_only(text: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Convert Slack mrkdwn to Telegram's MarkdownV2, e.g. `<url|text>` to `[text](url)`.
+///
+/// Everything else is escaped, so that titles can't inject markup.
+pub fn to_markdown_v2(text: &str) -> String {
+    let text = &slack::strip_mentions(text);
+    static CODE: Lazy<Regex> = lazy_regex! {r"(?s)```\n?(.*?)\n?```"};
+    static LINK: Lazy<Regex> = lazy_regex! {r"<([^<>|]+)\|([^<>]*)>"};
+    static BOLD: Lazy<Regex> = lazy_regex! {r"(^|\s)\*([^*\n]+)\*"};
+    let inline = |text: &str| {
+        let text = html_escape::decode_html_entities(text);
+        let mut result = String::new();
+        let mut last = 0;
+        for bold in BOLD.captures_iter(&text) {
+            let whole = bold.get(0).expect("the whole match");
+            result.push_str(&escape(&text[last..whole.start()]));
+            result.push_str(&bold[1]);
+            result.push_str(&format!("*{}*", escape(&bold[2])));
+            last = whole.end();
+        }
+        result.push_str(&escape(&text[last..]));
+        result
+    };
+    let links = |text: &str| {
+        let mut result = String::new();
+        let mut last = 0;
+        for link in LINK.captures_iter(text) {
+            let whole = link.get(0).expect("the whole match");
+            result.push_str(&inline(&text[last..whole.start()]));
+            result.push_str(&format!(
+                "[{}]({})",
+                inline(&link[2]),
+                escape_only(&html_escape::decode_html_entities(&link[1]), &['\\', ')']),
+            ));
+            last = whole.end();
+        }
+        result.push_str(&inline(&text[last..]));
+        result
+    };
+    let mut result = String::new();
+    let mut last = 0;
+    for code in CODE.captures_iter(text) {
+        let whole = code.get(0).expect("the whole match");
+        result.push_str(&links(&text[last..whole.start()]));
+        result.push_str(&format!(
+            "```\n{}\n```",
+            escape_only(&code[1], &['\\', '`'])
+        ));
+        last = whole.end();
+    }
+    result.push_str(&links(&text[last..]));
+    result
+}
+
+/// Render a section as one or more messages: the title, then one line per row of fields.
+///
+/// Long sections are split between rows, to fit within Telegram's limit.
+pub fn messages(section: &slack::Section) -> Vec<String> {
+    let mut messages = vec![];
+    let mut current = to_markdown_v2(section.title());
+    for row in &section.fields().chunks(2) {
+        let line = row
+            .map(to_markdown_v2)
+            .filter(|field| field.is_empty().not())
+            .join(" · ");
+        if current.chars().count() + 1 + line.chars().count() > MAX_MESSAGE {
+            messages.push(std::mem::take(&mut current));
+        } else {
+            current.push('\n');
+        }
+        current.push_str(&line);
+    }
+    messages.push(current);
+    messages
+}
+
+/// Render a section as the messages to send to a chat.
+pub fn payloads(chat: &TelegramChat, section: &slack::Section) -> Vec<serde_json::Value> {
+    messages(section)
+        .into_iter()
+        .map(|text| {
+            json!({
+                "chat_id": chat.chat_id,
+                "text": text,
+                "parse_mode": "MarkdownV2",
+                "link_preview_options": { "is_disabled": true },
+            })
+        })
+        .collect()
+}
+
+/// Send a message (see `payloads`) to a Telegram chat.
+///
+/// Returns the number of bytes posted.
+pub async fn send(
+    client: &Client,
+    chat: &TelegramChat,
+    payload: &serde_json::Value,
+) -> Result<usize, anyhow::Error> {
+    let url = format!("https://api.telegram.org/bot{}/sendMessage", chat.bot_token);
+    debug!(
+        "Sending: {}",
+        serde_json::to_string_pretty(payload).unwrap()
+    );
+    let body = serde_json::to_vec(payload).context("Could not serialize message")?;
+    let posted_bytes = body.len();
+    let response = client
+        .post(&url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body)
+        .send()
+        .await
+        .context("Error while posting message to Telegram")?;
+    let status = response.status();
+    if status.is_success().not() {
+        let body = response.text().await.context("Could not gather response")?;
+        return Err(Error::SinkRejected {
+            service: "Telegram".to_string(),
+            status,
+            body,
+        }
+        .into());
+    }
+    Ok(posted_bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use url::Url;
+
+    use super::{messages, to_markdown_v2, MAX_MESSAGE};
+    use crate::slack;
+
+    /// Do reports keep their links and bold text, with everything else escaped?
+    #[test]
+    fn test_to_markdown_v2() {
+        let url = Url::parse("https://github.com/owner1/project-1/pull/1").unwrap();
+        let mut section = slack::Section::new(format!(
+            "PRs of repo {} waiting for reviews",
+            slack::link(&url, Some("project-1"))
+        ));
+        section.append_fields(&["*Request*".to_string(), "*Reviewer*".to_string()]);
+        section.append_fields(&[
+            format!("🐞 {}", slack::link(&url, Some("Fix (a) <b>!"))),
+            "alice_b".to_string(),
+        ]);
+        assert_eq!(
+            messages(&section),
+            vec![
+                "PRs of repo [project\\-1](https://github.com/owner1/project-1/pull/1) waiting for reviews\n\
+                *Request* · *Reviewer*\n\
+                🐞 [Fix \\(a\\) <b\\>\\!](https://github.com/owner1/project-1/pull/1) · alice\\_b"
+            ]
+        );
+        assert_eq!(
+            to_markdown_v2("```\n<1d   █ 1\n>7d   ██ 2\n```"),
+            "```\n<1d   █ 1\n>7d   ██ 2\n```"
+        );
+
+        let mut long = slack::Section::new("Long".to_string());
+        for i in 0..1000 {
+            long.append_fields(&[format!("Item {i}"), String::new()]);
+        }
+        let long = messages(&long);
+        assert_eq!(long.len(), 3);
+        assert!(long
+            .iter()
+            .all(|message| message.chars().count() <= MAX_MESSAGE));
+    }
+}
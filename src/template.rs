@@ -0,0 +1,389 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    ops::Not,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Context};
+use itertools::Itertools;
+
+use crate::{config::Project, report::SectionKind, slack};
+
+/// The kinds of sinks that may have templates of their own, see `Sink::kind`.
+pub const SINK_KINDS: [&str; 8] = [
+    "slack", "discord", "teams", "json", "matrix", "telegram", "email", "terminal",
+];
+
+/// The default template of sections that only have a template for their rows.
+const ROWS: &str = "{title}\n{rows}";
+
+/// What a template renders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Part {
+    /// A whole section, from `<section>.tmpl`.
+    Section,
+
+    /// Each row of a section, from `<section>.row.tmpl`.
+    Row,
+}
+
+/// Templates of the sections of reports, read from a directory laid out as
+/// `<kind>/<section>.tmpl` (e.g. `email/pending_reviews.tmpl`), with `<section>.tmpl` as
+/// the template of the kinds of sinks that have none.
+///
+/// A template replaces the title of a section, given these placeholders: `{title}` (the
+/// default title), `{owner}`, `{repo}` and `{url}` (of the project), and `{rows}` (the
+/// rows of the section, one per line). Sections whose template uses `{rows}` have no
+/// other fields. Write `{{` and `}}` for literal braces.
+///
+/// Row templates, `<section>.row.tmpl`, render each of these rows (but not the headers of
+/// the columns) from `{item}` and `{details}`, its two columns, e.g. `• {item} ({details})`.
+///
+/// Projects may have templates of their own (see `Project::templates`), which take
+/// precedence over the others.
+#[derive(Debug, Default)]
+pub struct Templates {
+    /// By kind of sink, `None` for the fallbacks, kind of section and part.
+    templates: HashMap<(Option<String>, SectionKind, Part), String>,
+
+    /// The templates of projects, by directory, see `Project::templates`.
+    projects: HashMap<PathBuf, Templates>,
+}
+impl Templates {
+    /// Read all the templates of a directory.
+    pub fn load(dir: &Path) -> Result<Self, anyhow::Error> {
+        let mut templates = Templates::default();
+        templates.load_dir(dir, None)?;
+        for kind in SINK_KINDS {
+            let dir = dir.join(kind);
+            if dir.is_dir() {
+                templates.load_dir(&dir, Some(kind))?;
+            }
+        }
+        Ok(templates)
+    }
+
+    fn load_dir(&mut self, dir: &Path, kind: Option<&str>) -> Result<(), anyhow::Error> {
+        let entries = std::fs::read_dir(dir)
+            .with_context(|| format!("Could not list templates in {}", dir.display()))?;
+        for entry in entries {
+            let path = entry
+                .with_context(|| format!("Could not list templates in {}", dir.display()))?
+                .path();
+            if path.is_dir() {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                if kind.is_none() && SINK_KINDS.contains(&name.as_ref()).not() {
+                    return Err(anyhow!(
+                        "Unknown kind of sink {name} in templates, expected one of {}",
+                        SINK_KINDS.join(", ")
+                    ));
+                }
+                continue;
+            }
+            if path.extension().is_none_or(|extension| extension != "tmpl") {
+                continue;
+            }
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            let (stem, part) = match stem.strip_suffix(".row") {
+                Some(stem) => (stem, Part::Row),
+                None => (stem.as_ref(), Part::Section),
+            };
+            let section: SectionKind = serde_json::from_value(stem.into())
+                .with_context(|| format!("Unknown section {stem} in templates"))?;
+            let template = std::fs::read_to_string(&path)
+                .with_context(|| format!("Could not read template {}", path.display()))?;
+            self.templates.insert(
+                (kind.map(str::to_string), section, part),
+                template.trim_end().to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Use the templates loaded from a directory for the projects whose `templates` is that
+    /// directory, rather than the others.
+    pub fn set_project(&mut self, dir: &Path, templates: Templates) {
+        self.projects.insert(dir.to_path_buf(), templates);
+    }
+
+    /// The template of a section for a kind of sink, if any.
+    pub fn resolve(&self, kind: &str, section: SectionKind) -> Option<&str> {
+        self.resolve_part(kind, section, Part::Section)
+    }
+
+    fn resolve_part(&self, kind: &str, section: SectionKind, part: Part) -> Option<&str> {
+        self.templates
+            .get(&(Some(kind.to_string()), section, part))
+            .or_else(|| self.templates.get(&(None, section, part)))
+            .map(String::as_str)
+    }
+
+    /// The template of a part of a section of a project for a kind of sink, if any: that of
+    /// the project, then the others.
+    fn resolve_for(
+        &self,
+        project: &Project,
+        kind: &str,
+        section: SectionKind,
+        part: Part,
+    ) -> Option<&str> {
+        project
+            .templates
+            .as_ref()
+            .and_then(|dir| self.projects.get(dir))
+            .and_then(|templates| templates.resolve_part(kind, section, part))
+            .or_else(|| self.resolve_part(kind, section, part))
+    }
+
+    /// A section as a kind of sink should receive it, through its template if any.
+    pub fn apply<'a>(
+        &self,
+        kind: &str,
+        section_kind: SectionKind,
+        project: &Project,
+        section: &'a slack::Section,
+    ) -> Cow<'a, slack::Section> {
+        let row = self.resolve_for(project, kind, section_kind, Part::Row);
+        let template = match (
+            self.resolve_for(project, kind, section_kind, Part::Section),
+            row,
+        ) {
+            (Some(template), _) => template,
+            (None, Some(_)) => ROWS,
+            (None, None) => return Cow::Borrowed(section),
+        };
+        // The headers of the columns make no sense once the rows are rendered.
+        let rows = section
+            .rows()
+            .chunks(2)
+            .into_iter()
+            .map(|fields| {
+                let fields = fields.collect_vec();
+                match row {
+                    Some(row) => {
+                        let vars = HashMap::from([
+                            ("item", fields[0].to_string()),
+                            (
+                                "details",
+                                fields.get(1).copied().unwrap_or_default().to_string(),
+                            ),
+                        ]);
+                        render(row, &vars)
+                    }
+                    None => fields
+                        .into_iter()
+                        .filter(|field| field.is_empty().not())
+                        .join(" · "),
+                }
+            })
+            .join("\n");
+        let vars = HashMap::from([
+            ("title", section.title().to_string()),
+            ("owner", project.owner.clone()),
+            ("repo", project.repo.to_string()),
+            ("url", project.html_url().to_string()),
+            ("rows", rows),
+        ]);
+        let title = render(template, &vars);
+        Cow::Owned(match template.contains("{rows}") {
+            true => slack::Section::new(title),
+            false => section.retitled(title),
+        })
+    }
+}
+
+/// A section with the title and emoji of its kind in the project, if overridden (see
+/// `Project::sections`), before templates apply.
+pub fn restyle<'a>(
+    project: &Project,
+    kind: SectionKind,
+    section: &'a slack::Section,
+) -> Cow<'a, slack::Section> {
+    let Some(style) = project.sections.get(&kind) else {
+        return Cow::Borrowed(section);
+    };
+    let mut title = match style.title {
+        Some(ref template) => {
+            let vars = HashMap::from([
+                ("title", section.title().to_string()),
+                ("owner", project.owner.clone()),
+                ("repo", project.repo.to_string()),
+                ("url", project.html_url().to_string()),
+            ]);
+            render(template, &vars)
+        }
+        None => section.title().to_string(),
+    };
+    if let Some(ref emoji) = style.emoji {
+        // Some built-in titles start with the emoji of their kind already.
+        let bare = title
+            .strip_prefix(kind.emoji())
+            .map_or(title.as_str(), str::trim_start);
+        title = format!("{emoji} {bare}");
+    }
+    Cow::Owned(section.retitled(title))
+}
+
+/// Replace the `{placeholders}` of a template with their values. Unknown placeholders are
+/// left as is.
+pub fn render(template: &str, vars: &HashMap<&str, String>) -> String {
+    let mut result = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(['{', '}']) {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if rest.starts_with("{{") || rest.starts_with("}}") {
+            result.push_str(&rest[..1]);
+            rest = &rest[2..];
+            continue;
+        }
+        let placeholder = rest[1..]
+            .find('}')
+            .map(|end| &rest[1..=end])
+            .filter(|name| name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+        match placeholder.and_then(|name| Some((name, vars.get(name)?))) {
+            Some((name, value)) => {
+                result.push_str(value);
+                rest = &rest[name.len() + 2..];
+            }
+            None => {
+                result.push_str(&rest[..1]);
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashMap, path::Path};
+
+    use super::{render, restyle, Templates};
+    use crate::{config::Config, report::SectionKind, slack};
+
+    /// Are placeholders replaced, with braces escaped and unknown placeholders kept?
+    #[test]
+    fn test_render() {
+        let vars = HashMap::from([("repo", "project1".to_string())]);
+        assert_eq!(
+            render("{repo}: {{literal}} {unknown} }", &vars),
+            "project1: {literal} {unknown} }"
+        );
+    }
+
+    /// Do sinks get the template of their kind, then the fallback, then the default?
+    #[test]
+    fn test_templates() {
+        let dir = std::env::temp_dir().join("qastor-test-templates");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("email")).unwrap();
+        std::fs::write(dir.join("pending_reviews.tmpl"), "👀 {repo}\n").unwrap();
+        std::fs::write(
+            dir.join("email").join("pending_reviews.tmpl"),
+            "Dear team, these PRs of {repo} need you:\n{rows}",
+        )
+        .unwrap();
+        let templates = Templates::load(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let config = Config::parse(
+            r#"
+projects:
+  - url: "https://github.com/owner1/project1"
+"#,
+        )
+        .unwrap();
+        let project = &config.projects[0];
+        let mut section = slack::Section::new("PRs of repo project1".to_string());
+        section.append_fields(&["Fix".to_string(), "alice".to_string()]);
+
+        let slack = templates.apply("slack", SectionKind::PendingReviews, project, &section);
+        assert_eq!(slack.title(), "👀 project1");
+        assert_eq!(slack.fields().count(), 2);
+        let email = templates.apply("email", SectionKind::PendingReviews, project, &section);
+        assert_eq!(
+            email.title(),
+            "Dear team, these PRs of project1 need you:\nFix · alice"
+        );
+        assert_eq!(email.fields().count(), 0);
+        let issues = templates.apply("email", SectionKind::UpdatedIssues, project, &section);
+        assert_eq!(issues.title(), "PRs of repo project1");
+    }
+
+    /// Do row templates render each row, and projects have templates of their own?
+    #[test]
+    fn test_project_templates() {
+        let dir = std::env::temp_dir().join("qastor-test-project-templates");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("pending_reviews.row.tmpl"),
+            "• {item} ({details})\n",
+        )
+        .unwrap();
+        let mut templates = Templates::default();
+        templates.set_project(Path::new("sdk"), Templates::load(&dir).unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let config = Config::parse(
+            r#"
+projects:
+  - url: "https://github.com/owner1/project1"
+    templates: sdk
+  - url: "https://github.com/owner1/project2"
+"#,
+        )
+        .unwrap();
+        let mut section = slack::Section::new("PRs".to_string());
+        section.append_fields(&["Fix".to_string(), "alice".to_string()]);
+        section.append_fields(&["Docs".to_string(), "bob".to_string()]);
+        let sdk = templates.apply(
+            "slack",
+            SectionKind::PendingReviews,
+            &config.projects[0],
+            &section,
+        );
+        assert_eq!(sdk.title(), "PRs\n• Fix (alice)\n• Docs (bob)");
+        assert_eq!(sdk.fields().count(), 0);
+        let other = templates.apply(
+            "slack",
+            SectionKind::PendingReviews,
+            &config.projects[1],
+            &section,
+        );
+        assert_eq!(other.title(), "PRs");
+    }
+
+    /// Do projects override the titles and emojis of sections, and only theirs?
+    #[test]
+    fn test_restyle() {
+        let config = Config::parse(
+            r#"
+projects:
+  - url: "https://github.com/owner1/project1"
+    sections:
+      updated_issues:
+        title: "Fresh tickets of {repo}"
+        emoji: 🧾
+      milestone_progress:
+        emoji: 🏁
+"#,
+        )
+        .unwrap();
+        let project = &config.projects[0];
+        let mut section = slack::Section::new("Issues of repo project1".to_string());
+        section.append_fields(&["Crash".to_string(), "alice".to_string()]);
+
+        let issues = restyle(project, SectionKind::UpdatedIssues, &section);
+        assert_eq!(issues.title(), "🧾 Fresh tickets of project1");
+        assert_eq!(issues.fields().count(), 2);
+        let progress = slack::Section::new("🎯 Progress of repo project1".to_string());
+        let progress = restyle(project, SectionKind::MilestoneProgress, &progress);
+        assert_eq!(progress.title(), "🏁 Progress of repo project1");
+        let pulls = restyle(project, SectionKind::PendingReviews, &section);
+        assert_eq!(pulls.title(), "Issues of repo project1");
+    }
+}
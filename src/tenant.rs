@@ -0,0 +1,113 @@
+use std::{
+    ops::Not,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use crate::{
+    config::{Config, Secrets},
+    error::Error,
+};
+
+/// An independent set of projects, e.g. those of a department, with its own config,
+/// secrets and state.
+///
+/// Each tenant is a directory containing a `config.yml` and a `secrets.json` (with the
+/// same format as `QASTOR_SECRETS`). Relative paths in the config (state, archive) are
+/// resolved within this directory.
+pub struct Tenant {
+    pub name: String,
+    pub dir: PathBuf,
+}
+impl Tenant {
+    /// List the tenants in a directory, one per subdirectory, sorted by name.
+    pub fn list(root: &Path) -> Result<Vec<Tenant>, anyhow::Error> {
+        let mut tenants = vec![];
+        for entry in std::fs::read_dir(root)
+            .with_context(|| format!("Could not read tenants directory {}", root.display()))?
+        {
+            let entry = entry.context("Could not read tenants directory")?;
+            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false).not() {
+                continue;
+            }
+            tenants.push(Tenant {
+                name: entry.file_name().to_string_lossy().into_owned(),
+                dir: entry.path(),
+            });
+        }
+        tenants.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(tenants)
+    }
+
+    /// Load the config of this tenant.
+    pub fn load_config(&self) -> Result<Config, anyhow::Error> {
+        let path = self.dir.join("config.yml");
+        let source = std::fs::read_to_string(&path)
+            .with_context(|| format!("Could not open {}", path.display()))?;
+        let mut config = Config::parse(&source)
+            .map_err(|err| Error::Config(format!("{err:#}")))
+            .with_context(|| format!("Invalid {}", path.display()))?;
+        config.state = config.state.relative_to(&self.dir);
+        config.archive = config.archive.map(|archive| archive.relative_to(&self.dir));
+        config.replay = config.replay.map(|dir| self.dir.join(dir));
+        Ok(config)
+    }
+
+    /// Load the secrets of this tenant.
+    ///
+    /// Unlike in single-tenant mode, environment variables are ignored, so that hooks
+    /// never leak from one tenant to another.
+    pub fn load_secrets(&self) -> Result<Secrets, anyhow::Error> {
+        let path = self.dir.join("secrets.json");
+        let source = std::fs::read_to_string(&path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        let mut secrets: Secrets = serde_json::from_str(&source)
+            .map_err(|err| Error::Config(err.to_string()))
+            .with_context(|| format!("Invalid {}", path.display()))?;
+        secrets
+            .attach_bot()
+            .with_context(|| format!("Invalid {}", path.display()))?;
+        Ok(secrets)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Tenant;
+
+    /// Do we list tenants by subdirectory, resolving their state within it?
+    #[test]
+    fn test_tenants() {
+        let root = std::env::temp_dir().join(format!("qastor-tenants-{}", std::process::id()));
+        for name in ["physics", "chemistry"] {
+            std::fs::create_dir_all(root.join(name)).unwrap();
+            std::fs::write(
+                root.join(name).join("config.yml"),
+                "projects: []\nupdate_frequency: 12h\n",
+            )
+            .unwrap();
+        }
+        std::fs::write(root.join("README.md"), "Not a tenant").unwrap();
+
+        let tenants = Tenant::list(&root).unwrap();
+        let names: Vec<_> = tenants.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["chemistry", "physics"]);
+        let config = tenants[1].load_config().unwrap();
+        let physics = root.join("physics").canonicalize().unwrap();
+        assert_eq!(
+            config.state.to_string(),
+            format!("file {}", physics.join("qastor-state.json").display())
+        );
+
+        // Another path to the same state is the same state.
+        std::fs::write(
+            root.join("chemistry").join("config.yml"),
+            "projects: []\nupdate_frequency: 12h\nstate:\n  backend: file\n  path: ../physics/qastor-state.json\n",
+        )
+        .unwrap();
+        assert_eq!(tenants[0].load_config().unwrap().state, config.state);
+        assert!(tenants[1].load_secrets().is_err());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
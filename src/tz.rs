@@ -0,0 +1,431 @@
+//! Time zones of the IANA database, e.g. `Europe/Paris`, as installed on the system (see
+//! `TZDIR`, by default `/usr/share/zoneinfo`), so that local times follow daylight saving
+//! time.
+
+use std::{ops::Not, path::PathBuf};
+
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+
+/// A time zone, with its transitions between offsets from UTC.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Zone {
+    name: String,
+
+    /// When the offset changes (in seconds since the epoch), and the index of the new offset
+    /// in `offsets`, oldest first.
+    transitions: Vec<(i64, usize)>,
+
+    /// The offsets from UTC, in seconds.
+    offsets: Vec<i32>,
+
+    /// How the offset changes after the last transition, if it still does.
+    rule: Option<Rule>,
+}
+impl Zone {
+    /// Load a time zone of the system by name, e.g. `Europe/Paris`.
+    pub fn load(name: &str) -> Result<Self, anyhow::Error> {
+        let relative = std::path::Path::new(name);
+        if relative.is_absolute()
+            || relative
+                .components()
+                .any(|component| matches!(component, std::path::Component::ParentDir))
+        {
+            return Err(anyhow!("Invalid time zone {name}"));
+        }
+        let dir = std::env::var_os("TZDIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("/usr/share/zoneinfo"));
+        let path = dir.join(relative);
+        let data = std::fs::read(&path)
+            .with_context(|| format!("Unknown time zone {name} (no {})", path.display()))?;
+        Zone::parse(name, &data)
+            .with_context(|| format!("Invalid time zone file {}", path.display()))
+    }
+
+    /// Parse a time zone from a TZif file, see RFC 8536.
+    fn parse(name: &str, data: &[u8]) -> Result<Self, anyhow::Error> {
+        let mut reader = Reader { data, at: 0 };
+        let header = reader.header()?;
+        // Version 1 uses 32-bit times. Later versions repeat the data with 64-bit times,
+        // followed by a rule for the times after the last transition.
+        let (header, time_size) = match header.version {
+            0 => (header, 4),
+            _ => {
+                reader.skip(header.len(4))?;
+                (reader.header()?, 8)
+            }
+        };
+        let times = (0..header.time_count)
+            .map(|_| reader.int(time_size))
+            .collect::<Result<Vec<_>, _>>()?;
+        let indices = reader.take(header.time_count)?.to_vec();
+        let offsets = (0..header.type_count)
+            .map(|_| {
+                let offset = reader.int(4)? as i32;
+                reader.skip(2)?;
+                Ok(offset)
+            })
+            .collect::<Result<Vec<_>, anyhow::Error>>()?;
+        if offsets.is_empty() || indices.iter().any(|&index| index as usize >= offsets.len()) {
+            return Err(anyhow!("Invalid local time types"));
+        }
+        let rule = match header.version {
+            0 => None,
+            _ => {
+                reader.skip(
+                    header.char_count
+                        + header.leap_count * (time_size + 4)
+                        + header.std_count
+                        + header.ut_count,
+                )?;
+                let footer = std::str::from_utf8(&reader.data[reader.at..])?;
+                Rule::parse(footer.trim())?
+            }
+        };
+        Ok(Zone {
+            name: name.to_string(),
+            transitions: times
+                .into_iter()
+                .zip(indices.into_iter().map(usize::from))
+                .collect(),
+            offsets,
+            rule,
+        })
+    }
+
+    /// The offset from UTC at this instant, in seconds.
+    pub fn offset_at(&self, at: DateTime<Utc>) -> i32 {
+        let timestamp = at.timestamp();
+        match self.transitions.last() {
+            Some((last, _)) if timestamp >= *last && self.rule.is_some() => {}
+            None if self.rule.is_some() => {}
+            _ => {
+                let index = self
+                    .transitions
+                    .partition_point(|(time, _)| *time <= timestamp);
+                return match index {
+                    0 => self.offsets[0],
+                    _ => self.offsets[self.transitions[index - 1].1],
+                };
+            }
+        }
+        self.rule
+            .as_ref()
+            .expect("we have a rule")
+            .offset_at(timestamp)
+    }
+
+    /// The instant of a local time, e.g. 09:00 on a given day. Local times skipped when
+    /// clocks move forward are taken with the offset before the change.
+    pub fn instant(&self, local: NaiveDateTime) -> DateTime<Utc> {
+        let guess = local.and_utc();
+        let before = self.offset_at(guess - Duration::days(1));
+        let offset = self.offset_at(guess - Duration::seconds(before.into()));
+        let instant = guess - Duration::seconds(offset.into());
+        match self.offset_at(instant) == offset {
+            true => instant,
+            false => guess - Duration::seconds(before.into()),
+        }
+    }
+
+    /// The next instant, strictly after `now`, at which it is `time` in this time zone.
+    pub fn next(&self, time: NaiveTime, now: DateTime<Utc>) -> DateTime<Utc> {
+        let today = (now + Duration::seconds(self.offset_at(now).into())).date_naive();
+        std::iter::successors(Some(today - Duration::days(1)), NaiveDate::succ_opt)
+            .map(|day| self.instant(day.and_time(time)))
+            .find(|instant| *instant > now)
+            .expect("days go on")
+    }
+}
+
+/// The sizes of the blocks of a TZif file.
+struct Header {
+    version: u8,
+    ut_count: usize,
+    std_count: usize,
+    leap_count: usize,
+    time_count: usize,
+    type_count: usize,
+    char_count: usize,
+}
+impl Header {
+    /// The length of the data block, with times of `time_size` bytes.
+    fn len(&self, time_size: usize) -> usize {
+        self.time_count * (time_size + 1)
+            + self.type_count * 6
+            + self.char_count
+            + self.leap_count * (time_size + 4)
+            + self.std_count
+            + self.ut_count
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], anyhow::Error> {
+        let bytes = self
+            .data
+            .get(self.at..self.at + len)
+            .context("Truncated time zone file")?;
+        self.at += len;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Result<(), anyhow::Error> {
+        self.take(len).map(|_| ())
+    }
+
+    /// A big-endian signed integer of 4 or 8 bytes.
+    fn int(&mut self, size: usize) -> Result<i64, anyhow::Error> {
+        let bytes = self.take(size)?;
+        Ok(match size {
+            4 => i32::from_be_bytes(bytes.try_into()?).into(),
+            _ => i64::from_be_bytes(bytes.try_into()?),
+        })
+    }
+
+    fn header(&mut self) -> Result<Header, anyhow::Error> {
+        if self.take(4)? != b"TZif" {
+            return Err(anyhow!("Not a time zone file"));
+        }
+        let version = match self.take(1)?[0] {
+            0 => 0,
+            version => version - b'0',
+        };
+        self.skip(15)?;
+        let mut count = || -> Result<usize, anyhow::Error> { Ok(self.int(4)? as usize) };
+        Ok(Header {
+            version,
+            ut_count: count()?,
+            std_count: count()?,
+            leap_count: count()?,
+            time_count: count()?,
+            type_count: count()?,
+            char_count: count()?,
+        })
+    }
+}
+
+/// A POSIX rule for the offsets of a time zone, e.g. `CET-1CEST,M3.5.0,M10.5.0/3`.
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    /// The standard offset from UTC, in seconds.
+    standard: i32,
+
+    /// With daylight saving time, its offset and when it starts and ends.
+    daylight: Option<(i32, Change, Change)>,
+}
+impl Rule {
+    /// Parse a rule, `None` if empty.
+    fn parse(source: &str) -> Result<Option<Self>, anyhow::Error> {
+        if source.is_empty() {
+            return Ok(None);
+        }
+        let invalid = || anyhow!("Invalid time zone rule {source}");
+        let mut rest = source;
+        skip_name(&mut rest).ok_or_else(invalid)?;
+        // POSIX offsets are west of Greenwich.
+        let standard = -parse_time(&mut rest).ok_or_else(invalid)?;
+        if rest.is_empty() {
+            return Ok(Some(Rule {
+                standard,
+                daylight: None,
+            }));
+        }
+        skip_name(&mut rest).ok_or_else(invalid)?;
+        let daylight = match rest.starts_with(',') {
+            true => standard + 3600,
+            false => -parse_time(&mut rest).ok_or_else(invalid)?,
+        };
+        let (start, end) = rest
+            .strip_prefix(',')
+            .and_then(|rules| rules.split_once(','))
+            .ok_or_else(invalid)?;
+        let start = Change::parse(start).ok_or_else(invalid)?;
+        let end = Change::parse(end).ok_or_else(invalid)?;
+        Ok(Some(Rule {
+            standard,
+            daylight: Some((daylight, start, end)),
+        }))
+    }
+
+    fn offset_at(&self, timestamp: i64) -> i32 {
+        let Some((daylight, ref start, ref end)) = self.daylight else {
+            return self.standard;
+        };
+        let Some(local) = DateTime::from_timestamp(timestamp + i64::from(self.standard), 0) else {
+            return self.standard;
+        };
+        let year = local.year();
+        // Changes happen at local times, standard before the start, daylight before the end.
+        let start = start.local(year) - i64::from(self.standard);
+        let end = end.local(year) - i64::from(daylight);
+        let in_daylight = match start < end {
+            true => (start..end).contains(&timestamp),
+            // Southern hemisphere.
+            false => (end..start).contains(&timestamp).not(),
+        };
+        match in_daylight {
+            true => daylight,
+            false => self.standard,
+        }
+    }
+}
+
+/// When daylight saving time starts or ends, as `Mm.w.d[/time]`: on day `d` (0 for
+/// Sunday) of week `w` (5 for the last) of month `m`, at a local time (02:00 by default).
+#[derive(Debug, Clone, PartialEq)]
+struct Change {
+    month: u32,
+    week: u32,
+    weekday: Weekday,
+
+    /// Seconds since midnight, possibly negative or beyond a day.
+    time: i32,
+}
+impl Change {
+    fn parse(source: &str) -> Option<Self> {
+        let (date, time) = match source.split_once('/') {
+            Some((date, mut time)) => (date, parse_time(&mut time).filter(|_| time.is_empty())?),
+            None => (source, 7200),
+        };
+        let mut parts = date.strip_prefix('M')?.split('.');
+        let mut next = || parts.next()?.parse::<u32>().ok();
+        let (month, week, weekday) = (next()?, next()?, next()?);
+        if (1..=12).contains(&month).not() || (1..=5).contains(&week).not() || weekday > 6 {
+            return None;
+        }
+        Some(Change {
+            month,
+            week,
+            // Chrono counts from Monday.
+            weekday: Weekday::try_from(((weekday + 6) % 7) as u8).ok()?,
+            time,
+        })
+    }
+
+    /// The local time of the change in a year, in seconds since the epoch as if local time
+    /// were UTC.
+    fn local(&self, year: i32) -> i64 {
+        let day = NaiveDate::from_weekday_of_month_opt(year, self.month, self.weekday, 5)
+            .filter(|_| self.week == 5)
+            .or_else(|| {
+                NaiveDate::from_weekday_of_month_opt(
+                    year,
+                    self.month,
+                    self.weekday,
+                    self.week.min(4) as u8,
+                )
+            })
+            .expect("every month has 4 of each weekday");
+        day.and_time(NaiveTime::MIN).and_utc().timestamp() + i64::from(self.time)
+    }
+}
+
+/// Skip the name of an offset, e.g. `CET` or `<+03>`.
+fn skip_name(rest: &mut &str) -> Option<()> {
+    let len = match rest.strip_prefix('<') {
+        Some(quoted) => quoted.find('>')? + 2,
+        None => rest
+            .find(|c: char| c.is_ascii_alphabetic().not())
+            .unwrap_or(rest.len()),
+    };
+    if len < 3 {
+        return None;
+    }
+    *rest = &rest[len..];
+    Some(())
+}
+
+/// Parse a signed time, `[+-]hh[:mm[:ss]]`, in seconds.
+fn parse_time(rest: &mut &str) -> Option<i32> {
+    let (sign, unsigned) = match rest.as_bytes().first()? {
+        b'-' => (-1, &rest[1..]),
+        b'+' => (1, &rest[1..]),
+        _ => (1, *rest),
+    };
+    let len = unsigned
+        .find(|c: char| c.is_ascii_digit().not() && c != ':')
+        .unwrap_or(unsigned.len());
+    let mut seconds = 0;
+    let mut unit = 3600;
+    for part in unsigned[..len].split(':') {
+        if unit == 0 {
+            return None;
+        }
+        seconds += part.parse::<i32>().ok()? * unit;
+        unit /= 60;
+    }
+    *rest = &unsigned[len..];
+    Some(sign * seconds)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{NaiveTime, TimeZone, Utc};
+
+    use super::{Rule, Zone};
+
+    /// Do POSIX rules follow daylight saving time, in both hemispheres?
+    #[test]
+    fn test_rule() {
+        let paris = Rule::parse("CET-1CEST,M3.5.0,M10.5.0/3").unwrap().unwrap();
+        let at = |y, m, d, h| Utc.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap().timestamp();
+        assert_eq!(paris.offset_at(at(2040, 1, 15, 12)), 3600);
+        assert_eq!(paris.offset_at(at(2040, 7, 15, 12)), 7200);
+        // On March 25th, 2040, at 01:00 UTC.
+        assert_eq!(paris.offset_at(at(2040, 3, 25, 0)), 3600);
+        assert_eq!(paris.offset_at(at(2040, 3, 25, 1)), 7200);
+        let sydney = Rule::parse("AEST-10AEDT,M10.1.0,M4.1.0/3")
+            .unwrap()
+            .unwrap();
+        assert_eq!(sydney.offset_at(at(2040, 1, 15, 12)), 11 * 3600);
+        assert_eq!(sydney.offset_at(at(2040, 7, 15, 12)), 10 * 3600);
+        let utc = Rule::parse("<+03>-3").unwrap().unwrap();
+        assert_eq!(utc.offset_at(at(2040, 7, 15, 12)), 3 * 3600);
+        assert!(Rule::parse("").unwrap().is_none());
+        assert!(Rule::parse("CET-1CEST,M13.5.0,M10.5.0").is_err());
+    }
+
+    /// Does 09:00 in a time zone follow daylight saving time, from the system's database?
+    #[test]
+    fn test_zone() {
+        let Ok(paris) = Zone::load("Europe/Paris") else {
+            // No time zone database on this system.
+            return;
+        };
+        let nine = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
+        let winter = Utc.with_ymd_and_hms(2024, 1, 15, 5, 0, 0).unwrap();
+        assert_eq!(
+            paris.next(nine, winter),
+            Utc.with_ymd_and_hms(2024, 1, 15, 8, 0, 0).unwrap()
+        );
+        let summer = Utc.with_ymd_and_hms(2024, 7, 15, 5, 0, 0).unwrap();
+        assert_eq!(
+            paris.next(nine, summer),
+            Utc.with_ymd_and_hms(2024, 7, 15, 7, 0, 0).unwrap()
+        );
+        let late = Utc.with_ymd_and_hms(2024, 7, 15, 8, 0, 0).unwrap();
+        assert_eq!(
+            paris.next(nine, late),
+            Utc.with_ymd_and_hms(2024, 7, 16, 7, 0, 0).unwrap()
+        );
+        // Past the last transition of the file, with its rule.
+        let future = Utc.with_ymd_and_hms(2080, 7, 15, 5, 0, 0).unwrap();
+        assert_eq!(
+            paris.next(nine, future),
+            Utc.with_ymd_and_hms(2080, 7, 15, 7, 0, 0).unwrap()
+        );
+        let new_york = Zone::load("America/New_York").unwrap();
+        assert_eq!(
+            new_york.next(nine, summer),
+            Utc.with_ymd_and_hms(2024, 7, 15, 13, 0, 0).unwrap()
+        );
+        assert!(Zone::load("Europe/Atlantis").is_err());
+        assert!(Zone::load("../etc/passwd").is_err());
+    }
+}
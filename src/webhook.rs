@@ -0,0 +1,160 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use octocrab::models::{issues::Issue, IssueState};
+use reqwest::Client;
+use serde::Serialize;
+use serde_json::json;
+use url::Url;
+
+use crate::{github::PendingReview, sinks, slack};
+
+/// The findings of a run about a project, for machines rather than humans.
+#[derive(Serialize, Debug)]
+pub struct Payload {
+    /// The url of the project.
+    pub project: Url,
+    pub generated_at: DateTime<Utc>,
+    pub pending_reviews: Vec<PendingReviewItem>,
+    pub updated_issues: Vec<IssueItem>,
+}
+impl Payload {
+    pub fn new(
+        project: &Url,
+        generated_at: DateTime<Utc>,
+        pending_reviews: &[PendingReview],
+        updated_issues: &[Issue],
+    ) -> Self {
+        Payload {
+            project: project.clone(),
+            generated_at,
+            pending_reviews: pending_reviews
+                .iter()
+                .map(|pull| PendingReviewItem {
+                    number: pull.number,
+                    url: pull.url.clone(),
+                    title: pull.title.clone(),
+                    reviewers: pull.reviewers.clone(),
+                    teams: pull.teams.clone(),
+                    labels: pull.labels.clone(),
+                    created_at: pull.created_at,
+                })
+                .collect(),
+            updated_issues: updated_issues
+                .iter()
+                .map(|issue| IssueItem {
+                    number: issue.number,
+                    url: issue.html_url.clone(),
+                    title: issue.title.clone(),
+                    author: issue.user.login.clone(),
+                    state: match issue.state {
+                        IssueState::Closed => "closed",
+                        _ => "open",
+                    },
+                    labels: issue
+                        .labels
+                        .iter()
+                        .map(|label| label.name.clone())
+                        .collect(),
+                    created_at: issue.created_at,
+                    updated_at: issue.updated_at,
+                    closed_at: issue.closed_at,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A PR waiting for a review.
+#[derive(Serialize, Debug)]
+pub struct PendingReviewItem {
+    pub number: u64,
+    pub url: Url,
+    pub title: String,
+
+    /// The logins of the requested reviewers.
+    pub reviewers: Vec<String>,
+
+    /// The requested teams, as `org/slug`.
+    pub teams: Vec<String>,
+    pub labels: Vec<String>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// An issue updated during the window of the project.
+#[derive(Serialize, Debug)]
+pub struct IssueItem {
+    pub number: u64,
+    pub url: Url,
+    pub title: String,
+
+    /// The login of the author.
+    pub author: String,
+
+    /// Either `open` or `closed`.
+    pub state: &'static str,
+    pub labels: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+}
+
+/// Render a free-form message (e.g. an alert) for a JSON webhook, as `{"text": ...}`.
+pub fn section_payload(section: &slack::Section) -> serde_json::Value {
+    json!({ "text": section.plain_text() })
+}
+
+/// Post the findings about a project to a JSON webhook.
+///
+/// Returns the number of bytes posted.
+pub async fn send(client: &Client, hook: &Url, payload: &Payload) -> Result<usize, anyhow::Error> {
+    let payload = serde_json::to_value(payload).context("Could not serialize payload")?;
+    sinks::post_json(client, hook, &payload, "JSON webhook").await
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{TimeZone, Utc};
+    use url::Url;
+
+    use super::Payload;
+    use crate::github::PendingReview;
+
+    /// Are findings serialized with the fields that dashboards rely on?
+    #[test]
+    fn test_payload() {
+        let project = Url::parse("https://github.com/owner1/project1").unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 5, 6, 12, 0, 0).unwrap();
+        let pull = PendingReview {
+            number: 1,
+            url: Url::parse("https://github.com/owner1/project1/pull/1").unwrap(),
+            title: "Fix".to_string(),
+            reviewers: vec!["alice".to_string()],
+            teams: vec!["owner1/reviewers".to_string()],
+            labels: vec![],
+            body: Some("Long description".to_string()),
+            created_at: Some(now),
+            updated_at: None,
+            author: Some("bob".to_string()),
+            draft: false,
+            review_comments: None,
+        };
+        let payload = serde_json::to_value(Payload::new(&project, now, &[pull], &[])).unwrap();
+        assert_eq!(
+            payload,
+            serde_json::json!({
+                "project": "https://github.com/owner1/project1",
+                "generated_at": "2024-05-06T12:00:00Z",
+                "pending_reviews": [{
+                    "number": 1,
+                    "url": "https://github.com/owner1/project1/pull/1",
+                    "title": "Fix",
+                    "reviewers": ["alice"],
+                    "teams": ["owner1/reviewers"],
+                    "labels": [],
+                    "created_at": "2024-05-06T12:00:00Z",
+                }],
+                "updated_issues": [],
+            })
+        );
+    }
+}